@@ -0,0 +1,16 @@
+//! `Flock::new` with any numbers at all, including NaN, infinities and subnormals. It must
+//! never panic, and must turn down anything that isn't a finite number.
+
+#![no_main]
+
+use boids::Flock;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (u8, f32, f32, f32, f32, f32)| {
+    let (flock_size, crowding_dist, local_dist, repulsion, adhesion, cohesion) = input;
+    let flock = Flock::new(flock_size as usize, crowding_dist, local_dist, repulsion, adhesion, cohesion);
+    let all_finite = [crowding_dist, local_dist, repulsion, adhesion, cohesion].iter().all(|value| value.is_finite());
+    if !all_finite {
+        assert!(flock.is_err(), "accepted {:?}", input);
+    }
+});
@@ -0,0 +1,48 @@
+//! Stepping a flock, however it was set up. Anything the setters accept has to be safe to step
+//! with, and boids given NaN or infinite velocities mustn't make the step panic either.
+
+#![no_main]
+
+use boids::arena::Arena;
+use boids::distribution::InitialDistribution;
+use boids::formation::Formation;
+use boids::Flock;
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    flock_size: u8,
+    crowding_dist: f32,
+    local_dist: f32,
+    factors: (f32, f32, f32),
+    spread: f32,
+    formation_speed: Option<f32>, // a grid of boids all moving at this speed, instead of random boids
+    seed: u64,
+    arena_radius: Option<f32>,
+    speed_limits: (f32, f32),
+    num_ticks: u8,
+}
+
+fuzz_target!(|input: Input| {
+    let (adhesion, repulsion, cohesion) = input.factors;
+    let Ok(mut flock) = Flock::new(input.flock_size as usize, input.crowding_dist, input.local_dist, adhesion, repulsion, cohesion) else {
+        return;
+    };
+    if let Some(radius) = input.arena_radius {
+        if let Ok(arena) = Arena::circle((0.0, 0.0), radius) {
+            flock.set_arena(arena);
+        }
+    }
+    let _ = flock.set_speed_limits(input.speed_limits.0, input.speed_limits.1);
+    let placed = match input.formation_speed {
+        Some(speed) => flock.arrange_in_formation(&Formation::Grid { spacing: input.spread }, speed),
+        None => flock.randomise_boids(&InitialDistribution::GaussianBlob { std_dev: input.spread }, input.seed),
+    };
+    if placed.is_err() {
+        return;
+    }
+    for _ in 0..input.num_ticks % 32 {
+        flock.step();
+    }
+});
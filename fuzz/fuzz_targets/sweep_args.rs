@@ -0,0 +1,11 @@
+//! The `sweep` command line parser, which must reject bad arguments with an error rather than
+//! a panic.
+
+#![no_main]
+
+use boids::sweep::SweepConfig;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|args: Vec<String>| {
+    let _ = SweepConfig::from_args(&args);
+});
@@ -0,0 +1,546 @@
+//! The walls the flock is kept inside, either a polygon or a circle. A boid that would cross a
+//! wall during a step bounces off it instead, as if the wall were a mirror, or slides along it.
+//! A circle has no corners for boids to bunch up in.
+//!
+//! Boids can also be given whiskers: short rays cast ahead of them, which steer them sideways
+//! away from walls they're heading for, harder the sooner they'd hit, so that they detour round
+//! walls smoothly rather than only bouncing off at the last moment. A soft margin does the same
+//! for boids close to a wall whichever way they're heading, pushing them back towards the middle
+//! harder the closer they get, so they curve away rather than pinballing off the walls. Either way,
+//! boids that still reach a wall bounce or slide as usual.
+
+use std::f32::consts::TAU;
+use anyhow::{anyhow, Context, Result};
+use rand::rngs::StdRng;
+use rand::Rng;
+use crate::{check_finite, check_float_between_zero_and_one, is_positive_and_finite, Boid, CreationError, InvalidFlockConfig};
+
+// how far inside the wall a sliding boid is kept, so it isn't left balanced exactly on the wall
+pub(crate) const WALL_CLEARANCE: f32 = 1e-3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WallResponse {
+    /// the boid bounces off at the angle it hit the wall
+    Reflect,
+    /// the boid loses the part of its velocity into the wall and skims along it
+    Slide,
+}
+
+impl WallResponse {
+    pub fn parse(text: &str) -> Result<WallResponse> {
+        return match text {
+            "reflect" => Ok(WallResponse::Reflect),
+            "slide" => Ok(WallResponse::Slide),
+            _ => Err(anyhow!("unknown wall response '{}'; expected reflect or slide", text)),
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Whiskers {
+    pub length: f32, // how far ahead walls are seen
+    pub spread_degrees: f32, // the angle either side of straight ahead of the two side whiskers
+    pub steering_factor: f32, // how hard a boid turns away from a wall it's about to hit, relative to its speed
+}
+
+impl Whiskers {
+    pub fn new(length: f32, spread_degrees: f32, steering_factor: f32) -> Result<Whiskers, InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = check_float_between_zero_and_one(steering_factor, "whisker steering".to_string())
+            .into_iter()
+            .chain(check_finite(spread_degrees, "whisker spread"))
+            .collect();
+        if !is_positive_and_finite(length) {
+            errors.push(CreationError::ArenaParameterIsNotPositive("whisker length".to_string()));
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(Whiskers { length, spread_degrees, steering_factor });
+    }
+
+    /// `length:spread_degrees:steering_factor`, e.g. `20:30:0.2`
+    pub fn parse(text: &str) -> Result<Whiskers> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let [length, spread_degrees, steering_factor] = parts.as_slice() else {
+            return Err(anyhow!("whiskers '{}' should be length:spread_degrees:steering_factor", text));
+        };
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        return Ok(Whiskers::new(parse_float(length)?, parse_float(spread_degrees)?, parse_float(steering_factor)?)?);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoftMargin {
+    pub width: f32, // how far from the walls boids start being pushed back
+    pub push_factor: f32, // how much a boid's velocity changes each frame right at a wall
+}
+
+impl SoftMargin {
+    pub fn new(width: f32, push_factor: f32) -> Result<SoftMargin, InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = check_float_between_zero_and_one(push_factor, "margin push".to_string())
+            .into_iter()
+            .collect();
+        if !is_positive_and_finite(width) {
+            errors.push(CreationError::ArenaParameterIsNotPositive("margin width".to_string()));
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(SoftMargin { width, push_factor });
+    }
+
+    /// `width:push_factor`, e.g. `15:0.3`
+    pub fn parse(text: &str) -> Result<SoftMargin> {
+        let (width, push_factor) = text.split_once(':').ok_or_else(|| anyhow!("margin '{}' should be width:push_factor", text))?;
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        return Ok(SoftMargin::new(parse_float(width)?, parse_float(push_factor)?)?);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArenaShape {
+    /// the vertices are in order around the edge, either way round, and the last joins back up to the first.
+    /// The polygon can be concave but its edges shouldn't cross
+    Polygon(Vec<(f32, f32)>),
+    Circle { centre: (f32, f32), radius: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arena {
+    shape: ArenaShape,
+    pub wall_response: WallResponse,
+    pub whiskers: Option<Whiskers>,
+    pub soft_margin: Option<SoftMargin>,
+}
+
+impl Arena {
+    pub fn polygon(vertices: Vec<(f32, f32)>) -> Result<Arena, InvalidFlockConfig> {
+        if vertices.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::ValueIsNotFinite("arena vertex".to_string())] });
+        }
+        if signed_area(&vertices) == 0.0 {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::ArenaHasNoArea] });
+        }
+        return Ok(Arena { shape: ArenaShape::Polygon(vertices), wall_response: WallResponse::Reflect, whiskers: None, soft_margin: None });
+    }
+
+    pub fn circle(centre: (f32, f32), radius: f32) -> Result<Arena, InvalidFlockConfig> {
+        if !centre.0.is_finite() || !centre.1.is_finite() {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::ValueIsNotFinite("arena centre".to_string())] });
+        }
+        if !is_positive_and_finite(radius) {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::ArenaHasNoArea] });
+        }
+        return Ok(Arena { shape: ArenaShape::Circle { centre, radius }, wall_response: WallResponse::Reflect, whiskers: None, soft_margin: None });
+    }
+
+    /// `circle:radius`, centred on the origin, or `polygon:x,y;x,y;...` with the vertices in order around
+    /// the edge, e.g. `polygon:0,0;100,0;0,100`
+    pub fn parse(text: &str) -> Result<Arena> {
+        if let Some(radius) = text.strip_prefix("circle:") {
+            let radius = radius.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", radius, text))?;
+            return Ok(Arena::circle((0.0, 0.0), radius)?);
+        }
+        let vertices = text.strip_prefix("polygon:").ok_or_else(|| anyhow!("arena '{}' should start with circle: or polygon:", text))?;
+        let vertices = vertices
+            .split(';')
+            .map(|vertex| {
+                let (x, y) = vertex.split_once(',').ok_or_else(|| anyhow!("vertex '{}' in '{}' should be x,y", vertex, text))?;
+                let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+                return Ok((parse_float(x)?, parse_float(y)?));
+            })
+            .collect::<Result<Vec<(f32, f32)>>>()?;
+        return Ok(Arena::polygon(vertices)?);
+    }
+
+    pub fn shape(&self) -> &ArenaShape {
+        return &self.shape;
+    }
+
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        let vertices = match &self.shape {
+            ArenaShape::Polygon(vertices) => vertices,
+            ArenaShape::Circle { centre, radius } => return (x - centre.0).powi(2) + (y - centre.1).powi(2) <= radius * radius,
+        };
+        // count how many edges a ray heading right from the point crosses; inside if it's odd
+        let mut inside = false;
+        for (start, end) in edges(vertices) {
+            if (start.1 > y) != (end.1 > y) {
+                let crossing_x = start.0 + (y - start.1) / (end.1 - start.1) * (end.0 - start.0);
+                if x < crossing_x {
+                    inside = !inside;
+                }
+            }
+        }
+        return inside;
+    }
+
+    /// a point picked uniformly from inside the arena
+    pub fn random_point(&self, rng: &mut StdRng) -> (f32, f32) {
+        let vertices = match &self.shape {
+            ArenaShape::Polygon(vertices) => vertices,
+            ArenaShape::Circle { centre, radius } => {
+                // the square root stops the points bunching up in the middle
+                let dist_from_centre = radius * rng.gen::<f32>().sqrt();
+                let angle = rng.gen_range(0.0..TAU);
+                return (centre.0 + dist_from_centre * angle.cos(), centre.1 + dist_from_centre * angle.sin());
+            }
+        };
+        let min_x = vertices.iter().map(|(x, _)| *x).fold(f32::INFINITY, f32::min);
+        let max_x = vertices.iter().map(|(x, _)| *x).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = vertices.iter().map(|(_, y)| *y).fold(f32::INFINITY, f32::min);
+        let max_y = vertices.iter().map(|(_, y)| *y).fold(f32::NEG_INFINITY, f32::max);
+        // points are picked from the bounding box until one lands inside, which always happens eventually
+        // because the arena has some area
+        loop {
+            let point = (rng.gen_range(min_x..=max_x), rng.gen_range(min_y..=max_y));
+            if self.contains(point) {
+                return point;
+            }
+        }
+    }
+
+    /// bounces or slides a boid that has moved out of the arena since it was at `previous_position` back in.
+    /// Boids that were already outside are left alone. Returns whether the boid hit a wall
+    pub(crate) fn contain(&self, boid: &mut Boid, previous_position: (f32, f32)) -> bool {
+        if self.contains((boid.x_pos, boid.y_pos)) {
+            return false;
+        }
+        let movement = (boid.x_pos - previous_position.0, boid.y_pos - previous_position.1);
+        let Some((fraction, normal)) = self.first_wall_hit(previous_position, movement) else {
+            return false;
+        };
+
+        let mut hit = (previous_position.0 + movement.0 * fraction, previous_position.1 + movement.1 * fraction);
+        let remaining_movement = (movement.0 * (1.0 - fraction), movement.1 * (1.0 - fraction));
+        let remaining_movement = match self.wall_response {
+            WallResponse::Reflect => {
+                (boid.x_vel, boid.y_vel) = reflect((boid.x_vel, boid.y_vel), normal);
+                reflect(remaining_movement, normal)
+            }
+            WallResponse::Slide => {
+                (boid.x_vel, boid.y_vel) = along_wall((boid.x_vel, boid.y_vel), normal);
+                // the normal could point either way, but the boid was moving out through the wall
+                let outwards = if movement.0 * normal.0 + movement.1 * normal.1 > 0.0 { 1.0 } else { -1.0 };
+                hit = (hit.0 - normal.0 * outwards * WALL_CLEARANCE, hit.1 - normal.1 * outwards * WALL_CLEARANCE);
+                along_wall(remaining_movement, normal)
+            }
+        };
+        (boid.x_pos, boid.y_pos) = (hit.0 + remaining_movement.0, hit.1 + remaining_movement.1);
+        // bouncing or sliding into a corner can still leave the boid outside, so it stays where it was for this step
+        if !self.contains((boid.x_pos, boid.y_pos)) {
+            (boid.x_pos, boid.y_pos) = previous_position;
+        }
+        return true;
+    }
+
+    /// steers each boid sideways away from any wall its whiskers reach, harder the nearer the wall
+    pub(crate) fn avoid_walls(&self, boids: &mut [Boid]) {
+        let Some(whiskers) = self.whiskers else {
+            return;
+        };
+        let spread = whiskers.spread_degrees.to_radians();
+        for boid in boids {
+            let speed = boid.speed();
+            // a stationary boid isn't heading for anything
+            if speed == 0.0 || !self.contains((boid.x_pos, boid.y_pos)) {
+                continue;
+            }
+            let heading = (boid.x_vel / speed, boid.y_vel / speed);
+            let mut steering = (0.0, 0.0);
+            for angle in [0.0, spread, -spread] {
+                let (sin, cos) = f32::sin_cos(angle);
+                let whisker = ((heading.0 * cos - heading.1 * sin) * whiskers.length, (heading.0 * sin + heading.1 * cos) * whiskers.length);
+                let Some((fraction, normal)) = self.first_wall_hit((boid.x_pos, boid.y_pos), whisker) else {
+                    continue;
+                };
+                // the normal could point either way, so turn it to point back into the arena
+                let into_arena = if normal.0 * whisker.0 + normal.1 * whisker.1 > 0.0 { (-normal.0, -normal.1) } else { normal };
+                // only the part across the boid's heading turns it, rather than slowing it down
+                let across_heading = into_arena.0 * heading.0 + into_arena.1 * heading.1;
+                let mut sideways = (into_arena.0 - across_heading * heading.0, into_arena.1 - across_heading * heading.1);
+                let sideways_length = (sideways.0 * sideways.0 + sideways.1 * sideways.1).sqrt();
+                // heading straight at the wall, either way round is as good, so turn left
+                sideways = if sideways_length < 1e-6 { (-heading.1, heading.0) } else { (sideways.0 / sideways_length, sideways.1 / sideways_length) };
+                // 1 if the wall is right in front of the boid, down to 0 at the end of the whisker
+                let urgency = 1.0 - fraction;
+                steering = (steering.0 + sideways.0 * urgency, steering.1 + sideways.1 * urgency);
+            }
+            boid.x_vel += steering.0 * speed * whiskers.steering_factor;
+            boid.y_vel += steering.1 * speed * whiskers.steering_factor;
+        }
+    }
+
+    /// pushes boids within the soft margin back towards the middle of the arena
+    pub(crate) fn push_back_from_walls(&self, boids: &mut [Boid]) {
+        let Some(soft_margin) = self.soft_margin else {
+            return;
+        };
+        for boid in boids {
+            if !self.contains((boid.x_pos, boid.y_pos)) {
+                continue;
+            }
+            let (dist, away_from_wall) = self.nearest_wall((boid.x_pos, boid.y_pos));
+            if dist < soft_margin.width {
+                // nothing at the inner edge of the margin, up to the full push at the wall
+                let push = soft_margin.push_factor * (1.0 - dist / soft_margin.width);
+                boid.x_vel += away_from_wall.0 * push;
+                boid.y_vel += away_from_wall.1 * push;
+            }
+        }
+    }
+
+    /// the distance from a point inside the arena to the nearest wall, and the unit vector from the wall to the point
+    fn nearest_wall(&self, (x, y): (f32, f32)) -> (f32, (f32, f32)) {
+        match &self.shape {
+            ArenaShape::Polygon(vertices) => {
+                let mut nearest = (f32::INFINITY, (0.0, 0.0));
+                for (start, end) in edges(vertices) {
+                    let edge = (end.0 - start.0, end.1 - start.1);
+                    let fraction = (((x - start.0) * edge.0 + (y - start.1) * edge.1) / (edge.0 * edge.0 + edge.1 * edge.1)).clamp(0.0, 1.0);
+                    let from_wall = (x - (start.0 + edge.0 * fraction), y - (start.1 + edge.1 * fraction));
+                    let dist = (from_wall.0 * from_wall.0 + from_wall.1 * from_wall.1).sqrt();
+                    if dist < nearest.0 && dist > 0.0 {
+                        nearest = (dist, (from_wall.0 / dist, from_wall.1 / dist));
+                    }
+                }
+                return nearest;
+            }
+            ArenaShape::Circle { centre, radius } => {
+                let from_centre = (x - centre.0, y - centre.1);
+                let dist_from_centre = (from_centre.0 * from_centre.0 + from_centre.1 * from_centre.1).sqrt();
+                // right at the centre every wall is as far away, and none is in the margin
+                if dist_from_centre == 0.0 {
+                    return (*radius, (0.0, 0.0));
+                }
+                return (radius - dist_from_centre, (-from_centre.0 / dist_from_centre, -from_centre.1 / dist_from_centre));
+            }
+        }
+    }
+
+    /// how far through the movement the boid first hit a wall on its way out, and the wall's unit normal there
+    fn first_wall_hit(&self, start: (f32, f32), movement: (f32, f32)) -> Option<(f32, (f32, f32))> {
+        match &self.shape {
+            ArenaShape::Polygon(vertices) => {
+                return edges(vertices)
+                    .filter_map(|edge| fraction_of_movement_before_crossing(start, movement, edge).map(|fraction| (fraction, unit_normal(edge.0, edge.1))))
+                    .min_by(|(fraction, _), (other_fraction, _)| fraction.total_cmp(other_fraction));
+            }
+            ArenaShape::Circle { centre, radius } => {
+                // solve |start + fraction * movement - centre| = radius; starting inside, the larger root is where it leaves
+                let from_centre = (start.0 - centre.0, start.1 - centre.1);
+                let a = movement.0 * movement.0 + movement.1 * movement.1;
+                let b = 2.0 * (movement.0 * from_centre.0 + movement.1 * from_centre.1);
+                let c = from_centre.0 * from_centre.0 + from_centre.1 * from_centre.1 - radius * radius;
+                let discriminant = b * b - 4.0 * a * c;
+                if a == 0.0 || c > 0.0 || discriminant < 0.0 {
+                    return None;
+                }
+                let fraction = ((-b + discriminant.sqrt()) / (2.0 * a)).clamp(0.0, 1.0);
+                let hit_from_centre = (from_centre.0 + movement.0 * fraction, from_centre.1 + movement.1 * fraction);
+                return Some((fraction, (hit_from_centre.0 / radius, hit_from_centre.1 / radius)));
+            }
+        }
+    }
+}
+
+fn edges(vertices: &[(f32, f32)]) -> impl Iterator<Item = ((f32, f32), (f32, f32))> + '_ {
+    return vertices.iter().copied().zip(vertices.iter().copied().cycle().skip(1));
+}
+
+/// twice the area of the polygon, positive if the vertices go anticlockwise (shoelace formula)
+fn signed_area(vertices: &[(f32, f32)]) -> f32 {
+    return vertices.iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(start, end)| start.0 * end.1 - end.0 * start.1)
+        .sum();
+}
+
+/// how far from `start` to `start + movement` the edge is crossed, from 0 to 1, if it is at all
+pub(crate) fn fraction_of_movement_before_crossing(start: (f32, f32), movement: (f32, f32), (edge_start, edge_end): ((f32, f32), (f32, f32))) -> Option<f32> {
+    let cross = |a: (f32, f32), b: (f32, f32)| a.0 * b.1 - a.1 * b.0;
+    let edge = (edge_end.0 - edge_start.0, edge_end.1 - edge_start.1);
+    let denominator = cross(movement, edge);
+    // moving parallel to the edge never crosses it
+    if denominator == 0.0 {
+        return None;
+    }
+    let to_edge = (edge_start.0 - start.0, edge_start.1 - start.1);
+    let fraction_of_movement = cross(to_edge, edge) / denominator;
+    let fraction_of_edge = cross(to_edge, movement) / denominator;
+    if (0.0..=1.0).contains(&fraction_of_movement) && (0.0..=1.0).contains(&fraction_of_edge) {
+        return Some(fraction_of_movement);
+    }
+    return None;
+}
+
+pub(crate) fn unit_normal(start: (f32, f32), end: (f32, f32)) -> (f32, f32) {
+    let length = ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+    return (-(end.1 - start.1) / length, (end.0 - start.0) / length);
+}
+
+/// mirrors the vector in a line with the given unit normal
+pub(crate) fn reflect(vector: (f32, f32), normal: (f32, f32)) -> (f32, f32) {
+    let along_normal = vector.0 * normal.0 + vector.1 * normal.1;
+    return (vector.0 - 2.0 * along_normal * normal.0, vector.1 - 2.0 * along_normal * normal.1);
+}
+
+/// the vector without its part along the unit normal
+fn along_wall(vector: (f32, f32), normal: (f32, f32)) -> (f32, f32) {
+    let along_normal = vector.0 * normal.0 + vector.1 * normal.1;
+    return (vector.0 - along_normal * normal.0, vector.1 - along_normal * normal.1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn square() -> Arena {
+        return Arena::polygon(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]).unwrap();
+    }
+
+    #[test]
+    fn test_contains_concave_polygon() {
+        // an L shape, missing its top right quarter
+        let arena = Arena::polygon(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 5.0), (5.0, 5.0), (5.0, 10.0), (0.0, 10.0)]).unwrap();
+        assert!(arena.contains((2.0, 8.0)));
+        assert!(arena.contains((8.0, 2.0)));
+        assert!(!arena.contains((8.0, 8.0)));
+        assert!(!arena.contains((-1.0, 2.0)));
+    }
+
+    #[test]
+    fn test_boid_bounces_off_wall() {
+        let mut boid = Boid::new(12.0, 5.0, 4.0, 1.0);
+        assert!(square().contain(&mut boid, (8.0, 4.0)));
+        // it reached the wall halfway through its movement, then went back the other half
+        assert_eq!((boid.x_pos, boid.y_pos), (8.0, 5.0));
+        assert_eq!((boid.x_vel, boid.y_vel), (-4.0, 1.0));
+    }
+
+    #[test]
+    fn test_boid_slides_along_wall() {
+        let mut arena = square();
+        arena.wall_response = WallResponse::Slide;
+        let mut boid = Boid::new(12.0, 5.0, 4.0, 1.0);
+        arena.contain(&mut boid, (8.0, 4.0));
+        assert_eq!((boid.x_vel, boid.y_vel), (0.0, 1.0));
+        // it reached the wall halfway through its movement, then carried on up it
+        assert!((boid.x_pos - 10.0).abs() < 0.01 && boid.x_pos < 10.0);
+        assert_eq!(boid.y_pos, 5.0);
+    }
+
+    #[test]
+    fn test_whiskers_turn_boid_away_from_wall_ahead() {
+        let mut arena = square();
+        arena.whiskers = Some(Whiskers::new(4.0, 30.0, 0.5).unwrap());
+        // heading up and to the right, towards the right hand wall
+        let mut boids = vec![Boid::new(8.0, 5.0, 1.0, 1.0)];
+        arena.avoid_walls(&mut boids);
+        assert!(boids[0].x_vel < 1.0);
+        assert!(boids[0].y_vel > 1.0);
+
+        // nothing within reach of the whiskers
+        let mut boids = vec![Boid::new(2.0, 5.0, 1.0, 0.0)];
+        arena.avoid_walls(&mut boids);
+        assert_eq!((boids[0].x_vel, boids[0].y_vel), (1.0, 0.0));
+    }
+
+    #[test]
+    fn test_nearer_wall_steers_harder() {
+        let mut arena = Arena::circle((0.0, 0.0), 10.0).unwrap();
+        arena.whiskers = Some(Whiskers::new(5.0, 20.0, 0.5).unwrap());
+        let mut boids = vec![Boid::new(6.0, 0.0, 1.0, 1.0), Boid::new(8.0, 0.0, 1.0, 1.0)];
+        arena.avoid_walls(&mut boids);
+        assert!(boids[1].x_vel < boids[0].x_vel);
+    }
+
+    #[test]
+    fn test_whiskers_parsed_and_validated() {
+        assert_eq!(Whiskers::parse("20:30:0.2").unwrap(), Whiskers { length: 20.0, spread_degrees: 30.0, steering_factor: 0.2 });
+        assert!(Whiskers::parse("20:30").is_err());
+        assert_eq!(Whiskers::new(0.0, 30.0, 2.0).unwrap_err().errors, vec![
+            CreationError::FactorShouldBeLessThanOne("whisker steering".to_string()),
+            CreationError::ArenaParameterIsNotPositive("whisker length".to_string()),
+        ]);
+        assert_eq!(Whiskers::new(20.0, f32::NAN, 0.2).unwrap_err().errors, vec![
+            CreationError::ValueIsNotFinite("whisker spread".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_soft_margin_pushes_harder_nearer_the_wall() {
+        let mut arena = square();
+        arena.soft_margin = Some(SoftMargin::new(4.0, 0.5).unwrap());
+        let mut boids = vec![Boid::new(9.0, 5.0, 0.0, 0.0), Boid::new(8.0, 5.0, 0.0, 0.0), Boid::new(5.0, 5.0, 0.0, 0.0)];
+        arena.push_back_from_walls(&mut boids);
+        assert_eq!((boids[0].x_vel, boids[0].y_vel), (-0.375, 0.0));
+        assert_eq!((boids[1].x_vel, boids[1].y_vel), (-0.25, 0.0));
+        assert_eq!((boids[2].x_vel, boids[2].y_vel), (0.0, 0.0));
+
+        let mut arena = Arena::circle((0.0, 0.0), 10.0).unwrap();
+        arena.soft_margin = Some(SoftMargin::new(4.0, 0.5).unwrap());
+        let mut boids = vec![Boid::new(0.0, -8.0, 0.0, 0.0)];
+        arena.push_back_from_walls(&mut boids);
+        assert_eq!((boids[0].x_vel, boids[0].y_vel), (0.0, 0.25));
+    }
+
+    #[test]
+    fn test_soft_margin_parsed_and_validated() {
+        assert_eq!(SoftMargin::parse("15:0.3").unwrap(), SoftMargin { width: 15.0, push_factor: 0.3 });
+        assert!(SoftMargin::parse("15").is_err());
+        assert_eq!(SoftMargin::new(-1.0, 0.3).unwrap_err().errors, vec![CreationError::ArenaParameterIsNotPositive("margin width".to_string())]);
+    }
+
+    #[test]
+    fn test_boid_inside_or_already_outside_left_alone() {
+        let mut inside = Boid::new(5.0, 5.0, 1.0, 0.0);
+        assert!(!square().contain(&mut inside, (4.0, 5.0)));
+        assert_eq!((inside.x_pos, inside.x_vel), (5.0, 1.0));
+
+        let mut outside = Boid::new(21.0, 5.0, 1.0, 0.0);
+        assert!(!square().contain(&mut outside, (20.0, 5.0)));
+        assert_eq!((outside.x_pos, outside.x_vel), (21.0, 1.0));
+    }
+
+    #[test]
+    fn test_boid_bounces_off_circle() {
+        let arena = Arena::circle((0.0, 0.0), 10.0).unwrap();
+        let mut boid = Boid::new(0.0, 12.0, 0.0, 4.0);
+        arena.contain(&mut boid, (0.0, 8.0));
+        assert_eq!((boid.x_pos, boid.y_pos), (0.0, 8.0));
+        assert_eq!((boid.x_vel, boid.y_vel), (0.0, -4.0));
+
+        // glancing off the side rather than hitting it head on keeps some of the sideways speed
+        let mut boid = Boid::new(9.0, 9.0, 2.0, 2.0);
+        arena.contain(&mut boid, (7.0, 7.0));
+        assert!(arena.contains((boid.x_pos, boid.y_pos)));
+        assert!(boid.x_vel < 0.0 && boid.y_vel < 0.0);
+    }
+
+    #[test]
+    fn test_random_points_inside() {
+        let arena = Arena::polygon(vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)]).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let circle = Arena::circle((5.0, 5.0), 3.0).unwrap();
+        for _ in 0..100 {
+            assert!(arena.contains(arena.random_point(&mut rng)));
+            assert!(circle.contains(circle.random_point(&mut rng)));
+        }
+    }
+
+    #[test]
+    fn test_parse_and_degenerate_polygons() {
+        assert_eq!(Arena::parse("polygon:0,0;10,0;10,10;0,10").unwrap(), square());
+        assert!(Arena::parse("polygon:0,0;10,0").is_err());
+        assert!(Arena::parse("polygon:0,0;a,0;10,10").is_err());
+        assert!(Arena::parse("square:10").is_err());
+        // every vertex on one line
+        assert_eq!(Arena::polygon(vec![(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]).unwrap_err().errors, vec![CreationError::ArenaHasNoArea]);
+        assert_eq!(Arena::parse("circle:25").unwrap(), Arena::circle((0.0, 0.0), 25.0).unwrap());
+        assert!(Arena::parse("circle:0").is_err());
+        assert_eq!(WallResponse::parse("slide").unwrap(), WallResponse::Slide);
+        assert!(WallResponse::parse("stick").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Sound for what happens in the simulation, without tying it to any one audio library.
+//! [`SoundCues`] reads the events of each step, from `Flock::events` and, in the herding game,
+//! `HerdingGame::events`, and picks out the ones worth hearing: the predator catching a boid, two
+//! large clusters merging, and the player herding boids into the goal or the game ending. It hands
+//! each to a [`SoundPlayer`], which the app implements with whatever plays its sounds. The crate
+//! has no audio of its own: the Bevy adapter is built without `bevy_audio`, so a Bevy app would
+//! implement it by spawning an `AudioBundle` for the sound's file at the given volume.
+//!
+//! The cues are kept subtle: each sound plays at most once a step however many events set it off,
+//! and not again until `min_ticks_between_repeats` have passed. None of this runs inside
+//! `Flock::step`, so a flock steps the same with sound as without.
+
+use crate::events::FlockEvent;
+use crate::herding::GameEvent;
+use crate::{check_float_between_zero_and_one, InvalidFlockConfig};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sound {
+    Catch,
+    Merge,
+    Herded,
+    GameWon,
+    TimeUp,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 5] = [Sound::Catch, Sound::Merge, Sound::Herded, Sound::GameWon, Sound::TimeUp];
+
+    /// e.g. for the file the front end loads the sound from
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Sound::Catch => "catch",
+            Sound::Merge => "merge",
+            Sound::Herded => "herded",
+            Sound::GameWon => "game-won",
+            Sound::TimeUp => "time-up",
+        };
+    }
+
+    // how loud the sound is at full volume; the ones that happen all the time are the quietest
+    fn loudness(&self) -> f32 {
+        return match self {
+            Sound::Catch => 0.5,
+            Sound::Merge => 0.3,
+            Sound::Herded => 0.4,
+            Sound::GameWon | Sound::TimeUp => 0.8,
+        };
+    }
+
+    fn idx(&self) -> usize {
+        return Sound::ALL.iter().position(|sound| sound == self).unwrap_or(0);
+    }
+}
+
+/// plays sounds for the front end
+pub trait SoundPlayer {
+    /// `volume` is between 0 and 1
+    fn play(&mut self, sound: Sound, volume: f32);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioSettings {
+    pub volume: f32, // between 0 and 1
+    pub muted: bool,
+    pub large_merge_size: usize, // merges that make a cluster smaller than this go unheard
+    pub min_ticks_between_repeats: u32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        return AudioSettings { volume: 0.5, muted: false, large_merge_size: 20, min_ticks_between_repeats: 10 };
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SoundCues {
+    pub settings: AudioSettings,
+    ticks_elapsed: u32,
+    last_played: [Option<u32>; 5], // the tick each sound last played in, in the order of `Sound::ALL`
+}
+
+impl SoundCues {
+    pub fn new(settings: AudioSettings) -> Result<SoundCues, InvalidFlockConfig> {
+        if let Some(error) = check_float_between_zero_and_one(settings.volume, "volume".to_string()) {
+            return Err(InvalidFlockConfig { errors: vec![error] });
+        }
+        return Ok(SoundCues { settings, ticks_elapsed: 0, last_played: [None; 5] });
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.settings.muted = !self.settings.muted;
+    }
+
+    /// plays the sounds for a step's events; call once after every step. Outside the herding game
+    /// there are no `game_events`
+    pub fn play(&mut self, flock_events: &[FlockEvent], game_events: &[GameEvent], player: &mut impl SoundPlayer) {
+        self.ticks_elapsed = self.ticks_elapsed.saturating_add(1);
+        let flock_sounds = flock_events.iter().filter_map(|event| match event {
+            FlockEvent::BoidCaught { .. } => Some(Sound::Catch),
+            FlockEvent::ClusterMerge { main_size, part_size, .. } if main_size + part_size >= self.settings.large_merge_size => Some(Sound::Merge),
+            _ => None,
+        });
+        let game_sounds = game_events.iter().map(|event| match event {
+            GameEvent::BoidsHerded(_) => Sound::Herded,
+            GameEvent::Won => Sound::GameWon,
+            GameEvent::TimeUp => Sound::TimeUp,
+        });
+        let sounds: Vec<Sound> = flock_sounds.chain(game_sounds).collect();
+        // still kept track of while muted, so unmuting doesn't set off a burst of sounds
+        for sound in Sound::ALL.into_iter().filter(|sound| sounds.contains(sound)) {
+            let last_played = &mut self.last_played[sound.idx()];
+            if last_played.is_some_and(|tick| self.ticks_elapsed - tick < self.settings.min_ticks_between_repeats) {
+                continue;
+            }
+            *last_played = Some(self.ticks_elapsed);
+            if !self.settings.muted && self.settings.volume > 0.0 {
+                player.play(sound, sound.loudness() * self.settings.volume);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoidId, CreationError};
+
+    // writes down what it's asked to play
+    #[derive(Default)]
+    struct RecordingPlayer {
+        played: Vec<(Sound, f32)>,
+    }
+
+    impl SoundPlayer for RecordingPlayer {
+        fn play(&mut self, sound: Sound, volume: f32) {
+            self.played.push((sound, volume));
+        }
+    }
+
+    fn caught(id: u64) -> FlockEvent {
+        return FlockEvent::BoidCaught { boid: BoidId(id), position: (0.0, 0.0) };
+    }
+
+    #[test]
+    fn test_sounds_played_once_per_step_and_not_repeated_too_soon() {
+        let mut cues = SoundCues::new(AudioSettings { volume: 1.0, min_ticks_between_repeats: 3, ..AudioSettings::default() }).unwrap();
+        let mut player = RecordingPlayer::default();
+        cues.play(&[caught(1), caught(2)], &[], &mut player);
+        cues.play(&[caught(3)], &[], &mut player);
+        cues.play(&[], &[], &mut player);
+        cues.play(&[caught(4)], &[GameEvent::BoidsHerded(2), GameEvent::Won], &mut player);
+        assert_eq!(player.played, vec![(Sound::Catch, 0.5), (Sound::Catch, 0.5), (Sound::Herded, 0.4), (Sound::GameWon, 0.8)]);
+    }
+
+    #[test]
+    fn test_only_large_merges_heard() {
+        let mut cues = SoundCues::new(AudioSettings::default()).unwrap();
+        let mut player = RecordingPlayer::default();
+        let merge = |main_size, part_size| FlockEvent::ClusterMerge { into: 0, main: 0, main_size, part: 1, part_size };
+        cues.play(&[merge(10, 5)], &[], &mut player);
+        assert!(player.played.is_empty());
+        cues.play(&[merge(15, 5)], &[], &mut player);
+        assert_eq!(player.played, vec![(Sound::Merge, 0.15)]);
+    }
+
+    #[test]
+    fn test_muted_cues_play_nothing() {
+        let mut cues = SoundCues::new(AudioSettings::default()).unwrap();
+        cues.toggle_mute();
+        let mut player = RecordingPlayer::default();
+        cues.play(&[caught(1)], &[GameEvent::TimeUp], &mut player);
+        assert!(player.played.is_empty());
+
+        let errors = SoundCues::new(AudioSettings { volume: 2.0, ..AudioSettings::default() }).unwrap_err().errors;
+        assert_eq!(errors, vec![CreationError::FactorShouldBeLessThanOne("volume".to_string())]);
+    }
+}
@@ -0,0 +1,1635 @@
+//! Adapter for running the flock inside an existing Bevy app.
+//!
+//! Insert a `FlockResource`, spawn one entity per boid tagged with its id in a `BoidEntity`,
+//! and add the `BoidsPlugin`; the flock is stepped once per `Update` and each
+//! entity's `Transform` is synced to its boid.
+//!
+//! If the flock has a predator, it's steered with the arrow keys or WASD, and an entity tagged with
+//! `PredatorEntity` follows it around.
+//!
+//! Insert a `CameraResource` too, and tag the app's camera with `CameraEntity`, for the camera to
+//! follow a boid or the flock or zoom to fit it, as in `camera.rs`; C cycles through the modes.
+//! With a `DragResource` as well, boids can be picked up with the left mouse button, dragged
+//! around and flung, as in `drag.rs`; the boid picked up is the one the camera follows.
+//!
+//! Q and A nudge cohesion up and down, W and S adhesion and E and D repulsion, by 0.01 or, with
+//! shift held, by 0.1; the window's title confirms the new value. While the player is steering a
+//! predator, WASD steers it instead, so the factors are left alone.
+//!
+//! The number keys 1 to 9 switch the rules on and off, in the order of `Rule::ALL`, and the
+//! window's title shows which are on.
+//!
+//! Space pauses the flock and carries on, and . (full stop) steps it once while it's paused, on the
+//! same keys as the terminal front end in `terminal.rs`.
+//!
+//! With an `EditorResource` and a `CameraResource`, the flock's obstacles and walls can be edited
+//! while it's paused: a right click places an obstacle, or takes away the obstacle or wall under
+//! the cursor, and a right drag draws a wall. F5 saves the layout to the editor's file and F9
+//! loads it back; see `layout.rs`.
+//!
+//! With a `ProfileResource`, F6 saves the factors, radii, speed limits and rules that are on as a
+//! new profile in its file, F7 picks the next profile saved there, listing them all, and F8 gives
+//! the flock the profile picked; see `profile.rs`.
+//!
+//! With a `RewindResource`, each step is recorded before it's taken, and holding backspace winds
+//! the flock back a step a frame instead of stepping it, as in `rewind.rs`; letting go carries on
+//! from there.
+//!
+//! With a `HerdingResource`, the flock is stepped through its `HerdingGame`, the window's title
+//! keeps the time left, the boids herded and the score, and the `BoidsGizmosPlugin` draws the goal.
+//!
+//! With a `ShuffleResource`, R gives the flock a random set of parameters and shift-R goes back to
+//! the set before, as in `shuffle.rs`; the window's title shows the current set and its seed.
+//!
+//! Add the `BoidsGizmosPlugin` as well to draw the flock, and the world around it, with Bevy's
+//! gizmos through a `GizmoRenderer`, in the colours of a `ThemeResource`. With a
+//! `BoidSpriteResource`, it gives each boid a sprite of that texture instead of a triangle, tinted
+//! in its colour. Apps that draw their own sprites for the boids' entities can leave it out.
+//!
+//! F2 adds the flock's polarization and mean speed to the window's title, and with the
+//! `BoidsGizmosPlugin` and a `CameraResource` plots them over the last few thousand steps in the
+//! top left of the view, so a change in how the flock behaves shows up as the factors are nudged.
+//!
+//! F3 adds the frame rate and how long each part of the last steps took, from `Flock::step_timings`,
+//! to the window's title, and with the `BoidsGizmosPlugin` and a `CameraResource` a graph of them
+//! in the bottom left of the view, stacked by part with the time taken drawing on top.
+//!
+//! H or F1 shows a help line for every control in the window's title, with the current value of
+//! what each changes, a few at a time so they fit; pressing it again turns the page, and closes the
+//! help after the last. Every system looks its keys up in `BINDINGS`, so the help is generated from
+//! the same list and a new binding shows up in it without any more work.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use bevy::prelude::*;
+use crate::arena::ArenaShape;
+use crate::camera;
+use crate::drag::Drag;
+use crate::food::FoodSource;
+use crate::force_field::ForceSource;
+use crate::herding::{GameEvent, HerdingGame};
+use crate::layout::Layout;
+use crate::obstacle::{Obstacle, ObstacleMotion, Obstacles};
+use crate::predator::Predator;
+use crate::profile::{Profile, Profiles};
+use crate::render::{draw_flock, BoidColor, Bounds, ColorMode, Renderer};
+use crate::rewind::RewindBuffer;
+use crate::shuffle::ParameterShuffle;
+use crate::stats;
+use crate::svg::{triangle_for_boid, BOID_LENGTH, PREDATOR_LENGTH};
+use crate::theme::{self, Rgb, Theme};
+use crate::timing::StepTimings;
+use crate::walls::Wall;
+use crate::{Boid, BoidId, Factor, Flock, Rule};
+
+#[derive(Resource, Debug)]
+pub struct FlockResource(pub Flock);
+
+/// links an entity to the boid with this id, which it keeps following however the boids before it
+/// in the flock come and go
+#[derive(Component, Debug)]
+pub struct BoidEntity {
+    pub id: BoidId,
+}
+
+/// marks the entity that shows the flock's predator
+#[derive(Component, Debug)]
+pub struct PredatorEntity;
+
+#[derive(Resource, Debug)]
+pub struct CameraResource(pub camera::Camera);
+
+/// marks the app's camera, which is moved and scaled to match the `CameraResource`. At a scale of
+/// 1 a Bevy camera shows a world unit per pixel, so the scale is the camera's view width over the
+/// width of the window in pixels
+#[derive(Component, Debug)]
+pub struct CameraEntity {
+    pub window_width: f32, // in pixels
+}
+
+#[derive(Resource, Debug)]
+pub struct DragResource(pub Drag);
+
+#[derive(Resource, Debug)]
+pub struct ShuffleResource(pub ParameterShuffle);
+
+#[derive(Resource, Debug)]
+pub struct RewindResource(pub RewindBuffer);
+
+/// the game the flock is stepped through, from `HerdingGame::start`
+#[derive(Resource, Debug)]
+pub struct HerdingResource(pub HerdingGame);
+
+/// the layout editor's file, and the wall being drawn
+#[derive(Resource, Debug)]
+pub struct EditorResource {
+    pub path: PathBuf, // e.g. `layout::default_path`
+    pub obstacle_radius: f32, // of each obstacle placed
+    drawing: Option<((f32, f32), (f32, f32))>, // the wall being drawn, from where the drag started to the cursor
+}
+
+impl EditorResource {
+    pub fn new(path: PathBuf, obstacle_radius: f32) -> EditorResource {
+        return EditorResource { path, obstacle_radius, drawing: None };
+    }
+
+    /// the wall being drawn, if the player is drawing one
+    pub fn drawing(&self) -> Option<((f32, f32), (f32, f32))> {
+        return self.drawing;
+    }
+}
+
+/// the profiles file, what's in it and the profile picked with the keys
+#[derive(Resource, Debug)]
+pub struct ProfileResource {
+    pub path: PathBuf, // e.g. `profile::default_path`
+    pub profiles: Profiles,
+    selected: Option<usize>, // in `profiles`
+}
+
+impl ProfileResource {
+    /// with the profiles already saved at `path`, if there are any
+    pub fn load(path: PathBuf) -> anyhow::Result<ProfileResource> {
+        let profiles = Profiles::load(&path)?;
+        return Ok(ProfileResource { path, profiles, selected: None });
+    }
+
+    pub fn selected(&self) -> Option<&Profile> {
+        return self.profiles.profiles.get(self.selected?);
+    }
+
+    /// saves the flock's parameters under `name` and writes the file, e.g. for apps that let the player type a name
+    pub fn save_as(&mut self, name: &str, flock: &Flock) -> anyhow::Result<()> {
+        self.profiles.save(Profile::from_flock(name, flock));
+        self.selected = self.profiles.names().position(|other| other == name);
+        return self.profiles.write(&self.path);
+    }
+
+    /// picks the profile after the one picked, or the first, reading the file again in case it's
+    /// been edited or had profiles shared into it
+    fn select_next(&mut self) -> anyhow::Result<()> {
+        self.profiles = Profiles::load(&self.path)?;
+        let num_profiles = self.profiles.profiles.len();
+        self.selected = (num_profiles > 0).then(|| self.selected.map_or(0, |selected| (selected + 1) % num_profiles));
+        return Ok(());
+    }
+}
+
+// how near the cursor an obstacle or wall has to be to be picked, and how long a drag has to be
+// to draw a wall rather than click, as a share of the camera's view width
+const EDITOR_PICK_SHARE_OF_VIEW: f32 = 0.01;
+
+/// whether the flock is paused, when it's only stepped a step at a time from the keys
+#[derive(Resource, Debug, Default)]
+pub struct PauseResource {
+    pub paused: bool,
+}
+
+/// the flock's polarization and mean speed after each of the last steps, kept whether or not they're shown
+#[derive(Resource, Debug, Default)]
+pub struct OrderPlotResource {
+    pub visible: bool,
+    samples: VecDeque<OrderSample>, // the oldest first
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrderSample {
+    pub polarization: f32,
+    pub mean_speed: f32,
+}
+
+// how many steps the plot goes back
+const ORDER_PLOT_LEN: usize = 3000;
+
+impl OrderPlotResource {
+    /// the steps the plot is of, the oldest first
+    pub fn samples(&self) -> impl Iterator<Item = &OrderSample> {
+        return self.samples.iter();
+    }
+
+    fn record(&mut self, flock: &Flock) {
+        if self.samples.len() == ORDER_PLOT_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(OrderSample { polarization: stats::polarization(flock.boids()), mean_speed: stats::mean_speed(flock.boids()) });
+    }
+
+    /// e.g. "polarization 0.93 · mean speed 2.10", after the latest step
+    pub fn readout(&self) -> Option<String> {
+        return self.samples.back().map(|sample| format!("polarization {:.2} · mean speed {:.2}", sample.polarization, sample.mean_speed));
+    }
+}
+
+/// the frame rate and step timings of the last frames, while they're shown
+#[derive(Resource, Debug, Default)]
+pub struct PerformanceResource {
+    pub visible: bool,
+    frames: VecDeque<FrameTimings>, // the oldest first
+    drawing: Duration, // how long the `BoidsGizmosPlugin` took to draw the latest frame
+}
+
+/// how long a frame took, and its parts
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTimings {
+    pub frame: Duration, // from the start of the frame before, so its inverse is the frame rate
+    pub step: StepTimings, // of the last step taken, which is older than the frame while paused
+    pub drawing: Duration,
+}
+
+// how many frames the readout averages over and the graph shows
+const PERFORMANCE_HISTORY_LEN: usize = 120;
+
+impl PerformanceResource {
+    /// the frames the readout and graph are of, the oldest first
+    pub fn frames(&self) -> impl Iterator<Item = &FrameTimings> {
+        return self.frames.iter();
+    }
+
+    fn record(&mut self, frame: FrameTimings) {
+        if self.frames.len() == PERFORMANCE_HISTORY_LEN {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// e.g. "60 fps · step 1.20 ms (neighbours 0.80, rules 0.20, moving 0.10, clusters 0.10) · drawing 0.30 ms",
+    /// each averaged over the frames kept. The frame rate is left out if the app has no clock
+    pub fn readout(&self) -> String {
+        let num_frames = self.frames.len().max(1) as u32;
+        let mut step = StepTimings::default();
+        for frame in &self.frames {
+            step += frame.step;
+        }
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0 / num_frames as f64;
+        let parts: Vec<String> = step.parts().iter().map(|(name, duration)| format!("{} {:.2}", name, millis(*duration))).collect();
+        let mut readout = format!("step {:.2} ms ({}) · drawing {:.2} ms", millis(step.total()), parts.join(", "),
+                                  millis(self.frames.iter().map(|frame| frame.drawing).sum()));
+        let total_frame_time: Duration = self.frames.iter().map(|frame| frame.frame).sum();
+        if total_frame_time > Duration::ZERO {
+            readout = format!("{:.0} fps · {}", self.frames.len() as f64 / total_frame_time.as_secs_f64(), readout);
+        }
+        return readout;
+    }
+}
+
+/// what the window's title shows, as the apps this runs in may not draw text: the help while it's
+/// open, and otherwise the readouts and the latest change made from the keys, e.g. a factor's new value
+#[derive(Resource, Debug, Default)]
+pub struct HudResource {
+    pub status: Option<String>,
+    pub readouts: Vec<String>, // kept up to date every frame, e.g. the game's clock, and cleared once shown
+    pub help_page: Option<usize>, // the page of the help that's open, if it is
+}
+
+// how many of the help's lines fit in the title at once
+pub const HELP_LINES_PER_PAGE: usize = 5;
+
+/// what a key (or mouse button) does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    ToggleHelp,
+    SpeedUpPredator, // while the player is steering a predator
+    SlowDownPredator,
+    TurnPredatorLeft,
+    TurnPredatorRight,
+    RaiseFactor(Factor), // by a fine step, or a coarse one with shift held
+    LowerFactor(Factor),
+    ToggleRule(Rule),
+    CycleCameraMode,
+    DragBoid,
+    ShuffleParameters, // with shift held, goes back to the set before
+    Rewind, // while held
+    TogglePause,
+    StepOnce, // while paused
+    EditLayout, // while paused
+    SaveLayout,
+    LoadLayout,
+    SaveProfile,
+    SelectProfile,
+    ApplyProfile,
+    ToggleOrderPlot,
+    TogglePerformance,
+}
+
+impl Action {
+    pub fn description(&self) -> String {
+        return match self {
+            Action::ToggleHelp => "show the next page of this help, or hide it after the last".to_string(),
+            Action::SpeedUpPredator => "speed up the predator you're steering".to_string(),
+            Action::SlowDownPredator => "slow down the predator you're steering".to_string(),
+            Action::TurnPredatorLeft => "turn the predator you're steering left".to_string(),
+            Action::TurnPredatorRight => "turn the predator you're steering right".to_string(),
+            Action::RaiseFactor(factor) => format!("raise {} by {}, or {} with shift", factor.name(), FINE_FACTOR_STEP, COARSE_FACTOR_STEP),
+            Action::LowerFactor(factor) => format!("lower {} by {}, or {} with shift", factor.name(), FINE_FACTOR_STEP, COARSE_FACTOR_STEP),
+            Action::ToggleRule(rule) => format!("switch {} on or off", rule.name()),
+            Action::CycleCameraMode => "cycle the camera mode".to_string(),
+            Action::DragBoid => "pick up, drag and fling a boid".to_string(),
+            Action::ShuffleParameters => "shuffle the parameters, or go back to the set before with shift".to_string(),
+            Action::Rewind => "hold to rewind".to_string(),
+            Action::TogglePause => "pause or carry on".to_string(),
+            Action::StepOnce => "step once while paused".to_string(),
+            Action::EditLayout => "while paused, click to place or take away an obstacle or wall, or drag to draw a wall".to_string(),
+            Action::SaveLayout => "save the obstacles and walls".to_string(),
+            Action::LoadLayout => "load the saved obstacles and walls".to_string(),
+            Action::SaveProfile => "save the parameters as a new profile".to_string(),
+            Action::SelectProfile => "pick the next saved profile".to_string(),
+            Action::ApplyProfile => "apply the profile picked".to_string(),
+            Action::ToggleOrderPlot => "show or hide the plot of polarization and mean speed".to_string(),
+            Action::TogglePerformance => "show or hide the frame rate and how long each part of a step takes".to_string(),
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Keys(&'static [KeyCode]), // any of them
+    Mouse(MouseButton),
+}
+
+impl Input {
+    /// e.g. "Up/W"
+    pub fn name(&self) -> String {
+        return match self {
+            Input::Keys(keys) => keys.iter().map(|key| {
+                let name = format!("{:?}", key);
+                return ["Key", "Digit", "Arrow"].into_iter().find_map(|prefix| name.strip_prefix(prefix)).unwrap_or(&name).to_string();
+            }).collect::<Vec<String>>().join("/"),
+            Input::Mouse(button) => format!("{:?} mouse button", button).to_lowercase(),
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Binding {
+    pub input: Input,
+    pub action: Action,
+}
+
+const fn keys(keys: &'static [KeyCode], action: Action) -> Binding {
+    return Binding { input: Input::Keys(keys), action };
+}
+
+/// every control, in the order the help lists them. The systems look their keys up here, so the help can't disagree with them
+pub const BINDINGS: [Binding; 34] = [
+    keys(&[KeyCode::KeyH, KeyCode::F1], Action::ToggleHelp),
+    keys(&[KeyCode::ArrowUp, KeyCode::KeyW], Action::SpeedUpPredator),
+    keys(&[KeyCode::ArrowDown, KeyCode::KeyS], Action::SlowDownPredator),
+    keys(&[KeyCode::ArrowLeft, KeyCode::KeyA], Action::TurnPredatorLeft),
+    keys(&[KeyCode::ArrowRight, KeyCode::KeyD], Action::TurnPredatorRight),
+    // while the player is steering a predator, WASD steers it instead
+    keys(&[KeyCode::KeyQ], Action::RaiseFactor(Factor::Cohesion)),
+    keys(&[KeyCode::KeyA], Action::LowerFactor(Factor::Cohesion)),
+    keys(&[KeyCode::KeyW], Action::RaiseFactor(Factor::Adhesion)),
+    keys(&[KeyCode::KeyS], Action::LowerFactor(Factor::Adhesion)),
+    keys(&[KeyCode::KeyE], Action::RaiseFactor(Factor::Repulsion)),
+    keys(&[KeyCode::KeyD], Action::LowerFactor(Factor::Repulsion)),
+    keys(&[KeyCode::Digit1], Action::ToggleRule(Rule::ALL[0])),
+    keys(&[KeyCode::Digit2], Action::ToggleRule(Rule::ALL[1])),
+    keys(&[KeyCode::Digit3], Action::ToggleRule(Rule::ALL[2])),
+    keys(&[KeyCode::Digit4], Action::ToggleRule(Rule::ALL[3])),
+    keys(&[KeyCode::Digit5], Action::ToggleRule(Rule::ALL[4])),
+    keys(&[KeyCode::Digit6], Action::ToggleRule(Rule::ALL[5])),
+    keys(&[KeyCode::Digit7], Action::ToggleRule(Rule::ALL[6])),
+    keys(&[KeyCode::Digit8], Action::ToggleRule(Rule::ALL[7])),
+    keys(&[KeyCode::Digit9], Action::ToggleRule(Rule::ALL[8])),
+    keys(&[KeyCode::KeyC], Action::CycleCameraMode),
+    Binding { input: Input::Mouse(MouseButton::Left), action: Action::DragBoid },
+    keys(&[KeyCode::KeyR], Action::ShuffleParameters),
+    keys(&[KeyCode::Backspace], Action::Rewind),
+    keys(&[KeyCode::Space], Action::TogglePause),
+    keys(&[KeyCode::Period], Action::StepOnce),
+    Binding { input: Input::Mouse(MouseButton::Right), action: Action::EditLayout },
+    keys(&[KeyCode::F5], Action::SaveLayout),
+    keys(&[KeyCode::F9], Action::LoadLayout),
+    keys(&[KeyCode::F6], Action::SaveProfile),
+    keys(&[KeyCode::F7], Action::SelectProfile),
+    keys(&[KeyCode::F8], Action::ApplyProfile),
+    keys(&[KeyCode::F2], Action::ToggleOrderPlot),
+    keys(&[KeyCode::F3], Action::TogglePerformance),
+];
+
+fn keys_for(action: Action) -> &'static [KeyCode] {
+    return BINDINGS
+        .iter()
+        .find_map(|binding| match binding.input {
+            Input::Keys(keys) if binding.action == action => Some(keys),
+            _ => None,
+        })
+        .unwrap_or_default();
+}
+
+fn mouse_button_for(action: Action) -> Option<MouseButton> {
+    return BINDINGS.iter().find_map(|binding| match binding.input {
+        Input::Mouse(button) if binding.action == action => Some(button),
+        _ => None,
+    });
+}
+
+fn just_pressed(keys: &ButtonInput<KeyCode>, action: Action) -> bool {
+    return keys.any_just_pressed(keys_for(action).iter().copied());
+}
+
+fn pressed(keys: &ButtonInput<KeyCode>, action: Action) -> bool {
+    return keys.any_pressed(keys_for(action).iter().copied());
+}
+
+fn shift_held(keys: &ButtonInput<KeyCode>) -> bool {
+    return keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+}
+
+/// a line for each control, with the current value of what it changes where there is one, e.g.
+/// "Q: raise cohesion by 0.01, or 0.1 with shift (now 0.50)"
+pub fn help_lines(flock: &Flock, camera: Option<&camera::Camera>, shuffle: Option<&ParameterShuffle>, rewind: Option<&RewindBuffer>, paused: bool) -> Vec<String> {
+    return BINDINGS.iter().map(|binding| {
+        let current = match binding.action {
+            Action::RaiseFactor(factor) | Action::LowerFactor(factor) => Some(format!("{:.2}", flock.factor(factor))),
+            Action::ToggleRule(rule) => Some((if flock.is_rule_enabled(rule) { "on" } else { "off" }).to_string()),
+            Action::CycleCameraMode => camera.map(|camera| camera.mode.name().to_string()),
+            Action::ShuffleParameters => shuffle.and_then(|shuffle| shuffle.current()).map(|parameters| format!("seed {}", parameters.seed)),
+            Action::Rewind => rewind.map(|rewind| format!("{} steps kept", rewind.len())),
+            Action::TogglePause => Some((if paused { "paused" } else { "running" }).to_string()),
+            Action::TogglePerformance => Some((if flock.step_timings().is_some() { "shown" } else { "hidden" }).to_string()),
+            _ => None,
+        };
+        let line = format!("{}: {}", binding.input.name(), binding.action.description());
+        return match current {
+            Some(current) => format!("{} (now {})", line, current),
+            None => line,
+        };
+    }).collect();
+}
+
+pub struct BoidsPlugin;
+
+impl Plugin for BoidsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HudResource>();
+        app.init_resource::<PauseResource>();
+        app.init_resource::<OrderPlotResource>();
+        app.init_resource::<PerformanceResource>();
+        app.add_systems(Update, (toggle_help, steer_predator, adjust_factors, cycle_camera_mode, toggle_rules, shuffle_parameters, drag_boids, toggle_pause, toggle_order_plot, toggle_performance,
+                                 save_or_load_layout, manage_profiles, edit_layout, step_flock, record_performance, sync_boid_transforms, sync_predator_transform, move_camera, show_hud).chain());
+    }
+}
+
+pub fn toggle_help(keys: Option<Res<ButtonInput<KeyCode>>>, mut hud: ResMut<HudResource>) {
+    if keys.is_some_and(|keys| just_pressed(&keys, Action::ToggleHelp)) {
+        let num_pages = BINDINGS.len().div_ceil(HELP_LINES_PER_PAGE);
+        hud.help_page = match hud.help_page {
+            None => Some(0),
+            Some(page) if page + 1 < num_pages => Some(page + 1),
+            Some(_) => None,
+        };
+        if hud.help_page.is_none() {
+            // rather than leaving the help in the title once it's closed
+            hud.status = Some(format!("{} for help", Input::Keys(keys_for(Action::ToggleHelp)).name()));
+        }
+    }
+}
+
+/// up and down (or W and S) speed the predator up and slow it down, and left and right (or A and D) turn it
+pub fn steer_predator(keys: Option<Res<ButtonInput<KeyCode>>>, mut flock: ResMut<FlockResource>) {
+    // apps without Bevy's input plugin have no keys to read
+    let Some(keys) = keys else {
+        return;
+    };
+    let Some(predator) = flock.0.predator_mut() else {
+        return;
+    };
+    let control = |forward: Action, back: Action| {
+        return f32::from(pressed(&keys, forward)) - f32::from(pressed(&keys, back));
+    };
+    predator.set_controls(control(Action::SpeedUpPredator, Action::SlowDownPredator), control(Action::TurnPredatorLeft, Action::TurnPredatorRight));
+}
+
+pub fn cycle_camera_mode(keys: Option<Res<ButtonInput<KeyCode>>>, camera: Option<ResMut<CameraResource>>) {
+    let (Some(keys), Some(mut camera)) = (keys, camera) else {
+        return;
+    };
+    if just_pressed(&keys, Action::CycleCameraMode) {
+        camera.0.cycle_mode();
+    }
+}
+
+const FINE_FACTOR_STEP: f32 = 0.01;
+const COARSE_FACTOR_STEP: f32 = 0.1; // with shift held
+
+pub fn adjust_factors(keys: Option<Res<ButtonInput<KeyCode>>>, mut hud: ResMut<HudResource>, mut flock: ResMut<FlockResource>) {
+    let Some(keys) = keys else {
+        return;
+    };
+    // WASD steers a predator the player controls
+    if flock.0.predator().is_some_and(|predator| predator.hunting().is_none()) {
+        return;
+    }
+    let step = if shift_held(&keys) { COARSE_FACTOR_STEP } else { FINE_FACTOR_STEP };
+    for factor in [Factor::Cohesion, Factor::Adhesion, Factor::Repulsion] {
+        let change = match (just_pressed(&keys, Action::RaiseFactor(factor)), just_pressed(&keys, Action::LowerFactor(factor))) {
+            (true, false) => step,
+            (false, true) => -step,
+            _ => continue,
+        };
+        // a change that would take the factor out of range is refused, and it stays where it was
+        let confirmation = match flock.0.adjust_factor(factor, change) {
+            Ok(value) => format!("{} {:.2}", factor.name(), value),
+            Err(_) => format!("{} stays at {:.2}, as it should be between 0 and 1", factor.name(), flock.0.factor(factor)),
+        };
+        hud.status = Some(confirmation);
+    }
+}
+
+pub fn toggle_rules(keys: Option<Res<ButtonInput<KeyCode>>>, mut hud: ResMut<HudResource>, mut flock: ResMut<FlockResource>) {
+    let Some(keys) = keys else {
+        return;
+    };
+    let mut toggled = false;
+    for rule in Rule::ALL {
+        if just_pressed(&keys, Action::ToggleRule(rule)) {
+            flock.0.toggle_rule(rule);
+            toggled = true;
+        }
+    }
+    if toggled {
+        hud.status = Some(rules_summary(&flock.0));
+    }
+}
+
+/// e.g. "rules on: separation, cohesion | off: alignment"
+pub fn rules_summary(flock: &Flock) -> String {
+    let names = |enabled: bool| {
+        let names: Vec<&str> = Rule::ALL.into_iter().filter(|rule| flock.is_rule_enabled(*rule) == enabled).map(|rule| rule.name()).collect();
+        return if names.is_empty() { "none".to_string() } else { names.join(", ") };
+    };
+    return format!("rules on: {} | off: {}", names(true), names(false));
+}
+
+pub fn shuffle_parameters(keys: Option<Res<ButtonInput<KeyCode>>>, mut hud: ResMut<HudResource>, mut flock: ResMut<FlockResource>,
+                          shuffle: Option<ResMut<ShuffleResource>>) {
+    let (Some(keys), Some(mut shuffle)) = (keys, shuffle) else {
+        return;
+    };
+    if !just_pressed(&keys, Action::ShuffleParameters) {
+        return;
+    }
+    let parameters = if shift_held(&keys) {
+        shuffle.0.back(&mut flock.0).copied()
+    } else {
+        // only fails if the flock's minimum speed is above the shuffled maximum, in which case it's left as it was
+        shuffle.0.shuffle(&mut flock.0).ok().copied()
+    };
+    if let Some(parameters) = parameters {
+        hud.status = Some(parameters.to_string());
+    }
+}
+
+pub fn drag_boids(mouse: Option<Res<ButtonInput<MouseButton>>>, windows: Query<&Window>, mut flock: ResMut<FlockResource>,
+                  drag: Option<ResMut<DragResource>>, camera: Option<ResMut<CameraResource>>) {
+    let (Some(mouse), Some(mut drag), Some(mut camera), Some(button)) = (mouse, drag, camera, mouse_button_for(Action::DragBoid)) else {
+        return;
+    };
+    if mouse.just_released(button) {
+        drag.0.release(&mut flock.0);
+        return;
+    }
+    let Some(point) = cursor_in_world(&windows, &camera.0) else {
+        return;
+    };
+    if mouse.just_pressed(button) {
+        drag.0.press(&mut flock.0, point, &mut camera.0);
+    } else if mouse.pressed(button) {
+        drag.0.drag(&mut flock.0, point);
+    }
+}
+
+/// where the cursor is in the world, if it's over a window
+fn cursor_in_world(windows: &Query<&Window>, camera: &camera::Camera) -> Option<(f32, f32)> {
+    let (window, cursor) = windows.iter().find_map(|window| window.cursor_position().map(|cursor| (window, cursor)))?;
+    // the window's y runs down the screen, but the world's runs up it
+    let (width, height) = (window.width(), window.height());
+    return Some(camera.screen_to_world((cursor.x, height - cursor.y), width as usize, height as usize));
+}
+
+pub fn save_or_load_layout(keys: Option<Res<ButtonInput<KeyCode>>>, mut hud: ResMut<HudResource>, mut flock: ResMut<FlockResource>,
+                           editor: Option<Res<EditorResource>>) {
+    let (Some(keys), Some(editor)) = (keys, editor) else {
+        return;
+    };
+    if just_pressed(&keys, Action::SaveLayout) {
+        hud.status = Some(match Layout::from_flock(&flock.0).save(&editor.path) {
+            Ok(()) => format!("layout saved to {}", editor.path.display()),
+            Err(error) => format!("couldn't save the layout: {:#}", error),
+        });
+    } else if just_pressed(&keys, Action::LoadLayout) {
+        hud.status = Some(match Layout::load(&editor.path) {
+            Ok(layout) => {
+                layout.apply(&mut flock.0);
+                format!("layout loaded from {}", editor.path.display())
+            }
+            Err(error) => format!("couldn't load the layout: {:#}", error),
+        });
+    }
+}
+
+pub fn manage_profiles(keys: Option<Res<ButtonInput<KeyCode>>>, mut hud: ResMut<HudResource>, mut flock: ResMut<FlockResource>,
+                       profiles: Option<ResMut<ProfileResource>>) {
+    let (Some(keys), Some(mut profiles)) = (keys, profiles) else {
+        return;
+    };
+    if just_pressed(&keys, Action::SaveProfile) {
+        let name = profiles.profiles.unused_name();
+        hud.status = Some(match profiles.save_as(&name, &flock.0) {
+            Ok(()) => format!("saved as '{}' in {}, where it can be renamed", name, profiles.path.display()),
+            Err(error) => format!("couldn't save the profile: {:#}", error),
+        });
+    } else if just_pressed(&keys, Action::SelectProfile) {
+        hud.status = Some(match profiles.select_next().map(|()| profiles.selected()) {
+            Ok(Some(profile)) => format!("picked '{}', {} to apply (profiles: {})", profile.name, Input::Keys(keys_for(Action::ApplyProfile)).name(),
+                                         profiles.profiles.names().collect::<Vec<&str>>().join(", ")),
+            Ok(None) => format!("no profiles saved yet, {} to save one", Input::Keys(keys_for(Action::SaveProfile)).name()),
+            Err(error) => format!("couldn't read the profiles: {:#}", error),
+        });
+    } else if just_pressed(&keys, Action::ApplyProfile) {
+        hud.status = Some(match profiles.selected() {
+            Some(profile) => match profile.apply(&mut flock.0) {
+                Ok(()) => format!("applied '{}'", profile.name),
+                Err(error) => format!("couldn't apply '{}': {:?}", profile.name, error.errors),
+            },
+            None => format!("pick a profile with {} first", Input::Keys(keys_for(Action::SelectProfile)).name()),
+        });
+    }
+}
+
+pub fn edit_layout(mouse: Option<Res<ButtonInput<MouseButton>>>, windows: Query<&Window>, mut hud: ResMut<HudResource>, mut flock: ResMut<FlockResource>,
+                   pause: Res<PauseResource>, editor: Option<ResMut<EditorResource>>, camera: Option<Res<CameraResource>>) {
+    let (Some(mouse), Some(mut editor), Some(camera), Some(button)) = (mouse, editor, camera, mouse_button_for(Action::EditLayout)) else {
+        return;
+    };
+    if !pause.paused {
+        editor.drawing = None;
+        if mouse.just_pressed(button) {
+            hud.status = Some(format!("pause with {} to edit the obstacles and walls", Input::Keys(keys_for(Action::TogglePause)).name()));
+        }
+        return;
+    }
+    let Some(point) = cursor_in_world(&windows, &camera.0) else {
+        return;
+    };
+    if mouse.just_pressed(button) {
+        editor.drawing = Some((point, point));
+    } else if mouse.pressed(button) {
+        if let Some((start, _)) = editor.drawing {
+            editor.drawing = Some((start, point));
+        }
+    }
+    if !mouse.just_released(button) {
+        return;
+    }
+    let Some((start, _)) = editor.drawing.take() else {
+        return;
+    };
+    let pick_dist = camera.0.view_width * EDITOR_PICK_SHARE_OF_VIEW;
+    let mut walls = flock.0.walls().cloned().unwrap_or_default();
+    if (point.0 - start.0).hypot(point.1 - start.1) >= pick_dist {
+        // long enough to be a wall rather than a click, so the ends are different
+        walls.walls.push(Wall::new(start, point).expect("the ends are finite and apart"));
+        flock.0.set_walls(walls);
+        hud.status = Some(format!("wall drawn from ({:.0}, {:.0}) to ({:.0}, {:.0})", start.0, start.1, point.0, point.1));
+        return;
+    }
+    let mut obstacles = flock.0.obstacles().cloned().unwrap_or_else(|| Obstacles::new(Vec::new()));
+    let obstacle_under_cursor = obstacles.positions()
+        .zip(&obstacles.obstacles)
+        .position(|(centre, obstacle)| (point.0 - centre.0).hypot(point.1 - centre.1) <= obstacle.radius + pick_dist);
+    if let Some(obstacle_idx) = obstacle_under_cursor {
+        obstacles.obstacles.remove(obstacle_idx);
+        flock.0.set_obstacles(obstacles);
+        hud.status = Some("obstacle taken away".to_string());
+    } else if let Some(wall_idx) = walls.walls.iter().position(|wall| wall.dist_to(point) <= pick_dist) {
+        walls.walls.remove(wall_idx);
+        flock.0.set_walls(walls);
+        hud.status = Some("wall taken away".to_string());
+    } else {
+        hud.status = Some(match Obstacle::new(point, editor.obstacle_radius, ObstacleMotion::Still) {
+            Ok(obstacle) => {
+                obstacles.obstacles.push(obstacle);
+                flock.0.set_obstacles(obstacles);
+                format!("obstacle placed at ({:.0}, {:.0})", point.0, point.1)
+            }
+            Err(error) => format!("couldn't place an obstacle: {:?}", error.errors),
+        });
+    }
+}
+
+pub fn toggle_pause(keys: Option<Res<ButtonInput<KeyCode>>>, mut hud: ResMut<HudResource>, mut pause: ResMut<PauseResource>) {
+    if keys.is_some_and(|keys| just_pressed(&keys, Action::TogglePause)) {
+        pause.paused = !pause.paused;
+        hud.status = Some(if pause.paused {
+            format!("paused, {} to step", Input::Keys(keys_for(Action::StepOnce)).name())
+        } else {
+            "running".to_string()
+        });
+    }
+}
+
+pub fn toggle_order_plot(keys: Option<Res<ButtonInput<KeyCode>>>, mut order_plot: ResMut<OrderPlotResource>) {
+    if keys.is_some_and(|keys| just_pressed(&keys, Action::ToggleOrderPlot)) {
+        order_plot.visible = !order_plot.visible;
+    }
+}
+
+/// shows or hides the performance readout, timing the flock's steps only while it's shown
+pub fn toggle_performance(keys: Option<Res<ButtonInput<KeyCode>>>, mut flock: ResMut<FlockResource>, mut performance: ResMut<PerformanceResource>) {
+    if keys.is_some_and(|keys| just_pressed(&keys, Action::TogglePerformance)) {
+        performance.visible = !performance.visible;
+        flock.0.set_step_timing(performance.visible);
+        if !performance.visible {
+            // so the next time it's shown doesn't start from frames long gone
+            *performance = PerformanceResource::default();
+        }
+    }
+}
+
+/// keeps the latest frame's timings and puts the readout in the title, while it's shown
+pub fn record_performance(time: Option<Res<Time>>, flock: Res<FlockResource>, mut hud: ResMut<HudResource>, mut performance: ResMut<PerformanceResource>) {
+    if !performance.visible {
+        return;
+    }
+    let frame = FrameTimings {
+        frame: time.map_or(Duration::ZERO, |time| time.delta()),
+        step: flock.0.step_timings().unwrap_or_default(),
+        drawing: performance.drawing,
+    };
+    performance.record(frame);
+    hud.readouts.push(performance.readout());
+}
+
+/// steps the flock, unless it's paused and the step key wasn't pressed, and adds the step to the order plot.
+/// Rewinding still works while paused
+pub fn step_flock(keys: Option<Res<ButtonInput<KeyCode>>>, mut hud: ResMut<HudResource>, mut flock: ResMut<FlockResource>, pause: Res<PauseResource>,
+                  mut rewind: Option<ResMut<RewindResource>>, mut herding: Option<ResMut<HerdingResource>>, mut order_plot: ResMut<OrderPlotResource>) {
+    if let Some(rewind) = rewind.as_mut() {
+        if keys.as_ref().is_some_and(|keys| pressed(keys, Action::Rewind)) {
+            // stays at the oldest step kept once there's no further back to go
+            rewind.0.rewind(&mut flock.0);
+            hud.status = Some(format!("rewinding, {} steps further back kept", rewind.0.len()));
+            return;
+        }
+    }
+    let stepping = !pause.paused || keys.is_some_and(|keys| just_pressed(&keys, Action::StepOnce));
+    if stepping {
+        if let Some(rewind) = rewind.as_mut() {
+            rewind.0.record(&flock.0);
+        }
+        match herding.as_mut() {
+            // the game leaves the flock where it is once it's over
+            Some(herding) => herding.0.step(&mut flock.0),
+            None => flock.0.step(),
+        }
+        order_plot.record(&flock.0);
+    }
+    if let Some(herding) = herding {
+        for event in herding.0.events().iter().filter(|_| stepping) {
+            match event {
+                GameEvent::Won => hud.status = Some(format!("every boid herded, scoring {}", herding.0.score())),
+                GameEvent::TimeUp => hud.status = Some(format!("time's up, scoring {}", herding.0.score())),
+                GameEvent::BoidsHerded(_) => {}
+            }
+        }
+        hud.readouts.push(herding_readout(&herding.0, flock.0.boids().len()));
+    }
+    if order_plot.visible {
+        hud.readouts.extend(order_plot.readout());
+    }
+}
+
+/// e.g. "1200 ticks left · 3/20 herded · score 300"
+pub fn herding_readout(game: &HerdingGame, flock_size: usize) -> String {
+    return format!("{} ticks left · {}/{} herded · score {}", game.ticks_remaining(), game.num_herded(), flock_size, game.score());
+}
+
+pub fn sync_boid_transforms(flock: Res<FlockResource>, mut boid_entities: Query<(&BoidEntity, &mut Transform)>) {
+    for (boid_entity, mut transform) in &mut boid_entities {
+        // the entity may outlive its boid, in which case it's left where it is
+        let Some(boid) = flock.0.boid(boid_entity.id) else {
+            continue;
+        };
+        transform.translation.x = boid.x_pos;
+        transform.translation.y = boid.y_pos;
+        if boid.x_vel != 0.0 || boid.y_vel != 0.0 {
+            // point the entity along the boid's heading
+            transform.rotation = Quat::from_rotation_z(boid.y_vel.atan2(boid.x_vel));
+        }
+    }
+}
+
+pub fn sync_predator_transform(flock: Res<FlockResource>, mut predator_entities: Query<&mut Transform, With<PredatorEntity>>) {
+    let Some(predator) = flock.0.predator() else {
+        return;
+    };
+    for mut transform in &mut predator_entities {
+        transform.translation.x = predator.boid.x_pos;
+        transform.translation.y = predator.boid.y_pos;
+        if predator.boid.x_vel != 0.0 || predator.boid.y_vel != 0.0 {
+            transform.rotation = Quat::from_rotation_z(predator.boid.y_vel.atan2(predator.boid.x_vel));
+        }
+    }
+}
+
+pub fn move_camera(flock: Res<FlockResource>, camera: Option<ResMut<CameraResource>>, mut camera_entities: Query<(&CameraEntity, &mut Transform)>) {
+    let Some(mut camera) = camera else {
+        return;
+    };
+    camera.0.update(&flock.0);
+    for (camera_entity, mut transform) in &mut camera_entities {
+        transform.translation.x = camera.0.centre.0;
+        transform.translation.y = camera.0.centre.1;
+        let scale = camera.0.view_width / camera_entity.window_width;
+        transform.scale = Vec3::new(scale, scale, 1.0);
+    }
+}
+
+/// writes the open page of the help, or else the readouts followed by the latest status, to the window's title,
+/// leaving it alone while there's none of them
+pub fn show_hud(mut hud: ResMut<HudResource>, flock: Res<FlockResource>, camera: Option<Res<CameraResource>>, shuffle: Option<Res<ShuffleResource>>,
+                rewind: Option<Res<RewindResource>>, pause: Res<PauseResource>, mut windows: Query<&mut Window>) {
+    // the systems that fill the readouts push them again next frame
+    let readouts = std::mem::take(&mut hud.readouts);
+    let text = if let Some(page) = hud.help_page {
+        let lines = help_lines(&flock.0, camera.as_ref().map(|camera| &camera.0), shuffle.as_ref().map(|shuffle| &shuffle.0), rewind.as_ref().map(|rewind| &rewind.0), pause.paused);
+        let pages: Vec<&[String]> = lines.chunks(HELP_LINES_PER_PAGE).collect();
+        format!("help {}/{}: {}", page + 1, pages.len(), pages[page.min(pages.len() - 1)].join(" · "))
+    } else {
+        let lines: Vec<String> = readouts.into_iter().chain(hud.status.clone()).collect();
+        if lines.is_empty() {
+            return;
+        }
+        lines.join(" · ")
+    };
+    for mut window in &mut windows {
+        // only set when it changes, as Bevy passes every change to the title on to the window
+        if window.title != text {
+            window.title = text.clone();
+        }
+    }
+}
+
+/// the colours the `BoidsGizmosPlugin` draws in, and what boids are coloured by
+#[derive(Resource, Debug)]
+pub struct ThemeResource {
+    pub theme: Theme,
+    pub color_mode: ColorMode,
+}
+
+impl Default for ThemeResource {
+    fn default() -> Self {
+        return ThemeResource { theme: theme::LIGHT, color_mode: ColorMode::Plain };
+    }
+}
+
+/// a texture to draw every boid with instead of a triangle, e.g. a bird loaded with the app's
+/// `AssetServer`. It should point along +x, and white parts of it take on the boid's colour. Boids
+/// have no species, so the whole flock shares the one texture
+#[derive(Resource, Debug, Clone)]
+pub struct BoidSpriteResource {
+    pub texture: Handle<Image>,
+    pub length: f32, // in world units, along the boid's heading; the width keeps the texture's proportions
+}
+
+/// marks a boid's entity spawned by the `BoidsGizmosPlugin` to show a `BoidSpriteResource`
+#[derive(Component, Debug)]
+pub struct BoidSprite;
+
+/// draws a frame as outlines with Bevy's gizmos, which only last the frame they're drawn in
+pub struct GizmoRenderer<'a, 'w, 's> {
+    gizmos: &'a mut Gizmos<'w, 's>,
+    theme: &'a Theme,
+    draws_boids: bool, // false when the boids are sprites instead
+}
+
+impl<'a, 'w, 's> GizmoRenderer<'a, 'w, 's> {
+    pub fn new(gizmos: &'a mut Gizmos<'w, 's>, theme: &'a Theme) -> GizmoRenderer<'a, 'w, 's> {
+        return GizmoRenderer { gizmos, theme, draws_boids: true };
+    }
+
+    /// leaves the boids out, for when they're drawn some other way, e.g. as sprites
+    pub fn without_boids(self) -> GizmoRenderer<'a, 'w, 's> {
+        return GizmoRenderer { draws_boids: false, ..self };
+    }
+
+    fn draw_triangle(&mut self, corners: [(f32, f32); 3], color: Rgb) {
+        let [tip, left, right] = corners.map(|(x, y)| Vec2::new(x, y));
+        self.gizmos.linestrip_2d([tip, left, right, tip], gizmo_color(color));
+    }
+}
+
+fn gizmo_color(color: Rgb) -> Color {
+    return Color::srgb_u8(color[0], color[1], color[2]);
+}
+
+impl Renderer for GizmoRenderer<'_, '_, '_> {
+    // the app clears the screen and places the camera
+    fn begin_frame(&mut self, _bounds: Option<Bounds>) {}
+
+    fn draw_force_source(&mut self, source: &ForceSource) {
+        self.gizmos.circle_2d(Vec2::new(source.centre.0, source.centre.1), source.radius, gizmo_color(self.theme.path));
+    }
+
+    fn draw_walls(&mut self, shape: &ArenaShape) {
+        let color = gizmo_color(self.theme.boid);
+        match shape {
+            ArenaShape::Polygon(vertices) => {
+                // round to the first vertex again, to close the polygon
+                let points = vertices.iter().chain(vertices.first()).map(|(x, y)| Vec2::new(*x, *y));
+                self.gizmos.linestrip_2d(points, color);
+            }
+            ArenaShape::Circle { centre, radius } => {
+                self.gizmos.circle_2d(Vec2::new(centre.0, centre.1), *radius, color);
+            }
+        }
+    }
+
+    fn draw_wall(&mut self, start: (f32, f32), end: (f32, f32)) {
+        self.gizmos.line_2d(Vec2::new(start.0, start.1), Vec2::new(end.0, end.1), gizmo_color(self.theme.boid));
+    }
+
+    fn draw_path(&mut self, points: &[(f32, f32)]) {
+        self.gizmos.linestrip_2d(points.iter().map(|(x, y)| Vec2::new(*x, *y)), gizmo_color(self.theme.path));
+    }
+
+    fn draw_obstacle(&mut self, centre: (f32, f32), radius: f32) {
+        self.gizmos.circle_2d(Vec2::new(centre.0, centre.1), radius, gizmo_color(self.theme.path));
+    }
+
+    fn draw_food(&mut self, food: &FoodSource) {
+        self.gizmos.circle_2d(Vec2::new(food.x_pos, food.y_pos), BOID_LENGTH / 2.0, gizmo_color(self.theme.food));
+    }
+
+    fn draw_boid(&mut self, boid: &Boid, color: BoidColor) {
+        if self.draws_boids {
+            self.draw_triangle(triangle_for_boid(boid, BOID_LENGTH), self.theme.boid_color(color));
+        }
+    }
+
+    fn draw_predator(&mut self, predator: &Predator) {
+        self.draw_triangle(triangle_for_boid(&predator.boid, PREDATOR_LENGTH), self.theme.predator_color(predator.hunt_state()));
+    }
+
+    fn end_frame(&mut self) {}
+}
+
+/// draws the flock with gizmos each frame, after it's been stepped. Needs Bevy's `GizmoPlugin`, which
+/// comes with its `DefaultPlugins`
+pub struct BoidsGizmosPlugin;
+
+impl Plugin for BoidsGizmosPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ThemeResource>();
+        app.add_systems(Update, (draw_flock_gizmos.after(step_flock), sync_boid_sprites.after(step_flock).before(sync_boid_transforms), draw_performance_graph.after(record_performance), draw_order_plot.after(step_flock)));
+    }
+}
+
+pub fn draw_flock_gizmos(flock: Res<FlockResource>, theme: Res<ThemeResource>, herding: Option<Res<HerdingResource>>, editor: Option<Res<EditorResource>>,
+                         performance: Option<ResMut<PerformanceResource>>, sprite: Option<Res<BoidSpriteResource>>, mut gizmos: Gizmos) {
+    let started = Instant::now();
+    if let Some((start, end)) = editor.and_then(|editor| editor.drawing()) {
+        gizmos.line_2d(Vec2::new(start.0, start.1), Vec2::new(end.0, end.1), gizmo_color(theme.theme.path));
+    }
+    if let Some(herding) = herding {
+        let goal_centre = Vec2::new(herding.0.goal_centre.0, herding.0.goal_centre.1);
+        gizmos.circle_2d(goal_centre, herding.0.goal_radius, gizmo_color(theme.theme.food));
+    }
+    let renderer = GizmoRenderer::new(&mut gizmos, &theme.theme);
+    draw_flock(&flock.0, theme.color_mode, &mut if sprite.is_some() { renderer.without_boids() } else { renderer });
+    if let Some(mut performance) = performance.filter(|performance| performance.visible) {
+        performance.drawing = started.elapsed();
+    }
+}
+
+/// each boid's colour as `draw_flock` would draw it, for tinting its sprite
+struct BoidColors<'a> {
+    theme: &'a Theme,
+    colors: HashMap<BoidId, Rgb>,
+}
+
+impl Renderer for BoidColors<'_> {
+    fn begin_frame(&mut self, _bounds: Option<Bounds>) {}
+
+    fn draw_boid(&mut self, boid: &Boid, color: BoidColor) {
+        self.colors.insert(boid.id, self.theme.boid_color(color));
+    }
+
+    fn end_frame(&mut self) {}
+}
+
+/// spawns a sprite for each boid that joins the flock and despawns the sprites of boids that have
+/// left it, tinting the rest in their boids' colours. `sync_boid_transforms` moves them along with
+/// the boids. Does nothing without a `BoidSpriteResource`, leaving the boids as triangles
+pub fn sync_boid_sprites(mut commands: Commands, flock: Res<FlockResource>, sprite: Option<Res<BoidSpriteResource>>, theme: Res<ThemeResource>,
+                         images: Option<Res<Assets<Image>>>, mut sprites: Query<(Entity, &BoidEntity, &mut Sprite), With<BoidSprite>>) {
+    let Some(sprite) = sprite else {
+        return;
+    };
+    let mut boid_colors = BoidColors { theme: &theme.theme, colors: HashMap::new() };
+    draw_flock(&flock.0, theme.color_mode, &mut boid_colors);
+    // square until the texture has loaded and its proportions are known
+    let size = match images.as_ref().and_then(|images| images.get(&sprite.texture)) {
+        Some(image) => Vec2::new(sprite.length, sprite.length * image.height() as f32 / image.width().max(1) as f32),
+        None => Vec2::splat(sprite.length),
+    };
+    for (entity, boid_entity, mut boid_sprite) in &mut sprites {
+        match boid_colors.colors.remove(&boid_entity.id) {
+            Some(color) => {
+                boid_sprite.color = gizmo_color(color);
+                boid_sprite.custom_size = Some(size);
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+    // the boids left without a sprite
+    for (id, color) in boid_colors.colors {
+        let Some(boid) = flock.0.boid(id) else {
+            continue;
+        };
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite { color: gizmo_color(color), custom_size: Some(size), ..default() },
+                texture: sprite.texture.clone(),
+                transform: Transform::from_xyz(boid.x_pos, boid.y_pos, 0.0).with_rotation(Quat::from_rotation_z(boid.y_vel.atan2(boid.x_vel))),
+                ..default()
+            },
+            BoidEntity { id },
+            BoidSprite,
+        ));
+    }
+}
+
+// the graph's size, as a share of the camera's view, and the frame time its full height stands for
+const PERFORMANCE_GRAPH_SHARE_OF_VIEW: (f32, f32) = (0.3, 0.15);
+const PERFORMANCE_GRAPH_FULL_SCALE: Duration = Duration::from_micros(33_333);
+
+/// a bar for each frame kept, along the bottom left of the view, stacked from the bottom by the parts
+/// of the step and then drawing, with a line across at a 60 fps frame's worth of time
+pub fn draw_performance_graph(performance: Option<Res<PerformanceResource>>, camera: Option<Res<CameraResource>>, theme: Res<ThemeResource>, mut gizmos: Gizmos) {
+    let (Some(performance), Some(camera)) = (performance, camera) else {
+        return;
+    };
+    if !performance.visible {
+        return;
+    }
+    let view_height = camera.0.view_width / camera.0.aspect_ratio;
+    let (width, height) = (camera.0.view_width * PERFORMANCE_GRAPH_SHARE_OF_VIEW.0, view_height * PERFORMANCE_GRAPH_SHARE_OF_VIEW.1);
+    // a bar's width in from the corner of the view
+    let bar_width = width / PERFORMANCE_HISTORY_LEN as f32;
+    let left = camera.0.centre.0 - camera.0.view_width / 2.0 + bar_width;
+    let bottom = camera.0.centre.1 - view_height / 2.0 + bar_width;
+    let height_of = |duration: Duration| height * (duration.as_secs_f32() / PERFORMANCE_GRAPH_FULL_SCALE.as_secs_f32());
+
+    let palette = theme.theme.cluster_palette;
+    for (frame_idx, frame) in performance.frames().enumerate() {
+        let x = left + bar_width * frame_idx as f32;
+        let mut y = bottom;
+        let parts = frame.step.parts().map(|(_, duration)| duration).into_iter().chain([frame.drawing]);
+        for (part_idx, duration) in parts.enumerate() {
+            let top = (y + height_of(duration)).min(bottom + height);
+            gizmos.line_2d(Vec2::new(x, y), Vec2::new(x, top), gizmo_color(palette[part_idx % palette.len()]));
+            y = top;
+        }
+    }
+    let sixtieth = bottom + height_of(Duration::from_secs(1) / 60);
+    gizmos.line_2d(Vec2::new(left, sixtieth), Vec2::new(left + width, sixtieth), gizmo_color(theme.theme.boid));
+    gizmos.line_2d(Vec2::new(left, bottom), Vec2::new(left, bottom + height), gizmo_color(theme.theme.boid));
+}
+
+// the plot's size, as a share of the camera's view
+const ORDER_PLOT_SHARE_OF_VIEW: (f32, f32) = (0.4, 0.2);
+
+/// polarization and mean speed over the steps kept, along the top left of the view in the theme's path
+/// and food colours. Polarization runs from 0 to 1 up the plot, and mean speed from 0 to the flock's
+/// max speed, or the fastest in the plot if the flock has none
+pub fn draw_order_plot(order_plot: Option<Res<OrderPlotResource>>, flock: Res<FlockResource>, camera: Option<Res<CameraResource>>, theme: Res<ThemeResource>, mut gizmos: Gizmos) {
+    let (Some(order_plot), Some(camera)) = (order_plot, camera) else {
+        return;
+    };
+    if !order_plot.visible {
+        return;
+    }
+    let view_height = camera.0.view_width / camera.0.aspect_ratio;
+    let (width, height) = (camera.0.view_width * ORDER_PLOT_SHARE_OF_VIEW.0, view_height * ORDER_PLOT_SHARE_OF_VIEW.1);
+    // a hundredth of the plot's width in from the corner of the view
+    let margin = width / 100.0;
+    let left = camera.0.centre.0 - camera.0.view_width / 2.0 + margin;
+    let top = camera.0.centre.1 + view_height / 2.0 - margin;
+    let bottom = top - height;
+
+    let max_speed = flock.0.speed_limits().1;
+    let full_speed = if max_speed.is_finite() { max_speed } else { order_plot.samples().map(|sample| sample.mean_speed).fold(0.0, f32::max) };
+    let x_of = |sample_idx: usize| left + width * sample_idx as f32 / (ORDER_PLOT_LEN - 1) as f32;
+    let y_of = |value: f32, full_scale: f32| bottom + height * if full_scale > 0.0 { (value / full_scale).clamp(0.0, 1.0) } else { 0.0 };
+    gizmos.linestrip_2d(order_plot.samples().enumerate().map(|(sample_idx, sample)| Vec2::new(x_of(sample_idx), y_of(sample.polarization, 1.0))), gizmo_color(theme.theme.path));
+    gizmos.linestrip_2d(order_plot.samples().enumerate().map(|(sample_idx, sample)| Vec2::new(x_of(sample_idx), y_of(sample.mean_speed, full_speed))), gizmo_color(theme.theme.food));
+    let corners = [(left, bottom), (left + width, bottom), (left + width, top), (left, top), (left, bottom)];
+    gizmos.linestrip_2d(corners.map(|(x, y)| Vec2::new(x, y)), gizmo_color(theme.theme.boid));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Boid;
+    use crate::predator::Predator;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_transform_follows_boid() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(1.0, 2.0, 3.0, 0.0)]);
+        let id = flock.boids()[0].id;
+
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        let entity = app.world_mut().spawn((BoidEntity { id }, Transform::default())).id();
+        app.update();
+
+        let transform = app.world().get::<Transform>(entity).unwrap();
+        let boid = app.world().resource::<FlockResource>().0.boids()[0];
+        assert_eq!(transform.translation.x, boid.x_pos);
+        assert_eq!(transform.translation.y, boid.y_pos);
+        assert_eq!(transform.rotation, Quat::IDENTITY);
+    }
+
+    #[test]
+    fn test_entity_follows_its_boid_when_one_before_it_leaves() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(100.0, 0.0, 0.0, 0.0)]);
+        let id = flock.boids()[1].id;
+        flock.remove_boids_where(|boid| boid.x_pos < 50.0);
+
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        let entity = app.world_mut().spawn((BoidEntity { id }, Transform::default())).id();
+        app.update();
+
+        assert_eq!(app.world().get::<Transform>(entity).unwrap().translation.x, 100.0);
+    }
+
+    #[test]
+    fn test_camera_cycled_by_key_and_follows_flock() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(10.0, 20.0, 0.0, 0.0)];
+
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        let camera = camera::Camera { smoothing: 1.0, ..camera::Camera::new((0.0, 0.0), 200.0, 2.0) };
+        app.insert_resource(CameraResource(camera));
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::KeyC);
+        app.insert_resource(keys);
+        let entity = app.world_mut().spawn((CameraEntity { window_width: 800.0 }, Transform::default())).id();
+        app.update();
+
+        assert_eq!(app.world().resource::<CameraResource>().0.mode, camera::CameraMode::FollowCentroid);
+        let transform = app.world().get::<Transform>(entity).unwrap();
+        assert_eq!((transform.translation.x, transform.translation.y), (10.0, 20.0));
+        assert_eq!(transform.scale.x, 0.25);
+    }
+
+    #[test]
+    fn test_boid_under_cursor_picked_up() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(5.0, 10.0, 1.0, 0.0)]);
+
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        app.insert_resource(CameraResource(camera::Camera::new((0.0, 0.0), 1280.0, 16.0 / 9.0)));
+        app.insert_resource(DragResource(Drag::new(1.0, 1.0)));
+        let mut mouse = ButtonInput::<MouseButton>::default();
+        mouse.press(MouseButton::Left);
+        app.insert_resource(mouse);
+        // a pixel to the unit, with the cursor above and to the right of the window's centre
+        let mut window = Window::default();
+        window.set_cursor_position(Some(Vec2::new(640.0 + 5.0, 360.0 - 10.0)));
+        app.world_mut().spawn(window);
+        app.update();
+
+        let flock = &app.world().resource::<FlockResource>().0;
+        let held = flock.held_boid().unwrap();
+        assert_eq!(app.world().resource::<CameraResource>().0.selected, Some(held));
+        assert_eq!(flock.boid(held).unwrap().x_pos, 5.0);
+    }
+
+    #[test]
+    fn test_parameters_shuffled_by_key_and_shown_in_title() {
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap()));
+        app.insert_resource(ShuffleResource(ParameterShuffle::new(5)));
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::KeyR);
+        app.insert_resource(keys);
+        let window = app.world_mut().spawn(Window::default()).id();
+        app.update();
+
+        let parameters = *app.world().resource::<ShuffleResource>().0.current().unwrap();
+        assert_eq!(app.world().resource::<FlockResource>().0.factor(crate::Factor::Repulsion), parameters.repulsion);
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, parameters.to_string());
+    }
+
+    #[test]
+    fn test_factors_nudged_by_keys() {
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(Flock::new(0, 1.0, 10.0, 0.5, 0.5, 0.95).unwrap()));
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::KeyE);
+        keys.press(KeyCode::ShiftLeft);
+        app.insert_resource(keys);
+        let window = app.world_mut().spawn(Window::default()).id();
+        app.update();
+        assert_eq!(app.world().resource::<FlockResource>().0.factor(Factor::Repulsion), 0.6);
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, "repulsion 0.60");
+
+        // a coarse step would take cohesion past 1
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::KeyQ);
+        keys.press(KeyCode::ShiftLeft);
+        app.insert_resource(keys);
+        app.update();
+        assert_eq!(app.world().resource::<FlockResource>().0.factor(Factor::Cohesion), 0.95);
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, "cohesion stays at 0.95, as it should be between 0 and 1");
+    }
+
+    #[test]
+    fn test_rewound_while_backspace_held() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(3.0, 0.0, 0.0, 1.0)]);
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        app.insert_resource(RewindResource(RewindBuffer::new(1 << 20)));
+        app.insert_resource(ButtonInput::<KeyCode>::default());
+        let boids = |app: &App| app.world().resource::<FlockResource>().0.boids().iter().map(|boid| (boid.x_pos, boid.y_pos, boid.x_vel, boid.y_vel)).collect::<Vec<_>>();
+        let mut history = vec![boids(&app)];
+        for _ in 0..3 {
+            app.update();
+            history.push(boids(&app));
+        }
+        assert_ne!(history[3], history[1]);
+
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::Backspace);
+        app.update();
+        app.update();
+        assert_eq!(boids(&app), history[1]);
+        // and carries on forwards from there once it's let go
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(KeyCode::Backspace);
+        app.update();
+        assert_eq!(boids(&app), history[2]);
+    }
+
+    #[test]
+    fn test_rules_toggled_by_number_keys() {
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap()));
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::Digit2);
+        keys.press(KeyCode::Digit9);
+        app.insert_resource(keys);
+        let window = app.world_mut().spawn(Window::default()).id();
+        app.update();
+
+        let flock = &app.world().resource::<FlockResource>().0;
+        assert!(!flock.is_rule_enabled(Rule::Alignment) && !flock.is_rule_enabled(Rule::Scripted));
+        assert_eq!(app.world().get::<Window>(window).unwrap().title,
+                   "rules on: separation, cohesion, leader-following, path-following, foraging, force-field, evasion | off: alignment, scripted");
+    }
+
+    #[test]
+    fn test_help_shown_with_current_values() {
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.5).unwrap()));
+        app.insert_resource(RewindResource(RewindBuffer::new(1 << 20)));
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::KeyH);
+        app.insert_resource(keys);
+        let window = app.world_mut().spawn(Window::default()).id();
+        app.update();
+
+        let num_pages = BINDINGS.len().div_ceil(HELP_LINES_PER_PAGE);
+        let title = app.world().get::<Window>(window).unwrap().title.clone();
+        assert!(title.starts_with(&format!("help 1/{}: H/F1: show the next page of this help, or hide it after the last · ", num_pages)));
+        assert_eq!(title.split(" · ").count(), HELP_LINES_PER_PAGE);
+
+        // every page in turn, which between them have every line
+        let mut lines = Vec::new();
+        for page in 0..num_pages {
+            let title = app.world().get::<Window>(window).unwrap().title.clone();
+            let (heading, page_lines) = title.split_once(": ").unwrap();
+            assert_eq!(heading, format!("help {}/{}", page + 1, num_pages));
+            lines.extend(page_lines.split(" · ").map(str::to_string));
+            let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keys.release(KeyCode::KeyH);
+            keys.clear();
+            keys.press(KeyCode::KeyH);
+            app.update();
+        }
+        assert_eq!(lines.len(), BINDINGS.len());
+        assert!(lines.contains(&"Q: raise cohesion by 0.01, or 0.1 with shift (now 0.50)".to_string()));
+        assert!(lines.contains(&"9: switch scripted on or off (now on)".to_string()));
+        assert!(lines.contains(&"left mouse button: pick up, drag and fling a boid".to_string()));
+        assert!(lines.iter().any(|line| line.starts_with("Backspace: hold to rewind (now ")));
+        assert!(lines.contains(&"Space: pause or carry on (now running)".to_string()));
+        // closed after the last page
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, "H/F1 for help");
+
+        // a page stays open, keeping up with the values as they change
+        let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keys.release(KeyCode::KeyH);
+        keys.clear();
+        keys.press(KeyCode::F1);
+        app.update();
+        let rewind_page = lines.iter().position(|line| line.starts_with("Backspace")).unwrap() / HELP_LINES_PER_PAGE;
+        app.world_mut().resource_mut::<HudResource>().help_page = Some(rewind_page);
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+        let steps_kept = app.world().resource::<RewindResource>().0.len();
+        app.update();
+        assert!(app.world().get::<Window>(window).unwrap().title.contains(&format!("Backspace: hold to rewind (now {} steps kept)", steps_kept + 1)));
+    }
+
+    #[test]
+    fn test_flock_drawn_with_gizmos() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(3.0, 0.0, 0.0, 1.0)]);
+        flock.set_arena(crate::arena::Arena::parse("polygon:-50,-50;50,-50;50,50").unwrap());
+        flock.set_predator(Predator::new(Boid::new(20.0, 0.0, 0.0, 0.0), 0.5, 0.1, 2.0, 1.0, 10.0, 0.5).unwrap());
+
+        let mut app = App::new();
+        app.add_plugins(BoidsGizmosPlugin);
+        // the storage `GizmoPlugin` would set up, without the meshes it turns the gizmos into each frame
+        app.init_gizmo_group::<DefaultGizmoConfigGroup>();
+        app.insert_resource(FlockResource(flock));
+        app.insert_resource(HerdingResource(HerdingGame::new((40.0, 0.0), 5.0, 100).unwrap()));
+        app.world_mut().run_system_once(draw_flock_gizmos);
+        assert_eq!(app.world().resource::<ThemeResource>().theme, crate::theme::LIGHT);
+    }
+
+    #[test]
+    fn test_order_plotted_while_stepping() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(3.0, 0.0, 1.0, 0.0)]);
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        app.insert_resource(CameraResource(camera::Camera::new((0.0, 0.0), 1280.0, 16.0 / 9.0)));
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::F2);
+        app.insert_resource(keys);
+        let window = app.world_mut().spawn(Window::default()).id();
+        app.update();
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+        app.update();
+
+        let order_plot = app.world().resource::<OrderPlotResource>();
+        assert_eq!(order_plot.samples().count(), 2);
+        assert_eq!(order_plot.samples().last().unwrap().polarization, 1.0);
+        let speed = stats::mean_speed(app.world().resource::<FlockResource>().0.boids());
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, format!("polarization 1.00 · mean speed {:.2}", speed));
+
+        // steps aren't plotted while paused, but the readout stays
+        app.world_mut().resource_mut::<PauseResource>().paused = true;
+        app.update();
+        assert_eq!(app.world().resource::<OrderPlotResource>().samples().count(), 2);
+        assert!(app.world().get::<Window>(window).unwrap().title.starts_with("polarization 1.00"));
+
+        app.init_resource::<ThemeResource>();
+        app.init_gizmo_group::<DefaultGizmoConfigGroup>();
+        app.world_mut().run_system_once(draw_order_plot);
+    }
+
+    #[test]
+    fn test_performance_shown_in_title_and_graph() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(3.0, 0.0, 0.0, 1.0)]);
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        app.insert_resource(CameraResource(camera::Camera::new((0.0, 0.0), 1280.0, 16.0 / 9.0)));
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::F3);
+        app.insert_resource(keys);
+        let window = app.world_mut().spawn(Window::default()).id();
+        app.update();
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+        app.update();
+
+        let title = app.world().get::<Window>(window).unwrap().title.clone();
+        assert!(title.starts_with("step ") && title.contains(" ms (neighbours ") && title.contains("· drawing "), "{}", title);
+        let performance = app.world().resource::<PerformanceResource>();
+        assert_eq!(performance.frames().count(), 2);
+        assert!(performance.frames().all(|frame| frame.step.total() > Duration::ZERO));
+        // drawn as in `test_flock_drawn_with_gizmos`, which times the drawing for the next frame's readout
+        app.init_resource::<ThemeResource>();
+        app.init_gizmo_group::<DefaultGizmoConfigGroup>();
+        app.world_mut().run_system_once(draw_flock_gizmos);
+        app.world_mut().run_system_once(draw_performance_graph);
+        assert!(app.world().resource::<PerformanceResource>().drawing > Duration::ZERO);
+
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(KeyCode::F3);
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::F3);
+        app.world_mut().run_system_once(toggle_performance);
+        assert_eq!(app.world().resource::<FlockResource>().0.step_timings(), None);
+        assert_eq!(app.world().resource::<PerformanceResource>().frames().count(), 0);
+    }
+
+    #[test]
+    fn test_sprite_kept_for_each_boid() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(3.0, 0.0, 0.0, 1.0)]);
+        let mut app = App::new();
+        app.init_resource::<ThemeResource>();
+        app.insert_resource(FlockResource(flock));
+        app.insert_resource(BoidSpriteResource { texture: Handle::default(), length: 4.0 });
+        let sprites = |app: &mut App| app.world_mut().query_filtered::<(&BoidEntity, &Sprite, &Transform), With<BoidSprite>>()
+            .iter(app.world()).map(|(boid_entity, sprite, transform)| (boid_entity.id, sprite.custom_size, transform.translation.x)).collect::<Vec<_>>();
+        app.world_mut().run_system_once(sync_boid_sprites);
+        let mut spawned = sprites(&mut app);
+        spawned.sort_by_key(|(id, _, _)| *id);
+        let ids: Vec<BoidId> = app.world().resource::<FlockResource>().0.boids().iter().map(|boid| boid.id).collect();
+        assert_eq!(spawned, vec![(ids[0], Some(Vec2::splat(4.0)), 0.0), (ids[1], Some(Vec2::splat(4.0)), 3.0)]);
+
+        // a boid leaving takes its sprite with it
+        app.world_mut().resource_mut::<FlockResource>().0.remove_boids_where(|boid| boid.x_pos > 1.0);
+        app.world_mut().run_system_once(sync_boid_sprites);
+        assert_eq!(sprites(&mut app).iter().map(|(id, _, _)| *id).collect::<Vec<_>>(), vec![ids[0]]);
+    }
+
+    #[test]
+    fn test_profiles_saved_picked_and_applied_by_keys() {
+        let path = std::env::temp_dir().join(format!("boids-profile-keys-test-{}", std::process::id())).join("profiles.txt");
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(Flock::new(0, 1.0, 10.0, 0.2, 0.2, 0.2).unwrap()));
+        app.insert_resource(ProfileResource::load(path.clone()).unwrap());
+        app.world_mut().resource_mut::<PauseResource>().paused = true;
+        app.insert_resource(ButtonInput::<KeyCode>::default());
+        let window = app.world_mut().spawn(Window::default()).id();
+        let title = |app: &App| app.world().get::<Window>(window).unwrap().title.clone();
+        let tap = |app: &mut App, key: KeyCode| {
+            let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keys.release_all();
+            keys.clear();
+            keys.press(key);
+            app.update();
+        };
+
+        tap(&mut app, KeyCode::F8);
+        assert_eq!(title(&app), "pick a profile with F7 first");
+        tap(&mut app, KeyCode::F6);
+        assert!(title(&app).starts_with("saved as 'profile 1' in "));
+        app.world_mut().resource_mut::<FlockResource>().0.set_factor(Factor::Cohesion, 0.9).unwrap();
+        tap(&mut app, KeyCode::F6);
+        app.world_mut().resource_mut::<FlockResource>().0.set_factor(Factor::Cohesion, 0.5).unwrap();
+
+        tap(&mut app, KeyCode::F7);
+        assert_eq!(title(&app), "picked 'profile 1', F8 to apply (profiles: profile 1, profile 2)");
+        tap(&mut app, KeyCode::F8);
+        assert_eq!(title(&app), "applied 'profile 1'");
+        assert_eq!(app.world().resource::<FlockResource>().0.factor(Factor::Cohesion), 0.2);
+        tap(&mut app, KeyCode::F7);
+        tap(&mut app, KeyCode::F8);
+        assert_eq!(app.world().resource::<FlockResource>().0.factor(Factor::Cohesion), 0.9);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_paused_flock_only_stepped_by_key() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(3.0, 0.0, 0.0, 1.0)]);
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::Space);
+        app.insert_resource(keys);
+        let window = app.world_mut().spawn(Window::default()).id();
+        let x_pos = |app: &App| app.world().resource::<FlockResource>().0.boids()[0].x_pos;
+        app.update();
+        assert_eq!(x_pos(&app), 0.0);
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, "paused, Period to step");
+
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+        app.update();
+        assert_eq!(x_pos(&app), 0.0);
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::Period);
+        app.update();
+        assert_ne!(x_pos(&app), 0.0);
+    }
+
+    #[test]
+    fn test_obstacles_and_walls_edited_with_mouse_while_paused() {
+        let path = std::env::temp_dir().join(format!("boids-editor-test-{}", std::process::id())).join("layout.txt");
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap()));
+        app.insert_resource(CameraResource(camera::Camera::new((0.0, 0.0), 1280.0, 16.0 / 9.0)));
+        app.insert_resource(EditorResource::new(path.clone(), 5.0));
+        app.insert_resource(PauseResource { paused: true });
+        app.insert_resource(ButtonInput::<MouseButton>::default());
+        app.insert_resource(ButtonInput::<KeyCode>::default());
+        let window = app.world_mut().spawn(Window::default()).id();
+        // a pixel to the unit, centred on the origin
+        let move_cursor = |app: &mut App, (x, y): (f32, f32)| {
+            app.world_mut().get_mut::<Window>(window).unwrap().set_cursor_position(Some(Vec2::new(640.0 + x, 360.0 - y)));
+        };
+        let mouse = |app: &mut App, pressed: bool| {
+            let mut mouse = app.world_mut().resource_mut::<ButtonInput<MouseButton>>();
+            mouse.clear();
+            if pressed { mouse.press(MouseButton::Right) } else { mouse.release(MouseButton::Right) }
+        };
+        let click = |app: &mut App, point: (f32, f32)| {
+            move_cursor(app, point);
+            mouse(app, true);
+            app.update();
+            mouse(app, false);
+            app.update();
+        };
+        let layout = |app: &App| Layout::from_flock(&app.world().resource::<FlockResource>().0);
+
+        click(&mut app, (5.0, 10.0));
+        assert_eq!(layout(&app).obstacles, vec![Obstacle::new((5.0, 10.0), 5.0, ObstacleMotion::Still).unwrap()]);
+        move_cursor(&mut app, (-100.0, 0.0));
+        mouse(&mut app, true);
+        app.update();
+        // as Bevy's input plugin would at the start of the next frame
+        app.world_mut().resource_mut::<ButtonInput<MouseButton>>().clear();
+        move_cursor(&mut app, (100.0, 0.0));
+        app.update();
+        assert_eq!(app.world().resource::<EditorResource>().drawing(), Some(((-100.0, 0.0), (100.0, 0.0))));
+        mouse(&mut app, false);
+        app.update();
+        assert_eq!(layout(&app).walls, vec![Wall::new((-100.0, 0.0), (100.0, 0.0)).unwrap()]);
+
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::F5);
+        app.update();
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, format!("layout saved to {}", path.display()));
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+        // clicking on them takes them away again, and loading brings them back
+        click(&mut app, (0.0, 1.0));
+        click(&mut app, (8.0, 10.0));
+        assert_eq!(layout(&app), Layout::default());
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::F9);
+        app.update();
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!((layout(&app).obstacles.len(), layout(&app).walls.len()), (1, 1));
+
+        // and nothing is edited while the flock is running
+        app.world_mut().resource_mut::<PauseResource>().paused = false;
+        click(&mut app, (-50.0, -50.0));
+        assert_eq!(layout(&app).obstacles.len(), 1);
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, "pause with Space to edit the obstacles and walls");
+    }
+
+    #[test]
+    fn test_herding_game_stepped_and_kept_in_title() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0)]);
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        app.insert_resource(HerdingResource(HerdingGame::new((100.0, 0.0), 5.0, 2).unwrap()));
+        let window = app.world_mut().spawn(Window::default()).id();
+        app.update();
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, "1 ticks left · 0/1 herded · score 0");
+
+        app.update();
+        assert_eq!(app.world().get::<Window>(window).unwrap().title, "0 ticks left · 0/1 herded · score 0 · time's up, scoring 0");
+        // the flock stays where it was when time ran out
+        let x_pos = app.world().resource::<FlockResource>().0.boids()[0].x_pos;
+        app.update();
+        assert_eq!(app.world().resource::<FlockResource>().0.boids()[0].x_pos, x_pos);
+    }
+
+    #[test]
+    fn test_predator_steered_by_keys() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.set_predator(Predator::new(Boid::new(0.0, 0.0, 0.0, 0.0), 0.5, 0.1, 2.0, 1.0, 10.0, 0.5).unwrap());
+
+        let mut app = App::new();
+        app.add_plugins(BoidsPlugin);
+        app.insert_resource(FlockResource(flock));
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::KeyW);
+        app.insert_resource(keys);
+        let entity = app.world_mut().spawn((PredatorEntity, Transform::default())).id();
+        app.update();
+
+        let predator = app.world().resource::<FlockResource>().0.predator().unwrap().boid;
+        assert_eq!((predator.x_pos, predator.x_vel), (0.5, 0.5));
+        assert_eq!(app.world().get::<Transform>(entity).unwrap().translation.x, 0.5);
+    }
+}
@@ -0,0 +1,354 @@
+//! What each boid is busy doing. With boid states set, every boid is flocking, fleeing, foraging
+//! or resting, and how strongly it separates, aligns and coheres, and how fast it may fly, depend
+//! on which. Boids switch state at the start of each step:
+//!
+//! - a boid within `flee_dist` of the predator flees, whatever else it was doing
+//! - flying tires a boid out, and one whose energy has run down to `tired_energy` rests until it's
+//!   back to `max_energy`
+//! - otherwise, a boid that can see food forages, and the rest flock
+//!
+//! Eating food gives a boid `food_energy` back. Each change of state is reported as a
+//! `FlockEvent::BoidStateChanged`, and `ColorMode::ByState` colours boids by their state.
+
+use anyhow::{anyhow, Context, Result};
+use crate::events::FlockEvent;
+use crate::food::Foraging;
+use crate::{check_finite, is_positive_and_finite, Boid, BoidId, CreationError, InvalidFlockConfig};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoidState {
+    Flocking,
+    Fleeing,
+    Foraging,
+    Resting,
+}
+
+impl BoidState {
+    pub const ALL: [BoidState; 4] = [BoidState::Flocking, BoidState::Fleeing, BoidState::Foraging, BoidState::Resting];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            BoidState::Flocking => "flocking",
+            BoidState::Fleeing => "fleeing",
+            BoidState::Foraging => "foraging",
+            BoidState::Resting => "resting",
+        };
+    }
+
+    fn idx(&self) -> usize {
+        return BoidState::ALL.iter().position(|state| state == self).unwrap_or(0);
+    }
+}
+
+/// how a boid in one state flies: the flock's separation, alignment and cohesion are scaled by
+/// these, and its speed is kept below `max_speed` as well as the flock's own maximum
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StateWeights {
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+    pub max_speed: f32, // infinite for no limit beyond the flock's
+}
+
+impl StateWeights {
+    pub const UNCHANGED: StateWeights = StateWeights { separation: 1.0, alignment: 1.0, cohesion: 1.0, max_speed: f32::INFINITY };
+}
+
+/// when boids change state, and how they fly in each
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateRules {
+    pub weights: [StateWeights; 4], // in the order of `BoidState::ALL`
+    pub flee_dist: f32, // boids this close to the predator flee it
+    pub max_energy: f32, // boids start with this much, and rest until they're back to it
+    pub tired_energy: f32, // boids rest once their energy is down to this
+    pub tiring: f32, // energy used up each tick for each unit of speed
+    pub recovery: f32, // energy recovered each tick of resting
+    pub food_energy: f32, // energy gained from eating food
+}
+
+impl Default for StateRules {
+    fn default() -> Self {
+        return StateRules {
+            weights: [
+                StateWeights::UNCHANGED,
+                // huddling up makes each boid less likely to be the one caught
+                StateWeights { separation: 0.5, alignment: 1.5, cohesion: 2.0, max_speed: f32::INFINITY },
+                StateWeights { separation: 1.0, alignment: 0.5, cohesion: 0.5, max_speed: f32::INFINITY },
+                StateWeights { separation: 1.0, alignment: 0.5, cohesion: 1.0, max_speed: 0.5 },
+            ],
+            flee_dist: 30.0,
+            max_energy: 100.0,
+            tired_energy: 10.0,
+            tiring: 0.05,
+            recovery: 0.5,
+            food_energy: 50.0,
+        };
+    }
+}
+
+impl StateRules {
+    /// `flee_dist:max_energy:tired_energy:tiring:recovery:food_energy`, e.g. `30:100:10:0.05:0.5:50`,
+    /// with the default weights for each state
+    pub fn parse(text: &str) -> Result<StateRules> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let [flee_dist, max_energy, tired_energy, tiring, recovery, food_energy] = parts.as_slice() else {
+            return Err(anyhow!("boid states '{}' should be flee_dist:max_energy:tired_energy:tiring:recovery:food_energy", text));
+        };
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        let rules = StateRules {
+            flee_dist: parse_float(flee_dist)?,
+            max_energy: parse_float(max_energy)?,
+            tired_energy: parse_float(tired_energy)?,
+            tiring: parse_float(tiring)?,
+            recovery: parse_float(recovery)?,
+            food_energy: parse_float(food_energy)?,
+            ..StateRules::default()
+        };
+        // checked now so a bad option is reported before anything runs
+        BoidStates::new(rules.clone())?;
+        return Ok(rules);
+    }
+}
+
+/// a boid's state and energy, kept by its id so that it follows the boid as others join and leave
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoidCondition {
+    pub id: BoidId,
+    pub state: BoidState,
+    pub energy: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct BoidStates {
+    pub rules: StateRules,
+    conditions: Vec<BoidCondition>, // one per boid, in the same order as the flock's after each update
+}
+
+impl BoidStates {
+    pub fn new(rules: StateRules) -> Result<BoidStates, InvalidFlockConfig> {
+        let mut errors = Vec::new();
+        for (state, weights) in BoidState::ALL.iter().zip(&rules.weights) {
+            for (value, weight_name) in [(weights.separation, "separation"), (weights.alignment, "alignment"), (weights.cohesion, "cohesion")] {
+                let param_name = format!("{} {} weight", state.name(), weight_name);
+                if let Some(error) = check_finite(value, &param_name) {
+                    errors.push(error);
+                }
+                else if value < 0.0 {
+                    errors.push(CreationError::BoidStateParameterIsNegative(param_name));
+                }
+            }
+            // infinite is allowed, for no limit
+            if weights.max_speed.is_nan() || weights.max_speed <= 0.0 {
+                errors.push(CreationError::BoidStateParameterIsNotPositive(format!("{} max speed", state.name())));
+            }
+        }
+        for (value, param_name) in [(rules.flee_dist, "flee distance"), (rules.max_energy, "max energy"), (rules.recovery, "recovery")] {
+            if !is_positive_and_finite(value) {
+                errors.push(CreationError::BoidStateParameterIsNotPositive(param_name.to_string()));
+            }
+        }
+        for (value, param_name) in [(rules.tired_energy, "tired energy"), (rules.tiring, "tiring"), (rules.food_energy, "food energy")] {
+            if let Some(error) = check_finite(value, param_name) {
+                errors.push(error);
+            }
+            else if value < 0.0 {
+                errors.push(CreationError::BoidStateParameterIsNegative(param_name.to_string()));
+            }
+        }
+        if rules.tired_energy >= rules.max_energy {
+            errors.push(CreationError::TiredEnergyIsNotBelowMaxEnergy);
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(BoidStates { rules, conditions: Vec::new() });
+    }
+
+    /// the state and energy of each boid as of the last step, in the same order as `Flock::boids`
+    /// was then. Boids that have joined since aren't included until the next step
+    pub fn conditions(&self) -> &[BoidCondition] {
+        return &self.conditions;
+    }
+
+    pub fn condition(&self, id: BoidId) -> Option<&BoidCondition> {
+        return self.conditions.iter().find(|condition| condition.id == id);
+    }
+
+    /// how a boid in `state` flies
+    pub fn weights(&self, state: BoidState) -> StateWeights {
+        return self.rules.weights[state.idx()];
+    }
+
+    /// how the boid at `boid_idx` in the flock flies this step; only valid between `update` and
+    /// any boids leaving the flock
+    pub(crate) fn weights_of(&self, boid_idx: usize) -> StateWeights {
+        return self.conditions.get(boid_idx).map_or(StateWeights::UNCHANGED, |condition| self.weights(condition.state));
+    }
+
+    /// brings the conditions in line with the boids, then moves each boid on to its state for this
+    /// step and uses up or recovers its energy for it
+    pub(crate) fn update(&mut self, boids: &[Boid], predator: Option<&Boid>, foraging: Option<&Foraging>, events: &mut Vec<FlockEvent>) {
+        self.match_boids(boids);
+        for (boid, condition) in boids.iter().zip(&mut self.conditions) {
+            condition.energy = match condition.state {
+                BoidState::Resting => (condition.energy + self.rules.recovery).min(self.rules.max_energy),
+                _ => (condition.energy - self.rules.tiring * boid.speed()).max(0.0),
+            };
+            let near_predator = predator.is_some_and(|predator| predator.dist_to_boid(boid) < self.rules.flee_dist);
+            let next_state = if near_predator {
+                BoidState::Fleeing
+            }
+            // once resting, a boid carries on until it's fully recovered
+            else if condition.energy <= self.rules.tired_energy || (condition.state == BoidState::Resting && condition.energy < self.rules.max_energy) {
+                BoidState::Resting
+            }
+            else if foraging.is_some_and(|foraging| foraging.can_see_food(boid)) {
+                BoidState::Foraging
+            }
+            else {
+                BoidState::Flocking
+            };
+            if next_state != condition.state {
+                events.push(FlockEvent::BoidStateChanged { boid: boid.id, from: condition.state, to: next_state });
+                condition.state = next_state;
+            }
+        }
+    }
+
+    /// gives boids that ate food during the step its energy
+    pub(crate) fn feed(&mut self, events: &[FlockEvent]) {
+        for event in events {
+            let FlockEvent::FoodEaten { boid, .. } = event else {
+                continue;
+            };
+            if let Some(condition) = self.conditions.iter_mut().find(|condition| condition.id == *boid) {
+                condition.energy = (condition.energy + self.rules.food_energy).min(self.rules.max_energy);
+            }
+        }
+    }
+
+    /// takes out the condition of a boid that's sitting out a step, so that it's kept as it was
+    /// rather than forgotten when the conditions are matched to the boids left in the flock
+    pub(crate) fn set_aside(&mut self, id: BoidId) -> Option<BoidCondition> {
+        let condition_idx = self.conditions.iter().position(|condition| condition.id == id)?;
+        return Some(self.conditions.remove(condition_idx));
+    }
+
+    /// puts back a condition that was set aside, in its place by id
+    pub(crate) fn put_back(&mut self, condition: BoidCondition) {
+        let condition_idx = self.conditions.partition_point(|other| other.id < condition.id);
+        self.conditions.insert(condition_idx, condition);
+    }
+
+    pub(crate) fn restore(&mut self, conditions: &[BoidCondition]) {
+        self.conditions.clear();
+        self.conditions.extend_from_slice(conditions);
+    }
+
+    // boids stay in order of their ids, so the conditions of boids still in the flock are found by
+    // walking along both together; boids new to the flock start out flocking, full of energy
+    fn match_boids(&mut self, boids: &[Boid]) {
+        let old_conditions = std::mem::take(&mut self.conditions);
+        let mut old_conditions = old_conditions.into_iter().peekable();
+        for boid in boids {
+            while old_conditions.next_if(|condition| condition.id < boid.id).is_some() {}
+            let condition = old_conditions
+                .next_if(|condition| condition.id == boid.id)
+                .unwrap_or(BoidCondition { id: boid.id, state: BoidState::Flocking, energy: self.rules.max_energy });
+            self.conditions.push(condition);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::FoodSource;
+    use crate::predator::Predator;
+    use crate::Flock;
+
+    fn state_of(flock: &Flock, boid_idx: usize) -> Option<BoidState> {
+        return flock.boid_state(flock.boids()[boid_idx].id);
+    }
+
+    #[test]
+    fn test_boids_flee_forage_and_flock() {
+        let mut flock = Flock::new(0, 1.0, 5.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(100.0, 0.0, 1.0, 0.0), Boid::new(200.0, 0.0, 1.0, 0.0)]);
+        flock.set_boid_states(BoidStates::new(StateRules::default()).unwrap());
+        flock.set_foraging(Foraging::new(vec![FoodSource::new(110.0, 0.0)], 0.1, 20.0, 1.0, 100).unwrap());
+        flock.set_predator(Predator::new(Boid::new(-10.0, 0.0, 0.0, 0.0), 0.5, 0.1, 2.0, 1.0, 10.0, 0.5).unwrap());
+        assert_eq!(state_of(&flock, 0), None);
+
+        flock.step();
+        assert_eq!(state_of(&flock, 0), Some(BoidState::Fleeing));
+        assert_eq!(state_of(&flock, 1), Some(BoidState::Foraging));
+        assert_eq!(state_of(&flock, 2), Some(BoidState::Flocking));
+        let changes: Vec<&FlockEvent> = flock.events().iter().filter(|event| matches!(event, FlockEvent::BoidStateChanged { .. })).collect();
+        assert_eq!(changes, vec![
+            &FlockEvent::BoidStateChanged { boid: flock.boids()[0].id, from: BoidState::Flocking, to: BoidState::Fleeing },
+            &FlockEvent::BoidStateChanged { boid: flock.boids()[1].id, from: BoidState::Flocking, to: BoidState::Foraging },
+        ]);
+    }
+
+    #[test]
+    fn test_tired_boids_rest_until_recovered() {
+        let rules = StateRules { max_energy: 10.0, tired_energy: 5.0, tiring: 1.0, recovery: 2.0, ..StateRules::default() };
+        let mut states = BoidStates::new(rules).unwrap();
+        let boids = [Boid::new(0.0, 0.0, 3.0, 0.0)];
+        let mut events = Vec::new();
+
+        states.update(&boids, None, None, &mut events);
+        assert_eq!((states.conditions()[0].state, states.conditions()[0].energy), (BoidState::Flocking, 7.0));
+        states.update(&boids, None, None, &mut events);
+        assert_eq!((states.conditions()[0].state, states.conditions()[0].energy), (BoidState::Resting, 4.0));
+        assert_eq!(states.weights_of(0).max_speed, 0.5);
+        // still resting above the tired energy, until it's back to full
+        for _ in 0..2 {
+            states.update(&boids, None, None, &mut events);
+        }
+        assert_eq!((states.conditions()[0].state, states.conditions()[0].energy), (BoidState::Resting, 8.0));
+        states.update(&boids, None, None, &mut events);
+        assert_eq!((states.conditions()[0].state, states.conditions()[0].energy), (BoidState::Flocking, 10.0));
+
+        states.update(&boids, None, None, &mut events);
+        states.feed(&[FlockEvent::FoodEaten { boid: boids[0].id, food: 0 }]);
+        assert_eq!(states.conditions()[0].energy, 10.0);
+    }
+
+    #[test]
+    fn test_conditions_follow_boids_as_they_leave() {
+        let mut flock = Flock::new(0, 1.0, 5.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(100.0, 0.0, 0.0, 0.0), Boid::new(200.0, 0.0, 0.0, 0.0)]);
+        flock.set_boid_states(BoidStates::new(StateRules::default()).unwrap());
+        flock.set_predator(Predator::new(Boid::new(210.0, 0.0, 0.0, 0.0), 0.5, 0.1, 2.0, 1.0, 20.0, 0.5).unwrap());
+        flock.step();
+        let fleeing_id = flock.boids()[2].id;
+        flock.remove_boids_where(|boid| boid.x_pos < 50.0);
+        flock.add_boids([Boid::new(300.0, 0.0, 0.0, 0.0)]);
+        flock.step();
+        assert_eq!(flock.boid_state(fleeing_id), Some(BoidState::Fleeing));
+        assert_eq!(flock.boid_states().unwrap().conditions().len(), 3);
+    }
+
+    #[test]
+    fn test_rules_parsed() {
+        let rules = StateRules::parse("20:50:5:0.1:1:25").unwrap();
+        assert_eq!((rules.flee_dist, rules.max_energy, rules.tired_energy, rules.food_energy), (20.0, 50.0, 5.0, 25.0));
+        assert_eq!(rules.weights, StateRules::default().weights);
+        assert!(StateRules::parse("20:50:5").is_err());
+        assert!(StateRules::parse("20:50:60:0.1:1:25").is_err());
+    }
+
+    #[test]
+    fn test_invalid_rules_rejected() {
+        let mut rules = StateRules { tired_energy: 100.0, flee_dist: 0.0, ..StateRules::default() };
+        rules.weights[1].cohesion = -1.0;
+        let errors = BoidStates::new(rules).unwrap_err().errors;
+        assert_eq!(errors, vec![
+            CreationError::BoidStateParameterIsNegative("fleeing cohesion weight".to_string()),
+            CreationError::BoidStateParameterIsNotPositive("flee distance".to_string()),
+            CreationError::TiredEnergyIsNotBelowMaxEnergy,
+        ]);
+    }
+}
@@ -0,0 +1,220 @@
+//! A camera onto the flock, for front ends that show less than the whole world. It can be left
+//! where the player puts it (`Free`), follow the selected boid or the centre of the flock, or zoom
+//! to fit, keeping every boid in view. Each update moves the camera part of the way to where its
+//! mode wants it, by `smoothing`, so it glides rather than jumps as the flock moves; zooming is
+//! smoothed in proportion, so zooming out from 10 to 1000 units takes as long as from 1 to 100.
+//!
+//! `cycle_mode` steps through the modes in the order of `CameraMode::ALL`, e.g. on a key press,
+//! skipping following a boid while none is selected.
+
+use anyhow::{anyhow, Result};
+use crate::{Boid, BoidId, Flock};
+
+// zoom to fit never zooms in further than this, so a lone boid doesn't fill the view
+const MIN_FIT_VIEW_WIDTH: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    Free,
+    FollowBoid, // the camera's `selected` boid, at the current zoom
+    FollowCentroid, // the average position of the boids, at the current zoom
+    ZoomToFit,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 4] = [CameraMode::Free, CameraMode::FollowBoid, CameraMode::FollowCentroid, CameraMode::ZoomToFit];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            CameraMode::Free => "free",
+            CameraMode::FollowBoid => "follow-boid",
+            CameraMode::FollowCentroid => "follow-centroid",
+            CameraMode::ZoomToFit => "zoom-to-fit",
+        };
+    }
+
+    pub fn parse(text: &str) -> Result<CameraMode> {
+        return CameraMode::ALL
+            .into_iter()
+            .find(|mode| mode.name() == text)
+            .ok_or_else(|| anyhow!("unknown camera mode '{}'; expected one of {}", text, CameraMode::ALL.map(|mode| mode.name()).join(", ")));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub mode: CameraMode,
+    pub centre: (f32, f32), // the point in the world at the centre of the view
+    pub view_width: f32, // in world units
+    pub aspect_ratio: f32, // the view's width over its height
+    pub selected: Option<BoidId>, // the boid to follow
+    pub smoothing: f32, // how much of the way to its target the camera moves each update; 1 to go straight there
+    pub fit_margin: f32, // in zoom to fit, the share of the view left clear on each side of the flock
+}
+
+impl Camera {
+    /// a free camera
+    pub fn new(centre: (f32, f32), view_width: f32, aspect_ratio: f32) -> Camera {
+        return Camera {
+            mode: CameraMode::Free,
+            centre,
+            view_width,
+            aspect_ratio,
+            selected: None,
+            smoothing: 0.1,
+            fit_margin: 0.1,
+        };
+    }
+
+    /// in world units
+    pub fn view_height(&self) -> f32 {
+        return self.view_width / self.aspect_ratio;
+    }
+
+    /// moves on to the next mode, back round to the free camera after zooming to fit
+    pub fn cycle_mode(&mut self) {
+        let mode_idx = CameraMode::ALL.iter().position(|mode| *mode == self.mode).unwrap_or(0);
+        self.mode = CameraMode::ALL[(mode_idx + 1) % CameraMode::ALL.len()];
+        if self.mode == CameraMode::FollowBoid && self.selected.is_none() {
+            self.cycle_mode();
+        }
+    }
+
+    /// moves the view by `offset` world units, and leaves the camera there
+    pub fn pan(&mut self, offset: (f32, f32)) {
+        self.mode = CameraMode::Free;
+        self.centre = (self.centre.0 + offset.0, self.centre.1 + offset.1);
+    }
+
+    /// widens the view by `factor`, or narrows it for a factor below 1. Stops zooming to fit, but a
+    /// camera following something carries on following it at the new zoom
+    pub fn zoom(&mut self, factor: f32) {
+        if self.mode == CameraMode::ZoomToFit {
+            self.mode = CameraMode::Free;
+        }
+        if factor.is_finite() && factor > 0.0 {
+            self.view_width *= factor;
+        }
+    }
+
+    /// where the mode wants the camera's centre and view width to be. None if there's nothing to
+    /// point it at, e.g. a selected boid that's been caught, in which case the camera stays put
+    pub fn target(&self, flock: &Flock) -> Option<((f32, f32), f32)> {
+        return match self.mode {
+            CameraMode::Free => None,
+            CameraMode::FollowBoid => flock.boid(self.selected?).map(|boid| ((boid.x_pos, boid.y_pos), self.view_width)),
+            CameraMode::FollowCentroid => centroid(flock.boids()).map(|centroid| (centroid, self.view_width)),
+            CameraMode::ZoomToFit => {
+                let first = flock.boids().first()?;
+                let (min, max) = flock.boids().iter().fold(((first.x_pos, first.y_pos), (first.x_pos, first.y_pos)), |(min, max), boid| {
+                    ((min.0.min(boid.x_pos), min.1.min(boid.y_pos)), (max.0.max(boid.x_pos), max.1.max(boid.y_pos)))
+                });
+                // wide enough for the flock's width, and for its height once the aspect ratio is taken into account
+                let flock_width = (max.0 - min.0).max((max.1 - min.1) * self.aspect_ratio);
+                let view_width = (flock_width / (1.0 - 2.0 * self.fit_margin)).max(MIN_FIT_VIEW_WIDTH);
+                Some((((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0), view_width))
+            }
+        };
+    }
+
+    /// moves the camera part of the way to its target; call once a frame
+    pub fn update(&mut self, flock: &Flock) {
+        let Some((centre, view_width)) = self.target(flock) else {
+            return;
+        };
+        let smoothing = self.smoothing.clamp(0.0, 1.0);
+        self.centre = (self.centre.0 + (centre.0 - self.centre.0) * smoothing, self.centre.1 + (centre.1 - self.centre.1) * smoothing);
+        self.view_width *= (view_width / self.view_width).powf(smoothing);
+    }
+
+    /// moves the camera straight to its target, e.g. before the first frame
+    pub fn jump_to_target(&mut self, flock: &Flock) {
+        if let Some((centre, view_width)) = self.target(flock) {
+            self.centre = centre;
+            self.view_width = view_width;
+        }
+    }
+
+    /// where `point` in the world is in a view `width` by `height` pixels, from its top left corner
+    pub fn world_to_screen(&self, point: (f32, f32), width: usize, height: usize) -> (f32, f32) {
+        let pixels_per_unit = width as f32 / self.view_width;
+        return ((point.0 - self.centre.0) * pixels_per_unit + width as f32 / 2.0,
+                (point.1 - self.centre.1) * pixels_per_unit + height as f32 / 2.0);
+    }
+
+    /// the point in the world at `point` in the view, e.g. under the cursor; the opposite of `world_to_screen`
+    pub fn screen_to_world(&self, point: (f32, f32), width: usize, height: usize) -> (f32, f32) {
+        let units_per_pixel = self.view_width / width as f32;
+        return ((point.0 - width as f32 / 2.0) * units_per_pixel + self.centre.0,
+                (point.1 - height as f32 / 2.0) * units_per_pixel + self.centre.1);
+    }
+}
+
+fn centroid(boids: &[Boid]) -> Option<(f32, f32)> {
+    if boids.is_empty() {
+        return None;
+    }
+    let num_boids = boids.len() as f32;
+    return Some((boids.iter().map(|boid| boid.x_pos).sum::<f32>() / num_boids, boids.iter().map(|boid| boid.y_pos).sum::<f32>() / num_boids));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flock_of(boids: Vec<Boid>) -> Flock {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids(boids);
+        return flock;
+    }
+
+    #[test]
+    fn test_cycling_skips_following_with_nothing_selected() {
+        let mut camera = Camera::new((0.0, 0.0), 100.0, 2.0);
+        camera.cycle_mode();
+        assert_eq!(camera.mode, CameraMode::FollowCentroid);
+        camera.cycle_mode();
+        camera.cycle_mode();
+        assert_eq!(camera.mode, CameraMode::Free);
+        camera.selected = Some(BoidId(1));
+        camera.cycle_mode();
+        assert_eq!(camera.mode, CameraMode::FollowBoid);
+        assert_eq!(CameraMode::parse("zoom-to-fit").unwrap(), CameraMode::ZoomToFit);
+        assert!(CameraMode::parse("orbit").is_err());
+    }
+
+    #[test]
+    fn test_following_glides_towards_target() {
+        let flock = flock_of(vec![Boid::new(10.0, 0.0, 0.0, 0.0), Boid::new(30.0, 20.0, 0.0, 0.0)]);
+        let mut camera = Camera { mode: CameraMode::FollowCentroid, smoothing: 0.5, ..Camera::new((0.0, 0.0), 100.0, 2.0) };
+        camera.update(&flock);
+        assert_eq!((camera.centre, camera.view_width), ((10.0, 5.0), 100.0));
+        camera.update(&flock);
+        assert_eq!(camera.centre, (15.0, 7.5));
+
+        camera.mode = CameraMode::FollowBoid;
+        camera.selected = Some(flock.boids()[1].id);
+        camera.jump_to_target(&flock);
+        assert_eq!(camera.centre, (30.0, 20.0));
+        // once the boid has gone, the camera stays where it was
+        camera.selected = Some(BoidId(99));
+        camera.update(&flock);
+        assert_eq!(camera.centre, (30.0, 20.0));
+    }
+
+    #[test]
+    fn test_zoom_to_fit_keeps_flock_in_view() {
+        // twice as tall as wide, in a view twice as wide as tall, so the height decides the zoom
+        let flock = flock_of(vec![Boid::new(-10.0, -40.0, 0.0, 0.0), Boid::new(30.0, 40.0, 0.0, 0.0)]);
+        let mut camera = Camera { mode: CameraMode::ZoomToFit, fit_margin: 0.25, ..Camera::new((0.0, 0.0), 80.0, 2.0) };
+        assert_eq!(camera.target(&flock), Some(((10.0, 0.0), 320.0)));
+        camera.smoothing = 0.5;
+        camera.update(&flock);
+        assert_eq!((camera.centre, camera.view_width), ((5.0, 0.0), 160.0));
+
+        camera.zoom(0.5);
+        assert_eq!((camera.mode, camera.view_width), (CameraMode::Free, 80.0));
+        assert_eq!(camera.world_to_screen((15.0, 0.0), 400, 200), (250.0, 100.0));
+        assert_eq!(camera.screen_to_world((250.0, 100.0), 400, 200), (15.0, 0.0));
+    }
+}
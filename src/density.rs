@@ -0,0 +1,100 @@
+//! Crowd pressure. With a density response, a boid's repulsion and cohesion depend on how many
+//! boids it can see: those in a dense core push apart harder and pull together less, while
+//! stragglers with few neighbours cohere more strongly, so the flock evens out its density.
+
+use anyhow::{anyhow, Context, Result};
+use crate::{check_finite, is_positive_and_finite, CreationError, InvalidFlockConfig};
+
+/// Repulsion is scaled by `(local boids / reference_density)^exponent` and cohesion by its inverse,
+/// with neither scaled up or down by more than `max_scale` times
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DensityResponse {
+    pub reference_density: f32, // the number of local boids at which repulsion and cohesion are unchanged
+    pub exponent: f32, // how sharply they respond to density; 0 for not at all, 1 in proportion to it
+    pub max_scale: f32, // at least 1
+}
+
+impl DensityResponse {
+    pub fn new(reference_density: f32, exponent: f32, max_scale: f32) -> Result<DensityResponse, InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = check_finite(exponent, "density response exponent").into_iter().collect();
+        if !is_positive_and_finite(reference_density) {
+            errors.push(CreationError::DensityResponseParameterIsNotPositive("reference density".to_string()));
+        }
+        if !(max_scale.is_finite() && max_scale >= 1.0) {
+            errors.push(CreationError::DensityResponseMaxScaleIsBelowOne);
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(DensityResponse { reference_density, exponent, max_scale });
+    }
+
+    /// `reference_density:exponent:max_scale`, e.g. `8:1:4`
+    pub fn parse(text: &str) -> Result<DensityResponse> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let [reference_density, exponent, max_scale] = parts.as_slice() else {
+            return Err(anyhow!("density response '{}' should be reference_density:exponent:max_scale", text));
+        };
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        return Ok(DensityResponse::new(parse_float(reference_density)?, parse_float(exponent)?, parse_float(max_scale)?)?);
+    }
+
+    /// how much to scale repulsion by for a boid with `num_local_boids` in its local radius.
+    /// Cohesion is scaled by the inverse
+    pub fn crowd_pressure(&self, num_local_boids: usize) -> f32 {
+        let pressure = (num_local_boids as f32 / self.reference_density).powf(self.exponent);
+        return pressure.clamp(1.0 / self.max_scale, self.max_scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Boid, Flock};
+
+    #[test]
+    fn test_crowd_pressure_follows_curve_within_limits() {
+        let density_response = DensityResponse::new(4.0, 1.0, 3.0).unwrap();
+        assert_eq!(density_response.crowd_pressure(4), 1.0);
+        assert_eq!(density_response.crowd_pressure(8), 2.0);
+        assert_eq!(density_response.crowd_pressure(2), 0.5);
+        assert_eq!(density_response.crowd_pressure(40), 3.0);
+        assert_eq!(density_response.crowd_pressure(0), 1.0 / 3.0);
+
+        let squared = DensityResponse::parse("4:2:10").unwrap();
+        assert_eq!(squared.crowd_pressure(8), 4.0);
+    }
+
+    #[test]
+    fn test_invalid_density_response_rejected() {
+        assert_eq!(DensityResponse::new(0.0, f32::NAN, 0.5).unwrap_err().errors, vec![
+            CreationError::ValueIsNotFinite("density response exponent".to_string()),
+            CreationError::DensityResponseParameterIsNotPositive("reference density".to_string()),
+            CreationError::DensityResponseMaxScaleIsBelowOne,
+        ]);
+        assert!(DensityResponse::parse("4:1").is_err());
+    }
+
+    #[test]
+    fn test_crowded_boid_pushed_harder() {
+        // a boid crowded by one neighbour, with three more further off making the crowd
+        let boids = vec![
+            Boid::new(0.0, 0.0, 0.0, 0.0),
+            Boid::new(0.5, 0.0, 0.0, 0.0),
+            Boid::new(0.0, 5.0, 0.0, 0.0),
+            Boid::new(0.0, -5.0, 0.0, 0.0),
+            Boid::new(-5.0, 0.0, 0.0, 0.0),
+        ];
+        let x_vel_after_step = |density_response: Option<DensityResponse>| {
+            let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.0, 0.0).unwrap();
+            flock.boids = boids.clone();
+            flock.set_density_response(density_response);
+            flock.step();
+            return flock.boids()[0].x_vel;
+        };
+        let unscaled = x_vel_after_step(None);
+        // four local boids against a reference of two doubles the push
+        let scaled = x_vel_after_step(Some(DensityResponse::new(2.0, 1.0, 4.0).unwrap()));
+        assert_eq!(scaled, 2.0 * unscaled);
+    }
+}
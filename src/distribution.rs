@@ -0,0 +1,173 @@
+//! Where randomly generated boids start out, centred on the origin.
+
+use std::f32::consts::TAU;
+use anyhow::{anyhow, Context, Result};
+use rand::rngs::StdRng;
+use rand::Rng;
+use crate::{is_positive_and_finite, CreationError, InvalidFlockConfig};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InitialDistribution {
+    /// evenly spread over a width x height rectangle
+    UniformOverFrame { width: f32, height: f32 },
+    /// bunched around the centre, thinning out with distance
+    GaussianBlob { std_dev: f32 },
+    /// in a band `thickness` wide around a circle
+    Ring { radius: f32, thickness: f32 },
+    /// `n` gaussian blobs evenly spaced around a circle of radius `spread`
+    MultipleClusters { n: usize, spread: f32, std_dev: f32 },
+}
+
+impl Default for InitialDistribution {
+    fn default() -> Self {
+        return InitialDistribution::GaussianBlob { std_dev: 50.0 };
+    }
+}
+
+impl InitialDistribution {
+    /// `name:param:param...`, with the parameters in the order they're declared, e.g. `ring:100:10`
+    pub fn parse(text: &str) -> Result<InitialDistribution> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let param = |idx: usize| -> Result<f32> {
+            let part = parts.get(idx).ok_or_else(|| anyhow!("'{}' is missing parameters", text))?;
+            return part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        };
+        let num_params = match parts[0] {
+            "uniform" | "ring" => 2,
+            "gaussian" => 1,
+            "clusters" => 3,
+            name => return Err(anyhow!("unknown distribution '{}'; expected uniform, gaussian, ring or clusters", name)),
+        };
+        if parts.len() != num_params + 1 {
+            return Err(anyhow!("'{}' should have {} parameters", text, num_params));
+        }
+        let distribution = match parts[0] {
+            "uniform" => InitialDistribution::UniformOverFrame { width: param(1)?, height: param(2)? },
+            "gaussian" => InitialDistribution::GaussianBlob { std_dev: param(1)? },
+            "ring" => InitialDistribution::Ring { radius: param(1)?, thickness: param(2)? },
+            _ => InitialDistribution::MultipleClusters {
+                n: parts[1].parse().with_context(|| format!("'{}' in '{}' is not a number of clusters", parts[1], text))?,
+                spread: param(2)?,
+                std_dev: param(3)?,
+            },
+        };
+        distribution.validate()?;
+        return Ok(distribution);
+    }
+
+    pub fn validate(&self) -> Result<(), InvalidFlockConfig> {
+        let params: Vec<(&str, f32)> = match self {
+            InitialDistribution::UniformOverFrame { width, height } => vec![("width", *width), ("height", *height)],
+            InitialDistribution::GaussianBlob { std_dev } => vec![("std_dev", *std_dev)],
+            InitialDistribution::Ring { radius, thickness } => vec![("radius", *radius), ("thickness", *thickness)],
+            InitialDistribution::MultipleClusters { n, spread, std_dev } => vec![("n", *n as f32), ("spread", *spread), ("std_dev", *std_dev)],
+        };
+        let errors: Vec<CreationError> = params
+            .into_iter()
+            .filter(|(_, value)| !is_positive_and_finite(*value))
+            .map(|(name, _)| CreationError::DistributionParameterIsNotPositive(name.to_string()))
+            .collect();
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(());
+    }
+
+    pub fn generate_positions(&self, num_positions: usize, rng: &mut StdRng) -> Vec<(f32, f32)> {
+        return (0..num_positions)
+            .map(|position_idx| match self {
+                InitialDistribution::UniformOverFrame { width, height } => (
+                    rng.gen_range(-width / 2.0..=width / 2.0),
+                    rng.gen_range(-height / 2.0..=height / 2.0),
+                ),
+                InitialDistribution::GaussianBlob { std_dev } => {
+                    let (x, y) = standard_normal_pair(rng);
+                    (x * std_dev, y * std_dev)
+                }
+                InitialDistribution::Ring { radius, thickness } => {
+                    let dist_from_centre = radius + rng.gen_range(-thickness / 2.0..=thickness / 2.0);
+                    let angle = rng.gen_range(0.0..TAU);
+                    (dist_from_centre * angle.cos(), dist_from_centre * angle.sin())
+                }
+                InitialDistribution::MultipleClusters { n, spread, std_dev } => {
+                    // deal the boids out between the clusters in turn, so they're all the same size
+                    let cluster_angle = TAU * (position_idx % n) as f32 / *n as f32;
+                    let (x, y) = standard_normal_pair(rng);
+                    (spread * cluster_angle.cos() + x * std_dev, spread * cluster_angle.sin() + y * std_dev)
+                }
+            })
+            .collect();
+    }
+}
+
+/// two independent samples from a normal distribution with mean 0 and standard deviation 1 (Box-Muller)
+fn standard_normal_pair(rng: &mut StdRng) -> (f32, f32) {
+    // 1 - gen() is in (0, 1], avoiding ln(0)
+    let radius = (-2.0 * (1.0 - rng.gen::<f32>()).ln()).sqrt();
+    let angle = rng.gen_range(0.0..TAU);
+    return (radius * angle.cos(), radius * angle.sin());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn mean_and_std_dev(values: &[f32]) -> (f32, f32) {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / values.len() as f32;
+        return (mean, variance.sqrt());
+    }
+
+    #[test]
+    fn test_uniform_over_frame_stays_in_frame() {
+        let positions = InitialDistribution::UniformOverFrame { width: 40.0, height: 10.0 }.generate_positions(1000, &mut StdRng::seed_from_u64(1));
+        assert!(positions.iter().all(|(x, y)| x.abs() <= 20.0 && y.abs() <= 5.0));
+        // and reaches most of the way to the edges
+        assert!(positions.iter().any(|(x, _)| *x > 18.0) && positions.iter().any(|(x, _)| *x < -18.0));
+    }
+
+    #[test]
+    fn test_gaussian_blob_spread() {
+        let positions = InitialDistribution::GaussianBlob { std_dev: 10.0 }.generate_positions(5000, &mut StdRng::seed_from_u64(2));
+        let xs: Vec<f32> = positions.iter().map(|(x, _)| *x).collect();
+        let (mean, std_dev) = mean_and_std_dev(&xs);
+        assert!(mean.abs() < 0.5);
+        assert!((std_dev - 10.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_ring_stays_in_band() {
+        let positions = InitialDistribution::Ring { radius: 50.0, thickness: 4.0 }.generate_positions(1000, &mut StdRng::seed_from_u64(3));
+        assert!(positions.iter().all(|(x, y)| (x.hypot(*y) - 50.0).abs() <= 2.0 + 1e-3));
+    }
+
+    #[test]
+    fn test_multiple_clusters_around_each_centre() {
+        let positions = InitialDistribution::MultipleClusters { n: 4, spread: 100.0, std_dev: 1.0 }.generate_positions(400, &mut StdRng::seed_from_u64(4));
+        let centres = [(100.0, 0.0), (0.0, 100.0), (-100.0, 0.0), (0.0, -100.0)];
+        for (cluster, (centre_x, centre_y)) in centres.iter().enumerate() {
+            let num_near_centre = positions.iter().filter(|(x, y)| (x - centre_x).hypot(y - centre_y) < 10.0).count();
+            assert_eq!(num_near_centre, 100, "cluster {}", cluster);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(InitialDistribution::parse("ring:100:10").unwrap(), InitialDistribution::Ring { radius: 100.0, thickness: 10.0 });
+        assert_eq!(InitialDistribution::parse("clusters:3:80:5").unwrap(), InitialDistribution::MultipleClusters { n: 3, spread: 80.0, std_dev: 5.0 });
+        assert!(InitialDistribution::parse("gaussian").is_err());
+        assert!(InitialDistribution::parse("gaussian:5:5").is_err());
+        assert!(InitialDistribution::parse("square:5").is_err());
+        assert!(InitialDistribution::parse("clusters:0:80:5").is_err());
+    }
+
+    #[test]
+    fn test_non_positive_parameters_rejected() {
+        let result = InitialDistribution::UniformOverFrame { width: 0.0, height: -1.0 }.validate();
+        assert_eq!(result.unwrap_err().errors, vec![
+            CreationError::DistributionParameterIsNotPositive("width".to_string()),
+            CreationError::DistributionParameterIsNotPositive("height".to_string()),
+        ]);
+    }
+}
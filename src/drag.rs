@@ -0,0 +1,145 @@
+//! Picking boids up and throwing them. Pressing on a boid picks it up and selects it, so the camera
+//! can follow it; while it's held it sits out the flock's steps, staying wherever it's dragged to.
+//! Letting go flings it off in the direction it was being dragged, at the speed it was dragged at.
+//!
+//! The front end turns the cursor into a point in the world, e.g. with `Camera::screen_to_world`,
+//! and calls `press` when the button goes down, `drag` every frame it's held and `release` when
+//! it comes up.
+
+use crate::camera::Camera;
+use crate::{BoidId, Flock};
+
+// how much of the cursor's latest movement goes into the speed a boid's flung at; the rest is from
+// the moves before, so a jitter just before letting go doesn't send it off somewhere unexpected
+const DRAG_SMOOTHING: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drag {
+    pub pick_radius: f32, // how close to a boid a press has to be to pick it up, in world units
+    pub fling_scale: f32, // released boids fly off at the cursor's speed, in world units a frame, times this
+    cursor_velocity: (f32, f32), // smoothed over the latest frames
+    last_point: Option<(f32, f32)>, // where the cursor was last frame, while holding a boid
+}
+
+impl Drag {
+    pub fn new(pick_radius: f32, fling_scale: f32) -> Drag {
+        return Drag { pick_radius, fling_scale, cursor_velocity: (0.0, 0.0), last_point: None };
+    }
+
+    /// picks up and selects the boid nearest `point`, if there's one within the pick radius, and
+    /// returns its id. Pressing on empty space leaves the selection as it was
+    pub fn press(&mut self, flock: &mut Flock, point: (f32, f32), camera: &mut Camera) -> Option<BoidId> {
+        let id = flock.boid_near(point, self.pick_radius)?;
+        if !flock.hold_boid(id) {
+            return None;
+        }
+        camera.selected = Some(id);
+        flock.place_held_boid(point);
+        self.cursor_velocity = (0.0, 0.0);
+        self.last_point = Some(point);
+        return Some(id);
+    }
+
+    /// moves the held boid to `point`; call once a frame while the button's held down
+    pub fn drag(&mut self, flock: &mut Flock, point: (f32, f32)) {
+        let Some(last_point) = self.last_point else {
+            return;
+        };
+        let moved = (point.0 - last_point.0, point.1 - last_point.1);
+        self.cursor_velocity = (self.cursor_velocity.0 + (moved.0 - self.cursor_velocity.0) * DRAG_SMOOTHING,
+                                self.cursor_velocity.1 + (moved.1 - self.cursor_velocity.1) * DRAG_SMOOTHING);
+        self.last_point = Some(point);
+        flock.place_held_boid(point);
+    }
+
+    /// lets go of the held boid, flinging it at the speed it was being dragged at
+    pub fn release(&mut self, flock: &mut Flock) {
+        if self.last_point.take().is_none() {
+            return;
+        }
+        flock.release_held_boid((self.cursor_velocity.0 * self.fling_scale, self.cursor_velocity.1 * self.fling_scale));
+    }
+
+    pub fn is_holding(&self) -> bool {
+        return self.last_point.is_some();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boid_state::{BoidState, BoidStates, StateRules};
+    use crate::leader::{Leader, LeaderFollowing};
+    use crate::Boid;
+
+    #[test]
+    fn test_held_boid_sits_out_steps_then_is_flung() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(3.0, 0.0, 0.0, 1.0), Boid::new(6.0, 0.0, 0.0, 1.0)]);
+        let mut camera = Camera::new((0.0, 0.0), 100.0, 1.0);
+        let mut drag = Drag::new(1.0, 2.0);
+        assert_eq!(drag.press(&mut flock, (10.0, 10.0), &mut camera), None);
+        let held = drag.press(&mut flock, (3.5, 0.0), &mut camera).unwrap();
+        assert_eq!((held, camera.selected, flock.held_boid()), (flock.boids()[1].id, Some(held), Some(held)));
+
+        for step in 1..=3 {
+            drag.drag(&mut flock, (3.5, step as f32));
+            flock.step();
+        }
+        // the held boid stays put and in its place among the others, which still flock with it
+        assert_eq!(flock.boids()[1].id, held);
+        assert_eq!((flock.boids()[1].x_pos, flock.boids()[1].y_pos), (3.5, 3.0));
+        assert_eq!((flock.boids()[1].x_vel, flock.boids()[1].y_vel), (0.0, 1.0));
+        assert_eq!(flock.cluster_labels().len(), 3);
+
+        drag.release(&mut flock);
+        assert!(!drag.is_holding());
+        assert_eq!(flock.held_boid(), None);
+        let flung = flock.boid(held).unwrap();
+        assert_eq!((flung.x_vel, flung.y_vel), (0.0, 1.75));
+        flock.step();
+        assert!(flock.boid(held).unwrap().y_pos > 3.0);
+    }
+
+    #[test]
+    fn test_held_boid_keeps_its_state_and_energy() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(20.0, 0.0, 1.0, 0.0), Boid::new(40.0, 0.0, 1.0, 0.0)]);
+        flock.set_boid_states(BoidStates::new(StateRules { tiring: 5.0, ..StateRules::default() }).unwrap());
+        flock.step();
+        let held = flock.boids()[1].id;
+        let before = *flock.boid_states().unwrap().condition(held).unwrap();
+        assert!(before.energy < 100.0);
+
+        let mut camera = Camera::new((0.0, 0.0), 100.0, 1.0);
+        let mut drag = Drag::new(1.0, 1.0);
+        drag.press(&mut flock, (20.0, 0.0), &mut camera).unwrap();
+        for step in 1..=3 {
+            drag.drag(&mut flock, (20.0 + step as f32, 0.0));
+            flock.step();
+            assert_eq!(flock.boid_states().unwrap().condition(held), Some(&before));
+            assert_eq!(flock.boid_state(held), Some(BoidState::Flocking));
+        }
+        // the others carried on tiring, and it carries on from where it was once let go
+        assert!(flock.boid_states().unwrap().condition(flock.boids()[0].id).unwrap().energy < before.energy);
+        drag.release(&mut flock);
+        flock.step();
+        assert!(flock.boid_states().unwrap().condition(held).unwrap().energy < before.energy);
+    }
+
+    #[test]
+    fn test_leaders_cannot_be_picked_up() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(5.0, 0.0, 1.0, 0.0)]);
+        flock.set_leader_following(LeaderFollowing::new(vec![Leader::new(1, vec![(50.0, 0.0)], 1.0)], 1.0, 0.5, 2).unwrap());
+        let mut camera = Camera::new((0.0, 0.0), 100.0, 1.0);
+        let mut drag = Drag::new(1.0, 1.0);
+        assert_eq!(drag.press(&mut flock, (5.0, 0.0), &mut camera), None);
+        assert_eq!(camera.selected, None);
+
+        // holding a boid before a leader doesn't lose track of the leader as the held boid sits out
+        drag.press(&mut flock, (0.0, 0.0), &mut camera).unwrap();
+        flock.step();
+        assert!(flock.leader_following_mut().unwrap().is_leader(1));
+    }
+}
@@ -0,0 +1,36 @@
+//! Things that happen to boids during a step, for UIs, sound or loggers to react to without
+//! reaching into the step itself. The flock keeps the events from its latest step, available
+//! from `Flock::events` until the next step replaces them. Boids are given by id, so an event
+//! can be matched up with the boid after others have left the flock.
+
+use crate::boid_state::BoidState;
+use crate::hunting::HuntState;
+use crate::BoidId;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlockEvent {
+    /// the boid ran into the arena's walls, and was bounced or slid back in to `position`, or
+    /// bounced off a free-standing wall to `position`
+    WallHit { boid: BoidId, position: (f32, f32) },
+    /// the boid ran into the obstacle with this index in `Obstacles::obstacles`, or it ran into the boid,
+    /// and the boid was bounced or pushed out to `position`
+    ObstacleHit { boid: BoidId, obstacle: usize, position: (f32, f32) },
+    /// the boid went into one end of a portal at `from` and came out of the other at `to`
+    Teleported { boid: BoidId, from: (f32, f32), to: (f32, f32) },
+    /// the boid reached the food source with this index in `Foraging::food_sources` and ate it
+    FoodEaten { boid: BoidId, food: usize },
+    /// the predator caught the boid at `position`, and it was taken out of the flock
+    BoidCaught { boid: BoidId, position: (f32, f32) },
+    /// the boid has changed from doing one thing to another, e.g. from flocking to fleeing
+    BoidStateChanged { boid: BoidId, from: BoidState, to: BoidState },
+    /// the hunting predator has changed from doing one thing to another
+    PredatorStateChanged { from: HuntState, to: HuntState },
+    /// the cluster numbered `from` before the step has broken up: `part`, now `part_size` boids,
+    /// has gone its own way from `main`, the largest piece at `main_size` boids. A cluster that
+    /// breaks into three gives two of these. Clusters are numbered afresh every step, as by
+    /// `Flock::cluster_labels`, so `from` is numbered as before the step and the pieces as after it
+    ClusterSplit { from: usize, main: usize, main_size: usize, part: usize, part_size: usize },
+    /// clusters `main` and `part` from before the step, of `main_size` and `part_size` boids, have
+    /// joined up into the cluster numbered `into` after it. `main` is the largest of the clusters joining
+    ClusterMerge { into: usize, main: usize, main_size: usize, part: usize, part_size: usize },
+}
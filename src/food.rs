@@ -0,0 +1,190 @@
+//! Food placed in the world. Boids steer towards the nearest food they can see,
+//! and eat it when they reach it; eaten food regrows in the same place after a while.
+
+use anyhow::{anyhow, Context, Result};
+use crate::{check_finite, check_float_between_zero_and_one, Boid, CreationError, InvalidFlockConfig};
+use crate::events::FlockEvent;
+
+#[derive(Clone, Debug)]
+pub struct FoodSource {
+    pub x_pos: f32,
+    pub y_pos: f32,
+    ticks_until_regrown: u32, // 0 while the food is available to eat
+}
+
+impl FoodSource {
+    pub fn new(x_pos: f32, y_pos: f32) -> FoodSource {
+        return FoodSource {
+            x_pos,
+            y_pos,
+            ticks_until_regrown: 0,
+        };
+    }
+
+    pub fn is_available(&self) -> bool {
+        return self.ticks_until_regrown == 0;
+    }
+
+    fn squared_dist_to_boid(&self, boid: &Boid) -> f32 {
+        return (self.x_pos - boid.x_pos).powi(2) + (self.y_pos - boid.y_pos).powi(2);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Foraging {
+    pub food_sources: Vec<FoodSource>,
+    pub attraction_factor: f32, // how much a boid wants to move towards food it can see
+    pub max_dist_of_visible_food: f32,
+    pub max_dist_for_eating: f32,
+    pub ticks_to_regrow: u32, // how long eaten food takes to reappear
+}
+
+impl Foraging {
+    pub fn new(food_sources: Vec<FoodSource>,
+               attraction_factor: f32,
+               max_dist_of_visible_food: f32,
+               max_dist_for_eating: f32,
+               ticks_to_regrow: u32
+    ) -> Result<Foraging, InvalidFlockConfig> {
+        let foraging = Foraging {
+            food_sources,
+            attraction_factor,
+            max_dist_of_visible_food,
+            max_dist_for_eating,
+            ticks_to_regrow,
+        };
+        foraging.validate()?;
+        return Ok(foraging);
+    }
+
+    /// `attraction_factor:max_dist_of_visible_food:max_dist_for_eating:ticks_to_regrow:x,y;x,y;...`,
+    /// e.g. `0.05:40:2:300:100,0;-100,50` for two patches of food, seen from 40 away
+    pub fn parse(text: &str) -> Result<Foraging> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let [attraction_factor, max_dist_of_visible_food, max_dist_for_eating, ticks_to_regrow, food_sources] = parts.as_slice() else {
+            return Err(anyhow!("food '{}' should be attraction_factor:max_dist_of_visible_food:max_dist_for_eating:ticks_to_regrow:x,y;x,y;...", text));
+        };
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        let food_sources = food_sources.split(';').map(|part| {
+            let (x, y) = part.split_once(',').ok_or_else(|| anyhow!("'{}' in '{}' should be x,y", part, text))?;
+            return Ok(FoodSource::new(parse_float(x)?, parse_float(y)?));
+        }).collect::<Result<Vec<FoodSource>>>()?;
+        let ticks_to_regrow = ticks_to_regrow.parse().with_context(|| format!("'{}' in '{}' is not a number of ticks", ticks_to_regrow, text))?;
+        return Ok(Foraging::new(food_sources, parse_float(attraction_factor)?, parse_float(max_dist_of_visible_food)?,
+                                parse_float(max_dist_for_eating)?, ticks_to_regrow)?);
+    }
+
+    fn validate(&self) -> Result<(), InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = check_float_between_zero_and_one(self.attraction_factor, "food attraction".to_string())
+            .into_iter()
+            .chain(check_finite(self.max_dist_of_visible_food, "food visibility distance"))
+            .chain(check_finite(self.max_dist_for_eating, "food eating distance"))
+            .collect();
+        if self.max_dist_for_eating >= self.max_dist_of_visible_food {
+            errors.push(CreationError::FoodIsEatenBeforeItCanBeSeen);
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(());
+    }
+
+    /// steers each boid towards the nearest available food within sight,
+    /// then lets boids eat any food they've reached and regrows food eaten earlier
+    pub(crate) fn apply(&mut self, boids: &mut [Boid], events: &mut Vec<FlockEvent>) {
+        for food in &mut self.food_sources {
+            food.ticks_until_regrown = food.ticks_until_regrown.saturating_sub(1);
+        }
+
+        for boid in boids.iter_mut() {
+            let Some(nearest_food) = self.nearest_visible_food(boid) else {
+                continue;
+            };
+            let food = &mut self.food_sources[nearest_food];
+            if food.squared_dist_to_boid(boid) < self.max_dist_for_eating.powi(2) {
+                // with boid states set, the event is what gives the boid its energy back
+                food.ticks_until_regrown = self.ticks_to_regrow;
+                events.push(FlockEvent::FoodEaten { boid: boid.id, food: nearest_food });
+                continue;
+            }
+            boid.x_vel += (food.x_pos - boid.x_pos) * self.attraction_factor;
+            boid.y_vel += (food.y_pos - boid.y_pos) * self.attraction_factor;
+        }
+    }
+
+    pub(crate) fn can_see_food(&self, boid: &Boid) -> bool {
+        return self.nearest_visible_food(boid).is_some();
+    }
+
+    fn nearest_visible_food(&self, boid: &Boid) -> Option<usize> {
+        return self.food_sources
+            .iter()
+            .enumerate()
+            .filter(|(_, food)| food.is_available())
+            .map(|(food_idx, food)| (food_idx, food.squared_dist_to_boid(boid)))
+            .filter(|(_, squared_dist)| *squared_dist < self.max_dist_of_visible_food.powi(2))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(food_idx, _)| food_idx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoidId;
+
+    #[test]
+    fn test_boid_attracted_to_nearest_visible_food() {
+        let mut foraging = Foraging::new(vec![FoodSource::new(10.0, 0.0), FoodSource::new(0.0, 4.0), FoodSource::new(0.0, 1000.0)], 0.5, 20.0, 1.0, 10).unwrap();
+        let mut boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0)];
+
+        foraging.apply(&mut boids, &mut Vec::new());
+        assert_eq!(boids[0].x_vel, 0.0);
+        assert_eq!(boids[0].y_vel, 2.0);
+    }
+
+    #[test]
+    fn test_food_eaten_then_regrown() {
+        let mut foraging = Foraging::new(vec![FoodSource::new(0.5, 0.0)], 0.5, 20.0, 1.0, 2).unwrap();
+        let mut boids = vec![Boid { id: BoidId(3), ..Boid::new(0.0, 0.0, 0.0, 0.0) }];
+
+        let mut events = Vec::new();
+        foraging.apply(&mut boids, &mut events);
+        assert_eq!(events, vec![FlockEvent::FoodEaten { boid: BoidId(3), food: 0 }]);
+        assert!(!foraging.food_sources[0].is_available());
+        assert_eq!(boids[0].x_vel, 0.0);
+
+        // nothing left to steer towards while the food regrows
+        boids[0].x_pos = 5.0;
+        foraging.apply(&mut boids, &mut Vec::new());
+        assert_eq!(boids[0].x_vel, 0.0);
+        foraging.apply(&mut boids, &mut Vec::new());
+        assert!(foraging.food_sources[0].is_available());
+        assert!(boids[0].x_vel < 0.0);
+    }
+
+    #[test]
+    fn test_foraging_parsed() {
+        let foraging = Foraging::parse("0.05:40:2:300:100,0;-100,50").unwrap();
+        assert_eq!((foraging.attraction_factor, foraging.max_dist_of_visible_food, foraging.max_dist_for_eating, foraging.ticks_to_regrow), (0.05, 40.0, 2.0, 300));
+        assert_eq!(foraging.food_sources.iter().map(|food| (food.x_pos, food.y_pos)).collect::<Vec<_>>(), vec![(100.0, 0.0), (-100.0, 50.0)]);
+        assert!(Foraging::parse("0.05:40:2:300").is_err());
+        assert!(Foraging::parse("0.05:40:2:-1:0,0").is_err());
+        assert!(Foraging::parse("0.05:2:40:300:0,0").is_err());
+    }
+
+    #[test]
+    fn test_invalid_foraging_inputs() {
+        let result = Foraging::new(vec![], 1.5, 1.0, 2.0, 0);
+        assert_eq!(result.unwrap_err().errors, vec![
+            CreationError::FactorShouldBeLessThanOne("food attraction".to_string()),
+            CreationError::FoodIsEatenBeforeItCanBeSeen,
+        ]);
+        assert_eq!(Foraging::new(vec![], 0.5, f32::NAN, 1.0, 0).unwrap_err().errors, vec![
+            CreationError::ValueIsNotFinite("food visibility distance".to_string()),
+        ]);
+        assert_eq!(Foraging::new(vec![], 0.5, 20.0, f32::NAN, 0).unwrap_err().errors, vec![
+            CreationError::ValueIsNotFinite("food eating distance".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,164 @@
+//! Fixed sources of force in the world. Attractors pull boids in, repellers push them away and
+//! vortices swirl them round. Each source only acts on boids within its radius, hardest at its
+//! centre and fading to nothing at the edge.
+
+use anyhow::{anyhow, Context, Result};
+use crate::{is_positive_and_finite, Boid, CreationError, InvalidFlockConfig};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ForceKind {
+    Attractor,
+    Repeller,
+    Vortex { clockwise: bool },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ForceSource {
+    pub kind: ForceKind,
+    pub centre: (f32, f32),
+    pub radius: f32,
+    pub strength: f32, // how much a boid's velocity changes each frame at the centre
+}
+
+impl ForceSource {
+    /// `kind:x,y:radius:strength`, where kind is `attractor`, `repeller`, `vortex` (anticlockwise) or
+    /// `clockwise-vortex`, e.g. `vortex:0,0:50:0.2`
+    pub fn parse(text: &str) -> Result<ForceSource> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let [kind, centre, radius, strength] = parts.as_slice() else {
+            return Err(anyhow!("force source '{}' should be kind:x,y:radius:strength", text));
+        };
+        let kind = match *kind {
+            "attractor" => ForceKind::Attractor,
+            "repeller" => ForceKind::Repeller,
+            "vortex" => ForceKind::Vortex { clockwise: false },
+            "clockwise-vortex" => ForceKind::Vortex { clockwise: true },
+            _ => return Err(anyhow!("unknown force source '{}'; expected attractor, repeller, vortex or clockwise-vortex", kind)),
+        };
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        let (x, y) = centre.split_once(',').ok_or_else(|| anyhow!("centre '{}' in '{}' should be x,y", centre, text))?;
+        return Ok(ForceSource {
+            kind,
+            centre: (parse_float(x)?, parse_float(y)?),
+            radius: parse_float(radius)?,
+            strength: parse_float(strength)?,
+        });
+    }
+
+    /// the change in velocity of a boid at (x, y)
+    fn force_at(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (to_centre_x, to_centre_y) = (self.centre.0 - x, self.centre.1 - y);
+        let dist = (to_centre_x * to_centre_x + to_centre_y * to_centre_y).sqrt();
+        // a boid right at the centre has no direction to be pushed in
+        if dist >= self.radius || dist == 0.0 {
+            return (0.0, 0.0);
+        }
+        let magnitude = self.strength * (1.0 - dist / self.radius);
+        let (towards_x, towards_y) = (to_centre_x / dist, to_centre_y / dist);
+        let (force_x, force_y) = match self.kind {
+            ForceKind::Attractor => (towards_x, towards_y),
+            ForceKind::Repeller => (-towards_x, -towards_y),
+            // at right angles to the centre, a quarter turn on from pointing away from it
+            ForceKind::Vortex { clockwise: false } => (towards_y, -towards_x),
+            ForceKind::Vortex { clockwise: true } => (-towards_y, towards_x),
+        };
+        return (force_x * magnitude, force_y * magnitude);
+    }
+}
+
+#[derive(Debug)]
+pub struct ForceField {
+    pub sources: Vec<ForceSource>,
+}
+
+impl ForceField {
+    pub fn new(sources: Vec<ForceSource>) -> Result<ForceField, InvalidFlockConfig> {
+        let force_field = ForceField { sources };
+        force_field.validate()?;
+        return Ok(force_field);
+    }
+
+    fn validate(&self) -> Result<(), InvalidFlockConfig> {
+        let mut errors = Vec::new();
+        for source in &self.sources {
+            if !source.centre.0.is_finite() || !source.centre.1.is_finite() {
+                errors.push(CreationError::ValueIsNotFinite("force source centre".to_string()));
+            }
+            if !is_positive_and_finite(source.radius) {
+                errors.push(CreationError::ForceSourceParameterIsNotPositive("radius".to_string()));
+            }
+            if !is_positive_and_finite(source.strength) {
+                errors.push(CreationError::ForceSourceParameterIsNotPositive("strength".to_string()));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(());
+    }
+
+    /// the change in velocity of a boid at (x, y), from every source together
+    pub fn force_at(&self, position: (f32, f32)) -> (f32, f32) {
+        return self.sources
+            .iter()
+            .map(|source| source.force_at(position))
+            .fold((0.0, 0.0), |(total_x, total_y), (force_x, force_y)| (total_x + force_x, total_y + force_y));
+    }
+
+    pub(crate) fn apply(&self, boids: &mut [Boid]) {
+        for boid in boids {
+            let (force_x, force_y) = self.force_at((boid.x_pos, boid.y_pos));
+            boid.x_vel += force_x;
+            boid.y_vel += force_y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(kind: ForceKind) -> ForceSource {
+        return ForceSource { kind, centre: (0.0, 0.0), radius: 10.0, strength: 2.0 };
+    }
+
+    #[test]
+    fn test_force_directions() {
+        // halfway out, so half strength
+        assert_eq!(source(ForceKind::Attractor).force_at((5.0, 0.0)), (-1.0, 0.0));
+        assert_eq!(source(ForceKind::Repeller).force_at((5.0, 0.0)), (1.0, 0.0));
+        assert_eq!(source(ForceKind::Vortex { clockwise: false }).force_at((5.0, 0.0)), (0.0, 1.0));
+        assert_eq!(source(ForceKind::Vortex { clockwise: true }).force_at((5.0, 0.0)), (0.0, -1.0));
+    }
+
+    #[test]
+    fn test_no_force_outside_radius_or_at_centre() {
+        assert_eq!(source(ForceKind::Attractor).force_at((10.0, 0.0)), (0.0, 0.0));
+        assert_eq!(source(ForceKind::Repeller).force_at((0.0, 0.0)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_forces_add_up() {
+        let repeller = ForceSource { centre: (10.0, 0.0), ..source(ForceKind::Repeller) };
+        let force_field = ForceField::new(vec![source(ForceKind::Attractor), repeller]).unwrap();
+        let mut boids = vec![Boid::new(5.0, 0.0, 0.0, 0.0)];
+        force_field.apply(&mut boids);
+        // pulled towards the attractor and pushed away from the repeller, both to the left
+        assert_eq!(boids[0].x_vel, -2.0);
+    }
+
+    #[test]
+    fn test_parse_and_validate() {
+        assert_eq!(ForceSource::parse("clockwise-vortex:1,-2:30:0.5").unwrap(),
+                   ForceSource { kind: ForceKind::Vortex { clockwise: true }, centre: (1.0, -2.0), radius: 30.0, strength: 0.5 });
+        assert!(ForceSource::parse("sink:0,0:1:1").is_err());
+        assert!(ForceSource::parse("attractor:0:1:1").is_err());
+        assert!(ForceSource::parse("attractor:0,0:1").is_err());
+
+        let empty = ForceSource { radius: 0.0, strength: -1.0, ..source(ForceKind::Attractor) };
+        assert_eq!(ForceField::new(vec![empty]).unwrap_err().errors, vec![
+            CreationError::ForceSourceParameterIsNotPositive("radius".to_string()),
+            CreationError::ForceSourceParameterIsNotPositive("strength".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,184 @@
+//! Deterministic starting formations, centred on (or for the V, led from) the origin.
+//! Unlike the random distributions, a formation always puts the boids in exactly the same
+//! places, which makes it easy to see how a given set of parameters holds or breaks it up.
+
+use std::f32::consts::TAU;
+use anyhow::{anyhow, Context, Result};
+use crate::{check_finite, is_positive_and_finite, Boid, CreationError, InvalidFlockConfig};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CircleFacing {
+    Outward,
+    Inward,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Formation {
+    /// rows of boids `spacing` apart, as close to square as the flock size allows, all heading along x
+    Grid { spacing: f32 },
+    /// evenly spaced around a circle, each heading straight out from or in to the centre
+    Circle { radius: f32, facing: CircleFacing },
+    /// a leader at the point, with the rest in two trailing wings `spacing` apart,
+    /// each wing `half_angle_degrees` off straight behind the leader. Everyone heads along x
+    V { spacing: f32, half_angle_degrees: f32 },
+}
+
+impl Formation {
+    /// `grid:spacing`, `circle:radius:outward` or `circle:radius:inward`, or `v:spacing:half_angle_degrees`
+    pub fn parse(text: &str) -> Result<Formation> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let param = |idx: usize| -> Result<f32> {
+            let part = parts.get(idx).ok_or_else(|| anyhow!("'{}' is missing parameters", text))?;
+            return part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        };
+        let (formation, num_params) = match parts[0] {
+            "grid" => (Formation::Grid { spacing: param(1)? }, 1),
+            "circle" => {
+                let facing = match parts.get(2) {
+                    Some(&"outward") => CircleFacing::Outward,
+                    Some(&"inward") => CircleFacing::Inward,
+                    _ => return Err(anyhow!("'{}' should end in outward or inward", text)),
+                };
+                (Formation::Circle { radius: param(1)?, facing }, 2)
+            }
+            "v" => (Formation::V { spacing: param(1)?, half_angle_degrees: param(2)? }, 2),
+            name => return Err(anyhow!("unknown formation '{}'; expected grid, circle or v", name)),
+        };
+        if parts.len() != num_params + 1 {
+            return Err(anyhow!("'{}' should have {} parameters", text, num_params));
+        }
+        formation.validate()?;
+        return Ok(formation);
+    }
+
+    pub fn validate(&self) -> Result<(), InvalidFlockConfig> {
+        let params: Vec<(&str, f32)> = match self {
+            Formation::Grid { spacing } => vec![("spacing", *spacing)],
+            Formation::Circle { radius, .. } => vec![("radius", *radius)],
+            Formation::V { spacing, half_angle_degrees } => vec![("spacing", *spacing), ("half angle", *half_angle_degrees)],
+        };
+        let mut errors = Vec::new();
+        for (name, value) in params {
+            if let Some(error) = check_finite(value, name) {
+                errors.push(error);
+            }
+            else if !is_positive_and_finite(value) {
+                errors.push(CreationError::FormationParameterIsNotPositive(name.to_string()));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(());
+    }
+
+    /// every boid heads along at `speed`, which can be any finite number; a negative speed turns them all round
+    pub fn generate_boids(&self, flock_size: usize, speed: f32) -> Result<Vec<Boid>, InvalidFlockConfig> {
+        let mut errors = self.validate().err().map(|invalid| invalid.errors).unwrap_or_default();
+        errors.extend(check_finite(speed, "formation speed"));
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        let boids = match self {
+            Formation::Grid { spacing } => {
+                let num_columns = (flock_size as f32).sqrt().ceil().max(1.0) as usize;
+                let num_rows = flock_size.div_ceil(num_columns);
+                // offsets to put the middle of the grid on the origin
+                let x_offset = (num_columns - 1) as f32 * spacing / 2.0;
+                let y_offset = num_rows.saturating_sub(1) as f32 * spacing / 2.0;
+                (0..flock_size)
+                    .map(|boid_idx| Boid::new(
+                        (boid_idx % num_columns) as f32 * spacing - x_offset,
+                        (boid_idx / num_columns) as f32 * spacing - y_offset,
+                        speed,
+                        0.0,
+                    ))
+                    .collect()
+            }
+            Formation::Circle { radius, facing } => {
+                let direction = match facing {
+                    CircleFacing::Outward => 1.0,
+                    CircleFacing::Inward => -1.0,
+                };
+                (0..flock_size)
+                    .map(|boid_idx| {
+                        let angle = TAU * boid_idx as f32 / flock_size as f32;
+                        Boid::new(radius * angle.cos(), radius * angle.sin(), direction * speed * angle.cos(), direction * speed * angle.sin())
+                    })
+                    .collect()
+            }
+            Formation::V { spacing, half_angle_degrees } => {
+                let half_angle = half_angle_degrees.to_radians();
+                (0..flock_size)
+                    .map(|boid_idx| {
+                        // the leader is 0, then boids alternate between the wings, one place further back each pair
+                        let place_in_wing = boid_idx.div_ceil(2) as f32;
+                        let side = if boid_idx % 2 == 1 { 1.0 } else { -1.0 };
+                        Boid::new(
+                            -place_in_wing * spacing * half_angle.cos(),
+                            side * place_in_wing * spacing * half_angle.sin(),
+                            speed,
+                            0.0,
+                        )
+                    })
+                    .collect()
+            }
+        };
+        return Ok(boids);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(boids: &[Boid]) -> Vec<(f32, f32)> {
+        return boids.iter().map(|boid| (boid.x_pos, boid.y_pos)).collect();
+    }
+
+    #[test]
+    fn test_grid_centred_on_origin() {
+        let boids = Formation::Grid { spacing: 2.0 }.generate_boids(5, 1.5).unwrap();
+        assert_eq!(positions(&boids), vec![(-2.0, -1.0), (0.0, -1.0), (2.0, -1.0), (-2.0, 1.0), (0.0, 1.0)]);
+        assert!(boids.iter().all(|boid| boid.x_vel == 1.5 && boid.y_vel == 0.0));
+    }
+
+    #[test]
+    fn test_circle_facing() {
+        let outward = Formation::Circle { radius: 10.0, facing: CircleFacing::Outward }.generate_boids(4, 2.0).unwrap();
+        assert_eq!((outward[0].x_pos, outward[0].y_pos), (10.0, 0.0));
+        assert_eq!((outward[0].x_vel, outward[0].y_vel), (2.0, 0.0));
+        assert!(outward.iter().all(|boid| (boid.x_pos.hypot(boid.y_pos) - 10.0).abs() < 1e-4));
+
+        let inward = Formation::Circle { radius: 10.0, facing: CircleFacing::Inward }.generate_boids(4, 2.0).unwrap();
+        assert_eq!((inward[0].x_vel, inward[0].y_vel), (-2.0, 0.0));
+    }
+
+    #[test]
+    fn test_v_wings_trail_leader() {
+        let boids = Formation::V { spacing: 2.0, half_angle_degrees: 90.0 }.generate_boids(5, 1.0).unwrap();
+        let rounded: Vec<(f32, f32)> = positions(&boids).iter().map(|(x, y)| (x.round(), y.round())).collect();
+        assert_eq!(rounded, vec![(0.0, 0.0), (0.0, 2.0), (0.0, -2.0), (0.0, 4.0), (0.0, -4.0)]);
+
+        let boids = Formation::V { spacing: 2.0, half_angle_degrees: 30.0 }.generate_boids(3, 1.0).unwrap();
+        assert!(boids[1].x_pos < 0.0 && boids[1].y_pos > 0.0);
+        assert!(boids[2].x_pos < 0.0 && boids[2].y_pos < 0.0);
+        assert!(boids.iter().all(|boid| boid.x_vel == 1.0 && boid.y_vel == 0.0));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Formation::parse("grid:3").unwrap(), Formation::Grid { spacing: 3.0 });
+        assert_eq!(Formation::parse("circle:20:inward").unwrap(), Formation::Circle { radius: 20.0, facing: CircleFacing::Inward });
+        assert_eq!(Formation::parse("v:4:25").unwrap(), Formation::V { spacing: 4.0, half_angle_degrees: 25.0 });
+        assert!(Formation::parse("circle:20").is_err());
+        assert!(Formation::parse("grid:0").is_err());
+        assert!(Formation::parse("v:4:inf").is_err());
+        assert_eq!(Formation::V { spacing: 4.0, half_angle_degrees: f32::INFINITY }.validate().unwrap_err().errors,
+                   vec![CreationError::ValueIsNotFinite("half angle".to_string())]);
+        assert_eq!(Formation::Grid { spacing: 1.0 }.generate_boids(3, f32::NAN).unwrap_err().errors,
+                   vec![CreationError::ValueIsNotFinite("formation speed".to_string())]);
+        assert!(Formation::parse("grid:1:2").is_err());
+        assert!(Formation::parse("line:1").is_err());
+    }
+}
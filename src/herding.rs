@@ -0,0 +1,256 @@
+//! A herding game: the player steers a sheepdog, a predator that scares boids but doesn't catch
+//! them, to drive the flock into a goal before time runs out. The game ends early once every boid
+//! is in the goal, and the score is 100 for each boid herded plus a point for every tick to spare.
+//!
+//! The harder the difficulty, the more skittish the flock, the less time the player has and the
+//! more obstacles stand between the sheepdog and the goal.
+//!
+//! The app steps the game with `HerdingGame::step` in place of `Flock::step`, and shows the time
+//! left and the score from `ticks_remaining` and `score`. `events` says what the player achieved in
+//! the latest step, e.g. to play a sound for it.
+
+use crate::obstacle::{Obstacle, ObstacleMotion, Obstacles};
+use crate::predator::Predator;
+use crate::{check_finite, is_positive_and_finite, Boid, CreationError, Flock, InvalidFlockConfig};
+
+const POINTS_PER_BOID_HERDED: usize = 100;
+
+/// how easily the flock is spooked by the sheepdog, how long the player has to herd it, and what's in the way
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn time_limit(&self) -> u32 {
+        return match self {
+            Difficulty::Easy => 3600,
+            Difficulty::Normal => 2400,
+            Difficulty::Hard => 1200,
+        };
+    }
+
+    /// a sheepdog starting still at `position`. The harder the game, the further away the flock
+    /// notices the dog and the harder it bolts, so it scatters rather than being nudged along
+    pub fn sheepdog(&self, position: (f32, f32)) -> Predator {
+        let (fear_radius, evasion_factor) = match self {
+            Difficulty::Easy => (20.0, 0.1),
+            Difficulty::Normal => (30.0, 0.2),
+            Difficulty::Hard => (45.0, 0.4),
+        };
+        let mut sheepdog = Predator::new(Boid::new(position.0, position.1, 0.0, 0.0), 0.2, 0.1, 4.0, 1.0, fear_radius, evasion_factor)
+            .expect("difficulties give valid sheepdogs");
+        sheepdog.catches_boids = false;
+        return sheepdog;
+    }
+
+    /// still obstacles spaced along the way from where the sheepdog starts, opposite the goal
+    /// across the origin, to the goal, alternately either side of the straight line between them
+    /// so there's always a way round
+    pub fn obstacles(&self, goal_centre: (f32, f32)) -> Vec<Obstacle> {
+        let num_obstacles = match self {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 2,
+            Difficulty::Hard => 4,
+        };
+        let dist_to_goal = (goal_centre.0.powi(2) + goal_centre.1.powi(2)).sqrt();
+        // a goal at the origin leaves no way to the goal to put them along
+        if num_obstacles == 0 || dist_to_goal == 0.0 {
+            return Vec::new();
+        }
+        let radius = 2.0 * dist_to_goal * OBSTACLE_SHARE_OF_THE_WAY;
+        let across = (-goal_centre.1 / dist_to_goal, goal_centre.0 / dist_to_goal);
+        return (1..=num_obstacles).map(|i| {
+            // from -1 at the sheepdog to 1 at the goal
+            let along = 2.0 * i as f32 / (num_obstacles + 1) as f32 - 1.0;
+            let side = if i % 2 == 0 { 1.5 * radius } else { -1.5 * radius };
+            let centre = (goal_centre.0 * along + across.0 * side, goal_centre.1 * along + across.1 * side);
+            return Obstacle::new(centre, radius, ObstacleMotion::Still).expect("difficulties give valid obstacles");
+        }).collect();
+    }
+}
+
+// each obstacle's radius, as a share of the distance from the sheepdog to the goal
+const OBSTACLE_SHARE_OF_THE_WAY: f32 = 0.05;
+
+/// what happened in the game during a step, as opposed to the flock's `FlockEvent`s
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    /// this many more boids are in the goal than before the step
+    BoidsHerded(usize),
+    /// every boid is in the goal
+    Won,
+    /// time ran out with boids still to herd
+    TimeUp,
+}
+
+#[derive(Debug)]
+pub struct HerdingGame {
+    pub goal_centre: (f32, f32),
+    pub goal_radius: f32,
+    pub time_limit: u32, // in ticks
+    ticks_elapsed: u32,
+    num_herded: usize, // boids in the goal after the latest step
+    flock_size: usize,
+    events: Vec<GameEvent>, // what happened during the last step
+}
+
+impl HerdingGame {
+    pub fn new(goal_centre: (f32, f32), goal_radius: f32, time_limit: u32) -> Result<HerdingGame, InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = [check_finite(goal_centre.0, "goal centre"), check_finite(goal_centre.1, "goal centre")]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !is_positive_and_finite(goal_radius) {
+            errors.push(CreationError::GoalRadiusIsNotPositive);
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(HerdingGame { goal_centre, goal_radius, time_limit, ticks_elapsed: 0, num_herded: 0, flock_size: 0, events: Vec::new() });
+    }
+
+    /// gives the flock the difficulty's sheepdog, starting opposite the goal across the origin, and
+    /// its obstacles alongside any it already has, and the player the difficulty's time limit
+    pub fn start(flock: &mut Flock, goal_centre: (f32, f32), goal_radius: f32, difficulty: Difficulty) -> Result<HerdingGame, InvalidFlockConfig> {
+        let mut game = HerdingGame::new(goal_centre, goal_radius, difficulty.time_limit())?;
+        flock.set_predator(difficulty.sheepdog((-goal_centre.0, -goal_centre.1)));
+        let mut obstacles = flock.obstacles().cloned().unwrap_or_else(|| Obstacles::new(Vec::new()));
+        obstacles.obstacles.extend(difficulty.obstacles(goal_centre));
+        if !obstacles.obstacles.is_empty() {
+            flock.set_obstacles(obstacles);
+        }
+        game.count_herded(flock);
+        return Ok(game);
+    }
+
+    pub fn is_in_goal(&self, boid: &Boid) -> bool {
+        return (boid.x_pos - self.goal_centre.0).powi(2) + (boid.y_pos - self.goal_centre.1).powi(2) <= self.goal_radius.powi(2);
+    }
+
+    /// steps the flock, unless the game is over, in which case it's left as it is
+    pub fn step(&mut self, flock: &mut Flock) {
+        self.events.clear();
+        if self.is_over() {
+            return;
+        }
+        flock.step();
+        self.ticks_elapsed += 1;
+        let num_herded_before = self.num_herded;
+        self.count_herded(flock);
+        if self.num_herded > num_herded_before {
+            self.events.push(GameEvent::BoidsHerded(self.num_herded - num_herded_before));
+        }
+        if self.is_won() {
+            self.events.push(GameEvent::Won);
+        }
+        else if self.is_over() {
+            self.events.push(GameEvent::TimeUp);
+        }
+    }
+
+    /// what happened during the latest step; nothing once the game is over
+    pub fn events(&self) -> &[GameEvent] {
+        return &self.events;
+    }
+
+    fn count_herded(&mut self, flock: &Flock) {
+        self.num_herded = flock.boids().iter().filter(|boid| self.is_in_goal(boid)).count();
+        self.flock_size = flock.boids().len();
+    }
+
+    pub fn ticks_remaining(&self) -> u32 {
+        return self.time_limit.saturating_sub(self.ticks_elapsed);
+    }
+
+    pub fn num_herded(&self) -> usize {
+        return self.num_herded;
+    }
+
+    /// over when time's up or every boid is in the goal
+    pub fn is_over(&self) -> bool {
+        return self.ticks_remaining() == 0 || self.is_won();
+    }
+
+    // a game that's yet to count the flock hasn't been won, and nor has one with no flock to herd
+    fn is_won(&self) -> bool {
+        return self.flock_size > 0 && self.num_herded == self.flock_size;
+    }
+
+    /// points for the boids in the goal so far, and for the time to spare once they're all in
+    pub fn score(&self) -> usize {
+        let bonus = if self.is_won() { self.ticks_remaining() as usize } else { 0 };
+        return self.num_herded * POINTS_PER_BOID_HERDED + bonus;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flock_of(boids: Vec<Boid>) -> Flock {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = boids;
+        return flock;
+    }
+
+    #[test]
+    fn test_game_won_once_every_boid_herded() {
+        // one boid already in the goal, and one close by heading into it
+        let mut flock = flock_of(vec![Boid::new(50.0, 0.0, 0.0, 0.0), Boid::new(42.0, 0.0, 1.0, 0.0)]);
+        let mut game = HerdingGame::start(&mut flock, (50.0, 0.0), 5.0, Difficulty::Normal).unwrap();
+        assert_eq!((game.num_herded(), game.is_over()), (1, false));
+        assert_eq!(flock.predator().unwrap().boid.x_pos, -50.0);
+
+        while !game.is_over() {
+            game.step(&mut flock);
+        }
+        assert_eq!(game.events(), [GameEvent::BoidsHerded(1), GameEvent::Won]);
+        assert_eq!(game.num_herded(), 2);
+        assert!(game.ticks_remaining() > Difficulty::Normal.time_limit() - 10);
+        assert_eq!(game.score(), 200 + game.ticks_remaining() as usize);
+    }
+
+    #[test]
+    fn test_game_ends_when_time_runs_out() {
+        let mut flock = flock_of(vec![Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(50.0, 0.0, 0.0, 0.0)]);
+        let mut game = HerdingGame::new((50.0, 0.0), 5.0, 10).unwrap();
+        for _ in 0..10 {
+            game.step(&mut flock);
+        }
+        assert!(game.is_over());
+        assert_eq!(game.events(), [GameEvent::TimeUp]);
+        game.step(&mut flock);
+        assert_eq!(game.events(), []);
+        assert_eq!(game.score(), 100);
+    }
+
+    #[test]
+    fn test_harder_games_put_obstacles_in_the_way() {
+        let mut flock = flock_of(vec![Boid::new(0.0, 0.0, 0.0, 0.0)]);
+        HerdingGame::start(&mut flock, (50.0, 0.0), 5.0, Difficulty::Easy).unwrap();
+        assert!(flock.obstacles().is_none());
+
+        HerdingGame::start(&mut flock, (50.0, 0.0), 5.0, Difficulty::Hard).unwrap();
+        let obstacles = &flock.obstacles().unwrap().obstacles;
+        assert_eq!(obstacles.len(), 4);
+        for obstacle in obstacles {
+            // between the sheepdog and the goal, off to one side of the line between them or the other
+            assert!(obstacle.centre.0 > -50.0 && obstacle.centre.0 < 50.0);
+            assert_eq!(obstacle.centre.1.abs(), 1.5 * obstacle.radius);
+            assert_eq!(obstacle.radius, 5.0);
+        }
+        assert!(Difficulty::Normal.obstacles((0.0, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn test_sheepdog_scares_without_catching() {
+        let mut flock = flock_of(vec![Boid::new(0.5, 0.0, 0.0, 0.0)]);
+        flock.set_predator(Difficulty::Hard.sheepdog((0.0, 0.0)));
+        flock.step();
+        assert_eq!(flock.boids().len(), 1);
+        assert!(flock.boids()[0].x_vel > 0.0);
+    }
+}
@@ -0,0 +1,295 @@
+//! A predator that hunts on its own. It patrols a route until it notices the flock, stalks slowly
+//! towards it, and chases the nearest boid flat out once it's close enough to pounce. Chasing tires
+//! it out, so once its energy runs out, or it has caught its fill, it rests until it's recovered,
+//! then goes back to patrolling. It gives up the hunt if the flock gets away.
+//!
+//! The hunter drives the predator through the same controls as a player would, so it's held to the
+//! same acceleration and turning limits. Each change of state is reported as a
+//! `FlockEvent::PredatorStateChanged`, and renderers colour the predator by its state.
+
+use std::f32::consts::{PI, TAU};
+use crate::events::FlockEvent;
+use crate::steering::pursuit;
+use crate::{is_positive_and_finite, Boid, CreationError, InvalidFlockConfig};
+
+// chasing predators aim at where their prey will be at most this many frames ahead
+const MAX_PREDICTION_TIME: f32 = 10.0;
+// a patrolling predator moves on to its next waypoint once it's this close to the current one
+const WAYPOINT_RADIUS: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HuntState {
+    Patrol,
+    Stalk,
+    Chase,
+    Rest,
+}
+
+impl HuntState {
+    pub const ALL: [HuntState; 4] = [HuntState::Patrol, HuntState::Stalk, HuntState::Chase, HuntState::Rest];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            HuntState::Patrol => "patrol",
+            HuntState::Stalk => "stalk",
+            HuntState::Chase => "chase",
+            HuntState::Rest => "rest",
+        };
+    }
+}
+
+/// what makes the hunter change from one state to the next, and how fast it goes in each
+#[derive(Clone, Debug, PartialEq)]
+pub struct HuntingBehaviour {
+    pub patrol_route: Vec<(f32, f32)>, // visited in turn, round and round; with none, the predator patrols straight on
+    pub patrol_speed: f32,
+    pub stalk_speed: f32, // chasing is at the predator's max speed
+    pub notice_dist: f32, // patrolling stops and stalking starts once a boid is this close, and the hunt is given up once every boid is further
+    pub pounce_dist: f32, // stalking turns into a chase once a boid is this close
+    pub max_energy: f32, // the predator starts with this much, and rests until it's back to it
+    pub chase_cost: f32, // energy used up each tick of chasing
+    pub rest_recovery: f32, // energy recovered each tick of resting
+    pub catches_before_rest: usize, // this many catches within `recent_catch_ticks` and the predator stops to rest
+    pub recent_catch_ticks: u32,
+}
+
+impl Default for HuntingBehaviour {
+    fn default() -> Self {
+        return HuntingBehaviour {
+            patrol_route: Vec::new(),
+            patrol_speed: 1.0,
+            stalk_speed: 0.5,
+            notice_dist: 60.0,
+            pounce_dist: 20.0,
+            max_energy: 100.0,
+            chase_cost: 1.0,
+            rest_recovery: 0.5,
+            catches_before_rest: 3,
+            recent_catch_ticks: 300,
+        };
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Hunting {
+    pub behaviour: HuntingBehaviour,
+    state: HuntState,
+    energy: f32,
+    next_waypoint: usize,
+    ticks_elapsed: u32,
+    catch_ticks: Vec<u32>, // when each catch since the predator last rested was made
+}
+
+impl Hunting {
+    pub fn new(behaviour: HuntingBehaviour) -> Result<Hunting, InvalidFlockConfig> {
+        let mut errors = Vec::new();
+        if behaviour.patrol_route.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+            errors.push(CreationError::ValueIsNotFinite("patrol route point".to_string()));
+        }
+        for (value, param_name) in [
+            (behaviour.patrol_speed, "patrol speed"),
+            (behaviour.stalk_speed, "stalk speed"),
+            (behaviour.notice_dist, "notice distance"),
+            (behaviour.pounce_dist, "pounce distance"),
+            (behaviour.max_energy, "max energy"),
+            (behaviour.chase_cost, "chase cost"),
+            (behaviour.rest_recovery, "rest recovery"),
+        ] {
+            if !is_positive_and_finite(value) {
+                errors.push(CreationError::PredatorParameterIsNotPositive(param_name.to_string()));
+            }
+        }
+        if behaviour.pounce_dist >= behaviour.notice_dist {
+            errors.push(CreationError::PounceDistIsNotWithinNoticeDist);
+        }
+        if behaviour.catches_before_rest == 0 {
+            errors.push(CreationError::PredatorParameterIsNotPositive("catches before rest".to_string()));
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        let energy = behaviour.max_energy;
+        return Ok(Hunting { behaviour, state: HuntState::Patrol, energy, next_waypoint: 0, ticks_elapsed: 0, catch_ticks: Vec::new() });
+    }
+
+    pub fn state(&self) -> HuntState {
+        return self.state;
+    }
+
+    pub fn energy(&self) -> f32 {
+        return self.energy;
+    }
+
+    pub(crate) fn record_catches(&mut self, num_caught: usize) {
+        self.catch_ticks.extend(std::iter::repeat_n(self.ticks_elapsed, num_caught));
+    }
+
+    /// moves on to the state for this tick, given where the predator and boids are, and uses up or
+    /// recovers energy for it. Returns the `(thrust, turn)` controls to steer the predator with
+    pub(crate) fn decide(&mut self, predator: &Boid, heading: f32, limits: (f32, f32, f32), boids: &[Boid], events: &mut Vec<FlockEvent>) -> (f32, f32) {
+        self.ticks_elapsed = self.ticks_elapsed.saturating_add(1);
+        let recent_since = self.ticks_elapsed.saturating_sub(self.behaviour.recent_catch_ticks);
+        self.catch_ticks.retain(|tick| *tick > recent_since);
+        let nearest = boids.iter().min_by(|boid, other| predator.dist_to_boid(boid).total_cmp(&predator.dist_to_boid(other)));
+        let nearest_dist = nearest.map_or(f32::INFINITY, |boid| predator.dist_to_boid(boid));
+
+        let next_state = match self.state {
+            HuntState::Patrol if nearest_dist < self.behaviour.notice_dist => HuntState::Stalk,
+            HuntState::Stalk if nearest_dist >= self.behaviour.notice_dist => HuntState::Patrol,
+            HuntState::Stalk if nearest_dist < self.behaviour.pounce_dist => HuntState::Chase,
+            HuntState::Chase if self.energy <= 0.0 || self.catch_ticks.len() >= self.behaviour.catches_before_rest => HuntState::Rest,
+            HuntState::Chase if nearest_dist >= self.behaviour.notice_dist => HuntState::Patrol,
+            HuntState::Rest if self.energy >= self.behaviour.max_energy => HuntState::Patrol,
+            state => state,
+        };
+        if next_state != self.state {
+            events.push(FlockEvent::PredatorStateChanged { from: self.state, to: next_state });
+            // a rest leaves the predator ready to catch its fill again
+            if next_state == HuntState::Rest {
+                self.catch_ticks.clear();
+            }
+            self.state = next_state;
+        }
+        match self.state {
+            HuntState::Chase => self.energy = (self.energy - self.behaviour.chase_cost).max(0.0),
+            HuntState::Rest => self.energy = (self.energy + self.behaviour.rest_recovery).min(self.behaviour.max_energy),
+            HuntState::Patrol | HuntState::Stalk => {}
+        }
+
+        let (max_acceleration, max_turn_rate, max_speed) = limits;
+        let (target, speed) = match (self.state, nearest) {
+            (HuntState::Chase, Some(prey)) => {
+                let (x_change, y_change) = pursuit(predator, prey, 1.0, MAX_PREDICTION_TIME);
+                (Some((predator.x_pos + x_change, predator.y_pos + y_change)), max_speed)
+            }
+            (HuntState::Stalk, Some(prey)) => (Some((prey.x_pos, prey.y_pos)), self.behaviour.stalk_speed),
+            (HuntState::Rest, _) => (None, 0.0),
+            _ => (self.next_patrol_point(predator), self.behaviour.patrol_speed),
+        };
+        let turn = match target {
+            Some(target) => {
+                let wanted_heading = (target.1 - predator.y_pos).atan2(target.0 - predator.x_pos);
+                // the shortest way round, from -PI to PI
+                let heading_change = (wanted_heading - heading + PI).rem_euclid(TAU) - PI;
+                (heading_change / max_turn_rate).clamp(-1.0, 1.0)
+            }
+            None => 0.0,
+        };
+        let thrust = ((speed.min(max_speed) - predator.speed()) / max_acceleration).clamp(-1.0, 1.0);
+        return (thrust, turn);
+    }
+
+    /// the waypoint being patrolled towards, moving on to the next once the predator reaches it
+    fn next_patrol_point(&mut self, predator: &Boid) -> Option<(f32, f32)> {
+        let route = &self.behaviour.patrol_route;
+        let waypoint = *route.get(self.next_waypoint % route.len().max(1))?;
+        if (waypoint.0 - predator.x_pos).hypot(waypoint.1 - predator.y_pos) < WAYPOINT_RADIUS {
+            self.next_waypoint = (self.next_waypoint + 1) % route.len();
+            return Some(route[self.next_waypoint]);
+        }
+        return Some(waypoint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predator::Predator;
+    use crate::Flock;
+
+    fn hunter_at(x_pos: f32, behaviour: HuntingBehaviour) -> Predator {
+        let mut predator = Predator::new(Boid::new(x_pos, 0.0, 0.0, 0.0), 0.5, 0.5, 2.0, 1.0, 10.0, 0.5).unwrap();
+        predator.set_hunting(Hunting::new(behaviour).unwrap());
+        return predator;
+    }
+
+    fn state_changes(flock: &Flock) -> Vec<(HuntState, HuntState)> {
+        return flock.events()
+            .iter()
+            .filter_map(|event| match event {
+                FlockEvent::PredatorStateChanged { from, to } => Some((*from, *to)),
+                _ => None,
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_hunter_stalks_then_chases_then_gives_up() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 0.0, 0.0)]);
+        flock.set_predator(hunter_at(50.0, HuntingBehaviour::default()));
+        flock.step();
+        assert_eq!(state_changes(&flock), vec![(HuntState::Patrol, HuntState::Stalk)]);
+        // it creeps up at its stalking speed until it's close enough to pounce
+        while flock.predator().unwrap().hunt_state() == Some(HuntState::Stalk) {
+            assert!(flock.predator().unwrap().boid.speed() <= 0.5);
+            flock.step();
+        }
+        assert_eq!(state_changes(&flock), vec![(HuntState::Stalk, HuntState::Chase)]);
+        assert!(flock.predator().unwrap().boid.x_pos < 21.0);
+
+        // the boid gets away, out of sight
+        flock.boids[0].x_pos = -1000.0;
+        flock.step();
+        assert_eq!(state_changes(&flock), vec![(HuntState::Chase, HuntState::Patrol)]);
+    }
+
+    #[test]
+    fn test_hunter_rests_when_tired_or_full() {
+        let tiring = HuntingBehaviour { chase_cost: 40.0, rest_recovery: 50.0, ..HuntingBehaviour::default() };
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(-200.0, 0.0, 0.0, 0.0)]);
+        flock.set_predator(hunter_at(15.0, tiring));
+        let mut changes = Vec::new();
+        for _ in 0..6 {
+            flock.step();
+            changes.extend(state_changes(&flock));
+        }
+        // stalk, then chase for the three ticks its energy lasts, then rest for two ticks to get it back
+        assert_eq!(changes, vec![
+            (HuntState::Patrol, HuntState::Stalk),
+            (HuntState::Stalk, HuntState::Chase),
+            (HuntState::Chase, HuntState::Rest),
+        ]);
+        flock.step();
+        assert_eq!(state_changes(&flock), vec![(HuntState::Rest, HuntState::Patrol)]);
+
+        let sated_after_one = HuntingBehaviour { catches_before_rest: 1, ..HuntingBehaviour::default() };
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(0.5, 0.0, 0.0, 0.0), Boid::new(15.0, 0.0, 0.0, 0.0)]);
+        flock.set_predator(hunter_at(0.0, sated_after_one));
+        // it catches the two boids it starts on while it notices them, and pounces on the third
+        flock.step();
+        flock.step();
+        assert_eq!(flock.predator().unwrap().num_caught(), 2);
+        assert_eq!(state_changes(&flock), vec![(HuntState::Stalk, HuntState::Chase)]);
+        // but it's already had its fill, so stops for a rest
+        flock.step();
+        assert_eq!(state_changes(&flock), vec![(HuntState::Chase, HuntState::Rest)]);
+    }
+
+    #[test]
+    fn test_hunter_patrols_route() {
+        let behaviour = HuntingBehaviour { patrol_route: vec![(0.0, 10.0), (0.0, -10.0)], ..HuntingBehaviour::default() };
+        let mut predator = hunter_at(0.0, behaviour);
+        let mut highest = 0.0_f32;
+        for _ in 0..20 {
+            predator.hunt(&[], &mut Vec::new());
+            predator.move_by_controls(1.0);
+            highest = highest.max(predator.boid.y_pos);
+        }
+        // turned towards the first waypoint and headed up to it, then turned back for the second
+        assert!(highest > 8.0);
+        assert!(predator.boid.y_vel < 0.0);
+    }
+
+    #[test]
+    fn test_invalid_hunting_rejected() {
+        let behaviour = HuntingBehaviour { stalk_speed: 0.0, pounce_dist: 80.0, catches_before_rest: 0, ..HuntingBehaviour::default() };
+        assert_eq!(Hunting::new(behaviour).unwrap_err().errors, vec![
+            CreationError::PredatorParameterIsNotPositive("stalk speed".to_string()),
+            CreationError::PounceDistIsNotWithinNoticeDist,
+            CreationError::PredatorParameterIsNotPositive("catches before rest".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,134 @@
+//! A layout of obstacles and free-standing walls, e.g. as drawn in the Bevy adapter's editor,
+//! saved to and loaded from a text file with one on each line:
+//!
+//! ```text
+//! # a still obstacle in the middle, with a wall above it
+//! obstacle still:0,0:10
+//! wall -50,20;50,20
+//! ```
+//!
+//! Obstacles are in the form taken by `Obstacle::parse` and walls by `Wall::parse`. Unless the app
+//! says otherwise, the layout is kept in the user's config directory, at `default_path`.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use crate::obstacle::{Obstacle, Obstacles};
+use crate::walls::{Wall, Walls};
+use crate::Flock;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layout {
+    pub obstacles: Vec<Obstacle>,
+    pub walls: Vec<Wall>,
+}
+
+impl Layout {
+    /// the flock's obstacles and walls as they are now
+    pub fn from_flock(flock: &Flock) -> Layout {
+        return Layout {
+            obstacles: flock.obstacles().map(|obstacles| obstacles.obstacles.clone()).unwrap_or_default(),
+            walls: flock.walls().map(|walls| walls.walls.clone()).unwrap_or_default(),
+        };
+    }
+
+    /// replaces the flock's obstacles and walls with the layout's, keeping how boids avoid obstacles
+    pub fn apply(&self, flock: &mut Flock) {
+        let avoidance = flock.obstacles().and_then(|obstacles| obstacles.avoidance);
+        let mut obstacles = Obstacles::new(self.obstacles.clone());
+        obstacles.avoidance = avoidance;
+        flock.set_obstacles(obstacles);
+        flock.set_walls(Walls::new(self.walls.clone()));
+    }
+
+    /// the lines of a layout file, as described at the top of this module
+    pub fn parse(text: &str) -> Result<Layout> {
+        let mut layout = Layout::default();
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let line_number = line_idx + 1;
+            match line.split_once(char::is_whitespace) {
+                Some(("obstacle", obstacle)) => layout.obstacles.push(Obstacle::parse(obstacle.trim()).with_context(|| format!("line {}", line_number))?),
+                Some(("wall", wall)) => layout.walls.push(Wall::parse(wall.trim()).with_context(|| format!("line {}", line_number))?),
+                _ => bail!("line {}: '{}' should be obstacle or wall followed by its shape", line_number, line),
+            }
+        }
+        return Ok(layout);
+    }
+
+    pub fn load(path: &Path) -> Result<Layout> {
+        let text = fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+        return Layout::parse(&text).with_context(|| format!("in {}", path.display()));
+    }
+
+    /// writes the layout to `path`, making the directory it's in if need be
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("couldn't make {}", dir.display()))?;
+        }
+        return fs::write(path, self.to_string()).with_context(|| format!("couldn't write {}", path.display()));
+    }
+}
+
+/// the lines of a layout file
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for obstacle in &self.obstacles {
+            writeln!(f, "obstacle {}", obstacle)?;
+        }
+        for wall in &self.walls {
+            writeln!(f, "wall {},{};{},{}", wall.start.0, wall.start.1, wall.end.0, wall.end.1)?;
+        }
+        return Ok(());
+    }
+}
+
+/// `boids/layout.txt` in the user's config directory, e.g. `~/.config/boids/layout.txt` on Linux,
+/// if the platform has one
+pub fn default_path() -> Option<PathBuf> {
+    return dirs::config_dir().map(|dir| dir.join("boids").join("layout.txt"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obstacle::ObstacleAvoidance;
+
+    #[test]
+    fn test_layout_parsed_and_written_back() {
+        let text = "# a comment\nobstacle still:0,0:10\nobstacle path:2:1:0,0;10,0\n\nwall -50,20;50,20.5\n";
+        let layout = Layout::parse(text).unwrap();
+        assert_eq!(layout.obstacles.len(), 2);
+        assert_eq!(layout.walls, vec![Wall::new((-50.0, 20.0), (50.0, 20.5)).unwrap()]);
+        assert_eq!(Layout::parse(&layout.to_string()).unwrap(), layout);
+
+        assert!(Layout::parse("obstacle still:0,0:-1").is_err());
+        assert!(Layout::parse("portal 0,0;1,1").is_err());
+    }
+
+    #[test]
+    fn test_layout_saved_applied_and_loaded() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        let mut obstacles = Obstacles::new(vec![Obstacle::parse("still:5,5:1").unwrap()]);
+        obstacles.avoidance = Some(ObstacleAvoidance::new(10.0, 1.0, 0.1).unwrap());
+        flock.set_obstacles(obstacles);
+        flock.set_walls(Walls::new(vec![Wall::parse("0,0;10,0").unwrap()]));
+
+        let path = std::env::temp_dir().join(format!("boids-layout-test-{}", std::process::id())).join("layout.txt");
+        Layout::from_flock(&flock).save(&path).unwrap();
+        let loaded = Layout::load(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        let mut other_flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        let mut no_obstacles = Obstacles::new(Vec::new());
+        no_obstacles.avoidance = flock.obstacles().unwrap().avoidance;
+        other_flock.set_obstacles(no_obstacles);
+        loaded.apply(&mut other_flock);
+        assert_eq!(Layout::from_flock(&other_flock), Layout::from_flock(&flock));
+        assert!(other_flock.obstacles().unwrap().avoidance.is_some());
+    }
+}
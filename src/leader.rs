@@ -0,0 +1,187 @@
+//! Leaders steer themselves along a list of waypoints instead of flocking,
+//! and every other boid follows the nearest leader from slightly behind.
+
+use crate::steering::follow_leader;
+use crate::{check_finite, check_float_between_zero_and_one, Boid, CreationError, InvalidFlockConfig};
+
+#[derive(Debug)]
+pub struct Leader {
+    pub boid_idx: usize,
+    // visited in order, looping back to the first once the last is reached.
+    // A single waypoint that's moved every frame makes the leader track e.g. the mouse
+    pub waypoints: Vec<(f32, f32)>,
+    pub speed: f32,
+    pub(crate) next_waypoint: usize,
+}
+
+impl Leader {
+    pub fn new(boid_idx: usize, waypoints: Vec<(f32, f32)>, speed: f32) -> Leader {
+        return Leader {
+            boid_idx,
+            waypoints,
+            speed,
+            next_waypoint: 0,
+        };
+    }
+
+    /// head straight for the next waypoint at the leader's speed, moving on to the following
+    /// waypoint once it's reachable this frame. A leader without waypoints carries on as it was
+    fn steer(&mut self, boid: &mut Boid, time_per_frame: f32) {
+        if self.waypoints.is_empty() {
+            return;
+        }
+        let (target_x, target_y) = self.waypoints[self.next_waypoint % self.waypoints.len()];
+        let x_dist = target_x - boid.x_pos;
+        let y_dist = target_y - boid.y_pos;
+        let dist = (x_dist * x_dist + y_dist * y_dist).sqrt();
+        if dist <= self.speed * time_per_frame {
+            self.next_waypoint = (self.next_waypoint + 1) % self.waypoints.len();
+        }
+        if dist > 0.0 {
+            boid.x_vel = x_dist / dist * self.speed;
+            boid.y_vel = y_dist / dist * self.speed;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LeaderFollowing {
+    pub leaders: Vec<Leader>,
+    pub follow_factor: f32, // how much a boid wants to move to its place behind the leader
+    pub dist_behind_leader: f32,
+}
+
+impl LeaderFollowing {
+    pub fn new(leaders: Vec<Leader>, follow_factor: f32, dist_behind_leader: f32, flock_size: usize) -> Result<LeaderFollowing, InvalidFlockConfig> {
+        let leader_following = LeaderFollowing {
+            leaders,
+            follow_factor,
+            dist_behind_leader,
+        };
+        leader_following.validate(flock_size)?;
+        return Ok(leader_following);
+    }
+
+    fn validate(&self, flock_size: usize) -> Result<(), InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = [
+            check_float_between_zero_and_one(self.follow_factor, "follow".to_string()),
+            check_finite(self.dist_behind_leader, "distance behind leader"),
+        ].into_iter().flatten().collect();
+        for leader in &self.leaders {
+            if leader.boid_idx >= flock_size {
+                errors.push(CreationError::LeaderIsNotInFlock(leader.boid_idx));
+            }
+            errors.extend(check_finite(leader.speed, "leader speed"));
+            let waypoint_coords = leader.waypoints.iter().flat_map(|(x, y)| [*x, *y]);
+            errors.extend(waypoint_coords.filter_map(|coord| check_finite(coord, "leader waypoint")).take(1));
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(());
+    }
+
+    pub fn is_leader(&self, boid_idx: usize) -> bool {
+        return self.leaders.iter().any(|leader| leader.boid_idx == boid_idx);
+    }
+
+    /// leaders steer towards their waypoints and move; followers steer towards their nearest leader.
+    /// Followers are moved by the flocking rules, so their positions aren't updated here. Leaders
+    /// beyond the end of the flock, e.g. set up for a larger flock than the one they were given to,
+    /// are left out
+    pub(crate) fn apply(&mut self, boids: &mut [Boid], time_per_frame: f32) {
+        for leader in &mut self.leaders {
+            let Some(boid) = boids.get_mut(leader.boid_idx) else {
+                continue;
+            };
+            leader.steer(boid, time_per_frame);
+            boid.x_pos += boid.x_vel * time_per_frame;
+            boid.y_pos += boid.y_vel * time_per_frame;
+        }
+
+        for boid_idx in 0..boids.len() {
+            if self.is_leader(boid_idx) {
+                continue;
+            }
+            let Some(leader) = self.nearest_leader(boids, boid_idx) else {
+                return;
+            };
+            let (x_force, y_force) = follow_leader(&boids[boid_idx], &leader, self.dist_behind_leader, self.follow_factor);
+            boids[boid_idx].x_vel += x_force;
+            boids[boid_idx].y_vel += y_force;
+        }
+    }
+
+    fn nearest_leader(&self, boids: &[Boid], boid_idx: usize) -> Option<Boid> {
+        let boid = &boids[boid_idx];
+        return self.leaders
+            .iter()
+            .filter_map(|leader| boids.get(leader.boid_idx).copied())
+            .min_by(|a, b| {
+                let dist_to_a = (a.x_pos - boid.x_pos).powi(2) + (a.y_pos - boid.y_pos).powi(2);
+                let dist_to_b = (b.x_pos - boid.x_pos).powi(2) + (b.y_pos - boid.y_pos).powi(2);
+                dist_to_a.total_cmp(&dist_to_b)
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Flock;
+
+    #[test]
+    fn test_leader_moves_between_waypoints() {
+        let mut leader = Leader::new(0, vec![(10.0, 0.0), (10.0, 10.0)], 5.0);
+        let mut boid = Boid::new(0.0, 0.0, 0.0, 0.0);
+
+        for expected_pos in [(5.0, 0.0), (10.0, 0.0), (10.0, 5.0), (10.0, 10.0), (10.0, 5.0)] {
+            leader.steer(&mut boid, 1.0);
+            boid.x_pos += boid.x_vel;
+            boid.y_pos += boid.y_vel;
+            assert_eq!((boid.x_pos, boid.y_pos), expected_pos);
+        }
+    }
+
+    #[test]
+    fn test_followers_steer_towards_nearest_leader() {
+        let leaders = vec![Leader::new(0, vec![], 0.0), Leader::new(1, vec![], 0.0)];
+        let mut leader_following = LeaderFollowing::new(leaders, 1.0, 2.0, 3).unwrap();
+        let mut boids = vec![Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(100.0, 0.0, 0.0, 1.0), Boid::new(5.0, 5.0, 0.0, 0.0)];
+
+        leader_following.apply(&mut boids, 1.0);
+        // the first leader will be at (1, 0), so its follower aims 2 units behind that
+        assert_eq!((boids[2].x_vel, boids[2].y_vel), (-6.0, -5.0));
+        // leaders without waypoints keep going in a straight line
+        assert_eq!((boids[1].x_pos, boids[1].y_pos), (100.0, 1.0));
+    }
+
+    #[test]
+    fn test_leader_must_be_in_flock() {
+        let result = LeaderFollowing::new(vec![Leader::new(3, vec![], 1.0)], 2.0, 1.0, 3);
+        assert_eq!(result.unwrap_err().errors, vec![
+            CreationError::FactorShouldBeLessThanOne("follow".to_string()),
+            CreationError::LeaderIsNotInFlock(3),
+        ]);
+    }
+
+    #[test]
+    fn test_leader_numbers_must_be_finite() {
+        let result = LeaderFollowing::new(vec![Leader::new(0, vec![(f32::NAN, 0.0), (0.0, f32::INFINITY)], f32::NAN)], 0.5, f32::NAN, 1);
+        assert_eq!(result.unwrap_err().errors, vec![
+            CreationError::ValueIsNotFinite("distance behind leader".to_string()),
+            CreationError::ValueIsNotFinite("leader speed".to_string()),
+            CreationError::ValueIsNotFinite("leader waypoint".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_leaders_beyond_the_flock_left_out() {
+        // made for a flock of three, but given to a flock of two
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(5.0, 0.0, 1.0, 0.0)]);
+        flock.set_leader_following(LeaderFollowing::new(vec![Leader::new(0, vec![(50.0, 0.0)], 1.0), Leader::new(2, vec![], 1.0)], 0.5, 1.0, 3).unwrap());
+        flock.step();
+        assert_eq!((flock.boids()[0].x_pos, flock.boids()[0].y_pos), (1.0, 0.0));
+    }
+}
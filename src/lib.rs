@@ -0,0 +1,1899 @@
+#![allow(clippy::needless_return)]
+
+use std::ops::{AddAssign, Mul};
+use std::error;
+use std::fmt;
+use std::time::Instant;
+use anyhow::{Error, anyhow};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use arena::Arena;
+use boid_state::{BoidState, BoidStates, StateWeights};
+use density::DensityResponse;
+use distribution::InitialDistribution;
+use events::FlockEvent;
+use food::Foraging;
+use force_field::ForceField;
+use formation::Formation;
+use leader::LeaderFollowing;
+use obstacle::Obstacles;
+use path::PathFollowing;
+use portal::Portals;
+use predator::Predator;
+use rewind::FlockState;
+use timing::StepTimings;
+use walls::Walls;
+use weather::Weather;
+
+/// times the rest of the enclosing block as a puffin scope, when built with the `profiling` feature.
+/// Nothing is recorded until the app turns puffin's scopes on with `puffin::set_scopes_on(true)`
+macro_rules! profile_scope {
+    ($name:expr) => {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!($name);
+    };
+}
+
+/// marks the end of a frame for puffin, when built with the `profiling` feature
+macro_rules! profile_frame_end {
+    () => {
+        #[cfg(feature = "profiling")]
+        puffin::GlobalProfiler::lock().new_frame();
+    };
+}
+
+pub mod arena;
+pub mod audio;
+pub mod boid_state;
+pub mod camera;
+pub mod density;
+pub mod distribution;
+pub mod drag;
+pub mod events;
+pub mod food;
+pub mod force_field;
+pub mod formation;
+pub mod herding;
+pub mod hunting;
+pub mod layout;
+pub mod leader;
+pub mod obstacle;
+pub mod path;
+pub mod point_of_view;
+pub mod profile;
+pub mod portal;
+pub mod predator;
+pub mod render;
+pub mod report;
+pub mod rewind;
+pub mod scenario;
+pub mod shard;
+pub mod shuffle;
+pub mod stats;
+pub mod steering;
+pub mod svg;
+pub mod sweep;
+pub mod theme;
+pub mod timing;
+pub mod video;
+pub mod walls;
+pub mod weather;
+
+#[cfg(feature = "bevy")]
+pub mod bevy_adapter;
+
+#[cfg(feature = "scripting")]
+pub mod scripting;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+
+#[cfg(feature = "terminal")]
+pub mod terminal;
+
+#[cfg(test)]
+mod snapshot_tests;
+
+const MIN_SEPARATION_DIST: f32 = 0.1;
+// randomly generated boids start at up to this speed
+const INITIAL_MAX_SPEED: f32 = 2.0;
+
+/// With a hard cutoff, every boid within the local radius counts fully towards alignment and
+/// cohesion, so a boid's behaviour jumps as others cross the edge of the radius.
+/// The falloffs count nearer boids more, fading to nothing at the edge of the radius.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LocalWeighting {
+    HardCutoff,
+    Linear,
+    Smoothstep,
+}
+
+impl LocalWeighting {
+    fn weight(&self, dist: f32, max_dist_of_local_boid: f32) -> f32 {
+        // 1 for a boid in the same place, down to 0 at the edge of the local radius
+        let closeness = (1.0 - dist / max_dist_of_local_boid).clamp(0.0, 1.0);
+        return match self {
+            LocalWeighting::HardCutoff => 1.0,
+            LocalWeighting::Linear => closeness,
+            LocalWeighting::Smoothstep => closeness * closeness * (3.0 - 2.0 * closeness),
+        };
+    }
+}
+
+/// The factors that set how strongly the three classic rules steer the boids, each between 0 and 1
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Factor {
+    Repulsion,
+    Adhesion,
+    Cohesion,
+}
+
+impl Factor {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Factor::Repulsion => "repulsion",
+            Factor::Adhesion => "adhesion",
+            Factor::Cohesion => "cohesion",
+        };
+    }
+}
+
+/// The rules that steer the boids, each of which can be switched off to see what the others do without it.
+/// The rules beyond the first three only do anything once they've been set up on the flock
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rule {
+    Separation,
+    Alignment,
+    Cohesion,
+    LeaderFollowing,
+    PathFollowing,
+    Foraging,
+    ForceField,
+    Evasion,
+    Scripted, // the rule from a script, with the `scripting` feature; see `scripting.rs`
+}
+
+impl Rule {
+    pub const ALL: [Rule; 9] = [Rule::Separation, Rule::Alignment, Rule::Cohesion, Rule::LeaderFollowing, Rule::PathFollowing, Rule::Foraging, Rule::ForceField, Rule::Evasion, Rule::Scripted];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Rule::Separation => "separation",
+            Rule::Alignment => "alignment",
+            Rule::Cohesion => "cohesion",
+            Rule::LeaderFollowing => "leader-following",
+            Rule::PathFollowing => "path-following",
+            Rule::Foraging => "foraging",
+            Rule::ForceField => "force-field",
+            Rule::Evasion => "evasion",
+            Rule::Scripted => "scripted",
+        };
+    }
+
+    pub fn parse(text: &str) -> Result<Rule, Error> {
+        return Rule::ALL
+            .into_iter()
+            .find(|rule| rule.name() == text)
+            .ok_or_else(|| anyhow!("unknown rule '{}'; expected one of {}", text, Rule::ALL.map(|rule| rule.name()).join(", ")));
+    }
+}
+
+#[derive(Debug)]
+pub struct Flock {
+    boids: Vec<Boid>,
+    max_dist_before_boid_is_no_longer_crowded: f32,
+    max_dist_of_local_boid: f32, // i.e. the radius of the local flock; far boids in the flock don't influence a boid's behaviour. Also the cohesion radius
+    max_dist_of_aligning_boid: f32, // boids between this and the local radius only affect cohesion
+    local_weighting: LocalWeighting, // how much each local boid counts towards alignment and cohesion
+    density_response: Option<DensityResponse>, // scales repulsion and cohesion by how crowded each boid is
+    repulsion_factor: f32, // how much a boid wants to move away from other boids
+    separation_falloff_exponent: f32, // each crowding boid's push is weighted by 1/d^exponent, so the nearest boids push hardest
+    adhesion_factor: f32, // how much a boid wants to stay with the flock
+    cohesion_factor: f32, // how much a boid wants to move towards the average position of the flock
+    min_speed: f32,
+    max_speed: f32, // infinite unless set
+    time_per_frame: i32,
+    sub_steps: u32, // how many parts each step's movement is split into when checking for collisions
+    disabled_rules: Vec<Rule>,
+    foraging: Option<Foraging>,
+    leader_following: Option<LeaderFollowing>,
+    path_following: Option<PathFollowing>,
+    force_field: Option<ForceField>,
+    arena: Option<Arena>,
+    portals: Option<Portals>,
+    obstacles: Option<Obstacles>,
+    walls: Option<Walls>,
+    predator: Option<Predator>,
+    weather: Option<Weather>,
+    boid_states: Option<BoidStates>, // what each boid is busy doing, which changes how it flies
+    boids_seen_through_portals: Vec<Boid>, // seen by the flocking rules as well as the boids themselves
+    ghost_boids: Vec<Boid>, // boids beyond this flock's part of the world, also seen by the flocking rules
+    held_boid: Option<BoidId>, // picked up by the player, so sits out each step where it's put
+    cluster_labels: Vec<usize>, // the cluster each boid was in at the end of the last step
+    events: Vec<FlockEvent>, // what happened during the last step
+    next_boid_id: u64, // the id given to the latest boid to join
+    step_timings: Option<StepTimings>, // how long the parts of the last step took, if they're being timed
+    scratch: StepScratch,
+}
+
+/// Working space for `Flock::step`, kept between steps so that stepping doesn't allocate once the buffers
+/// have grown to fit the flock
+#[derive(Debug, Default)]
+struct StepScratch {
+    previous_positions: Vec<(f32, f32)>,
+    sub_step_movements: Vec<SubStepMovement>,
+    cluster_of: Vec<usize>,
+    previous_cluster_labels: Vec<usize>,
+    cluster_changes: stats::ClusterChangeScratch,
+}
+
+/// a boid's movement over each sub-step, and its velocity going into the sub-step, to tell if it bounced
+#[derive(Clone, Copy, Debug)]
+struct SubStepMovement {
+    id: BoidId,
+    movement: (f32, f32),
+    velocity: (f32, f32),
+}
+
+fn validate_factors(repulsion_factor: f32, adhesion_factor: f32, cohesion_factor: f32) -> Vec<CreationError> {
+    let repulsion = check_float_between_zero_and_one(repulsion_factor, Factor::Repulsion.name().to_string());
+    let adhesion =  check_float_between_zero_and_one(adhesion_factor, Factor::Adhesion.name().to_string());
+    let cohesion =  check_float_between_zero_and_one(cohesion_factor, Factor::Cohesion.name().to_string());
+
+    return [repulsion, adhesion, cohesion]
+        .into_iter()
+        .flatten()
+        .collect();
+}
+
+fn validate_distances(max_dist_before_boid_is_crowded: f32, max_dist_of_local_boid: f32) -> Option<CreationError> {
+    if max_dist_before_boid_is_crowded >= max_dist_of_local_boid {
+         return Some(CreationError::LocalEnvironmentIsSmallerThanCrowdingEnvironment);
+    }
+    return None;
+}
+
+// separation acts closest to the boid, then alignment, then cohesion furthest out
+fn validate_alignment_radius(max_dist_before_boid_is_crowded: f32, max_dist_of_aligning_boid: f32, max_dist_of_local_boid: f32) -> Option<CreationError> {
+    if let Some(creation_error) = check_finite(max_dist_of_aligning_boid, "alignment distance") {
+        return Some(creation_error);
+    }
+    if max_dist_of_aligning_boid <= max_dist_before_boid_is_crowded || max_dist_of_aligning_boid > max_dist_of_local_boid {
+        return Some(CreationError::AlignmentRadiusIsOutsideCrowdingToLocalRange);
+    }
+    return None;
+}
+
+fn validate_radii(max_dist_before_boid_is_crowded: f32, max_dist_of_local_boid: f32, max_dist_of_aligning_boid: f32) -> Vec<CreationError> {
+    let unusable_distances: Vec<CreationError> = [
+        check_finite(max_dist_before_boid_is_crowded, "crowding distance"),
+        check_finite(max_dist_of_local_boid, "local distance"),
+    ].into_iter().flatten().collect();
+    // distances that aren't numbers can't be compared with each other
+    if !unusable_distances.is_empty() {
+        return unusable_distances;
+    }
+    // only worth checking the alignment radius once the crowding and local radii are known to be in order
+    return validate_distances(max_dist_before_boid_is_crowded, max_dist_of_local_boid)
+        .or_else(|| validate_alignment_radius(max_dist_before_boid_is_crowded, max_dist_of_aligning_boid, max_dist_of_local_boid))
+        .into_iter()
+        .collect();
+}
+
+fn validate_speed_limits(min_speed: f32, max_speed: f32) -> Vec<CreationError> {
+    let mut errors = Vec::new();
+    // no maximum speed is given as infinity, but no number is never meant
+    if !min_speed.is_finite() || max_speed.is_nan() {
+        errors.push(CreationError::ValueIsNotFinite("speed limit".to_string()));
+        return errors;
+    }
+    if min_speed < 0.0 {
+        errors.push(CreationError::MinSpeedIsNegative);
+    }
+    if max_speed <= min_speed {
+        errors.push(CreationError::MaxSpeedIsNotAboveMinSpeed);
+    }
+    return errors;
+}
+
+#[derive(Debug)]
+pub struct InvalidFlockConfig {
+    errors: Vec<CreationError>,
+}
+
+impl From<InvalidFlockConfig> for Error {
+    fn from(invalid_flock_config: InvalidFlockConfig) -> Self {
+        anyhow!("Invalid Flock input: {:?}", invalid_flock_config.errors)
+    }
+}
+
+impl Flock {
+    /// the pattern of Flock{}, flock.validate()?, flock.init() is used to avoid
+    /// initializing the list of boids if the input is invalid,
+    /// required by making "validate()" a method
+    pub fn new(flock_size: usize,
+           max_dist_before_boid_is_crowded: f32,
+           max_dist_of_local_boid: f32,
+           repulsion_factor: f32,
+           adhesion_factor: f32,
+           cohesion_factor: f32
+    ) -> Result<Flock, InvalidFlockConfig> {
+        let mut flock = Flock {
+            boids: Vec::new(),
+            max_dist_before_boid_is_no_longer_crowded: max_dist_before_boid_is_crowded,
+            max_dist_of_local_boid,
+            max_dist_of_aligning_boid: max_dist_of_local_boid,
+            local_weighting: LocalWeighting::HardCutoff,
+            density_response: None,
+            repulsion_factor,
+            separation_falloff_exponent: 2.0,
+            adhesion_factor,
+            cohesion_factor,
+            min_speed: 0.0,
+            max_speed: f32::INFINITY,
+            time_per_frame: 1,
+            sub_steps: 1,
+            disabled_rules: Vec::new(),
+            foraging: None,
+            leader_following: None,
+            path_following: None,
+            force_field: None,
+            arena: None,
+            portals: None,
+            obstacles: None,
+            walls: None,
+            boid_states: None,
+            predator: None,
+            weather: None,
+            boids_seen_through_portals: Vec::new(),
+            ghost_boids: Vec::new(),
+            held_boid: None,
+            cluster_labels: Vec::new(),
+            events: Vec::new(),
+            next_boid_id: 0,
+            step_timings: None,
+            scratch: StepScratch::default(),
+        };
+        flock.validate()?;
+        flock.init(flock_size);
+        Ok(flock)
+    }
+
+    fn validate(&self) -> Result<(), InvalidFlockConfig> {
+
+        let mut errors = validate_factors(self.repulsion_factor, self.adhesion_factor, self.cohesion_factor);
+        errors.extend(validate_radii(self.max_dist_before_boid_is_no_longer_crowded, self.max_dist_of_local_boid, self.max_dist_of_aligning_boid));
+
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors }); // the "into()" will use the From trait to convert the InvalidFlockConfig into an Error
+        }
+
+        return Ok(());
+    }
+
+    /// Not necessary to split this out for a single fn call
+    /// But done to show how initialisation can be done in a separate function
+    fn init(&mut self, flock_size: usize) {
+        self.add_boids(Self::generate_boids(flock_size));
+    }
+
+    fn generate_boids(flock_size: usize) -> Vec<Boid> {
+        let mut boids = Vec::new();
+        for _ in 0..flock_size {
+            boids.push(Boid::new(0.0, 0.0, 0.0, 0.0));
+        }
+        return boids;
+    }
+
+    /// replaces the boids with the same number placed according to `distribution`, moving in random directions.
+    /// The same seed always gives the same boids
+    pub fn randomise_boids(&mut self, distribution: &InitialDistribution, seed: u64) -> Result<(), InvalidFlockConfig> {
+        distribution.validate()?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let boids = Self::randomly_generate_boids(self.boids.len(), distribution, self.arena.as_ref(), &mut rng);
+        self.replace_boids_keeping_ids(boids);
+        // the new boids are nothing to do with the old clusters, so shouldn't be seen as splitting from them
+        self.cluster_labels.clear();
+        return Ok(());
+    }
+
+    /// replaces the boids with the same number placed in `formation`, all moving at `speed`
+    pub fn arrange_in_formation(&mut self, formation: &Formation, speed: f32) -> Result<(), InvalidFlockConfig> {
+        let boids = formation.generate_boids(self.boids.len(), speed)?;
+        self.replace_boids_keeping_ids(boids);
+        self.cluster_labels.clear();
+        return Ok(());
+    }
+
+    /// the boids are moved rather than replaced by new ones, so keep their ids
+    fn replace_boids_keeping_ids(&mut self, boids: Vec<Boid>) {
+        for (boid, new_boid) in self.boids.iter_mut().zip(boids) {
+            *boid = Boid { id: boid.id, ..new_boid };
+        }
+    }
+
+    /// any positions from the distribution that are outside the arena are moved to random points inside it
+    fn randomly_generate_boids(flock_size: usize, distribution: &InitialDistribution, arena: Option<&Arena>, rng: &mut StdRng) -> Vec<Boid> {
+        let mut boids = Vec::new();
+        for position in distribution.generate_positions(flock_size, rng) {
+            let (x_pos, y_pos) = match arena {
+                Some(arena) if !arena.contains(position) => arena.random_point(rng),
+                _ => position,
+            };
+            let speed = rng.gen_range(0.0..INITIAL_MAX_SPEED);
+            let heading = rng.gen_range(0.0..std::f32::consts::TAU);
+            boids.push(Boid::new(x_pos, y_pos, speed * heading.cos(), speed * heading.sin()));
+        }
+        return boids;
+    }
+    /// the weighted distances are the sum over the crowding boids of `weighted_dist_from_crowding_boid()`
+    fn uncrowd_boid(&mut self, boid_to_update: usize,
+        total_x_weighted_dist_from_crowding_boids: f32,
+        total_y_weighted_dist_from_crowding_boids: f32,
+        repulsion_factor: f32) {
+
+        // update velocity to move away from the crowding boids, mostly from the nearest ones
+        let x_vel_change = total_x_weighted_dist_from_crowding_boids * repulsion_factor;
+        let y_vel_change = total_y_weighted_dist_from_crowding_boids * repulsion_factor;
+        self.boids[boid_to_update].move_then_steer(x_vel_change, y_vel_change, self.time_per_frame as f32);
+    }
+    /// the totals are weighted by `local_weighting`; with a hard cutoff the total weight is the number of local boids
+    fn align_boid(&mut self, boid_to_update: usize,
+                  total_weight_of_local_boids: f32, total_x_vel_of_local_boids: f32,
+                  total_y_vel_of_local_boids: f32, adhesion_factor: f32){
+        let average_x_vel : f32 = total_x_vel_of_local_boids / total_weight_of_local_boids;
+        let average_y_vel : f32 = total_y_vel_of_local_boids / total_weight_of_local_boids;
+        // update the boid's velocity to move towards the average velocity of the local flock, by some adhesion factor
+        let boid = &mut self.boids[boid_to_update];
+        let x_vel_change = (average_x_vel - boid.x_vel) * adhesion_factor;
+        let y_vel_change = (average_y_vel - boid.y_vel) * adhesion_factor;
+        boid.move_then_steer(x_vel_change, y_vel_change, self.time_per_frame as f32);
+    }
+    fn cohere_boid(&mut self, boid_to_update: usize,
+                   total_weight_of_local_boids: f32, total_x_dist_of_local_boids: f32,
+                   total_y_dist_of_local_boids: f32, cohesion_factor: f32){
+        let average_x_pos : f32 = total_x_dist_of_local_boids / total_weight_of_local_boids;
+        let average_y_pos : f32 = total_y_dist_of_local_boids / total_weight_of_local_boids;
+        // update the boid's velocity to move towards the average position of the local flock, by some cohesion factor
+        let boid = &mut self.boids[boid_to_update];
+        let x_vel_change = (average_x_pos - boid.x_pos) * cohesion_factor;
+        let y_vel_change = (average_y_pos - boid.y_pos) * cohesion_factor;
+        boid.move_then_steer(x_vel_change, y_vel_change, self.time_per_frame as f32);
+    }
+    fn update_boid(&mut self, boid_to_update: usize) {
+
+        // the rules here don't look at the arena or the walls, so may steer a boid into them; once the
+        // boids have moved, `collide_boids` keeps them in the arena and bounces them off the walls
+        let mut total_x_weighted_dist_from_crowding_boids: f32 = 0.0;
+        let mut total_y_weighted_dist_from_crowding_boids: f32 = 0.0;
+        let mut num_crowding_boids: i32 = 0;
+
+        let mut total_of_aligning_boids: Boid = Boid::new(0.0, 0.0, 0.0, 0.0);
+        let mut total_weight_of_aligning_boids: f32 = 0.0;
+        let mut total_of_local_boids: Boid = Boid::new(0.0, 0.0, 0.0, 0.0);
+        let mut total_weight_of_local_boids: f32 = 0.0;
+        let mut num_local_boids: usize = 0;
+
+        // rain shortens how far boids can see, but not how close they let others get
+        let visibility = self.weather.as_ref().map_or(1.0, |weather| weather.current().visibility());
+        let max_dist_of_local_boid = self.max_dist_of_local_boid * visibility;
+        let max_dist_of_aligning_boid = self.max_dist_of_aligning_boid * visibility;
+
+        let search_started = self.step_timings.is_some().then(Instant::now);
+        let boid = &self.boids[boid_to_update];
+        let mut boid_idx = 0;
+        // the boids seen through portals and the ghosts come after the boids themselves, so are never skipped as the boid being updated
+        for other_boid in self.boids.iter().chain(&self.boids_seen_through_portals).chain(&self.ghost_boids) {
+            if boid_idx == boid_to_update {
+                boid_idx += 1;
+                continue;
+            }
+            boid_idx += 1;
+            if boid.is_crowded_by_boid(other_boid, self.max_dist_before_boid_is_no_longer_crowded) {
+                num_crowding_boids += 1;
+                let (x_weighted_dist, y_weighted_dist) = boid.weighted_dist_from_crowding_boid(other_boid, self.separation_falloff_exponent);
+                total_x_weighted_dist_from_crowding_boids += x_weighted_dist;
+                total_y_weighted_dist_from_crowding_boids += y_weighted_dist;
+            }
+            else if boid.is_within_sight_of_local_boid(other_boid, max_dist_of_local_boid) {
+                let dist = boid.dist_to_boid(other_boid);
+                let weight = self.local_weighting.weight(dist, max_dist_of_local_boid);
+                num_local_boids += 1;
+                total_weight_of_local_boids += weight;
+                total_of_local_boids += *other_boid * weight;
+
+                if boid.is_within_sight_of_local_boid(other_boid, max_dist_of_aligning_boid) {
+                    let weight = self.local_weighting.weight(dist, max_dist_of_aligning_boid);
+                    total_weight_of_aligning_boids += weight;
+                    total_of_aligning_boids += *other_boid * weight;
+                }
+            }
+            // else, the other_boid is too far away to affect the boid we're updating
+        }
+        if let (Some(step_timings), Some(search_started)) = (&mut self.step_timings, search_started) {
+            step_timings.neighbour_search += search_started.elapsed();
+        }
+
+        // crowding boids are within the local radius too, so count towards how crowded the boid feels
+        let crowd_pressure = self.density_response.map_or(1.0, |density_response| density_response.crowd_pressure(num_crowding_boids as usize + num_local_boids));
+        let weights = self.boid_states.as_ref().map_or(StateWeights::UNCHANGED, |boid_states| boid_states.weights_of(boid_to_update));
+        if num_crowding_boids > 0 && self.is_rule_enabled(Rule::Separation) {
+            Flock::uncrowd_boid(self, boid_to_update, total_x_weighted_dist_from_crowding_boids, total_y_weighted_dist_from_crowding_boids,
+                                self.repulsion_factor * crowd_pressure * weights.separation);
+        }
+        // local boids right at the edge of the radius may all have no weight
+        if total_weight_of_aligning_boids > 0.0 && self.is_rule_enabled(Rule::Alignment) {
+            Flock::align_boid(self, boid_to_update, total_weight_of_aligning_boids, total_of_aligning_boids.x_vel, total_of_aligning_boids.y_vel,
+                              self.adhesion_factor * weights.alignment);
+        }
+        if total_weight_of_local_boids > 0.0 && self.is_rule_enabled(Rule::Cohesion) {
+            Flock::cohere_boid(self, boid_to_update, total_weight_of_local_boids, total_of_local_boids.x_pos, total_of_local_boids.y_pos,
+                               self.cohesion_factor / crowd_pressure * weights.cohesion);
+        }
+    }
+
+    /// advance the simulation by one frame, applying the rules to every boid in turn
+    pub fn step(&mut self) {
+        profile_scope!("Flock::step");
+        if let Some(step_timings) = &mut self.step_timings {
+            *step_timings = StepTimings::default();
+        }
+        let mut stage_started = Instant::now();
+        self.events.clear();
+        // a held boid sits out the step as a ghost, so the others still see it but it isn't moved
+        let held_boid = self.held_boid.and_then(|id| self.remove_boids_where(|boid| boid.id == id).pop());
+        // and keeps its state and energy as they were while it's held
+        let held_condition = held_boid.and_then(|boid| self.boid_states.as_mut()?.set_aside(boid.id));
+        if let Some(boid) = held_boid {
+            self.ghost_boids.push(boid);
+        }
+        // only needed to work out where boids crossed the arena's or free-standing walls, went into portals or ran into obstacles,
+        // and where they were along the way for sub-steps
+        self.scratch.previous_positions.clear();
+        if self.arena.is_some() || self.portals.is_some() || self.obstacles.is_some() || self.walls.is_some() || self.sub_steps > 1 {
+            self.scratch.previous_positions.extend(self.boids.iter().map(|boid| (boid.x_pos, boid.y_pos)));
+        }
+        if let Some(portals) = &self.portals {
+            portals.boids_seen_through(&self.boids, self.max_dist_of_local_boid, &mut self.boids_seen_through_portals);
+        }
+        let leader_following_enabled = self.is_rule_enabled(Rule::LeaderFollowing);
+        let foraging_enabled = self.is_rule_enabled(Rule::Foraging);
+        if let Some(boid_states) = &mut self.boid_states {
+            let foraging = self.foraging.as_ref().filter(|_| foraging_enabled);
+            boid_states.update(&self.boids, self.predator.as_ref().map(|predator| &predator.boid), foraging, &mut self.events);
+        }
+        let evasion_enabled = self.is_rule_enabled(Rule::Evasion);
+        let predator_start = self.predator.as_ref().map(|predator| (predator.boid.x_pos, predator.boid.y_pos));
+        {
+            profile_scope!("flocking rules");
+            for boid_to_update in 0..self.boids.len() {
+                // leaders steer themselves rather than flocking, unless they've stopped leading
+                if leader_following_enabled && self.leader_following.as_ref().is_some_and(|leader_following| leader_following.is_leader(boid_to_update)) {
+                    continue;
+                }
+                self.update_boid(boid_to_update);
+            }
+        }
+        {
+            profile_scope!("other rules and walls");
+            if let Some(leader_following) = self.leader_following.as_mut().filter(|_| leader_following_enabled) {
+                leader_following.apply(&mut self.boids, self.time_per_frame as f32);
+            }
+            if let Some(path_following) = self.path_following.as_ref().filter(|_| self.is_rule_enabled(Rule::PathFollowing)) {
+                path_following.apply(&mut self.boids);
+            }
+            if let Some(foraging) = self.foraging.as_mut().filter(|_| foraging_enabled) {
+                foraging.apply(&mut self.boids, &mut self.events);
+                if let Some(boid_states) = &mut self.boid_states {
+                    boid_states.feed(&self.events);
+                }
+            }
+            if let Some(force_field) = self.force_field.as_ref().filter(|_| self.is_rule_enabled(Rule::ForceField)) {
+                force_field.apply(&mut self.boids);
+            }
+            if let Some(obstacles) = &self.obstacles {
+                obstacles.avoid(&mut self.boids);
+            }
+            // the predator is steered by the player or hunts by itself, so moves whether or not the boids flee it
+            if let Some(predator) = &mut self.predator {
+                predator.hunt(&self.boids, &mut self.events);
+                predator.move_by_controls(self.time_per_frame as f32);
+                if evasion_enabled {
+                    predator.scare(&mut self.boids, self.max_dist_of_local_boid);
+                }
+            }
+            if let Some(step_timings) = &mut self.step_timings {
+                // everything before here, bar the neighbour search, was working out where the boids steer
+                step_timings.rules = stage_started.elapsed().saturating_sub(step_timings.neighbour_search);
+                stage_started = Instant::now();
+            }
+            // the cold slows boids down, but never below their minimum speed
+            let max_speed = self.weather.as_ref().map_or(self.max_speed, |weather| (self.max_speed * weather.current().temperature).max(self.min_speed));
+            for (boid_idx, boid) in self.boids.iter_mut().enumerate() {
+                // resting boids may be held below the flock's max speed, but are still kept above its min
+                let state_max_speed = self.boid_states.as_ref().map_or(f32::INFINITY, |boid_states| boid_states.weights_of(boid_idx).max_speed);
+                boid.limit_speed(self.min_speed, max_speed.min(state_max_speed).max(self.min_speed));
+            }
+            if let Some(weather) = &self.weather {
+                weather.blow(&mut self.boids, self.time_per_frame as f32);
+            }
+            if let Some(arena) = &self.arena {
+                arena.avoid_walls(&mut self.boids);
+                arena.push_back_from_walls(&mut self.boids);
+            }
+            if self.sub_steps == 1 {
+                self.collide_boids((0.0, 1.0));
+                self.let_predator_catch_boids();
+            } else {
+                self.collide_boids_in_sub_steps(predator_start);
+            }
+        }
+        if let Some(weather) = &mut self.weather {
+            weather.advance();
+        }
+        if let Some(obstacles) = &mut self.obstacles {
+            obstacles.advance();
+        }
+        // back before the clusters are labelled, so the labels line up with the boids from step to step
+        if let Some(boid) = held_boid {
+            self.ghost_boids.pop();
+            self.insert_boid_in_order(boid);
+        }
+        if let (Some(boid_states), Some(condition)) = (&mut self.boid_states, held_condition) {
+            boid_states.put_back(condition);
+        }
+        if let Some(step_timings) = &mut self.step_timings {
+            step_timings.integration = stage_started.elapsed();
+            stage_started = Instant::now();
+        }
+        profile_scope!("cluster labels");
+        std::mem::swap(&mut self.cluster_labels, &mut self.scratch.previous_cluster_labels);
+        stats::label_clusters_into(&self.boids, self.max_dist_of_local_boid, &mut self.cluster_labels, &mut self.scratch.cluster_of);
+        // boids caught during the step shift the others' indices, so they can't be followed from the labels before it
+        if !self.events.iter().any(|event| matches!(event, FlockEvent::BoidCaught { .. })) {
+            stats::find_cluster_changes(&self.scratch.previous_cluster_labels, &self.cluster_labels, &mut self.scratch.cluster_changes, &mut self.events);
+        }
+        if let Some(step_timings) = &mut self.step_timings {
+            step_timings.clusters = stage_started.elapsed();
+        }
+    }
+
+    /// times the parts of each step from now on, or stops timing them
+    pub fn set_step_timing(&mut self, on: bool) {
+        self.step_timings = on.then(StepTimings::default);
+    }
+
+    /// how long the parts of the last step took, if steps are being timed
+    pub fn step_timings(&self) -> Option<StepTimings> {
+        return self.step_timings;
+    }
+
+    pub fn boids(&self) -> &[Boid] {
+        return &self.boids;
+    }
+
+    /// adds boids to the flock, e.g. ones arriving from another part of the world. Each is given a new id
+    pub fn add_boids(&mut self, boids: impl IntoIterator<Item = Boid>) {
+        for mut boid in boids {
+            self.next_boid_id += 1;
+            boid.id = BoidId(self.next_boid_id);
+            self.boids.push(boid);
+        }
+    }
+
+    /// the boid with this id, if it's still in the flock
+    pub fn boid(&self, id: BoidId) -> Option<&Boid> {
+        return self.boid_idx(id).map(|boid_idx| &self.boids[boid_idx]);
+    }
+
+    /// where the boid with this id is in `boids()`, e.g. to find its cluster in `cluster_labels()`
+    pub fn boid_idx(&self, id: BoidId) -> Option<usize> {
+        // boids join at the end with increasing ids and leave without the rest being reordered, so stay sorted by id
+        return self.boids.binary_search_by_key(&id, |boid| boid.id).ok();
+    }
+
+    /// takes the boids for which `should_remove` is true out of the flock, keeping the rest in order.
+    /// Leaders are never removed, and keep leading as the boids before them are taken out
+    pub fn remove_boids_where(&mut self, mut should_remove: impl FnMut(&Boid) -> bool) -> Vec<Boid> {
+        let mut removed = Vec::new();
+        let mut new_idx_of_boid = Vec::with_capacity(self.boids.len());
+        let old_boids = std::mem::take(&mut self.boids);
+        for (boid_idx, boid) in old_boids.into_iter().enumerate() {
+            let is_leader = self.leader_following.as_ref().is_some_and(|leader_following| leader_following.is_leader(boid_idx));
+            if !is_leader && should_remove(&boid) {
+                new_idx_of_boid.push(None);
+                removed.push(boid);
+            }
+            else {
+                new_idx_of_boid.push(Some(self.boids.len()));
+                self.boids.push(boid);
+            }
+        }
+        if let Some(leader_following) = &mut self.leader_following {
+            for leader in &mut leader_following.leaders {
+                // leaders that weren't in the flock to begin with stay out of it
+                if let Some(Some(new_idx)) = new_idx_of_boid.get(leader.boid_idx) {
+                    leader.boid_idx = *new_idx;
+                }
+            }
+        }
+        return removed;
+    }
+
+    /// takes the boids the predator has reached out of the flock. Leaders are never caught
+    /// keeps boids out of the arena's walls, the free-standing walls and the obstacles, and takes them
+    /// through portals, over their movement from `scratch.previous_positions`. That movement is over
+    /// `tick_fraction` of the step, from its start to its end, which is where the obstacles are taken to be
+    fn collide_boids(&mut self, tick_fraction: (f32, f32)) {
+        if let Some(arena) = &self.arena {
+            for (boid, previous_position) in self.boids.iter_mut().zip(&self.scratch.previous_positions) {
+                if arena.contain(boid, *previous_position) {
+                    self.events.push(FlockEvent::WallHit { boid: boid.id, position: (boid.x_pos, boid.y_pos) });
+                }
+            }
+        }
+        if let Some(walls) = &self.walls {
+            walls.bounce(&mut self.boids, &self.scratch.previous_positions, &mut self.events);
+        }
+        if let Some(obstacles) = &self.obstacles {
+            obstacles.collide(&mut self.boids, &self.scratch.previous_positions, tick_fraction, &mut self.events);
+        }
+        if let Some(portals) = &self.portals {
+            portals.apply(&mut self.boids, &self.scratch.previous_positions, &mut self.events);
+        }
+    }
+
+    /// moves the boids from where they started the step to where the rules took them in `sub_steps`
+    /// equal parts, checking for collisions and catches after each. A boid that bounces off one thing
+    /// carries on the way it bounced for the rest of the step, so is checked against everything else
+    /// again, and the predator catches boids it passes through as well as those it ends up on
+    fn collide_boids_in_sub_steps(&mut self, predator_start: Option<(f32, f32)>) {
+        let sub_steps = self.sub_steps as f32;
+        self.scratch.sub_step_movements.clear();
+        for (boid, start) in self.boids.iter_mut().zip(&self.scratch.previous_positions) {
+            let movement = ((boid.x_pos - start.0) / sub_steps, (boid.y_pos - start.1) / sub_steps);
+            self.scratch.sub_step_movements.push(SubStepMovement { id: boid.id, movement, velocity: (boid.x_vel, boid.y_vel) });
+            (boid.x_pos, boid.y_pos) = *start;
+        }
+        let predator_end = self.predator.as_ref().map(|predator| (predator.boid.x_pos, predator.boid.y_pos));
+        for sub_step in 1..=self.sub_steps {
+            self.scratch.previous_positions.clear();
+            self.scratch.previous_positions.extend(self.boids.iter().map(|boid| (boid.x_pos, boid.y_pos)));
+            for (boid, sub_step_movement) in self.boids.iter_mut().zip(&mut self.scratch.sub_step_movements) {
+                boid.x_pos += sub_step_movement.movement.0;
+                boid.y_pos += sub_step_movement.movement.1;
+                sub_step_movement.velocity = (boid.x_vel, boid.y_vel);
+            }
+            self.collide_boids(((sub_step - 1) as f32 / sub_steps, sub_step as f32 / sub_steps));
+            for (boid, sub_step_movement) in self.boids.iter().zip(&mut self.scratch.sub_step_movements) {
+                // bounced, so the rest of its movement is the way it's heading now
+                let speed = boid.speed();
+                if (boid.x_vel, boid.y_vel) != sub_step_movement.velocity && speed > 0.0 {
+                    let length = sub_step_movement.movement.0.hypot(sub_step_movement.movement.1);
+                    sub_step_movement.movement = (boid.x_vel * length / speed, boid.y_vel * length / speed);
+                }
+            }
+            // the predator is somewhere along its way until the last sub-step, when it's where it moved to
+            if let (Some(start), Some(end), Some(predator)) = (predator_start, predator_end, self.predator.as_mut()) {
+                let fraction = sub_step as f32 / sub_steps;
+                (predator.boid.x_pos, predator.boid.y_pos) = if sub_step == self.sub_steps { end } else { (start.0 + (end.0 - start.0) * fraction, start.1 + (end.1 - start.1) * fraction) };
+            }
+            let num_boids = self.boids.len();
+            self.let_predator_catch_boids();
+            if self.boids.len() != num_boids {
+                let boids = &self.boids;
+                self.scratch.sub_step_movements.retain(|sub_step_movement| boids.binary_search_by_key(&sub_step_movement.id, |boid| boid.id).is_ok());
+            }
+        }
+    }
+
+    fn let_predator_catch_boids(&mut self) {
+        let Some(predator) = self.predator.as_ref().filter(|predator| predator.catches_boids) else {
+            return;
+        };
+        let (predator_boid, catch_radius) = (predator.boid, predator.catch_radius);
+        // most steps catch nothing, so don't rebuild the flock for them
+        if !self.boids.iter().any(|boid| Predator::is_catching(&predator_boid, catch_radius, boid)) {
+            return;
+        }
+        let mut events = std::mem::take(&mut self.events);
+        let caught = self.remove_boids_where(|boid| {
+            let is_caught = Predator::is_catching(&predator_boid, catch_radius, boid);
+            if is_caught {
+                events.push(FlockEvent::BoidCaught { boid: boid.id, position: (boid.x_pos, boid.y_pos) });
+            }
+            return is_caught;
+        });
+        self.events = events;
+        if let Some(predator) = &mut self.predator {
+            predator.count_caught(caught.len());
+        }
+    }
+
+    /// puts a boid back where it belongs by its id, such as one taken out for a step
+    fn insert_boid_in_order(&mut self, boid: Boid) {
+        let num_boids_before = self.boids.len();
+        let boid_idx = self.boids.partition_point(|other| other.id < boid.id);
+        self.boids.insert(boid_idx, boid);
+        if let Some(leader_following) = &mut self.leader_following {
+            for leader in &mut leader_following.leaders {
+                // leaders that weren't in the flock to begin with stay out of it
+                if boid_idx <= leader.boid_idx && leader.boid_idx < num_boids_before {
+                    leader.boid_idx += 1;
+                }
+            }
+        }
+    }
+
+    /// the nearest boid to `point` that's within `max_dist` of it, e.g. the one under the cursor
+    pub fn boid_near(&self, point: (f32, f32), max_dist: f32) -> Option<BoidId> {
+        let squared_dist = |boid: &Boid| (boid.x_pos - point.0).powi(2) + (boid.y_pos - point.1).powi(2);
+        return self.boids
+            .iter()
+            .filter(|boid| squared_dist(boid) <= max_dist.powi(2))
+            .min_by(|boid, other| squared_dist(boid).total_cmp(&squared_dist(other)))
+            .map(|boid| boid.id);
+    }
+
+    /// picks up the boid with this id, so that from the next step it stays where it's put by
+    /// `place_held_boid` rather than flying, though the rest of the flock still sees it. Any boid
+    /// held before is let go of where it is. Leaders can't be picked up. Returns whether the boid was
+    pub fn hold_boid(&mut self, id: BoidId) -> bool {
+        let Some(boid_idx) = self.boid_idx(id) else {
+            return false;
+        };
+        if self.leader_following.as_ref().is_some_and(|leader_following| leader_following.is_leader(boid_idx)) {
+            return false;
+        }
+        self.held_boid = Some(id);
+        return true;
+    }
+
+    pub fn held_boid(&self) -> Option<BoidId> {
+        return self.held_boid;
+    }
+
+    /// moves the held boid to `position`, e.g. as it's dragged around
+    pub fn place_held_boid(&mut self, position: (f32, f32)) {
+        let Some(boid_idx) = self.held_boid.and_then(|id| self.boid_idx(id)) else {
+            return;
+        };
+        let boid = &mut self.boids[boid_idx];
+        (boid.x_pos, boid.y_pos) = position;
+    }
+
+    /// lets go of the held boid, which flies off from where it was put at `velocity`
+    pub fn release_held_boid(&mut self, velocity: (f32, f32)) {
+        let Some(boid_idx) = self.held_boid.take().and_then(|id| self.boid_idx(id)) else {
+            return;
+        };
+        let boid = &mut self.boids[boid_idx];
+        (boid.x_vel, boid.y_vel) = velocity;
+    }
+
+    /// boids that aren't part of this flock but that its boids flock with, e.g. those just over the
+    /// boundary of a world shared with another process. They're seen by the flocking rules until
+    /// replaced, but never moved
+    pub fn set_ghost_boids(&mut self, ghost_boids: Vec<Boid>) {
+        self.ghost_boids = ghost_boids;
+    }
+
+    /// everything that happened during the last step, in the order it happened
+    pub fn events(&self) -> &[FlockEvent] {
+        return &self.events;
+    }
+
+    /// the cluster each boid is in, where boids within the local radius of each other are in the same cluster.
+    /// Clusters are numbered from 0 in order of their first boid
+    pub fn cluster_labels(&self) -> Vec<usize> {
+        // labels are only worked out each step, so boids that have been replaced since need labelling afresh
+        if self.cluster_labels.len() != self.boids.len() {
+            return stats::label_clusters(&self.boids, self.max_dist_of_local_boid);
+        }
+        return self.cluster_labels.clone();
+    }
+
+    /// by default boids align with every boid in the local radius, as well as cohering with them
+    pub fn set_alignment_radius(&mut self, max_dist_of_aligning_boid: f32) -> Result<(), InvalidFlockConfig> {
+        if let Some(creation_error) = validate_alignment_radius(self.max_dist_before_boid_is_no_longer_crowded, max_dist_of_aligning_boid, self.max_dist_of_local_boid) {
+            return Err(InvalidFlockConfig { errors: vec![creation_error] });
+        }
+        self.max_dist_of_aligning_boid = max_dist_of_aligning_boid;
+        return Ok(());
+    }
+
+    /// changes the crowding, local and alignment radii together, so they can be moved past each
+    /// other; they're left as they were if the new ones aren't in order
+    pub fn set_radii(&mut self, max_dist_before_boid_is_crowded: f32, max_dist_of_local_boid: f32, max_dist_of_aligning_boid: f32) -> Result<(), InvalidFlockConfig> {
+        let errors = validate_radii(max_dist_before_boid_is_crowded, max_dist_of_local_boid, max_dist_of_aligning_boid);
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        self.max_dist_before_boid_is_no_longer_crowded = max_dist_before_boid_is_crowded;
+        self.max_dist_of_local_boid = max_dist_of_local_boid;
+        self.max_dist_of_aligning_boid = max_dist_of_aligning_boid;
+        return Ok(());
+    }
+
+    /// how far boids can see other boids, which rain shortens
+    pub fn vision_radius(&self) -> f32 {
+        let visibility = self.weather.as_ref().map_or(1.0, |weather| weather.current().visibility());
+        return self.max_dist_of_local_boid * visibility;
+    }
+
+    /// the crowding, local and alignment radii
+    pub fn radii(&self) -> (f32, f32, f32) {
+        return (self.max_dist_before_boid_is_no_longer_crowded, self.max_dist_of_local_boid, self.max_dist_of_aligning_boid);
+    }
+
+    pub fn factor(&self, factor: Factor) -> f32 {
+        return match factor {
+            Factor::Repulsion => self.repulsion_factor,
+            Factor::Adhesion => self.adhesion_factor,
+            Factor::Cohesion => self.cohesion_factor,
+        };
+    }
+
+    /// the factor is left as it was if the new value is out of range
+    pub fn set_factor(&mut self, factor: Factor, value: f32) -> Result<(), InvalidFlockConfig> {
+        if let Some(creation_error) = check_float_between_zero_and_one(value, factor.name().to_string()) {
+            return Err(InvalidFlockConfig { errors: vec![creation_error] });
+        }
+        match factor {
+            Factor::Repulsion => self.repulsion_factor = value,
+            Factor::Adhesion => self.adhesion_factor = value,
+            Factor::Cohesion => self.cohesion_factor = value,
+        }
+        return Ok(());
+    }
+
+    /// e.g. for keys that nudge a factor up and down while the flock runs. Returns the new value,
+    /// or an error, leaving the factor as it was, if the change would take it out of range
+    pub fn adjust_factor(&mut self, factor: Factor, change: f32) -> Result<f32, InvalidFlockConfig> {
+        let value = self.factor(factor) + change;
+        self.set_factor(factor, value)?;
+        return Ok(value);
+    }
+
+    pub fn set_local_weighting(&mut self, local_weighting: LocalWeighting) {
+        self.local_weighting = local_weighting;
+    }
+
+    /// every rule is enabled to begin with
+    pub fn set_rule_enabled(&mut self, rule: Rule, enabled: bool) {
+        self.disabled_rules.retain(|disabled_rule| *disabled_rule != rule);
+        if !enabled {
+            self.disabled_rules.push(rule);
+        }
+    }
+
+    /// e.g. to switch a rule on and off from a key
+    pub fn toggle_rule(&mut self, rule: Rule) {
+        self.set_rule_enabled(rule, !self.is_rule_enabled(rule));
+    }
+
+    pub fn is_rule_enabled(&self, rule: Rule) -> bool {
+        return !self.disabled_rules.contains(&rule);
+    }
+
+    /// scale each boid's repulsion and cohesion by how crowded it is, or with None, don't
+    pub fn set_density_response(&mut self, density_response: Option<DensityResponse>) {
+        self.density_response = density_response;
+    }
+
+    /// boids going faster than `max_speed` are slowed to it, and those going slower than `min_speed` are sped up to it,
+    /// at the end of each step. Pass `f32::INFINITY` for no maximum
+    pub fn set_speed_limits(&mut self, min_speed: f32, max_speed: f32) -> Result<(), InvalidFlockConfig> {
+        let errors = validate_speed_limits(min_speed, max_speed);
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        self.min_speed = min_speed;
+        self.max_speed = max_speed;
+        return Ok(());
+    }
+
+    /// the minimum and maximum speeds
+    pub fn speed_limits(&self) -> (f32, f32) {
+        return (self.min_speed, self.max_speed);
+    }
+
+    /// 2 gives inverse-square separation; 0 makes every crowding boid push equally hard
+    pub fn set_separation_falloff_exponent(&mut self, separation_falloff_exponent: f32) -> Result<(), InvalidFlockConfig> {
+        if let Some(creation_error) = check_finite(separation_falloff_exponent, "separation falloff exponent") {
+            return Err(InvalidFlockConfig { errors: vec![creation_error] });
+        }
+        if separation_falloff_exponent < 0.0 {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::FalloffExponentIsNegative] });
+        }
+        self.separation_falloff_exponent = separation_falloff_exponent;
+        return Ok(());
+    }
+
+    /// place food in the world for the boids to forage; replaces any food placed before
+    pub fn set_foraging(&mut self, foraging: Foraging) {
+        self.foraging = Some(foraging);
+    }
+
+    pub fn foraging(&self) -> Option<&Foraging> {
+        return self.foraging.as_ref();
+    }
+
+    /// have some boids lead the rest of the flock; replaces any leaders set before
+    pub fn set_leader_following(&mut self, leader_following: LeaderFollowing) {
+        self.leader_following = Some(leader_following);
+    }
+
+    /// have the flock follow a route; replaces any path set before
+    pub fn set_path_following(&mut self, path_following: PathFollowing) {
+        self.path_following = Some(path_following);
+    }
+
+    pub fn path_following(&self) -> Option<&PathFollowing> {
+        return self.path_following.as_ref();
+    }
+
+    /// place attractors, repellers and vortices in the world; replaces any set before
+    pub fn set_force_field(&mut self, force_field: ForceField) {
+        self.force_field = Some(force_field);
+    }
+
+    pub fn force_field(&self) -> Option<&ForceField> {
+        return self.force_field.as_ref();
+    }
+
+    /// keep the flock inside walls; replaces any arena set before. Boids already outside the arena stay there
+    pub fn set_arena(&mut self, arena: Arena) {
+        self.arena = Some(arena);
+    }
+
+    pub fn arena(&self) -> Option<&Arena> {
+        return self.arena.as_ref();
+    }
+
+    /// link parts of the world together; replaces any portals set before
+    pub fn set_portals(&mut self, portals: Portals) {
+        self.portals = Some(portals);
+    }
+
+    pub fn portals(&self) -> Option<&Portals> {
+        return self.portals.as_ref();
+    }
+
+    /// put obstacles in the world for the flock to find its way round; replaces any obstacles set before
+    pub fn set_obstacles(&mut self, obstacles: Obstacles) {
+        self.obstacles = Some(obstacles);
+    }
+
+    pub fn obstacles(&self) -> Option<&Obstacles> {
+        return self.obstacles.as_ref();
+    }
+
+    /// splits each step's movement into `sub_steps` parts, each checked for collisions with the walls and
+    /// obstacles and for the predator's catches, while the rules are still worked out once a step. One,
+    /// to begin with, checks the whole movement at once, which is enough for boids bouncing off a single
+    /// wall or obstacle however fast they go, but more let a boid bounced into something else bounce off
+    /// that too, and a fast predator catch boids it passes through
+    pub fn set_sub_steps(&mut self, sub_steps: u32) -> Result<(), InvalidFlockConfig> {
+        if sub_steps == 0 {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::NoSubSteps] });
+        }
+        self.sub_steps = sub_steps;
+        return Ok(());
+    }
+
+    pub fn sub_steps(&self) -> u32 {
+        return self.sub_steps;
+    }
+
+    /// put free-standing walls in the world for the flock to bounce off; replaces any walls set before
+    pub fn set_walls(&mut self, walls: Walls) {
+        self.walls = Some(walls);
+    }
+
+    pub fn walls(&self) -> Option<&Walls> {
+        return self.walls.as_ref();
+    }
+
+    /// give every boid a state, such as fleeing or resting, that changes how it flies; replaces any set before
+    pub fn set_boid_states(&mut self, boid_states: BoidStates) {
+        self.boid_states = Some(boid_states);
+    }
+
+    pub fn boid_states(&self) -> Option<&BoidStates> {
+        return self.boid_states.as_ref();
+    }
+
+    /// what the boid with this id was doing in the last step, if the flock has boid states and the
+    /// boid has been through a step
+    pub fn boid_state(&self, id: BoidId) -> Option<BoidState> {
+        return self.boid_states.as_ref()?.condition(id).map(|condition| condition.state);
+    }
+
+    /// add a predator for the player to chase the flock with; replaces any predator set before
+    pub fn set_predator(&mut self, predator: Predator) {
+        self.predator = Some(predator);
+    }
+
+    pub fn predator(&self) -> Option<&Predator> {
+        return self.predator.as_ref();
+    }
+
+    /// have rain, wind and temperature change how the flock flies; replaces any weather set before
+    pub fn set_weather(&mut self, weather: Weather) {
+        self.weather = Some(weather);
+    }
+
+    pub fn weather(&self) -> Option<&Weather> {
+        return self.weather.as_ref();
+    }
+
+    /// e.g. to steer the predator from the keyboard
+    pub fn predator_mut(&mut self) -> Option<&mut Predator> {
+        return self.predator.as_mut();
+    }
+
+    /// e.g. to move a leader's waypoint to the mouse
+    pub fn leader_following_mut(&mut self) -> Option<&mut LeaderFollowing> {
+        return self.leader_following.as_mut();
+    }
+
+    /// everything that changes as the flock steps, to go back to later with `restore_state`
+    pub fn save_state(&self) -> FlockState {
+        return FlockState {
+            boids: self.boids.clone(),
+            cluster_labels: self.cluster_labels.clone(),
+            food_sources: self.foraging.as_ref().map(|foraging| foraging.food_sources.clone()).unwrap_or_default(),
+            next_waypoints: self.leader_following.as_ref()
+                .map(|leader_following| leader_following.leaders.iter().map(|leader| leader.next_waypoint).collect())
+                .unwrap_or_default(),
+            boid_conditions: self.boid_states.as_ref().map(|boid_states| boid_states.conditions().to_vec()).unwrap_or_default(),
+        };
+    }
+
+    /// puts the boids back as they were when `state` was saved. The rest of the flock's settings are
+    /// kept as they are now, so food or leaders that have been replaced since are left alone
+    pub fn restore_state(&mut self, state: &FlockState) {
+        self.boids.clone_from(&state.boids);
+        self.cluster_labels.clone_from(&state.cluster_labels);
+        self.events.clear();
+        if let Some(boid_states) = &mut self.boid_states {
+            boid_states.restore(&state.boid_conditions);
+        }
+        if let Some(foraging) = self.foraging.as_mut().filter(|foraging| foraging.food_sources.len() == state.food_sources.len()) {
+            foraging.food_sources.clone_from(&state.food_sources);
+        }
+        if let Some(leader_following) = self.leader_following.as_mut().filter(|leader_following| leader_following.leaders.len() == state.next_waypoints.len()) {
+            for (leader, next_waypoint) in leader_following.leaders.iter_mut().zip(&state.next_waypoints) {
+                leader.next_waypoint = *next_waypoint;
+            }
+        }
+    }
+}
+
+/// Identifies a boid for as long as it's in the flock, whatever boids are added or removed around it.
+/// The flock gives out ids in increasing order as boids join it, and never reuses them; boids not
+/// yet in a flock have the default id of 0
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoidId(pub u64);
+
+#[derive(Copy, Clone, Debug)]
+pub struct Boid {
+    pub id: BoidId,
+    pub x_pos: f32,
+    pub y_pos: f32,
+    pub x_vel: f32,
+    pub y_vel: f32,
+}
+
+impl Boid {
+    pub fn new(x_pos: f32, y_pos: f32, x_vel: f32, y_vel: f32) -> Boid {
+        return Boid {
+            id: BoidId::default(),
+            x_pos,
+            y_pos,
+            x_vel,
+            y_vel,
+        };
+    }
+
+    pub fn speed(&self) -> f32 {
+        return (self.x_vel * self.x_vel + self.y_vel * self.y_vel).sqrt();
+    }
+
+    /// moves the boid on by its velocity, then changes the velocity by the given amounts, so a rule's
+    /// steering only takes effect from the next move
+    fn move_then_steer(&mut self, x_vel_change: f32, y_vel_change: f32, time_per_frame: f32) {
+        self.x_pos += self.x_vel * time_per_frame;
+        self.y_pos += self.y_vel * time_per_frame;
+        self.x_vel += x_vel_change;
+        self.y_vel += y_vel_change;
+    }
+
+    /// rescales the velocity to keep the speed between the limits, keeping the boid's heading
+    fn limit_speed(&mut self, min_speed: f32, max_speed: f32) {
+        let speed = self.speed();
+        if speed > max_speed {
+            self.x_vel *= max_speed / speed;
+            self.y_vel *= max_speed / speed;
+        }
+        else if speed < min_speed {
+            // a stationary boid has no heading to keep, so just set it off along the x axis
+            if speed == 0.0 {
+                self.x_vel = min_speed;
+                return;
+            }
+            self.x_vel *= min_speed / speed;
+            self.y_vel *= min_speed / speed;
+        }
+    }
+
+    fn dist_to_boid(&self, other_boid: &Boid) -> f32 {
+        return ((self.x_pos - other_boid.x_pos).powi(2) + (self.y_pos - other_boid.y_pos).powi(2)).sqrt();
+    }
+
+    /// the displacement from `other_boid` to this boid, scaled by 1/d^falloff_exponent.
+    /// Distances are floored at `MIN_SEPARATION_DIST` so nearly-touching boids don't get an
+    /// enormous push, and boids at exactly the same spot don't divide by zero
+    fn weighted_dist_from_crowding_boid(&self, other_boid: &Boid, falloff_exponent: f32) -> (f32, f32) {
+        let x_dist = self.x_pos - other_boid.x_pos;
+        let y_dist = self.y_pos - other_boid.y_pos;
+        let dist = (x_dist * x_dist + y_dist * y_dist).sqrt().max(MIN_SEPARATION_DIST);
+        let weight = 1.0 / dist.powf(falloff_exponent);
+        return (x_dist * weight, y_dist * weight);
+    }
+
+    fn is_crowded_by_boid(&self, other_boid: &Boid, max_dist_before_boid_is_no_longer_crowded: f32) -> bool {
+        return (self.x_pos - other_boid.x_pos).abs() < max_dist_before_boid_is_no_longer_crowded &&
+            (self.y_pos - other_boid.y_pos).abs() < max_dist_before_boid_is_no_longer_crowded;
+    }
+
+    fn is_within_sight_of_local_boid(&self, other_boid: &Boid, max_dist_of_local_boid: f32) -> bool {
+        return (self.x_pos - other_boid.x_pos).abs() < max_dist_of_local_boid &&
+            (self.y_pos - other_boid.y_pos).abs() < max_dist_of_local_boid;
+    }
+
+}
+
+impl AddAssign for Boid {
+    fn add_assign(&mut self, other: Self) {
+        self.x_pos += other.x_pos;
+        self.y_pos += other.y_pos;
+        self.x_vel += other.x_vel;
+        self.y_vel += other.y_vel;
+    }
+}
+
+impl Mul<f32> for Boid {
+    type Output = Boid;
+
+    fn mul(self, factor: f32) -> Boid {
+        return Boid::new(self.x_pos * factor, self.y_pos * factor, self.x_vel * factor, self.y_vel * factor);
+    }
+}
+
+
+fn check_float_between_zero_and_one(value: f32, name: String) -> Option<CreationError> {
+    match value {
+        x if x.is_nan() => Some(CreationError::ValueIsNotFinite(name)),
+        x if x < 0.0 => Some(CreationError::FactorShouldBeMoreThanZero(name)),
+        x if x > 1.0 => Some(CreationError::FactorShouldBeLessThanOne(name)),
+        _ => None
+    }
+}
+
+fn check_finite(value: f32, name: &str) -> Option<CreationError> {
+    if !value.is_finite() {
+        return Some(CreationError::ValueIsNotFinite(name.to_string()));
+    }
+    return None;
+}
+
+/// sizes and strengths have to be actual numbers above zero; infinity and NaN aren't
+fn is_positive_and_finite(value: f32) -> bool {
+    return value.is_finite() && value > 0.0;
+}
+
+#[derive(PartialEq, Debug)]
+pub enum CreationError {
+    FactorShouldBeMoreThanZero(String),
+    FactorShouldBeLessThanOne(String),
+    LocalEnvironmentIsSmallerThanCrowdingEnvironment,
+    FoodIsEatenBeforeItCanBeSeen,
+    LeaderIsNotInFlock(usize),
+    PathNeedsAtLeastTwoPoints,
+    FalloffExponentIsNegative,
+    AlignmentRadiusIsOutsideCrowdingToLocalRange,
+    DistributionParameterIsNotPositive(String),
+    FormationParameterIsNotPositive(String),
+    ArenaHasNoArea,
+    PortalRadiusIsNotPositive,
+    ForceSourceParameterIsNotPositive(String),
+    ArenaParameterIsNotPositive(String),
+    PredatorParameterIsNotPositive(String),
+    DensityResponseParameterIsNotPositive(String),
+    DensityResponseMaxScaleIsBelowOne,
+    TemperatureIsNotPositive,
+    WeatherNeedsAKeyframe,
+    WeatherKeyframesAreOutOfOrder,
+    ScenarioHasTwoKeyframesAtOnce(String),
+    ObstacleParameterIsNotPositive(String),
+    PounceDistIsNotWithinNoticeDist,
+    BoidStateParameterIsNotPositive(String),
+    BoidStateParameterIsNegative(String),
+    TiredEnergyIsNotBelowMaxEnergy,
+    GoalRadiusIsNotPositive,
+    MinSpeedIsNegative,
+    MaxSpeedIsNotAboveMinSpeed,
+    ValueIsNotFinite(String),
+    WallHasNoLength,
+    NoSubSteps,
+}
+
+// This is required so that `CreationError` can implement `error::Error`.
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            CreationError::FactorShouldBeMoreThanZero(factor_name) => factor_name.to_owned() + " factor is negative",
+            CreationError::FactorShouldBeLessThanOne(factor_name) => factor_name.to_owned() + " factor is too large and should be below zero",
+            CreationError::LocalEnvironmentIsSmallerThanCrowdingEnvironment => "local environment is smaller than (or equal to) crowding environment".to_owned(),
+            CreationError::FoodIsEatenBeforeItCanBeSeen => "distance for eating food is larger than (or equal to) distance food can be seen from".to_owned(),
+            CreationError::LeaderIsNotInFlock(boid_idx) => format!("leader {} is not in the flock", boid_idx),
+            CreationError::PathNeedsAtLeastTwoPoints => "path needs at least two points".to_owned(),
+            CreationError::FalloffExponentIsNegative => "separation falloff exponent is negative".to_owned(),
+            CreationError::AlignmentRadiusIsOutsideCrowdingToLocalRange => "alignment radius should be larger than the crowding environment and no larger than the local environment".to_owned(),
+            CreationError::DistributionParameterIsNotPositive(param_name) => format!("initial distribution {} should be positive and finite", param_name),
+            CreationError::FormationParameterIsNotPositive(param_name) => format!("formation {} should be positive and finite", param_name),
+            CreationError::ForceSourceParameterIsNotPositive(param_name) => format!("force source {} should be positive and finite", param_name),
+            CreationError::MinSpeedIsNegative => "minimum speed is negative".to_owned(),
+            CreationError::MaxSpeedIsNotAboveMinSpeed => "maximum speed should be above the minimum speed".to_owned(),
+            CreationError::ArenaParameterIsNotPositive(param_name) => format!("arena {} should be positive and finite", param_name),
+            CreationError::ObstacleParameterIsNotPositive(param_name) => format!("obstacle {} should be positive and finite", param_name),
+            CreationError::PounceDistIsNotWithinNoticeDist => "predator pounce distance should be less than its notice distance".to_owned(),
+            CreationError::PredatorParameterIsNotPositive(param_name) => format!("predator {} should be positive and finite", param_name),
+            CreationError::BoidStateParameterIsNotPositive(param_name) => format!("boid state {} should be positive", param_name),
+            CreationError::BoidStateParameterIsNegative(param_name) => format!("boid state {} is negative", param_name),
+            CreationError::TiredEnergyIsNotBelowMaxEnergy => "boid state tired energy should be less than the max energy".to_owned(),
+            CreationError::DensityResponseParameterIsNotPositive(param_name) => format!("density response {} should be positive and finite", param_name),
+            CreationError::DensityResponseMaxScaleIsBelowOne => "density response max scale should be at least 1 and finite".to_owned(),
+            CreationError::TemperatureIsNotPositive => "temperature should be positive and finite".to_owned(),
+            CreationError::WeatherNeedsAKeyframe => "weather needs at least one keyframe".to_owned(),
+            CreationError::WeatherKeyframesAreOutOfOrder => "weather keyframes should be in order of their ticks, with no two at the same tick".to_owned(),
+            CreationError::ScenarioHasTwoKeyframesAtOnce(param_name) => format!("scenario has two keyframes for {} at the same tick", param_name),
+            CreationError::GoalRadiusIsNotPositive => "goal radius should be positive and finite".to_owned(),
+            CreationError::PortalRadiusIsNotPositive => "portal radius should be positive and finite".to_owned(),
+            CreationError::ValueIsNotFinite(name) => format!("{} should be a finite number", name),
+            CreationError::WallHasNoLength => "wall should have two different ends".to_owned(),
+            CreationError::NoSubSteps => "a step should have at least one sub-step".to_owned(),
+            CreationError::ArenaHasNoArea => "arena has no area; a polygon needs at least three vertices not all in a line, and a circle a positive radius".to_owned(),
+        };
+        f.write_str(&description)
+    }
+}
+
+impl error::Error for CreationError {}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use portal::{PortalEnd, PortalPair};
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    // counts allocations made by each test's own thread, so tests running alongside don't interfere
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+            return System.alloc(layout);
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+            return System.realloc(ptr, layout, new_size);
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn test_no_crowding_by_boid_outside_of_crowding_zone() {
+        let mut flock = Flock::new(0, 4.0, 5.0, 0.0, 0.0, 0.0).unwrap();
+        let boid = Boid::new(1.0, 1.0, 1.0, 1.0);
+        let other_boid = Boid::new(10.0, 10.0, 2.0, 2.0);
+        flock.boids = vec![boid, other_boid];
+
+        assert!(!flock.boids[0].is_crowded_by_boid(&flock.boids[1], flock.max_dist_before_boid_is_no_longer_crowded));
+        assert!(!flock.boids[1].is_crowded_by_boid(&flock.boids[0], flock.max_dist_before_boid_is_no_longer_crowded));
+    }
+
+    #[test]
+    fn test_crowding_by_boid_inside_of_crowding_zone() {
+        let mut flock = Flock::new(0, 40.0, 500.0, 0.0, 0.0, 0.0).unwrap();
+        let boid = Boid::new(1.0, 1.0, 1.0, 1.0);
+        let other_boid = Boid::new(10.0, 10.0, 2.0, 2.0);
+        flock.boids = vec![boid, other_boid];
+
+        assert!(flock.boids[0].is_crowded_by_boid(&flock.boids[1], flock.max_dist_before_boid_is_no_longer_crowded));
+        assert!(flock.boids[1].is_crowded_by_boid(&flock.boids[0], flock.max_dist_before_boid_is_no_longer_crowded));
+    }
+
+    #[test]
+    fn test_crowded_boid_has_updated_velocity() {
+        let mut flock = Flock::new(0, 40.0, 500.0, 0.0, 0.0, 0.0).unwrap();
+        let boid = Boid::new(1.0, 1.0, 1.0, 1.0);
+        let other_boid = Boid::new(10.0, 10.0, 1.0, 5.0);
+        flock.boids = vec![boid, other_boid];
+
+        let (x_weighted_dist, y_weighted_dist) = flock.boids[0].weighted_dist_from_crowding_boid(&flock.boids[1], flock.separation_falloff_exponent);
+        flock.uncrowd_boid(0, x_weighted_dist, y_weighted_dist, flock.repulsion_factor);
+        assert_eq!(flock.boids[0].x_vel, boid.x_vel);
+        assert_eq!(flock.boids[0].y_vel, boid.y_vel);
+        // v = d/t; t = 1
+        assert_eq!(flock.boids[0].x_pos, boid.x_pos + boid.x_vel); // = 2
+        assert_eq!(flock.boids[0].y_pos, boid.y_pos + boid.y_vel); // = 2
+
+        flock.repulsion_factor = 1.0;
+        flock.separation_falloff_exponent = 0.0;
+        let (x_weighted_dist, y_weighted_dist) = flock.boids[1].weighted_dist_from_crowding_boid(&flock.boids[0], flock.separation_falloff_exponent);
+        flock.uncrowd_boid(1, x_weighted_dist, y_weighted_dist, flock.repulsion_factor);
+        // with no falloff, new velocity = original velocity + repulsion*(difference in displacement)*time
+
+        assert_eq!(flock.boids[1].x_vel, other_boid.x_vel + flock.repulsion_factor * (other_boid.x_pos - flock.boids[0].x_pos));
+        assert_eq!(flock.boids[1].y_vel, other_boid.y_vel + flock.repulsion_factor * (other_boid.y_pos - flock.boids[0].y_pos));
+
+    }
+
+    #[test]
+    fn test_nearer_crowding_boid_pushes_harder() {
+        let boid = Boid::new(0.0, 0.0, 0.0, 0.0);
+        let near_boid = Boid::new(1.0, 0.0, 0.0, 0.0);
+        let far_boid = Boid::new(-4.0, 0.0, 0.0, 0.0);
+
+        // inverse-square: the push from each is 1/d
+        assert_eq!(boid.weighted_dist_from_crowding_boid(&near_boid, 2.0), (-1.0, 0.0));
+        assert_eq!(boid.weighted_dist_from_crowding_boid(&far_boid, 2.0), (0.25, 0.0));
+
+        let mut flock = Flock::new(0, 5.0, 50.0, 1.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![boid, near_boid, far_boid];
+        flock.update_boid(0);
+        assert_eq!(flock.boids[0].x_vel, -0.75);
+    }
+
+    #[test]
+    fn test_separation_of_boids_in_same_place_is_finite() {
+        let boid = Boid::new(3.0, 3.0, 0.0, 0.0);
+        assert_eq!(boid.weighted_dist_from_crowding_boid(&boid, 2.0), (0.0, 0.0));
+
+        let touching_boid = Boid::new(3.0, 3.0 + MIN_SEPARATION_DIST / 2.0, 0.0, 0.0);
+        let (x_weighted_dist, y_weighted_dist) = boid.weighted_dist_from_crowding_boid(&touching_boid, 2.0);
+        assert_eq!(x_weighted_dist, 0.0);
+        assert!(y_weighted_dist.is_finite());
+        assert!(y_weighted_dist >= -1.0 / MIN_SEPARATION_DIST);
+    }
+
+    #[test]
+    fn test_negative_falloff_exponent_rejected() {
+        let mut flock = Flock::new(0, 5.0, 50.0, 1.0, 0.0, 0.0).unwrap();
+        assert!(flock.set_separation_falloff_exponent(-1.0).is_err());
+        assert!(flock.set_separation_falloff_exponent(1.0).is_ok());
+        assert_eq!(flock.separation_falloff_exponent, 1.0);
+    }
+
+    #[test]
+    fn test_boid_outside_of_local_zone() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        let boid = Boid::new(1.0, 1.0, 1.0, 1.0);
+        let other_boid = Boid::new(10.0, 10.0, 2.0, 2.0);
+        flock.boids = vec![boid, other_boid];
+
+        // not crowded
+        assert!(!flock.boids[0].is_crowded_by_boid(&flock.boids[1], flock.max_dist_before_boid_is_no_longer_crowded));
+        assert!(!flock.boids[1].is_crowded_by_boid(&flock.boids[0], flock.max_dist_before_boid_is_no_longer_crowded));
+
+        // but still within local zone
+        assert!(flock.boids[0].is_within_sight_of_local_boid(&flock.boids[1], flock.max_dist_of_local_boid));
+        assert!(flock.boids[1].is_within_sight_of_local_boid(&flock.boids[0], flock.max_dist_of_local_boid));
+    }
+
+    #[test]
+    fn test_adhesion() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 1.0, 0.0).unwrap();
+        let boid = Boid::new(1.0, 1.0, 1.0, 5.0);
+        let boid_2 = Boid::new(3.0, 3.0, 10.0, 1000.0);
+        let boid_3 = Boid::new(5.0, 5.0, 10.0, -1000.0);
+        flock.boids = vec![boid, boid_2, boid_3];
+
+        flock.align_boid(0, 2.0, 20.0, 0.0, flock.adhesion_factor);
+        assert_eq!(flock.boids[0].x_vel, 10.0);
+        assert_eq!(flock.boids[0].y_vel, 0.0);
+    }
+
+    #[test]
+    fn test_no_adhesion() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        let boid = Boid::new(1.0, 1.0, 1.0, 5.0);
+        let boid_2 = Boid::new(3.0, 3.0, 10.0, 1000.0);
+        let boid_3 = Boid::new(5.0, 5.0, 10.0, -1000.0);
+        flock.boids = vec![boid, boid_2, boid_3];
+
+        flock.align_boid(0, 2.0, 20.0, 0.0, flock.adhesion_factor);
+        assert_eq!(flock.boids[0].x_vel, 1.0);
+        assert_eq!(flock.boids[0].y_vel, 5.0);
+    }
+
+    #[test]
+    fn test_half_adhesion() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.5, 0.0).unwrap();
+        let boid = Boid::new(1.0, 1.0, 1.0, 5.0);
+        let boid_2 = Boid::new(3.0, 3.0, 10.0, 1000.0);
+        let boid_3 = Boid::new(5.0, 5.0, 10.0, -1000.0);
+        flock.boids = vec![boid, boid_2, boid_3];
+
+        flock.align_boid(0, 2.0, 20.0, 0.0, flock.adhesion_factor);
+        assert_eq!(flock.boids[0].x_vel, 5.5);
+        assert_eq!(flock.boids[0].y_vel, 2.5);
+    }
+    #[test]
+    fn test_cohesion() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.5).unwrap();
+        let boid = Boid::new(1.0, 1.0, 1.0, 5.0);
+        let boid_2 = Boid::new(3.0, 3.0, 10.0, 1000.0);
+        let boid_3 = Boid::new(5.0, 9.0, 10.0, -1000.0);
+        flock.boids = vec![boid, boid_2, boid_3];
+
+        // average position of the others is (4, 6)
+        flock.cohere_boid(0, 2.0, 8.0, 12.0, flock.cohesion_factor);
+        assert_eq!(flock.boids[0].x_vel, 2.5);
+        assert_eq!(flock.boids[0].y_vel, 7.5);
+    }
+
+    #[test]
+    fn test_local_weighting_falloff() {
+        assert_eq!(LocalWeighting::HardCutoff.weight(9.0, 10.0), 1.0);
+        assert_eq!(LocalWeighting::Linear.weight(0.0, 10.0), 1.0);
+        assert_eq!(LocalWeighting::Linear.weight(7.5, 10.0), 0.25);
+        assert_eq!(LocalWeighting::Linear.weight(12.0, 10.0), 0.0);
+        assert_eq!(LocalWeighting::Smoothstep.weight(5.0, 10.0), 0.5);
+        assert_eq!(LocalWeighting::Smoothstep.weight(10.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn test_weighted_alignment_favours_nearer_boids() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 1.0, 0.0).unwrap();
+        flock.set_local_weighting(LocalWeighting::Linear);
+        let boid = Boid::new(0.0, 0.0, 0.0, 0.0);
+        let near_boid = Boid::new(2.0, 0.0, 0.0, 6.0); // weight 0.8
+        let far_boid = Boid::new(-8.0, 0.0, 0.0, -6.0); // weight 0.2
+        flock.boids = vec![boid, near_boid, far_boid];
+
+        flock.update_boid(0);
+        assert_eq!(flock.boids[0].x_vel, 0.0);
+        assert!((flock.boids[0].y_vel - 3.6).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_boid_beyond_alignment_radius_only_coheres() {
+        let mut flock = Flock::new(0, 1.0, 20.0, 0.0, 1.0, 0.5).unwrap();
+        flock.set_alignment_radius(5.0).unwrap();
+        let boid = Boid::new(0.0, 0.0, 0.0, 0.0);
+        let aligning_boid = Boid::new(4.0, 0.0, 0.0, 2.0);
+        let cohering_boid = Boid::new(-10.0, 0.0, 0.0, 100.0);
+        flock.boids = vec![boid, aligning_boid, cohering_boid];
+
+        flock.update_boid(0);
+        // aligns with only the nearer boid, but coheres towards the average position of both, (-3, 0)
+        assert_eq!(flock.boids[0].y_vel, 2.0);
+        assert_eq!(flock.boids[0].x_vel, -1.5);
+    }
+
+    #[test]
+    fn test_alignment_radius_must_be_between_crowding_and_local() {
+        let mut flock = Flock::new(0, 2.0, 20.0, 0.0, 0.0, 0.0).unwrap();
+        assert_eq!(flock.set_alignment_radius(1.0).unwrap_err().errors, vec![CreationError::AlignmentRadiusIsOutsideCrowdingToLocalRange]);
+        assert!(flock.set_alignment_radius(25.0).is_err());
+        assert_eq!(flock.max_dist_of_aligning_boid, 20.0);
+        assert!(flock.set_alignment_radius(20.0).is_ok());
+        assert!(flock.set_alignment_radius(2.5).is_ok());
+    }
+
+    #[test]
+    fn test_random_boids_reproducible_from_seed() {
+        let mut flock = Flock::new(20, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        let distribution = InitialDistribution::default();
+        flock.randomise_boids(&distribution, 7).unwrap();
+        let first_positions: Vec<(f32, f32)> = flock.boids.iter().map(|boid| (boid.x_pos, boid.y_pos)).collect();
+        assert_eq!(first_positions.len(), 20);
+        assert!(flock.boids.iter().all(|boid| boid.speed() <= INITIAL_MAX_SPEED));
+
+        flock.randomise_boids(&distribution, 7).unwrap();
+        let second_positions: Vec<(f32, f32)> = flock.boids.iter().map(|boid| (boid.x_pos, boid.y_pos)).collect();
+        assert_eq!(first_positions, second_positions);
+    }
+
+    #[test]
+    fn test_disabled_rules_ignored() {
+        // a crowding boid and a local boid, moving in different directions
+        let make_flock = || {
+            let mut flock = Flock::new(0, 1.0, 10.0, 0.5, 0.5, 0.5).unwrap();
+            flock.boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(0.5, 0.0, 0.0, 0.0), Boid::new(0.0, 5.0, 2.0, 0.0)];
+            return flock;
+        };
+        let mut only_cohesion = make_flock();
+        only_cohesion.set_rule_enabled(Rule::Separation, false);
+        only_cohesion.set_rule_enabled(Rule::Alignment, false);
+        only_cohesion.update_boid(0);
+        // towards the local boid at (0, 5) and nothing else
+        assert_eq!((only_cohesion.boids[0].x_vel, only_cohesion.boids[0].y_vel), (0.0, 2.5));
+
+        let mut all_rules = make_flock();
+        all_rules.update_boid(0);
+        assert!(all_rules.boids[0].x_vel != 0.0);
+
+        all_rules.toggle_rule(Rule::Cohesion);
+        assert!(!all_rules.is_rule_enabled(Rule::Cohesion));
+        all_rules.toggle_rule(Rule::Cohesion);
+        assert!(all_rules.is_rule_enabled(Rule::Cohesion));
+    }
+
+    #[test]
+    fn test_factors_adjusted_within_range() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.5, 0.5, 0.5).unwrap();
+        assert_eq!(flock.adjust_factor(Factor::Cohesion, 0.25).unwrap(), 0.75);
+        assert_eq!(flock.factor(Factor::Cohesion), 0.75);
+        assert_eq!(flock.adjust_factor(Factor::Cohesion, 0.5).unwrap_err().errors, vec![CreationError::FactorShouldBeLessThanOne("cohesion".to_string())]);
+        assert_eq!(flock.factor(Factor::Cohesion), 0.75);
+
+        assert!(flock.set_factor(Factor::Repulsion, -0.1).is_err());
+        flock.set_factor(Factor::Repulsion, 0.0).unwrap();
+        assert_eq!(flock.repulsion_factor, 0.0);
+        assert_eq!(flock.factor(Factor::Adhesion), 0.5);
+    }
+
+    #[test]
+    fn test_rule_names() {
+        for rule in Rule::ALL {
+            assert_eq!(Rule::parse(rule.name()).unwrap(), rule);
+        }
+        assert!(Rule::parse("flying").is_err());
+    }
+
+    #[test]
+    fn test_boid_moves_before_it_is_steered() {
+        let mut boid = Boid::new(1.0, 2.0, 3.0, -1.0);
+        boid.move_then_steer(0.5, 0.25, 2.0);
+        assert_eq!((boid.x_pos, boid.y_pos, boid.x_vel, boid.y_vel), (7.0, 0.0, 3.5, -0.75));
+    }
+
+    #[test]
+    fn test_speed_limited_each_step() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 6.0, 8.0), Boid::new(100.0, 0.0, 0.0, 0.5), Boid::new(-100.0, 0.0, 0.0, 0.0)];
+        flock.set_speed_limits(1.0, 5.0).unwrap();
+        flock.step();
+        assert_eq!((flock.boids[0].x_vel, flock.boids[0].y_vel), (3.0, 4.0));
+        assert_eq!((flock.boids[1].x_vel, flock.boids[1].y_vel), (0.0, 1.0));
+        // stalled boids are set going again
+        assert_eq!(flock.boids[2].speed(), 1.0);
+    }
+
+    #[test]
+    fn test_speed_limits_validated() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        assert_eq!(flock.set_speed_limits(-1.0, -2.0).unwrap_err().errors, vec![CreationError::MinSpeedIsNegative, CreationError::MaxSpeedIsNotAboveMinSpeed]);
+        assert!(flock.set_speed_limits(3.0, 3.0).is_err());
+        assert!(flock.set_speed_limits(0.0, f32::INFINITY).is_ok());
+    }
+
+    #[test]
+    fn test_radii_changed_together_or_not_at_all() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        // past the old local radius, which changing them one at a time wouldn't allow
+        flock.set_radii(12.0, 40.0, 30.0).unwrap();
+        assert_eq!(flock.radii(), (12.0, 40.0, 30.0));
+        assert_eq!(flock.set_radii(50.0, 40.0, 45.0).unwrap_err().errors, vec![CreationError::LocalEnvironmentIsSmallerThanCrowdingEnvironment]);
+        assert_eq!(flock.set_radii(1.0, 40.0, 41.0).unwrap_err().errors, vec![CreationError::AlignmentRadiusIsOutsideCrowdingToLocalRange]);
+        assert_eq!(flock.radii(), (12.0, 40.0, 30.0));
+    }
+
+    #[test]
+    fn test_step_does_not_allocate_once_warmed_up() {
+        let mut flock = Flock::new(50, 2.0, 10.0, 0.5, 0.1, 0.1).unwrap();
+        flock.set_arena(Arena::circle((0.0, 0.0), 100.0).unwrap());
+        let pair = PortalPair {
+            first: PortalEnd { centre: (-50.0, 0.0), radius: 10.0, heading: 0.0 },
+            second: PortalEnd { centre: (50.0, 0.0), radius: 10.0, heading: 0.0 },
+        };
+        flock.set_portals(Portals::new(vec![pair], true).unwrap());
+        // the scratch buffers grow to fit over the first few steps
+        for _ in 0..20 {
+            flock.step();
+        }
+        let allocations_before = ALLOCATIONS.with(Cell::get);
+        for _ in 0..100 {
+            flock.step();
+        }
+        assert_eq!(ALLOCATIONS.with(Cell::get), allocations_before);
+    }
+
+    #[test]
+    fn test_wall_hits_reported_until_next_step() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.set_arena(Arena::circle((0.0, 0.0), 10.0).unwrap());
+        flock.add_boids([Boid::new(9.0, 0.0, 2.0, 0.0), Boid::new(0.0, 0.0, 1.0, 0.0)]);
+        flock.step();
+        // alignment and cohesion each move the boid on by its velocity, taking it to 13 and bouncing it back to 7
+        assert_eq!(flock.events(), [FlockEvent::WallHit { boid: BoidId(1), position: (7.0, 0.0) }]);
+        flock.step();
+        assert!(flock.events().is_empty());
+    }
+
+    #[test]
+    fn test_boids_keep_ids_as_others_leave_and_join() {
+        let mut flock = Flock::new(3, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.randomise_boids(&InitialDistribution::default(), 0).unwrap();
+        assert_eq!(flock.boids().iter().map(|boid| boid.id).collect::<Vec<BoidId>>(), [BoidId(1), BoidId(2), BoidId(3)]);
+
+        let last_boid = flock.boids()[2];
+        flock.remove_boids_where(|boid| boid.id == BoidId(1));
+        flock.add_boids([Boid::new(0.0, 0.0, 0.0, 0.0)]);
+        assert_eq!(flock.boid_idx(BoidId(3)), Some(1));
+        assert_eq!(flock.boid(BoidId(3)).unwrap().x_pos, last_boid.x_pos);
+        assert!(flock.boid(BoidId(1)).is_none());
+        assert_eq!(flock.boids()[2].id, BoidId(4));
+    }
+
+    #[test]
+    fn test_non_finite_values_rejected() {
+        assert_eq!(Flock::new(0, f32::NAN, f32::INFINITY, 0.1, 0.1, 0.1).unwrap_err().errors, vec![
+            CreationError::ValueIsNotFinite("crowding distance".to_string()),
+            CreationError::ValueIsNotFinite("local distance".to_string()),
+        ]);
+        assert_eq!(Flock::new(0, 1.0, 10.0, f32::NAN, 0.1, 0.1).unwrap_err().errors, vec![CreationError::ValueIsNotFinite("repulsion".to_string())]);
+
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        assert!(flock.set_alignment_radius(f32::NAN).is_err());
+        assert!(flock.set_separation_falloff_exponent(f32::INFINITY).is_err());
+        assert!(flock.set_speed_limits(f32::NAN, 5.0).is_err());
+        assert!(flock.set_speed_limits(1.0, f32::NAN).is_err());
+        assert!(flock.set_factor(Factor::Cohesion, f32::NAN).is_err());
+        assert!(Arena::circle((0.0, 0.0), f32::INFINITY).is_err());
+        assert!(Arena::polygon(vec![(0.0, 0.0), (f32::NAN, 0.0), (0.0, 1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_boids_cohere_through_portal() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.5).unwrap();
+        // a stationary boid just outside one end of a portal, and another just outside the other end
+        flock.boids = vec![Boid::new(-4.0, 0.0, 0.0, 0.0), Boid::new(104.0, 0.0, 0.0, 0.0)];
+        let pair = PortalPair {
+            first: PortalEnd { centre: (0.0, 0.0), radius: 1.0, heading: 0.0 },
+            second: PortalEnd { centre: (100.0, 0.0), radius: 1.0, heading: 0.0 },
+        };
+        flock.set_portals(Portals::new(vec![pair], false).unwrap());
+        flock.step();
+        assert_eq!(flock.boids[0].x_vel, 0.0);
+
+        // seen through the portal, each boid is 8 away from the other
+        flock.set_portals(Portals::new(vec![pair], true).unwrap());
+        flock.step();
+        assert_eq!(flock.boids[0].x_vel, 4.0);
+    }
+
+    #[test]
+    fn test_flock_kept_in_arena() {
+        let mut flock = Flock::new(30, 1.0, 10.0, 0.1, 0.1, 0.01).unwrap();
+        flock.set_arena(Arena::polygon(vec![(-20.0, -20.0), (20.0, -20.0), (0.0, 20.0)]).unwrap());
+        // most of a wide blob starts outside the triangle, so has to be moved in
+        flock.randomise_boids(&InitialDistribution::GaussianBlob { std_dev: 100.0 }, 2).unwrap();
+        let arena = flock.arena().unwrap().clone();
+        assert!(flock.boids.iter().all(|boid| arena.contains((boid.x_pos, boid.y_pos))));
+
+        for _ in 0..200 {
+            flock.step();
+        }
+        assert!(flock.boids.iter().all(|boid| arena.contains((boid.x_pos, boid.y_pos))));
+    }
+
+    #[test]
+    fn test_clusters_labelled_each_step() {
+        let mut flock = Flock::new(0, 1.0, 5.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(20.0, 0.0, 0.0, 0.0), Boid::new(3.0, 0.0, 0.0, 0.0)];
+        flock.step();
+        assert_eq!(flock.cluster_labels, vec![0, 1, 0]);
+
+        flock.boids[1].x_pos = 6.0;
+        flock.step();
+        assert_eq!(flock.cluster_labels(), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_predator_catches_boid_it_passes_through_with_sub_steps() {
+        let predator = Predator::new(Boid::new(-10.0, 0.0, 20.0, 0.0), 0.5, 0.1, 20.0, 1.0, 1.0, 0.5).unwrap();
+        let caught_with_sub_steps = |sub_steps: u32| {
+            let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+            flock.add_boids([Boid::new(0.0, 0.0, 0.0, 0.0)]);
+            flock.set_predator(predator.clone());
+            flock.set_sub_steps(sub_steps).unwrap();
+            flock.step();
+            assert_eq!(flock.predator().unwrap().boid.x_pos, 10.0);
+            return flock.boids().is_empty();
+        };
+        // it goes from 10 behind the boid to 10 past it in one step
+        assert!(!caught_with_sub_steps(1));
+        assert!(caught_with_sub_steps(4));
+        assert_eq!(Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap().set_sub_steps(0).unwrap_err().errors, vec![CreationError::NoSubSteps]);
+    }
+
+    #[test]
+    fn test_boid_bounced_into_obstacle_hits_it_with_sub_steps() {
+        let obstacle_hits_with_sub_steps = |sub_steps: u32| {
+            let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+            flock.set_walls(Walls::new(vec![walls::Wall::parse("5,-100;5,100").unwrap()]));
+            // off the wall, the boid heads back through where the obstacle is, well away from where it started
+            flock.set_obstacles(obstacle::Obstacles::new(vec![obstacle::Obstacle::parse("still:-1,5.5:0.5").unwrap()]));
+            flock.set_sub_steps(sub_steps).unwrap();
+            // as if the rules had just moved it from the origin
+            flock.add_boids([Boid::new(20.0, 10.0, 20.0, 10.0)]);
+            flock.scratch.previous_positions = vec![(0.0, 0.0)];
+            if sub_steps == 1 {
+                flock.collide_boids((0.0, 1.0));
+            } else {
+                flock.collide_boids_in_sub_steps(None);
+            }
+            assert!(flock.boids()[0].x_pos < 5.0);
+            return flock.events().iter().filter(|event| matches!(event, FlockEvent::ObstacleHit { .. })).count();
+        };
+        assert_eq!(obstacle_hits_with_sub_steps(1), 0);
+        assert_eq!(obstacle_hits_with_sub_steps(10), 1);
+    }
+
+    #[test]
+    fn test_incorrect_factor_inputs() {
+        let flock = Flock::new(0, 1.0, 50.0, 2.0, -20.2, 1.0);
+        assert!(flock.is_err());
+
+        let result = validate_factors(2.0, -4.9, 1.0);
+        let expected_errors = vec![
+            CreationError::FactorShouldBeLessThanOne("repulsion".to_string()),
+            CreationError::FactorShouldBeMoreThanZero("adhesion".to_string()),
+        ];
+        assert_eq!(result, expected_errors);
+    }
+    #[test]
+    fn test_incorrect_distance_inputs() {
+        let flock = Flock::new(0, 20.0, 2.0, 2.0, -20.2, 1.0);
+        assert!(flock.is_err());
+
+        let result = validate_distances( 20.0, 2.0);
+        assert_eq!(result, Some(CreationError::LocalEnvironmentIsSmallerThanCrowdingEnvironment));
+    }
+    #[test]
+    fn test_all_creation_errors_reported() {
+        let result = Flock::new(0, 2.0, -4.9, 3.0, 20.0, 2.0);
+        assert!(result.is_err());
+        let error = result.unwrap_err();
+        assert_eq!(error.errors.len(), 4);
+    }
+    #[test]
+    fn test_error_display() {
+        assert_eq!(CreationError::FactorShouldBeLessThanOne("adhesion".to_string()).to_string(), "adhesion factor is too large and should be below zero".to_string());
+        assert_eq!(CreationError::FactorShouldBeMoreThanZero("repulsion".to_string()).to_string(), "repulsion factor is negative".to_string());
+        assert_eq!(CreationError::LocalEnvironmentIsSmallerThanCrowdingEnvironment.to_string(), "local environment is smaller than (or equal to) crowding environment".to_string());
+    }
+}
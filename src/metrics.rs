@@ -0,0 +1,128 @@
+//! Live metrics for long headless runs, served in the OpenMetrics text format on `/metrics` so a
+//! run can be scraped by Prometheus and watched in Grafana like any other service.
+//!
+//! `boids_ticks_total` counts every step taken, so the tick rate is `rate(boids_ticks_total[1m])`.
+//! `boids_collisions_total` counts collisions summed over every step, and `boids_population` and
+//! `boids_polarization` are gauges of the flock as of the latest step. When several runs report
+//! to the same metrics, as in a parallel sweep, the counters add up across runs and the gauges
+//! are those of whichever run stepped last.
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use crate::stats::polarization;
+use crate::Boid;
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    values: Mutex<MetricValues>,
+}
+
+#[derive(Debug, Default)]
+struct MetricValues {
+    ticks_total: u64,
+    collisions_total: u64,
+    population: usize,
+    polarization: f32,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        return Metrics::default();
+    }
+
+    /// records a step that left the flock as `boids`, with `collisions` collisions
+    pub fn record_tick(&self, boids: &[Boid], collisions: usize) {
+        let polarization = polarization(boids);
+        let mut values = self.values.lock().expect("metrics lock poisoned");
+        values.ticks_total += 1;
+        values.collisions_total += collisions as u64;
+        values.population = boids.len();
+        values.polarization = polarization;
+    }
+
+    /// the metrics as an OpenMetrics text exposition
+    pub fn render(&self) -> String {
+        let values = self.values.lock().expect("metrics lock poisoned");
+        let mut text = String::new();
+        let _ = writeln!(text, "# TYPE boids_ticks counter\n# HELP boids_ticks Simulation steps taken.\nboids_ticks_total {}", values.ticks_total);
+        let _ = writeln!(text, "# TYPE boids_collisions counter\n# HELP boids_collisions Collisions, summed over every step.\nboids_collisions_total {}", values.collisions_total);
+        let _ = writeln!(text, "# TYPE boids_population gauge\n# HELP boids_population Boids in the flock.\nboids_population {}", values.population);
+        let _ = writeln!(text, "# TYPE boids_polarization gauge\n# HELP boids_polarization How aligned the flock's headings are, from 0 to 1.\nboids_polarization {}", values.polarization);
+        text.push_str("# EOF\n");
+        return text;
+    }
+}
+
+/// answers requests for `/metrics` on a background thread for as long as the program runs.
+/// Returns the address being listened on, which is useful when `address` asks for any free port
+pub fn serve(address: &str, metrics: Arc<Metrics>) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(address)?;
+    let local_address = listener.local_addr()?;
+    thread::spawn(move || {
+        // a scraper that hangs up early shouldn't stop the metrics being served to the next one
+        for stream in listener.incoming().flatten() {
+            let _ = respond(stream, &metrics);
+        }
+    });
+    return Ok(local_address);
+}
+
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    // e.g. "GET /metrics HTTP/1.1"
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    if path != "/metrics" {
+        return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    }
+    let body = metrics.render();
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/openmetrics-text; version=1.0.0; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+           body.len(), body)?;
+    return stream.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn get(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        return response;
+    }
+
+    #[test]
+    fn test_ticks_recorded() {
+        let metrics = Metrics::new();
+        let boids = [Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(5.0, 0.0, 2.0, 0.0)];
+        metrics.record_tick(&boids, 3);
+        metrics.record_tick(&boids, 1);
+
+        let text = metrics.render();
+        assert!(text.contains("\nboids_ticks_total 2\n"));
+        assert!(text.contains("\nboids_collisions_total 4\n"));
+        assert!(text.contains("\nboids_population 2\n"));
+        assert!(text.contains("\nboids_polarization 1\n"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn test_metrics_served() {
+        let metrics = Arc::new(Metrics::new());
+        let address = serve("127.0.0.1:0", Arc::clone(&metrics)).unwrap();
+        metrics.record_tick(&[Boid::new(0.0, 0.0, 1.0, 0.0)], 0);
+
+        let response = get(address, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("application/openmetrics-text"));
+        assert!(response.contains("boids_ticks_total 1\n"));
+
+        assert!(get(address, "/").starts_with("HTTP/1.1 404"));
+    }
+}
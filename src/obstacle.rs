@@ -0,0 +1,401 @@
+//! Round obstacles in the world, which can stand still, swing back and forth, or travel round a
+//! closed path, so the flock has to keep finding a way round them. A boid that runs into an
+//! obstacle bounces off it, or is pushed aside by one that runs into it. Collisions are worked out
+//! from the boid's movement relative to the obstacle over the whole step, so neither can pass
+//! through the other however fast they're going.
+//!
+//! Like an arena's whiskers, obstacle avoidance lets boids see obstacles coming: a boid that will
+//! pass too close to an obstacle within the look-ahead, allowing for how the obstacle is moving,
+//! steers away from it, harder the sooner they'd meet.
+
+use std::f32::consts::TAU;
+use std::fmt;
+use anyhow::{anyhow, Context, Result};
+use crate::arena::reflect;
+use crate::events::FlockEvent;
+use crate::{check_finite, check_float_between_zero_and_one, is_positive_and_finite, Boid, CreationError, InvalidFlockConfig};
+
+// how far outside an obstacle a boid is put after hitting it, so it isn't left balanced exactly on its edge
+const OBSTACLE_CLEARANCE: f32 = 1e-3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObstacleMotion {
+    Still,
+    /// swings along `amplitude` either side of the obstacle's centre and back again every `period` ticks
+    Oscillate { amplitude: (f32, f32), period: f32 },
+    /// travels round the path at `speed` per tick, starting from the first point; the last point joins back up to the first
+    Path { points: Vec<(f32, f32)>, speed: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Obstacle {
+    pub centre: (f32, f32), // where an oscillating obstacle swings about; unused by one following a path
+    pub radius: f32,
+    pub motion: ObstacleMotion,
+}
+
+impl Obstacle {
+    pub fn new(centre: (f32, f32), radius: f32, motion: ObstacleMotion) -> Result<Obstacle, InvalidFlockConfig> {
+        let obstacle = Obstacle { centre, radius, motion };
+        obstacle.validate()?;
+        return Ok(obstacle);
+    }
+
+    fn validate(&self) -> Result<(), InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = [check_finite(self.centre.0, "obstacle centre"), check_finite(self.centre.1, "obstacle centre")]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !is_positive_and_finite(self.radius) {
+            errors.push(CreationError::ObstacleParameterIsNotPositive("radius".to_string()));
+        }
+        match &self.motion {
+            ObstacleMotion::Still => {}
+            ObstacleMotion::Oscillate { amplitude, period } => {
+                errors.extend([check_finite(amplitude.0, "obstacle amplitude"), check_finite(amplitude.1, "obstacle amplitude")].into_iter().flatten());
+                if !is_positive_and_finite(*period) {
+                    errors.push(CreationError::ObstacleParameterIsNotPositive("period".to_string()));
+                }
+            }
+            ObstacleMotion::Path { points, speed } => {
+                if points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+                    errors.push(CreationError::ValueIsNotFinite("obstacle path point".to_string()));
+                }
+                if points.len() < 2 {
+                    errors.push(CreationError::PathNeedsAtLeastTwoPoints);
+                }
+                if !is_positive_and_finite(*speed) {
+                    errors.push(CreationError::ObstacleParameterIsNotPositive("speed".to_string()));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(());
+    }
+
+    /// `still:x,y:radius`, `oscillate:x,y:radius:amplitude_x,amplitude_y:period` or
+    /// `path:radius:speed:x,y;x,y;...`, e.g. `oscillate:0,0:10:40,0:200` for an obstacle swinging 40
+    /// either side of the origin every 200 ticks
+    pub fn parse(text: &str) -> Result<Obstacle> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        let parse_pair = |part: &str| -> Result<(f32, f32)> {
+            let (x, y) = part.split_once(',').ok_or_else(|| anyhow!("'{}' in '{}' should be x,y", part, text))?;
+            return Ok((parse_float(x)?, parse_float(y)?));
+        };
+        let obstacle = match parts.as_slice() {
+            ["still", centre, radius] => Obstacle::new(parse_pair(centre)?, parse_float(radius)?, ObstacleMotion::Still),
+            ["oscillate", centre, radius, amplitude, period] => Obstacle::new(parse_pair(centre)?, parse_float(radius)?,
+                                                                              ObstacleMotion::Oscillate { amplitude: parse_pair(amplitude)?, period: parse_float(period)? }),
+            ["path", radius, speed, points] => {
+                let points = points.split(';').map(parse_pair).collect::<Result<Vec<(f32, f32)>>>()?;
+                let centre = points.first().copied().unwrap_or_default();
+                Obstacle::new(centre, parse_float(radius)?, ObstacleMotion::Path { points, speed: parse_float(speed)? })
+            }
+            _ => return Err(anyhow!("obstacle '{}' should be still:x,y:radius, oscillate:x,y:radius:amplitude_x,amplitude_y:period or path:radius:speed:x,y;x,y;...", text)),
+        };
+        return Ok(obstacle?);
+    }
+
+    /// where the obstacle is after `tick` ticks
+    pub fn position_at(&self, tick: u32) -> (f32, f32) {
+        match &self.motion {
+            ObstacleMotion::Still => return self.centre,
+            ObstacleMotion::Oscillate { amplitude, period } => {
+                let swing = (TAU * tick as f32 / period).sin();
+                return (self.centre.0 + amplitude.0 * swing, self.centre.1 + amplitude.1 * swing);
+            }
+            ObstacleMotion::Path { points, speed } => {
+                let edges = || points.iter().zip(points.iter().cycle().skip(1));
+                let perimeter: f32 = edges().map(|(start, end)| dist(*start, *end)).sum();
+                // every point in the same place leaves nowhere to go
+                if perimeter == 0.0 {
+                    return points[0];
+                }
+                let mut dist_left = (speed * tick as f32).rem_euclid(perimeter);
+                for (start, end) in edges() {
+                    let length = dist(*start, *end);
+                    if dist_left < length {
+                        let fraction = dist_left / length;
+                        return (start.0 + (end.0 - start.0) * fraction, start.1 + (end.1 - start.1) * fraction);
+                    }
+                    dist_left -= length;
+                }
+                return points[0];
+            }
+        }
+    }
+}
+
+/// in the form taken by `Obstacle::parse`, so it can be read back in
+impl fmt::Display for Obstacle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match &self.motion {
+            ObstacleMotion::Still => write!(f, "still:{},{}:{}", self.centre.0, self.centre.1, self.radius),
+            ObstacleMotion::Oscillate { amplitude, period } => write!(f, "oscillate:{},{}:{}:{},{}:{}", self.centre.0, self.centre.1, self.radius, amplitude.0, amplitude.1, period),
+            ObstacleMotion::Path { points, speed } => {
+                let points: Vec<String> = points.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+                write!(f, "path:{}:{}:{}", self.radius, speed, points.join(";"))
+            }
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObstacleAvoidance {
+    pub look_ahead: f32, // how many ticks ahead boids see collisions coming
+    pub clearance: f32, // how far from an obstacle's edge a boid wants to pass
+    pub steering_factor: f32, // how hard a boid turns away, relative to how fast it's closing on the obstacle
+}
+
+impl ObstacleAvoidance {
+    pub fn new(look_ahead: f32, clearance: f32, steering_factor: f32) -> Result<ObstacleAvoidance, InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = check_float_between_zero_and_one(steering_factor, "obstacle steering".to_string())
+            .into_iter()
+            .collect();
+        if !is_positive_and_finite(look_ahead) {
+            errors.push(CreationError::ObstacleParameterIsNotPositive("look ahead".to_string()));
+        }
+        if !clearance.is_finite() || clearance < 0.0 {
+            errors.push(CreationError::ObstacleParameterIsNotPositive("clearance".to_string()));
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(ObstacleAvoidance { look_ahead, clearance, steering_factor });
+    }
+
+    /// `look_ahead:clearance:steering_factor`, e.g. `30:5:0.2`
+    pub fn parse(text: &str) -> Result<ObstacleAvoidance> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let [look_ahead, clearance, steering_factor] = parts.as_slice() else {
+            return Err(anyhow!("obstacle avoidance '{}' should be look_ahead:clearance:steering_factor", text));
+        };
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        return Ok(ObstacleAvoidance::new(parse_float(look_ahead)?, parse_float(clearance)?, parse_float(steering_factor)?)?);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Obstacles {
+    pub obstacles: Vec<Obstacle>,
+    pub avoidance: Option<ObstacleAvoidance>,
+    ticks_elapsed: u32,
+}
+
+impl Obstacles {
+    pub fn new(obstacles: Vec<Obstacle>) -> Obstacles {
+        return Obstacles { obstacles, avoidance: None, ticks_elapsed: 0 };
+    }
+
+    /// where each obstacle is now
+    pub fn positions(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        return self.obstacles.iter().map(|obstacle| obstacle.position_at(self.ticks_elapsed));
+    }
+
+    /// how far each obstacle will move during the next step
+    fn movement(&self, obstacle: &Obstacle) -> (f32, f32) {
+        let (now, next) = (obstacle.position_at(self.ticks_elapsed), obstacle.position_at(self.ticks_elapsed.saturating_add(1)));
+        return (next.0 - now.0, next.1 - now.1);
+    }
+
+    /// steers each boid away from any obstacle it's on course to pass too close to, allowing for the obstacle's movement
+    pub(crate) fn avoid(&self, boids: &mut [Boid]) {
+        let Some(avoidance) = self.avoidance else {
+            return;
+        };
+        for obstacle in &self.obstacles {
+            let position = obstacle.position_at(self.ticks_elapsed);
+            let velocity = self.movement(obstacle);
+            for boid in boids.iter_mut() {
+                let relative_position = (boid.x_pos - position.0, boid.y_pos - position.1);
+                let relative_velocity = (boid.x_vel - velocity.0, boid.y_vel - velocity.1);
+                let closing_speed_squared = relative_velocity.0 * relative_velocity.0 + relative_velocity.1 * relative_velocity.1;
+                if closing_speed_squared == 0.0 {
+                    continue;
+                }
+                let time_to_closest = -(relative_position.0 * relative_velocity.0 + relative_position.1 * relative_velocity.1) / closing_speed_squared;
+                if time_to_closest <= 0.0 || time_to_closest > avoidance.look_ahead {
+                    continue;
+                }
+                let closest = (relative_position.0 + relative_velocity.0 * time_to_closest, relative_position.1 + relative_velocity.1 * time_to_closest);
+                let miss_dist = (closest.0 * closest.0 + closest.1 * closest.1).sqrt();
+                if miss_dist >= obstacle.radius + avoidance.clearance {
+                    continue;
+                }
+                let closing_speed = closing_speed_squared.sqrt();
+                // heading straight for the middle, either way round is as good, so turn left
+                let sideways = if miss_dist < 1e-6 {
+                    (-relative_velocity.1 / closing_speed, relative_velocity.0 / closing_speed)
+                } else {
+                    (closest.0 / miss_dist, closest.1 / miss_dist)
+                };
+                let urgency = 1.0 - time_to_closest / avoidance.look_ahead;
+                boid.x_vel += sideways.0 * urgency * closing_speed * avoidance.steering_factor;
+                boid.y_vel += sideways.1 * urgency * closing_speed * avoidance.steering_factor;
+            }
+        }
+    }
+
+    /// bounces each boid that ran into an obstacle since it was at its previous position back off it,
+    /// and pushes aside any boid an obstacle ran into. Call before `advance`, while the obstacles are
+    /// still where they were at the start of the step
+    pub(crate) fn collide(&self, boids: &mut [Boid], previous_positions: &[(f32, f32)], tick_fraction: (f32, f32), events: &mut Vec<FlockEvent>) {
+        for (obstacle_idx, obstacle) in self.obstacles.iter().enumerate() {
+            let position = obstacle.position_at(self.ticks_elapsed);
+            let movement = self.movement(obstacle);
+            // where the obstacle is at the start and end of the part of the step the boids moved over
+            let start = (position.0 + movement.0 * tick_fraction.0, position.1 + movement.1 * tick_fraction.0);
+            let end = (position.0 + movement.0 * tick_fraction.1, position.1 + movement.1 * tick_fraction.1);
+            for (boid, previous_position) in boids.iter_mut().zip(previous_positions) {
+                // everything is worked out as if the obstacle were standing still and the boid moving past it
+                let relative_start = (previous_position.0 - start.0, previous_position.1 - start.1);
+                let relative_end = (boid.x_pos - end.0, boid.y_pos - end.1);
+                let Some(relative_position) = bounce_off_circle(boid, movement, relative_start, relative_end, obstacle.radius) else {
+                    continue;
+                };
+                (boid.x_pos, boid.y_pos) = (end.0 + relative_position.0, end.1 + relative_position.1);
+                events.push(FlockEvent::ObstacleHit { boid: boid.id, obstacle: obstacle_idx, position: (boid.x_pos, boid.y_pos) });
+            }
+        }
+    }
+
+    /// moves the obstacles on to where they are for the next step
+    pub(crate) fn advance(&mut self) {
+        self.ticks_elapsed = self.ticks_elapsed.saturating_add(1);
+    }
+}
+
+/// where the boid ends up relative to a circle of `radius` at the origin, if it hit it moving from
+/// `start` to `end` relative to the circle; its velocity is bounced off the circle, which is moving by `circle_movement` each tick
+fn bounce_off_circle(boid: &mut Boid, circle_movement: (f32, f32), start: (f32, f32), end: (f32, f32), radius: f32) -> Option<(f32, f32)> {
+    let movement = (end.0 - start.0, end.1 - start.1);
+    let start_dist = (start.0 * start.0 + start.1 * start.1).sqrt();
+    let (normal, mut position) = if start_dist < radius {
+        // the obstacle ran into the boid, or appeared on top of it, so it's shoved out the quickest way
+        let end_dist = (end.0 * end.0 + end.1 * end.1).sqrt();
+        if end_dist >= radius {
+            return None;
+        }
+        let normal = if end_dist > 0.0 { (end.0 / end_dist, end.1 / end_dist) } else { away_from(circle_movement) };
+        (normal, (normal.0 * (radius + OBSTACLE_CLEARANCE), normal.1 * (radius + OBSTACLE_CLEARANCE)))
+    } else {
+        // solve |start + fraction * movement| = radius; from outside, the smaller root is where it hits
+        let a = movement.0 * movement.0 + movement.1 * movement.1;
+        let b = 2.0 * (movement.0 * start.0 + movement.1 * start.1);
+        let c = start_dist * start_dist - radius * radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if a == 0.0 || discriminant < 0.0 {
+            return None;
+        }
+        let fraction = (-b - discriminant.sqrt()) / (2.0 * a);
+        if !(0.0..=1.0).contains(&fraction) {
+            return None;
+        }
+        let hit = (start.0 + movement.0 * fraction, start.1 + movement.1 * fraction);
+        let normal = (hit.0 / radius, hit.1 / radius);
+        let remaining_movement = reflect((movement.0 * (1.0 - fraction), movement.1 * (1.0 - fraction)), normal);
+        (normal, (hit.0 + remaining_movement.0, hit.1 + remaining_movement.1))
+    };
+    // a corner case of the bounce, e.g. grazing the edge, could still leave it inside
+    if position.0 * position.0 + position.1 * position.1 < radius * radius {
+        position = (normal.0 * (radius + OBSTACLE_CLEARANCE), normal.1 * (radius + OBSTACLE_CLEARANCE));
+    }
+    // only the boid's velocity relative to the obstacle bounces, and only if it's heading into it
+    let relative_velocity = (boid.x_vel - circle_movement.0, boid.y_vel - circle_movement.1);
+    if relative_velocity.0 * normal.0 + relative_velocity.1 * normal.1 < 0.0 {
+        let bounced = reflect(relative_velocity, normal);
+        (boid.x_vel, boid.y_vel) = (bounced.0 + circle_movement.0, bounced.1 + circle_movement.1);
+    }
+    return Some(position);
+}
+
+/// a unit vector the way the obstacle is going, or any one if it's still
+fn away_from(movement: (f32, f32)) -> (f32, f32) {
+    let length = (movement.0 * movement.0 + movement.1 * movement.1).sqrt();
+    if length == 0.0 {
+        return (1.0, 0.0);
+    }
+    return (movement.0 / length, movement.1 / length);
+}
+
+fn dist(start: (f32, f32), end: (f32, f32)) -> f32 {
+    return ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Flock, Rule};
+
+    fn flock_with(boids: Vec<Boid>, obstacles: Obstacles) -> Flock {
+        let mut flock = Flock::new(0, 1.0, 20.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = boids;
+        flock.set_obstacles(obstacles);
+        return flock;
+    }
+
+    #[test]
+    fn test_obstacles_move() {
+        let oscillating = Obstacle::parse("oscillate:0,0:1:10,0:4").unwrap();
+        assert_eq!(oscillating.position_at(0), (0.0, 0.0));
+        assert_eq!(oscillating.position_at(1), (10.0, 0.0));
+
+        let square_path = Obstacle::parse("path:1:3:0,0;2,0;2,2;0,2").unwrap();
+        assert_eq!(square_path.position_at(0), (0.0, 0.0));
+        assert_eq!(square_path.position_at(1), (2.0, 1.0));
+        assert_eq!(square_path.position_at(3), (1.0, 0.0));
+    }
+
+    #[test]
+    fn test_boid_bounces_off_still_obstacle() {
+        let obstacles = Obstacles::new(vec![Obstacle::parse("still:0,0:2").unwrap()]);
+        // a lone boid doesn't flock, so doesn't move; with every factor 0, its flockmate just keeps it company,
+        // and with only alignment on, each boid moves once
+        let mut flock = flock_with(vec![Boid::new(-5.0, 0.0, 4.0, 0.0), Boid::new(-5.0, 10.0, 4.0, 0.0)], obstacles);
+        flock.set_rule_enabled(Rule::Cohesion, false);
+        flock.step();
+        // it hit the edge at -2 with 1 of its movement left, and went back that far
+        assert_eq!((flock.boids()[0].x_pos, flock.boids()[0].x_vel), (-3.0, -4.0));
+        assert!(matches!(flock.events(), [FlockEvent::ObstacleHit { obstacle: 0, .. }]));
+    }
+
+    #[test]
+    fn test_fast_obstacle_doesnt_pass_through_boid() {
+        // the obstacle jumps from one side of the boid to the other in a single tick
+        let obstacles = Obstacles::new(vec![Obstacle::parse("path:1:20:-10,0;10,0").unwrap()]);
+        let mut flock = flock_with(vec![Boid::new(0.0, 0.0, 0.0, 0.0)], obstacles);
+        flock.step();
+        let boid = flock.boids()[0];
+        assert_eq!(flock.events().len(), 1);
+        // knocked along in front of the obstacle, and going at least as fast as it
+        assert!(boid.x_pos >= 11.0);
+        assert!(boid.x_vel >= 20.0);
+    }
+
+    #[test]
+    fn test_boid_steers_round_obstacle_coming_towards_it() {
+        let mut obstacles = Obstacles::new(vec![Obstacle::parse("path:2:1:20,0.5;-20,0.5").unwrap()]);
+        obstacles.avoidance = Some(ObstacleAvoidance::parse("30:1:0.5").unwrap());
+        let mut flock = flock_with(vec![Boid::new(0.0, 0.0, 1.0, 0.0)], obstacles);
+        flock.step();
+        // the obstacle is a little above its path, so it turns down
+        assert!(flock.boids()[0].y_vel < 0.0);
+    }
+
+    #[test]
+    fn test_invalid_obstacles_rejected() {
+        let errors = Obstacle::new((0.0, f32::NAN), 0.0, ObstacleMotion::Path { points: vec![(0.0, 0.0)], speed: -1.0 }).unwrap_err().errors;
+        assert_eq!(errors, vec![
+            CreationError::ValueIsNotFinite("obstacle centre".to_string()),
+            CreationError::ObstacleParameterIsNotPositive("radius".to_string()),
+            CreationError::PathNeedsAtLeastTwoPoints,
+            CreationError::ObstacleParameterIsNotPositive("speed".to_string()),
+        ]);
+        assert!(Obstacle::parse("oscillate:0,0:1:10,0:0").is_err());
+        assert!(Obstacle::parse("square:0,0:1").is_err());
+        assert!(ObstacleAvoidance::parse("30:1:2").is_err());
+    }
+}
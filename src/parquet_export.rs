@@ -0,0 +1,138 @@
+//! Every boid's position and velocity after every tick of every run of a sweep, written as a
+//! Parquet file for analysis in Polars, pandas or anything else that reads Arrow.
+//!
+//! There's one row per boid per tick, in the columns of [`schema`]: `run` matches the main CSV's,
+//! `tick` counts from 1 and `boid_id` is the boid's `BoidId`, so a boid's path through a run is
+//! every row with its `run` and `boid_id`. Each run is written as its own row group, compressed
+//! with Snappy, so a reader can load a single run without the rest of the file, e.g.
+//! `pl.read_parquet("trajectories.parquet").filter(pl.col("run") == 3)`.
+
+use std::io::Write;
+use std::sync::Arc;
+use arrow_array::{ArrayRef, Float32Array, RecordBatch, UInt32Array, UInt64Array};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::Result;
+use parquet::file::properties::WriterProperties;
+use crate::Boid;
+
+/// the state of every boid after every tick of a run, stored column by column
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Trajectory {
+    ticks: Vec<u32>,
+    boid_ids: Vec<u64>,
+    x_pos: Vec<f32>,
+    y_pos: Vec<f32>,
+    x_vel: Vec<f32>,
+    y_vel: Vec<f32>,
+}
+
+impl Trajectory {
+    pub fn new() -> Trajectory {
+        return Trajectory::default();
+    }
+
+    /// adds a row for each of `boids`, as they are after `tick`
+    pub fn record_tick(&mut self, tick: usize, boids: &[Boid]) {
+        self.ticks.extend(std::iter::repeat_n(tick as u32, boids.len()));
+        self.boid_ids.extend(boids.iter().map(|boid| boid.id.0));
+        self.x_pos.extend(boids.iter().map(|boid| boid.x_pos));
+        self.y_pos.extend(boids.iter().map(|boid| boid.y_pos));
+        self.x_vel.extend(boids.iter().map(|boid| boid.x_vel));
+        self.y_vel.extend(boids.iter().map(|boid| boid.y_vel));
+    }
+
+    /// the number of rows
+    pub fn len(&self) -> usize {
+        return self.ticks.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.ticks.is_empty();
+    }
+
+    /// the rows as an Arrow record batch, with every row's `run` set to `run`
+    pub fn to_record_batch(&self, run: usize) -> std::result::Result<RecordBatch, ArrowError> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt32Array::from(vec![run as u32; self.len()])),
+            Arc::new(UInt32Array::from(self.ticks.clone())),
+            Arc::new(UInt64Array::from(self.boid_ids.clone())),
+            Arc::new(Float32Array::from(self.x_pos.clone())),
+            Arc::new(Float32Array::from(self.y_pos.clone())),
+            Arc::new(Float32Array::from(self.x_vel.clone())),
+            Arc::new(Float32Array::from(self.y_vel.clone())),
+        ];
+        return RecordBatch::try_new(schema(), columns);
+    }
+}
+
+/// the columns of a trajectories file; none of them are ever null
+pub fn schema() -> SchemaRef {
+    return Arc::new(Schema::new(vec![
+        Field::new("run", DataType::UInt32, false),
+        Field::new("tick", DataType::UInt32, false),
+        Field::new("boid_id", DataType::UInt64, false),
+        Field::new("x_pos", DataType::Float32, false),
+        Field::new("y_pos", DataType::Float32, false),
+        Field::new("x_vel", DataType::Float32, false),
+        Field::new("y_vel", DataType::Float32, false),
+    ]));
+}
+
+/// writes the trajectory of each run, numbered in the order they're given in, to `writer`
+pub fn write_trajectories<'a>(trajectories: impl IntoIterator<Item = &'a Trajectory>, writer: impl Write + Send) -> Result<()> {
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(writer, schema(), Some(properties))?;
+    for (run, trajectory) in trajectories.into_iter().enumerate() {
+        writer.write(&trajectory.to_record_batch(run)?)?;
+        // a row group per run, so each can be read on its own
+        writer.flush()?;
+    }
+    writer.close()?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float32Type, UInt32Type, UInt64Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use crate::BoidId;
+
+    fn boid(id: u64, x_pos: f32) -> Boid {
+        let mut boid = Boid::new(x_pos, 2.0, 0.5, -0.5);
+        boid.id = BoidId(id);
+        return boid;
+    }
+
+    #[test]
+    fn test_trajectories_round_trip() {
+        let mut first = Trajectory::new();
+        first.record_tick(1, &[boid(0, 1.0), boid(1, 3.0)]);
+        first.record_tick(2, &[boid(0, 1.5), boid(1, 3.5)]);
+        let mut second = Trajectory::new();
+        second.record_tick(1, &[boid(4, 7.0)]);
+        assert_eq!((first.len(), second.len()), (4, 1));
+
+        let path = env::temp_dir().join(format!("boids-trajectories-test-{}.parquet", std::process::id()));
+        write_trajectories([&first, &second], File::create(&path).unwrap()).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(builder.metadata().num_row_groups(), 2);
+        assert_eq!(builder.schema(), &schema());
+        let batches: Vec<RecordBatch> = builder.build().unwrap().collect::<std::result::Result<_, _>>().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 5);
+        let last = batches.last().unwrap();
+        let row = last.num_rows() - 1;
+        assert_eq!(last.column(0).as_primitive::<UInt32Type>().value(row), 1);
+        assert_eq!(last.column(1).as_primitive::<UInt32Type>().value(row), 1);
+        assert_eq!(last.column(2).as_primitive::<UInt64Type>().value(row), 4);
+        assert_eq!(last.column(3).as_primitive::<Float32Type>().value(row), 7.0);
+        assert_eq!(batches[0].column(3).as_primitive::<Float32Type>().value(3), 3.5);
+    }
+}
@@ -0,0 +1,226 @@
+//! A route for the flock to follow. Each boid steers towards the point a little further along
+//! the path than the point on the path closest to it, so the flock flows along the route.
+
+use anyhow::{anyhow, Context, Result};
+use crate::{check_finite, check_float_between_zero_and_one, Boid, CreationError, InvalidFlockConfig};
+
+// how finely splines given in a config are sampled
+const SPLINE_SAMPLES_PER_SEGMENT: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    // splines are sampled into a polyline up front, so following any path only needs straight segments
+    points: Vec<(f32, f32)>,
+}
+
+impl Path {
+    pub fn polyline(points: Vec<(f32, f32)>) -> Result<Path, InvalidFlockConfig> {
+        if points.len() < 2 {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::PathNeedsAtLeastTwoPoints] });
+        }
+        return Ok(Path { points });
+    }
+
+    /// a smooth curve passing through every control point
+    pub fn catmull_rom(control_points: Vec<(f32, f32)>, samples_per_segment: usize) -> Result<Path, InvalidFlockConfig> {
+        if control_points.len() < 2 {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::PathNeedsAtLeastTwoPoints] });
+        }
+        let samples_per_segment = samples_per_segment.max(1);
+        let last = control_points.len() - 1;
+        let mut points = Vec::new();
+        for segment in 0..last {
+            // the end points are repeated to give the first and last segments a neighbour on both sides
+            let p0 = control_points[segment.saturating_sub(1)];
+            let p1 = control_points[segment];
+            let p2 = control_points[segment + 1];
+            let p3 = control_points[(segment + 2).min(last)];
+            for sample in 0..samples_per_segment {
+                let t = sample as f32 / samples_per_segment as f32;
+                points.push((catmull_rom_component(p0.0, p1.0, p2.0, p3.0, t), catmull_rom_component(p0.1, p1.1, p2.1, p3.1, t)));
+            }
+        }
+        points.push(control_points[last]);
+        return Ok(Path { points });
+    }
+
+    pub fn points(&self) -> &[(f32, f32)] {
+        return &self.points;
+    }
+
+    pub fn length(&self) -> f32 {
+        return self.points.windows(2).map(|segment| segment_length(segment[0], segment[1])).sum();
+    }
+
+    /// how far along the path the closest point on the path to (x, y) is
+    pub fn distance_along_path_of_closest_point(&self, x: f32, y: f32) -> f32 {
+        let mut closest_squared_dist = f32::INFINITY;
+        let mut closest_distance_along_path = 0.0;
+        let mut distance_to_segment_start = 0.0;
+        for segment in self.points.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            let length = segment_length(start, end);
+            // how far along this segment the closest point is, from 0 at the start to 1 at the end
+            let fraction = if length > 0.0 {
+                (((x - start.0) * (end.0 - start.0) + (y - start.1) * (end.1 - start.1)) / (length * length)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let closest_x = start.0 + (end.0 - start.0) * fraction;
+            let closest_y = start.1 + (end.1 - start.1) * fraction;
+            let squared_dist = (x - closest_x).powi(2) + (y - closest_y).powi(2);
+            if squared_dist < closest_squared_dist {
+                closest_squared_dist = squared_dist;
+                closest_distance_along_path = distance_to_segment_start + fraction * length;
+            }
+            distance_to_segment_start += length;
+        }
+        return closest_distance_along_path;
+    }
+
+    /// the point `distance` along the path, stopping at the ends
+    pub fn point_at_distance_along_path(&self, distance: f32) -> (f32, f32) {
+        let mut remaining = distance.max(0.0);
+        for segment in self.points.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            let length = segment_length(start, end);
+            if remaining <= length && length > 0.0 {
+                let fraction = remaining / length;
+                return (start.0 + (end.0 - start.0) * fraction, start.1 + (end.1 - start.1) * fraction);
+            }
+            remaining -= length;
+        }
+        return self.points[self.points.len() - 1];
+    }
+}
+
+fn segment_length(start: (f32, f32), end: (f32, f32)) -> f32 {
+    return ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+}
+
+fn catmull_rom_component(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    return 0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathFollowing {
+    pub path: Path,
+    pub follow_factor: f32, // how much a boid wants to move towards the path
+    pub lookahead: f32, // how far along the path, past the closest point, a boid aims for
+}
+
+impl PathFollowing {
+    pub fn new(path: Path, follow_factor: f32, lookahead: f32) -> Result<PathFollowing, InvalidFlockConfig> {
+        let errors: Vec<CreationError> = check_float_between_zero_and_one(follow_factor, "path following".to_string())
+            .into_iter()
+            .chain(check_finite(lookahead, "path lookahead"))
+            .collect();
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(PathFollowing {
+            path,
+            follow_factor,
+            lookahead,
+        });
+    }
+
+    /// `polyline:follow_factor:lookahead:x,y;x,y;...`, or `spline:...` for a smooth curve through
+    /// the points, e.g. `spline:0.1:5:0,0;100,50;200,0`
+    pub fn parse(text: &str) -> Result<PathFollowing> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let [kind, follow_factor, lookahead, points] = parts.as_slice() else {
+            return Err(anyhow!("path '{}' should be polyline:follow_factor:lookahead:x,y;x,y;... or spline:follow_factor:lookahead:x,y;x,y;...", text));
+        };
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        let parse_pair = |part: &str| -> Result<(f32, f32)> {
+            let (x, y) = part.split_once(',').ok_or_else(|| anyhow!("'{}' in '{}' should be x,y", part, text))?;
+            return Ok((parse_float(x)?, parse_float(y)?));
+        };
+        let points = points.split(';').map(parse_pair).collect::<Result<Vec<(f32, f32)>>>()?;
+        let path = match *kind {
+            "polyline" => Path::polyline(points)?,
+            "spline" => Path::catmull_rom(points, SPLINE_SAMPLES_PER_SEGMENT)?,
+            _ => return Err(anyhow!("unknown path '{}' in '{}'; expected polyline or spline", kind, text)),
+        };
+        return Ok(PathFollowing::new(path, parse_float(follow_factor)?, parse_float(lookahead)?)?);
+    }
+
+    pub(crate) fn apply(&self, boids: &mut [Boid]) {
+        for boid in boids {
+            let distance_along_path = self.path.distance_along_path_of_closest_point(boid.x_pos, boid.y_pos);
+            let (target_x, target_y) = self.path.point_at_distance_along_path(distance_along_path + self.lookahead);
+            boid.x_vel += (target_x - boid.x_pos) * self.follow_factor;
+            boid.y_vel += (target_y - boid.y_pos) * self.follow_factor;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest_point_on_polyline() {
+        let path = Path::polyline(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]).unwrap();
+        assert_eq!(path.length(), 20.0);
+        assert_eq!(path.distance_along_path_of_closest_point(4.0, 3.0), 4.0);
+        assert_eq!(path.distance_along_path_of_closest_point(13.0, 6.0), 16.0);
+        assert_eq!(path.distance_along_path_of_closest_point(-5.0, -5.0), 0.0);
+        assert_eq!(path.point_at_distance_along_path(12.0), (10.0, 2.0));
+        assert_eq!(path.point_at_distance_along_path(100.0), (10.0, 10.0));
+    }
+
+    #[test]
+    fn test_catmull_rom_passes_through_control_points() {
+        let control_points = vec![(0.0, 0.0), (10.0, 5.0), (20.0, 0.0)];
+        let path = Path::catmull_rom(control_points.clone(), 8).unwrap();
+        assert_eq!(path.points().len(), 17);
+        for control_point in control_points {
+            assert!(path.points().contains(&control_point));
+        }
+    }
+
+    #[test]
+    fn test_boid_steers_towards_lookahead_point() {
+        let path = Path::polyline(vec![(0.0, 0.0), (64.0, 0.0)]).unwrap();
+        let path_following = PathFollowing::new(path, 0.5, 16.0).unwrap();
+        let mut boids = vec![Boid::new(16.0, 4.0, 0.0, 0.0)];
+
+        path_following.apply(&mut boids);
+        assert_eq!((boids[0].x_vel, boids[0].y_vel), (8.0, -2.0));
+    }
+
+    #[test]
+    fn test_path_needs_two_points() {
+        assert_eq!(Path::polyline(vec![(1.0, 1.0)]).unwrap_err().errors, vec![CreationError::PathNeedsAtLeastTwoPoints]);
+        assert!(Path::catmull_rom(vec![], 4).is_err());
+    }
+
+    #[test]
+    fn test_path_following_parsed() {
+        let path_following = PathFollowing::parse("polyline:0.2:5:0,0;10,0;10,10").unwrap();
+        assert_eq!(path_following.path.points(), &[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]);
+        assert_eq!((path_following.follow_factor, path_following.lookahead), (0.2, 5.0));
+        let spline = PathFollowing::parse("spline:0.2:5:0,0;10,0;10,10").unwrap();
+        assert_eq!(spline.path.points().len(), 2 * SPLINE_SAMPLES_PER_SEGMENT + 1);
+        assert_eq!(spline.path.points().last(), Some(&(10.0, 10.0)));
+
+        assert!(PathFollowing::parse("polyline:0.2:5:0,0").is_err());
+        assert!(PathFollowing::parse("polyline:2:5:0,0;10,0").is_err());
+        assert!(PathFollowing::parse("circle:0.2:5:0,0;10,0").is_err());
+        assert!(PathFollowing::parse("polyline:0.2:0,0;10,0").is_err());
+    }
+
+    #[test]
+    fn test_invalid_path_following_inputs() {
+        let path = Path::polyline(vec![(0.0, 0.0), (10.0, 0.0)]).unwrap();
+        assert_eq!(PathFollowing::new(path, f32::NAN, f32::NAN).unwrap_err().errors, vec![
+            CreationError::ValueIsNotFinite("path following".to_string()),
+            CreationError::ValueIsNotFinite("path lookahead".to_string()),
+        ]);
+    }
+}
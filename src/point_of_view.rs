@@ -0,0 +1,155 @@
+//! The world as one boid sees it, e.g. for an inset beside the main view that shows why it turned
+//! the way it did. The view is centred on the boid and turned so that it's heading up the screen,
+//! and shows only what's within its vision radius, `Flock::vision_radius`: the boids it's flocking
+//! with, and any food, obstacles or predator. Boids see all the way round, so nothing behind a boid
+//! is hidden from it.
+//!
+//! [`draw_point_of_view`] hands the view to a [`Renderer`] in the boid's own frame, so the SVG
+//! export and the video recorder draw it as they would the whole flock.
+
+use crate::food::FoodSource;
+use crate::render::{BoidColor, Bounds, ColorMode, Renderer};
+use crate::{Boid, BoidId, Flock};
+
+/// where a boid is and which way it's facing, for moving things from the world into its view
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointOfView {
+    pub position: (f32, f32),
+    pub heading: (f32, f32), // of unit length
+    pub radius: f32, // how far the boid can see
+}
+
+impl PointOfView {
+    /// None if the boid isn't in the flock
+    pub fn of(flock: &Flock, id: BoidId) -> Option<PointOfView> {
+        let boid = flock.boid(id)?;
+        let speed = boid.speed();
+        // a stationary boid has no heading, so face it along the x axis, as it's drawn
+        let heading = if speed > 0.0 { (boid.x_vel / speed, boid.y_vel / speed) } else { (1.0, 0.0) };
+        return Some(PointOfView { position: (boid.x_pos, boid.y_pos), heading, radius: flock.vision_radius() });
+    }
+
+    /// where `point` in the world is in the boid's view, which has the boid at the origin facing
+    /// towards negative y, up the screen, with its right towards positive x
+    pub fn to_view(&self, point: (f32, f32)) -> (f32, f32) {
+        return self.rotate((point.0 - self.position.0, point.1 - self.position.1));
+    }
+
+    pub fn can_see(&self, point: (f32, f32)) -> bool {
+        return (point.0 - self.position.0).powi(2) + (point.1 - self.position.1).powi(2) <= self.radius.powi(2);
+    }
+
+    /// the boid as it appears in the view, moving in the same direction relative to the viewer
+    pub fn boid_in_view(&self, boid: &Boid) -> Boid {
+        let (x_pos, y_pos) = self.to_view((boid.x_pos, boid.y_pos));
+        let (x_vel, y_vel) = self.rotate((boid.x_vel, boid.y_vel));
+        return Boid { x_pos, y_pos, x_vel, y_vel, ..*boid };
+    }
+
+    fn rotate(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (heading_x, heading_y) = self.heading;
+        return (heading_x * y - heading_y * x, -(heading_x * x + heading_y * y));
+    }
+}
+
+/// draws what the boid with this id can see, in its own frame, with the view's bounds the square
+/// around its vision radius. Returns false, having drawn nothing, if the boid isn't in the flock
+pub fn draw_point_of_view(flock: &Flock, id: BoidId, color_mode: ColorMode, renderer: &mut impl Renderer) -> bool {
+    let Some(point_of_view) = PointOfView::of(flock, id) else {
+        return false;
+    };
+    let radius = point_of_view.radius;
+    renderer.begin_frame(Some(Bounds { min: (-radius, -radius), max: (radius, radius) }));
+
+    if let Some(obstacles) = flock.obstacles() {
+        for (centre, obstacle) in obstacles.positions().zip(&obstacles.obstacles) {
+            // seen once any of it is within sight
+            let dist = ((centre.0 - point_of_view.position.0).powi(2) + (centre.1 - point_of_view.position.1).powi(2)).sqrt();
+            if dist - obstacle.radius <= radius {
+                renderer.draw_obstacle(point_of_view.to_view(centre), obstacle.radius);
+            }
+        }
+    }
+    let foraging_food = flock.foraging().map(|foraging| foraging.food_sources.as_slice()).unwrap_or_default();
+    for food in foraging_food.iter().filter(|food| food.is_available() && point_of_view.can_see((food.x_pos, food.y_pos))) {
+        let (x_pos, y_pos) = point_of_view.to_view((food.x_pos, food.y_pos));
+        renderer.draw_food(&FoodSource::new(x_pos, y_pos));
+    }
+    let cluster_labels = match color_mode {
+        ColorMode::ByCluster => flock.cluster_labels(),
+        _ => Vec::new(),
+    };
+    for (boid_idx, boid) in flock.boids().iter().enumerate() {
+        if boid.id != id && !point_of_view.can_see((boid.x_pos, boid.y_pos)) {
+            continue;
+        }
+        let color = match color_mode {
+            ColorMode::Plain => None,
+            ColorMode::ByCluster => cluster_labels.get(boid_idx).map(|cluster_label| BoidColor::Cluster(*cluster_label)),
+            ColorMode::ByState => flock.boid_state(boid.id).map(BoidColor::State),
+        };
+        renderer.draw_boid(&point_of_view.boid_in_view(boid), color.unwrap_or(BoidColor::Plain));
+    }
+    if let Some(predator) = flock.predator().filter(|predator| point_of_view.can_see((predator.boid.x_pos, predator.boid.y_pos))) {
+        let mut predator_in_view = predator.clone();
+        predator_in_view.boid = point_of_view.boid_in_view(&predator.boid);
+        renderer.draw_predator(&predator_in_view);
+    }
+    renderer.end_frame();
+    return true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::Foraging;
+
+    #[derive(Default)]
+    struct RecordingRenderer {
+        calls: Vec<String>,
+    }
+
+    impl Renderer for RecordingRenderer {
+        fn begin_frame(&mut self, bounds: Option<Bounds>) {
+            self.calls.push(format!("begin {:?}", bounds.map(|bounds| (bounds.min, bounds.max))));
+        }
+
+        fn draw_food(&mut self, food: &FoodSource) {
+            self.calls.push(format!("food {},{}", food.x_pos, food.y_pos));
+        }
+
+        fn draw_boid(&mut self, boid: &Boid, _color: BoidColor) {
+            self.calls.push(format!("boid {} at {},{} heading {},{}", boid.id.0, boid.x_pos, boid.y_pos, boid.x_vel, boid.y_vel));
+        }
+
+        fn end_frame(&mut self) {
+            self.calls.push("end".to_string());
+        }
+    }
+
+    #[test]
+    fn test_view_turned_to_heading_and_limited_to_sight() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        // heading along positive x, with one boid ahead, one to its right and one out of sight
+        flock.add_boids([
+            Boid::new(0.0, 0.0, 2.0, 0.0),
+            Boid::new(5.0, 0.0, 2.0, 0.0),
+            Boid::new(0.0, 3.0, 0.0, 0.0),
+            Boid::new(50.0, 0.0, 0.0, 0.0),
+        ]);
+        flock.set_foraging(Foraging::new(vec![FoodSource::new(-4.0, 0.0), FoodSource::new(0.0, -40.0)], 0.1, 5.0, 1.0, 100).unwrap());
+        let viewer = flock.boids()[0].id;
+
+        let mut renderer = RecordingRenderer::default();
+        assert!(draw_point_of_view(&flock, viewer, ColorMode::Plain, &mut renderer));
+        assert_eq!(renderer.calls, vec![
+            "begin Some(((-10.0, -10.0), (10.0, 10.0)))",
+            "food 0,4",
+            "boid 1 at 0,-0 heading 0,-2",
+            "boid 2 at 0,-5 heading 0,-2",
+            "boid 3 at 3,-0 heading 0,-0",
+            "end",
+        ]);
+        assert!(!draw_point_of_view(&flock, BoidId(99), ColorMode::Plain, &mut renderer));
+    }
+}
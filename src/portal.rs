@@ -0,0 +1,177 @@
+//! Pairs of linked discs. A boid that moves into one end of a pair comes out of the other, at
+//! the matching spot and turned through the difference between the ends' headings, so a boid
+//! entering one end moving along its heading leaves the other moving along that end's heading.
+//!
+//! Boids can also see through portals: boids near one end are treated by the flocking rules as
+//! if they were near the other end as well, so a flock can thread through together.
+
+use crate::{is_positive_and_finite, Boid, CreationError, InvalidFlockConfig};
+use crate::events::FlockEvent;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PortalEnd {
+    pub centre: (f32, f32),
+    pub radius: f32,
+    pub heading: f32, // in radians anticlockwise from the x axis
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PortalPair {
+    pub first: PortalEnd,
+    pub second: PortalEnd,
+}
+
+#[derive(Debug)]
+pub struct Portals {
+    pub pairs: Vec<PortalPair>, // every pair works both ways
+    pub see_through: bool, // whether the flocking rules see boids through the portals
+}
+
+impl PortalEnd {
+    fn contains(&self, (x, y): (f32, f32)) -> bool {
+        return (x - self.centre.0).powi(2) + (y - self.centre.1).powi(2) < self.radius * self.radius;
+    }
+
+    /// where the boid would be, and how it would be moving, if it were at the same place relative to `other`
+    fn carry_to(&self, other: &PortalEnd, boid: &Boid) -> Boid {
+        let (sin, cos) = (other.heading - self.heading).sin_cos();
+        let rotate = |(x, y): (f32, f32)| (x * cos - y * sin, x * sin + y * cos);
+        // a boid halfway to the edge of one end is halfway to the edge of the other, whatever their sizes
+        let scale = other.radius / self.radius;
+        let offset = rotate(((boid.x_pos - self.centre.0) * scale, (boid.y_pos - self.centre.1) * scale));
+        let (x_vel, y_vel) = rotate((boid.x_vel, boid.y_vel));
+        return Boid { id: boid.id, ..Boid::new(other.centre.0 + offset.0, other.centre.1 + offset.1, x_vel, y_vel) };
+    }
+}
+
+impl PortalPair {
+    /// each end as seen from the other
+    fn ends(&self) -> [(&PortalEnd, &PortalEnd); 2] {
+        return [(&self.first, &self.second), (&self.second, &self.first)];
+    }
+}
+
+impl Portals {
+    pub fn new(pairs: Vec<PortalPair>, see_through: bool) -> Result<Portals, InvalidFlockConfig> {
+        let portals = Portals { pairs, see_through };
+        portals.validate()?;
+        return Ok(portals);
+    }
+
+    fn validate(&self) -> Result<(), InvalidFlockConfig> {
+        let ends = || self.pairs.iter().flat_map(|pair| [pair.first, pair.second]);
+        if ends().any(|end| !end.centre.0.is_finite() || !end.centre.1.is_finite() || !end.heading.is_finite()) {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::ValueIsNotFinite("portal centre or heading".to_string())] });
+        }
+        if ends().any(|end| !is_positive_and_finite(end.radius)) {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::PortalRadiusIsNotPositive] });
+        }
+        return Ok(());
+    }
+
+    /// replaces `images` with the images, at the other end, of boids within `max_dist_of_local_boid` of either
+    /// end of a pair, for the flocking rules to see alongside the real boids. Left empty if boids can't see through the portals
+    pub(crate) fn boids_seen_through(&self, boids: &[Boid], max_dist_of_local_boid: f32, images: &mut Vec<Boid>) {
+        images.clear();
+        if !self.see_through {
+            return;
+        }
+        for pair in &self.pairs {
+            for (near_end, far_end) in pair.ends() {
+                let seen_from = PortalEnd { radius: near_end.radius + max_dist_of_local_boid, ..*near_end };
+                images.extend(boids
+                    .iter()
+                    .filter(|boid| seen_from.contains((boid.x_pos, boid.y_pos)))
+                    .map(|boid| near_end.carry_to(far_end, boid)));
+            }
+        }
+    }
+
+    /// moves boids that have entered a portal since they were at `previous_positions` out of the other end
+    pub(crate) fn apply(&self, boids: &mut [Boid], previous_positions: &[(f32, f32)], events: &mut Vec<FlockEvent>) {
+        for (boid, previous_position) in boids.iter_mut().zip(previous_positions) {
+            // a boid that has just come out of an end is still inside it, and mustn't be sent straight back
+            let entered = self.pairs
+                .iter()
+                .flat_map(PortalPair::ends)
+                .find(|(end, _)| end.contains((boid.x_pos, boid.y_pos)) && !end.contains(*previous_position));
+            if let Some((entrance, exit)) = entered {
+                let from = (boid.x_pos, boid.y_pos);
+                *boid = entrance.carry_to(exit, boid);
+                events.push(FlockEvent::Teleported { boid: boid.id, from, to: (boid.x_pos, boid.y_pos) });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoidId;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn pair() -> PortalPair {
+        return PortalPair {
+            first: PortalEnd { centre: (0.0, 0.0), radius: 2.0, heading: 0.0 },
+            // twice the size, and turned a quarter turn anticlockwise
+            second: PortalEnd { centre: (100.0, 0.0), radius: 4.0, heading: FRAC_PI_2 },
+        };
+    }
+
+    fn assert_near(actual: (f32, f32), expected: (f32, f32)) {
+        assert!((actual.0 - expected.0).abs() < 1e-4 && (actual.1 - expected.1).abs() < 1e-4, "{:?} is not {:?}", actual, expected);
+    }
+
+    #[test]
+    fn test_boid_entering_portal_comes_out_of_other_end() {
+        let portals = Portals::new(vec![pair()], false).unwrap();
+        let mut boids = vec![Boid { id: BoidId(3), ..Boid::new(-1.0, 0.0, 2.0, 0.0) }];
+        let mut events = Vec::new();
+        portals.apply(&mut boids, &[(-3.0, 0.0)], &mut events);
+        assert_eq!(boids[0].id, BoidId(3));
+        assert_near((boids[0].x_pos, boids[0].y_pos), (100.0, -2.0));
+        assert_near((boids[0].x_vel, boids[0].y_vel), (0.0, 2.0));
+        let [FlockEvent::Teleported { boid: BoidId(3), from, to }] = events.as_slice() else {
+            panic!("expected a single teleport, got {:?}", events);
+        };
+        assert_near(*from, (-1.0, 0.0));
+        assert_near(*to, (100.0, -2.0));
+
+        // still inside the second end a step later, but it came out of there so stays put
+        let mut boids = vec![Boid::new(100.0, 0.0, 0.0, 2.0)];
+        portals.apply(&mut boids, &[(100.0, -2.0)], &mut events);
+        assert_eq!(events.len(), 1);
+        assert_near((boids[0].x_pos, boids[0].y_pos), (100.0, 0.0));
+    }
+
+    #[test]
+    fn test_portals_work_both_ways() {
+        let portals = Portals::new(vec![pair()], false).unwrap();
+        let mut boids = vec![Boid::new(100.0, -2.0, 0.0, 2.0)];
+        portals.apply(&mut boids, &[(100.0, -6.0)], &mut Vec::new());
+        assert_near((boids[0].x_pos, boids[0].y_pos), (-1.0, 0.0));
+        assert_near((boids[0].x_vel, boids[0].y_vel), (2.0, 0.0));
+    }
+
+    #[test]
+    fn test_boids_seen_through_portals() {
+        let boids = vec![Boid::new(-4.0, 0.0, 1.0, 0.0), Boid::new(50.0, 0.0, 1.0, 0.0)];
+        let mut images = vec![Boid::new(0.0, 0.0, 0.0, 0.0)];
+        let opaque = Portals::new(vec![pair()], false).unwrap();
+        opaque.boids_seen_through(&boids, 5.0, &mut images);
+        assert!(images.is_empty());
+
+        let see_through = Portals::new(vec![pair()], true).unwrap();
+        see_through.boids_seen_through(&boids, 5.0, &mut images);
+        // only the boid near the first end is close enough to be seen from the second
+        assert_eq!(images.len(), 1);
+        assert_near((images[0].x_pos, images[0].y_pos), (100.0, -8.0));
+    }
+
+    #[test]
+    fn test_empty_portal_rejected() {
+        let mut empty_end = pair();
+        empty_end.second.radius = 0.0;
+        assert_eq!(Portals::new(vec![empty_end], false).unwrap_err().errors, vec![CreationError::PortalRadiusIsNotPositive]);
+    }
+}
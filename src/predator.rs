@@ -0,0 +1,228 @@
+//! A predator for a player to steer, e.g. with the arrow keys. It can only speed up, slow down and
+//! turn so fast, boids that see it coming flee it with the evasion rule, and any boid it reaches is
+//! caught and taken out of the flock. A predator that doesn't catch boids is a sheepdog, for herding them.
+//! A predator given a `Hunting` steers itself instead; see `hunting.rs`.
+
+use crate::events::FlockEvent;
+use crate::hunting::{HuntState, Hunting};
+use crate::steering::evasion;
+use crate::{check_finite, check_float_between_zero_and_one, is_positive_and_finite, Boid, CreationError, InvalidFlockConfig};
+
+// fleeing boids predict where the predator will be at most this many frames ahead
+const MAX_PREDICTION_TIME: f32 = 10.0;
+
+#[derive(Clone, Debug)]
+pub struct Predator {
+    pub boid: Boid, // where the predator is and how it's moving
+    pub max_acceleration: f32, // the most its speed can change in a frame
+    pub max_turn_rate: f32, // in radians per frame
+    pub max_speed: f32,
+    pub catch_radius: f32,
+    pub fear_radius: f32, // boids flee once the predator is this close
+    pub evasion_factor: f32, // how much a boid wants to get away from the predator
+    pub grouping_factor: f32, // how much a fleeing boid heads for the densest part of its local flock; 0 unless set
+    pub catches_boids: bool, // true unless set otherwise
+    heading: f32, // radians anticlockwise from the x axis, kept so a stopped predator sets off the way it was facing
+    thrust: f32, // from the controls: 1 to speed up, -1 to slow down
+    turn: f32, // 1 to turn anticlockwise, -1 clockwise
+    num_caught: usize,
+    hunting: Option<Hunting>, // sets the controls each step when set, in place of the player
+    neighbours: Vec<Boid>, // working space for the boids a fleeing boid can see
+}
+
+impl Predator {
+    pub fn new(boid: Boid,
+               max_acceleration: f32,
+               max_turn_rate: f32,
+               max_speed: f32,
+               catch_radius: f32,
+               fear_radius: f32,
+               evasion_factor: f32
+    ) -> Result<Predator, InvalidFlockConfig> {
+        let predator = Predator {
+            boid,
+            max_acceleration,
+            max_turn_rate,
+            max_speed,
+            catch_radius,
+            fear_radius,
+            evasion_factor,
+            grouping_factor: 0.0,
+            catches_boids: true,
+            heading: boid.y_vel.atan2(boid.x_vel),
+            thrust: 0.0,
+            turn: 0.0,
+            num_caught: 0,
+            hunting: None,
+            neighbours: Vec::new(),
+        };
+        predator.validate()?;
+        return Ok(predator);
+    }
+
+    fn validate(&self) -> Result<(), InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = [
+            check_finite(self.boid.x_pos, "predator position"),
+            check_finite(self.boid.y_pos, "predator position"),
+            check_finite(self.boid.x_vel, "predator velocity"),
+            check_finite(self.boid.y_vel, "predator velocity"),
+            check_float_between_zero_and_one(self.evasion_factor, "evasion".to_string()),
+            check_float_between_zero_and_one(self.grouping_factor, "grouping".to_string()),
+        ].into_iter().flatten().collect();
+        for (value, param_name) in [
+            (self.max_acceleration, "max acceleration"),
+            (self.max_turn_rate, "max turn rate"),
+            (self.max_speed, "max speed"),
+            (self.catch_radius, "catch radius"),
+            (self.fear_radius, "fear radius"),
+        ] {
+            if !is_positive_and_finite(value) {
+                errors.push(CreationError::PredatorParameterIsNotPositive(param_name.to_string()));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(());
+    }
+
+    /// sets how the player is steering until the controls next change. `thrust` and `turn` are
+    /// between -1 and 1; e.g. holding the up and left keys is a thrust of 1 and a turn of 1
+    pub fn set_controls(&mut self, thrust: f32, turn: f32) {
+        self.thrust = thrust.clamp(-1.0, 1.0);
+        self.turn = turn.clamp(-1.0, 1.0);
+    }
+
+    /// how many boids the predator has caught, e.g. for a score counter
+    pub fn num_caught(&self) -> usize {
+        return self.num_caught;
+    }
+
+    /// has the predator hunt by itself, rather than being steered by the player
+    pub fn set_hunting(&mut self, hunting: Hunting) {
+        self.hunting = Some(hunting);
+    }
+
+    pub fn hunting(&self) -> Option<&Hunting> {
+        return self.hunting.as_ref();
+    }
+
+    /// what a hunting predator is doing, or None if it's steered by the player
+    pub fn hunt_state(&self) -> Option<HuntState> {
+        return self.hunting.as_ref().map(Hunting::state);
+    }
+
+    pub(crate) fn is_catching(predator: &Boid, catch_radius: f32, boid: &Boid) -> bool {
+        return predator.dist_to_boid(boid) < catch_radius;
+    }
+
+    pub(crate) fn count_caught(&mut self, num_caught: usize) {
+        self.num_caught += num_caught;
+        if let Some(hunting) = &mut self.hunting {
+            hunting.record_catches(num_caught);
+        }
+    }
+
+    /// lets a hunting predator decide how to steer for this step, given where the boids are
+    pub(crate) fn hunt(&mut self, boids: &[Boid], events: &mut Vec<FlockEvent>) {
+        let Some(hunting) = &mut self.hunting else {
+            return;
+        };
+        let (thrust, turn) = hunting.decide(&self.boid, self.heading, (self.max_acceleration, self.max_turn_rate, self.max_speed), boids, events);
+        self.set_controls(thrust, turn);
+    }
+
+    /// turns and speeds up or slows down as far as the limits allow this frame, then moves
+    pub(crate) fn move_by_controls(&mut self, time_per_frame: f32) {
+        self.heading += self.turn * self.max_turn_rate;
+        let speed = (self.boid.speed() + self.thrust * self.max_acceleration).clamp(0.0, self.max_speed);
+        self.boid.x_vel = speed * self.heading.cos();
+        self.boid.y_vel = speed * self.heading.sin();
+        self.boid.x_pos += self.boid.x_vel * time_per_frame;
+        self.boid.y_pos += self.boid.y_vel * time_per_frame;
+    }
+
+    /// steers the boids within the fear radius away from the predator. The boids a fleeing boid
+    /// regroups with are those within its local radius, as are the boids that make a part of them dense
+    pub(crate) fn scare(&mut self, boids: &mut [Boid], max_dist_of_local_boid: f32) {
+        for boid_idx in 0..boids.len() {
+            let boid = boids[boid_idx];
+            if boid.dist_to_boid(&self.boid) >= self.fear_radius {
+                continue;
+            }
+            self.neighbours.clear();
+            if self.grouping_factor > 0.0 {
+                self.neighbours.extend(boids
+                    .iter()
+                    .enumerate()
+                    .filter(|(other_idx, other_boid)| *other_idx != boid_idx && boid.dist_to_boid(other_boid) < max_dist_of_local_boid)
+                    .map(|(_, other_boid)| *other_boid));
+            }
+            let (x_vel_change, y_vel_change) = evasion(&boid, &self.boid, &self.neighbours, self.evasion_factor,
+                                                       self.grouping_factor, max_dist_of_local_boid, MAX_PREDICTION_TIME);
+            boids[boid_idx].x_vel += x_vel_change;
+            boids[boid_idx].y_vel += y_vel_change;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+    use crate::{BoidId, Flock};
+    use crate::events::FlockEvent;
+
+    fn predator_at(x_pos: f32, y_pos: f32) -> Predator {
+        return Predator::new(Boid::new(x_pos, y_pos, 0.0, 0.0), 0.5, FRAC_PI_2, 2.0, 1.0, 10.0, 0.5).unwrap();
+    }
+
+    #[test]
+    fn test_acceleration_and_turning_limited() {
+        let mut predator = predator_at(0.0, 0.0);
+        predator.set_controls(5.0, 0.0);
+        predator.move_by_controls(1.0);
+        assert_eq!((predator.boid.x_pos, predator.boid.x_vel), (0.5, 0.5));
+        for _ in 0..5 {
+            predator.move_by_controls(1.0);
+        }
+        assert_eq!(predator.boid.speed(), 2.0);
+
+        // a quarter turn in one frame, to head up the y axis
+        predator.set_controls(0.0, 1.0);
+        predator.move_by_controls(1.0);
+        assert!(predator.boid.x_vel.abs() < 1e-6);
+        assert_eq!(predator.boid.y_vel, 2.0);
+    }
+
+    #[test]
+    fn test_only_boids_within_fear_radius_flee() {
+        let mut predator = predator_at(0.0, 0.0);
+        let mut boids = [Boid::new(5.0, 0.0, 0.0, 0.0), Boid::new(50.0, 0.0, 0.0, 0.0)];
+        predator.scare(&mut boids, 10.0);
+        assert_eq!((boids[0].x_vel, boids[0].y_vel), (0.5, 0.0));
+        assert_eq!((boids[1].x_vel, boids[1].y_vel), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_caught_boids_removed_and_counted() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(30.0, 0.0, 0.0, 0.0), Boid::new(0.5, 0.0, 0.0, 0.0), Boid::new(0.0, 0.5, 0.0, 0.0)]);
+        flock.set_predator(predator_at(0.0, 0.0));
+        flock.step();
+
+        assert_eq!(flock.boids().len(), 1);
+        assert_eq!(flock.predator().unwrap().num_caught(), 2);
+        assert!(matches!(flock.events(), [FlockEvent::BoidCaught { boid: BoidId(2), .. }, FlockEvent::BoidCaught { boid: BoidId(3), .. }]));
+    }
+
+    #[test]
+    fn test_invalid_predator_rejected() {
+        let boid = Boid::new(0.0, 0.0, 0.0, 0.0);
+        let errors = Predator::new(boid, 0.0, 1.0, f32::INFINITY, 1.0, 10.0, 0.5).unwrap_err().errors;
+        assert_eq!(errors, vec![
+            CreationError::PredatorParameterIsNotPositive("max acceleration".to_string()),
+            CreationError::PredatorParameterIsNotPositive("max speed".to_string()),
+        ]);
+    }
+}
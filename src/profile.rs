@@ -0,0 +1,233 @@
+//! Named sets of the parameters that can be changed while the flock runs, e.g. from the Bevy
+//! adapter's keys: the three factors, the crowding, local and alignment radii, the speed limits and
+//! which rules are off. They're kept in a text file with one profile on each line, so a profile can
+//! be shared by copying its line into someone else's file:
+//!
+//! ```text
+//! # name:repulsion,adhesion,cohesion:crowding,local,alignment radii:min,max speed:rules off
+//! tight:0.2,0.8,0.9:2,10,10:0,inf:
+//! no alignment:0.5,0.5,0.5:2,20,15:0.5,3:alignment,scripted
+//! ```
+//!
+//! Unless the app says otherwise, the profiles are kept in the user's config directory, at `default_path`.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use crate::{validate_factors, validate_radii, validate_speed_limits, Factor, Flock, InvalidFlockConfig, Rule};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub name: String, // can't have a ':' or '#' in it, or be blank
+    pub repulsion: f32,
+    pub adhesion: f32,
+    pub cohesion: f32,
+    pub radii: (f32, f32, f32), // crowding, local and alignment
+    pub speed_limits: (f32, f32), // min and max
+    pub disabled_rules: Vec<Rule>,
+}
+
+impl Profile {
+    /// the flock's parameters as they are now
+    pub fn from_flock(name: &str, flock: &Flock) -> Profile {
+        return Profile {
+            name: name.to_string(),
+            repulsion: flock.factor(Factor::Repulsion),
+            adhesion: flock.factor(Factor::Adhesion),
+            cohesion: flock.factor(Factor::Cohesion),
+            radii: flock.radii(),
+            speed_limits: flock.speed_limits(),
+            disabled_rules: Rule::ALL.into_iter().filter(|rule| !flock.is_rule_enabled(*rule)).collect(),
+        };
+    }
+
+    /// whether the parameters would make a valid flock
+    pub fn validate(&self) -> Result<(), InvalidFlockConfig> {
+        let mut errors = validate_factors(self.repulsion, self.adhesion, self.cohesion);
+        errors.extend(validate_radii(self.radii.0, self.radii.1, self.radii.2));
+        errors.extend(validate_speed_limits(self.speed_limits.0, self.speed_limits.1));
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(());
+    }
+
+    /// gives the flock these parameters, or fails, changing nothing, if they aren't valid
+    pub fn apply(&self, flock: &mut Flock) -> Result<(), InvalidFlockConfig> {
+        self.validate()?;
+        flock.set_factor(Factor::Repulsion, self.repulsion)?;
+        flock.set_factor(Factor::Adhesion, self.adhesion)?;
+        flock.set_factor(Factor::Cohesion, self.cohesion)?;
+        flock.set_radii(self.radii.0, self.radii.1, self.radii.2)?;
+        flock.set_speed_limits(self.speed_limits.0, self.speed_limits.1)?;
+        for rule in Rule::ALL {
+            flock.set_rule_enabled(rule, !self.disabled_rules.contains(&rule));
+        }
+        return Ok(());
+    }
+
+    /// a line of a profiles file, as described at the top of this module
+    pub fn parse(text: &str) -> Result<Profile> {
+        let fields: Vec<&str> = text.split(':').collect();
+        let [name, factors, radii, speed_limits, disabled_rules] = fields[..] else {
+            bail!("profile '{}' should be name:repulsion,adhesion,cohesion:crowding,local,alignment:min,max:rules off", text);
+        };
+        let parse_floats = |field: &str, count: usize| -> Result<Vec<f32>> {
+            let values = field.split(',').map(|value| value.trim().parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", value, text)))
+                .collect::<Result<Vec<f32>>>()?;
+            if values.len() != count {
+                bail!("'{}' in '{}' should be {} numbers", field, text, count);
+            }
+            return Ok(values);
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("profile '{}' has no name", text);
+        }
+        let factors = parse_floats(factors, 3)?;
+        let radii = parse_floats(radii, 3)?;
+        let speed_limits = parse_floats(speed_limits, 2)?;
+        let disabled_rules = disabled_rules.split(',').map(str::trim).filter(|rule| !rule.is_empty()).map(Rule::parse).collect::<Result<Vec<Rule>>>()?;
+        let profile = Profile {
+            name: name.to_string(),
+            repulsion: factors[0],
+            adhesion: factors[1],
+            cohesion: factors[2],
+            radii: (radii[0], radii[1], radii[2]),
+            speed_limits: (speed_limits[0], speed_limits[1]),
+            disabled_rules,
+        };
+        profile.validate().map_err(|error| anyhow!("profile '{}': {:?}", name, error.errors))?;
+        return Ok(profile);
+    }
+}
+
+/// a line of a profiles file
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let disabled_rules: Vec<&str> = self.disabled_rules.iter().map(Rule::name).collect();
+        return write!(f, "{}:{},{},{}:{},{},{}:{},{}:{}", self.name, self.repulsion, self.adhesion, self.cohesion,
+                      self.radii.0, self.radii.1, self.radii.2, self.speed_limits.0, self.speed_limits.1, disabled_rules.join(","));
+    }
+}
+
+/// the profiles in a file, in the order they were saved
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    pub fn parse(text: &str) -> Result<Profiles> {
+        let mut profiles = Profiles::default();
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            profiles.save(Profile::parse(line).with_context(|| format!("line {}", line_idx + 1))?);
+        }
+        return Ok(profiles);
+    }
+
+    /// no profiles if the file isn't there yet, e.g. before the first is saved
+    pub fn load(path: &Path) -> Result<Profiles> {
+        if !path.exists() {
+            return Ok(Profiles::default());
+        }
+        let text = fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+        return Profiles::parse(&text).with_context(|| format!("in {}", path.display()));
+    }
+
+    /// writes the profiles to `path`, making the directory it's in if need be
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("couldn't make {}", dir.display()))?;
+        }
+        return fs::write(path, self.to_string()).with_context(|| format!("couldn't write {}", path.display()));
+    }
+
+    /// adds the profile, replacing any with the same name where it was
+    pub fn save(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|other| other.name == profile.name) {
+            Some(other) => *other = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        return self.profiles.iter().find(|profile| profile.name == name);
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        return self.profiles.iter().map(|profile| profile.name.as_str());
+    }
+
+    /// "profile 1", or the first number after it that isn't taken, for saving without typing a name
+    pub fn unused_name(&self) -> String {
+        return (1..).map(|number| format!("profile {}", number)).find(|name| self.get(name).is_none()).unwrap_or_default();
+    }
+}
+
+/// the lines of a profiles file
+impl fmt::Display for Profiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for profile in &self.profiles {
+            writeln!(f, "{}", profile)?;
+        }
+        return Ok(());
+    }
+}
+
+/// `boids/profiles.txt` in the user's config directory, e.g. `~/.config/boids/profiles.txt` on Linux,
+/// if the platform has one
+pub fn default_path() -> Option<PathBuf> {
+    return dirs::config_dir().map(|dir| dir.join("boids").join("profiles.txt"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CreationError;
+
+    #[test]
+    fn test_profiles_parsed_and_written_back() {
+        let text = "# a comment\ntight:0.2,0.8,0.9:2,10,10:0,inf:\nno alignment:0.5,0.5,0.5:2,20,15:0.5,3:alignment, scripted\n";
+        let profiles = Profiles::parse(text).unwrap();
+        assert_eq!(profiles.names().collect::<Vec<&str>>(), vec!["tight", "no alignment"]);
+        assert_eq!(profiles.get("tight").unwrap().speed_limits, (0.0, f32::INFINITY));
+        assert_eq!(profiles.get("no alignment").unwrap().disabled_rules, vec![Rule::Alignment, Rule::Scripted]);
+        assert_eq!(Profiles::parse(&profiles.to_string()).unwrap(), profiles);
+        assert_eq!(profiles.unused_name(), "profile 1");
+
+        assert!(Profile::parse("bad:0.5,0.5:2,10,10:0,1:").is_err());
+        assert!(Profile::parse("bad:0.5,0.5,0.5:2,10,10:0,1:flying").is_err());
+        // the crowding radius is past the local radius
+        assert!(Profile::parse("bad:0.5,0.5,0.5:20,10,10:0,1:").is_err());
+    }
+
+    #[test]
+    fn test_profile_saved_and_applied() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.2, 0.3).unwrap();
+        flock.set_speed_limits(0.5, 4.0).unwrap();
+        flock.set_rule_enabled(Rule::Cohesion, false);
+        let mut profiles = Profiles::default();
+        profiles.save(Profile::from_flock("mine", &flock));
+
+        let path = std::env::temp_dir().join(format!("boids-profiles-test-{}", std::process::id())).join("profiles.txt");
+        profiles.write(&path).unwrap();
+        let loaded = Profiles::load(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(Profiles::load(&path).unwrap(), Profiles::default());
+
+        let mut other_flock = Flock::new(0, 2.0, 30.0, 0.9, 0.9, 0.9).unwrap();
+        loaded.get("mine").unwrap().apply(&mut other_flock).unwrap();
+        assert_eq!(Profile::from_flock("mine", &other_flock), Profile::from_flock("mine", &flock));
+
+        // an invalid profile leaves the flock as it was
+        let invalid = Profile { repulsion: 2.0, ..Profile::from_flock("invalid", &flock) };
+        assert_eq!(invalid.apply(&mut other_flock).unwrap_err().errors, vec![CreationError::FactorShouldBeLessThanOne("repulsion".to_string())]);
+        assert_eq!(other_flock.factor(Factor::Repulsion), 0.1);
+    }
+}
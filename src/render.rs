@@ -0,0 +1,227 @@
+//! Drawing a flock without tying the simulation to any one front end. [`draw_flock`] works out
+//! what there is to see and hands it, piece by piece, to a [`Renderer`], which decides how to
+//! draw it. The SVG export and the video recorder are both renderers, as are the Bevy adapter's
+//! `GizmoRenderer` with the `bevy` feature and the `TerminalRenderer` with the `terminal` feature,
+//! and [`HeadlessRenderer`] draws nothing at all, for running the same code with no display.
+
+use crate::arena::{Arena, ArenaShape};
+use crate::boid_state::BoidState;
+use crate::food::FoodSource;
+use crate::force_field::ForceSource;
+use crate::predator::Predator;
+use crate::walls::Wall;
+use crate::{Boid, Flock};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMode {
+    Plain,
+    ByCluster,
+    ByState, // what each boid is doing, if the flock has boid states
+}
+
+/// what a boid is coloured by
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoidColor {
+    Plain,
+    Cluster(usize), // the boid's cluster label
+    State(BoidState),
+}
+
+/// the smallest box around everything drawn in a frame, in world units
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+/// Each frame is drawn back to front: everything else in the world, then the boids on top.
+/// A renderer that has no use for some part of the world can leave its method as the default,
+/// which draws nothing
+pub trait Renderer {
+    /// `bounds` is None if there's nothing to draw
+    fn begin_frame(&mut self, bounds: Option<Bounds>);
+
+    fn draw_force_source(&mut self, _source: &ForceSource) {}
+
+    fn draw_walls(&mut self, _shape: &ArenaShape) {}
+
+    fn draw_path(&mut self, _points: &[(f32, f32)]) {}
+
+    /// a free-standing wall, from one end to the other
+    fn draw_wall(&mut self, _start: (f32, f32), _end: (f32, f32)) {}
+
+    /// drawn where the obstacle is now
+    fn draw_obstacle(&mut self, _centre: (f32, f32), _radius: f32) {}
+
+    /// only food that's there to be eaten is drawn
+    fn draw_food(&mut self, _food: &FoodSource) {}
+
+    fn draw_boid(&mut self, boid: &Boid, color: BoidColor);
+
+    /// drawn over the boids, coloured by `Predator::hunt_state`
+    fn draw_predator(&mut self, _predator: &Predator) {}
+
+    fn end_frame(&mut self);
+}
+
+/// draws nothing, for running the drawing code without a display
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeadlessRenderer;
+
+impl Renderer for HeadlessRenderer {
+    fn begin_frame(&mut self, _bounds: Option<Bounds>) {}
+
+    fn draw_boid(&mut self, _boid: &Boid, _color: BoidColor) {}
+
+    fn end_frame(&mut self) {}
+}
+
+pub fn draw_flock(flock: &Flock, color_mode: ColorMode, renderer: &mut impl Renderer) {
+    let force_sources: &[ForceSource] = flock.force_field()
+        .map(|force_field| force_field.sources.as_slice())
+        .unwrap_or_default();
+    let walls = flock.arena().map(Arena::shape);
+    let path_points: &[(f32, f32)] = flock.path_following()
+        .map(|path_following| path_following.path.points())
+        .unwrap_or_default();
+    let available_food = flock.foraging()
+        .map(|foraging| foraging.food_sources.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter(|food| food.is_available());
+    let free_standing_walls: &[Wall] = flock.walls().map(|walls| walls.walls.as_slice()).unwrap_or_default();
+    let obstacles: Vec<((f32, f32), f32)> = flock.obstacles()
+        .map(|obstacles| obstacles.positions().zip(obstacles.obstacles.iter().map(|obstacle| obstacle.radius)).collect())
+        .unwrap_or_default();
+
+    // a circle's extent is marked by the corners of the square around it
+    let wall_extent: Vec<(f32, f32)> = match walls {
+        Some(ArenaShape::Polygon(vertices)) => vertices.clone(),
+        Some(ArenaShape::Circle { centre, radius }) => vec![(centre.0 - radius, centre.1 - radius), (centre.0 + radius, centre.1 + radius)],
+        None => Vec::new(),
+    };
+    let positions = flock.boids()
+        .iter()
+        .map(|boid| (boid.x_pos, boid.y_pos))
+        .chain(available_food.clone().map(|food| (food.x_pos, food.y_pos)))
+        .chain(path_points.iter().copied())
+        .chain(flock.predator().map(|predator| (predator.boid.x_pos, predator.boid.y_pos)))
+        .chain(wall_extent)
+        .chain(free_standing_walls.iter().flat_map(|wall| [wall.start, wall.end]))
+        .chain(obstacles.iter().flat_map(|(centre, radius)| [(centre.0 - radius, centre.1 - radius), (centre.0 + radius, centre.1 + radius)]));
+    renderer.begin_frame(bounds(positions));
+
+    for source in force_sources {
+        renderer.draw_force_source(source);
+    }
+    if let Some(shape) = walls {
+        renderer.draw_walls(shape);
+    }
+    for wall in free_standing_walls {
+        renderer.draw_wall(wall.start, wall.end);
+    }
+    if !path_points.is_empty() {
+        renderer.draw_path(path_points);
+    }
+    for (centre, radius) in obstacles {
+        renderer.draw_obstacle(centre, radius);
+    }
+    for food in available_food {
+        renderer.draw_food(food);
+    }
+    let cluster_labels = match color_mode {
+        ColorMode::ByCluster => flock.cluster_labels(),
+        _ => Vec::new(),
+    };
+    for (boid_idx, boid) in flock.boids().iter().enumerate() {
+        // boids that haven't been through a step yet have no state or cluster
+        let color = match color_mode {
+            ColorMode::Plain => None,
+            ColorMode::ByCluster => cluster_labels.get(boid_idx).map(|cluster_label| BoidColor::Cluster(*cluster_label)),
+            ColorMode::ByState => flock.boid_state(boid.id).map(BoidColor::State),
+        };
+        renderer.draw_boid(boid, color.unwrap_or(BoidColor::Plain));
+    }
+    if let Some(predator) = flock.predator() {
+        renderer.draw_predator(predator);
+    }
+    renderer.end_frame();
+}
+
+fn bounds(positions: impl Iterator<Item = (f32, f32)>) -> Option<Bounds> {
+    return positions.fold(None, |bounds, (x, y)| match bounds {
+        None => Some(Bounds { min: (x, y), max: (x, y) }),
+        Some(Bounds { min, max }) => Some(Bounds {
+            min: (min.0.min(x), min.1.min(y)),
+            max: (max.0.max(x), max.1.max(y)),
+        }),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::Foraging;
+    use crate::obstacle::{Obstacle, Obstacles};
+
+    // writes down what it's asked to draw
+    #[derive(Default)]
+    struct RecordingRenderer {
+        calls: Vec<String>,
+    }
+
+    impl Renderer for RecordingRenderer {
+        fn begin_frame(&mut self, bounds: Option<Bounds>) {
+            self.calls.push(format!("begin {:?}", bounds.map(|bounds| (bounds.min, bounds.max))));
+        }
+
+        fn draw_obstacle(&mut self, centre: (f32, f32), radius: f32) {
+            self.calls.push(format!("obstacle {},{} {}", centre.0, centre.1, radius));
+        }
+
+        fn draw_food(&mut self, food: &FoodSource) {
+            self.calls.push(format!("food {},{}", food.x_pos, food.y_pos));
+        }
+
+        fn draw_boid(&mut self, boid: &Boid, color: BoidColor) {
+            self.calls.push(format!("boid {},{} {:?}", boid.x_pos, boid.y_pos, color));
+        }
+
+        fn end_frame(&mut self) {
+            self.calls.push("end".to_string());
+        }
+    }
+
+    #[test]
+    fn test_world_drawn_then_boids() {
+        let mut flock = Flock::new(0, 1.0, 5.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, -1.0, 0.0, 0.0), Boid::new(20.0, 0.0, 0.0, 0.0)];
+        flock.set_foraging(Foraging::new(vec![FoodSource::new(-4.0, 8.0)], 0.1, 2.0, 1.0, 100).unwrap());
+        flock.set_obstacles(Obstacles::new(vec![Obstacle::parse("still:10,10:2").unwrap()]));
+
+        let mut renderer = RecordingRenderer::default();
+        draw_flock(&flock, ColorMode::ByCluster, &mut renderer);
+        assert_eq!(renderer.calls, vec![
+            "begin Some(((-4.0, -1.0), (20.0, 12.0)))",
+            "obstacle 10,10 2",
+            "food -4,8",
+            "boid 0,-1 Cluster(0)",
+            "boid 20,0 Cluster(1)",
+            "end",
+        ]);
+
+        let mut renderer = RecordingRenderer::default();
+        draw_flock(&flock, ColorMode::Plain, &mut renderer);
+        assert_eq!(renderer.calls[3], "boid 0,-1 Plain");
+    }
+
+    #[test]
+    fn test_nothing_to_draw_has_no_bounds() {
+        let flock = Flock::new(0, 1.0, 5.0, 0.0, 0.0, 0.0).unwrap();
+        let mut renderer = RecordingRenderer::default();
+        draw_flock(&flock, ColorMode::Plain, &mut renderer);
+        assert_eq!(renderer.calls, vec!["begin None", "end"]);
+
+        draw_flock(&flock, ColorMode::Plain, &mut HeadlessRenderer);
+    }
+}
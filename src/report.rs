@@ -0,0 +1,239 @@
+//! A self-documenting report of a sweep: the command it was run with, the main settings, summary
+//! statistics across the runs and of each run, and plots of every run's polarization and number
+//! of clusters over time.
+//!
+//! A report written to a `.html` file is a single page with the plots drawn inline. Anything else
+//! is written as Markdown, with the plots saved as SVG images next to it, e.g. `report.md` comes
+//! with `report-polarization.svg` and `report-clusters.svg`.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::sweep::{RunSummary, SweepConfig};
+use crate::theme::{hex, LIGHT};
+
+const CHART_WIDTH: f32 = 640.0;
+const CHART_HEIGHT: f32 = 320.0;
+// room for the axis labels, and for the legend on the right
+const CHART_MARGIN: f32 = 40.0;
+const LEGEND_WIDTH: f32 = 80.0;
+
+struct Report {
+    command: String,
+    settings: Vec<(&'static str, String)>,
+    overall: Vec<(&'static str, String)>,
+    run_headers: [&'static str; 9],
+    run_rows: Vec<[String; 9]>,
+    polarization_chart: String,
+    cluster_chart: String,
+}
+
+/// `command` is how the sweep was run, e.g. `boids sweep --repulsion 0:1:5`
+pub fn write_report(command: &str, config: &SweepConfig, summaries: &[RunSummary], path: &Path) -> io::Result<()> {
+    let report = build_report(command, config, summaries);
+    let is_html = path.extension().is_some_and(|extension| extension == "html" || extension == "htm");
+    if is_html {
+        return fs::write(path, html(&report));
+    }
+    let polarization_path = plot_path(path, "polarization");
+    let cluster_path = plot_path(path, "clusters");
+    fs::write(&polarization_path, &report.polarization_chart)?;
+    fs::write(&cluster_path, &report.cluster_chart)?;
+    return fs::write(path, markdown(&report, &polarization_path, &cluster_path));
+}
+
+fn build_report(command: &str, config: &SweepConfig, summaries: &[RunSummary]) -> Report {
+    let settings = vec![
+        ("flock size", config.flock_size.to_string()),
+        ("ticks per run", config.num_ticks.to_string()),
+        ("seed", config.seed.to_string()),
+        ("runs", summaries.len().to_string()),
+        ("invalid combinations skipped", (config.runs().len() - summaries.len()).to_string()),
+    ];
+
+    let final_polarizations: Vec<f32> = summaries.iter().map(|summary| summary.final_polarization).collect();
+    let mean = |values: &[f32]| if values.is_empty() { 0.0 } else { values.iter().sum::<f32>() / values.len() as f32 };
+    let overall = vec![
+        ("mean final polarization", format!("{:.3}", mean(&final_polarizations))),
+        ("lowest final polarization", format!("{:.3}", final_polarizations.iter().copied().fold(f32::INFINITY, f32::min))),
+        ("highest final polarization", format!("{:.3}", final_polarizations.iter().copied().fold(f32::NEG_INFINITY, f32::max))),
+        ("mean total collisions", format!("{:.1}", mean(&summaries.iter().map(|summary| summary.total_collisions as f32).collect::<Vec<f32>>()))),
+        ("mean final group count", format!("{:.1}", mean(&summaries.iter().map(|summary| summary.final_group_count as f32).collect::<Vec<f32>>()))),
+    ];
+
+    let run_rows = summaries.iter().enumerate().map(|(run, summary)| {
+        let params = summary.params;
+        return [
+            run.to_string(), params.repulsion.to_string(), params.adhesion.to_string(), params.cohesion.to_string(),
+            params.crowding_radius.to_string(), params.local_radius.to_string(), format!("{:.3}", summary.final_polarization),
+            summary.total_collisions.to_string(), summary.final_group_count.to_string(),
+        ];
+    }).collect();
+
+    let polarization_series: Vec<Vec<f32>> = summaries.iter().map(|summary| summary.polarization_series.clone()).collect();
+    let cluster_series: Vec<Vec<f32>> = summaries.iter()
+        .map(|summary| summary.cluster_counts.iter().map(|count| *count as f32).collect())
+        .collect();
+    let most_clusters = cluster_series.iter().flatten().copied().fold(1.0, f32::max);
+    return Report {
+        command: command.to_string(),
+        settings,
+        overall,
+        run_headers: ["run", "repulsion", "adhesion", "cohesion", "crowding radius", "local radius", "final polarization", "total collisions", "final group count"],
+        run_rows,
+        polarization_chart: line_chart(&polarization_series, 1.0),
+        cluster_chart: line_chart(&cluster_series, most_clusters),
+    };
+}
+
+/// e.g. report.md and "clusters" give report-clusters.svg
+fn plot_path(report_path: &Path, plot_name: &str) -> PathBuf {
+    let stem = report_path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    return report_path.with_file_name(format!("{}-{}.svg", stem, plot_name));
+}
+
+/// a line for each run, from the first tick to the last, with the y axis running from 0 to `y_max`.
+/// Runs are coloured as clusters are, and only labelled while the colours don't repeat
+fn line_chart(series: &[Vec<f32>], y_max: f32) -> String {
+    let num_ticks = series.iter().map(Vec::len).max().unwrap_or(0);
+    let plot_width = CHART_WIDTH - 2.0 * CHART_MARGIN - LEGEND_WIDTH;
+    let plot_height = CHART_HEIGHT - 2.0 * CHART_MARGIN;
+    let to_x = |tick: usize| CHART_MARGIN + plot_width * tick as f32 / (num_ticks.max(2) - 1) as f32;
+    let to_y = |value: f32| CHART_MARGIN + plot_height * (1.0 - value / y_max);
+
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+                     CHART_WIDTH, CHART_HEIGHT, CHART_WIDTH, CHART_HEIGHT);
+    let _ = writeln!(svg, r#"<rect width="{}" height="{}" fill="{}"/>"#, CHART_WIDTH, CHART_HEIGHT, hex(LIGHT.background));
+    let (left, right, top, bottom) = (CHART_MARGIN, CHART_MARGIN + plot_width, CHART_MARGIN, CHART_MARGIN + plot_height);
+    let _ = writeln!(svg, r#"<polyline points="{},{} {},{} {},{}" fill="none" stroke="{}"/>"#, left, top, left, bottom, right, bottom, hex(LIGHT.boid));
+    let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#, left - 4.0, top + 4.0, y_max);
+    let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="end">0</text>"#, left - 4.0, bottom + 4.0);
+    let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="middle">tick 1</text>"#, left, bottom + 16.0);
+    let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="middle">tick {}</text>"#, right, bottom + 16.0, num_ticks);
+    for (run, values) in series.iter().enumerate() {
+        let points: Vec<String> = values.iter().enumerate().map(|(tick, value)| format!("{},{}", to_x(tick), to_y(*value))).collect();
+        let _ = writeln!(svg, r#"<polyline points="{}" fill="none" stroke="{}"/>"#, points.join(" "), hex(LIGHT.cluster_color(run)));
+    }
+    if series.len() <= LIGHT.cluster_palette.len() {
+        for run in 0..series.len() {
+            let _ = writeln!(svg, r#"<text x="{}" y="{}" fill="{}">run {}</text>"#, right + 12.0, top + 16.0 * run as f32, hex(LIGHT.cluster_color(run)), run);
+        }
+    }
+    svg.push_str("</svg>\n");
+    return svg;
+}
+
+fn html(report: &Report) -> String {
+    let table = |headers: &[&str], rows: &[Vec<String>]| {
+        let mut table = String::from("<table>\n<tr>");
+        for header in headers {
+            let _ = write!(table, "<th>{}</th>", escape_html(header));
+        }
+        table.push_str("</tr>\n");
+        for row in rows {
+            table.push_str("<tr>");
+            for cell in row {
+                let _ = write!(table, "<td>{}</td>", escape_html(cell));
+            }
+            table.push_str("</tr>\n");
+        }
+        table.push_str("</table>\n");
+        return table;
+    };
+    let pairs = |pairs: &[(&str, String)]| pairs.iter().map(|(name, value)| vec![name.to_string(), value.clone()]).collect::<Vec<_>>();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Sweep report</title>\n");
+    html.push_str("<style>body { font-family: sans-serif; } table { border-collapse: collapse; } th, td { border: 1px solid #ccc; padding: 2px 8px; }</style>\n");
+    html.push_str("</head>\n<body>\n<h1>Sweep report</h1>\n<h2>Configuration</h2>\n");
+    let _ = writeln!(html, "<pre>{}</pre>", escape_html(&report.command));
+    html.push_str(&table(&["setting", "value"], &pairs(&report.settings)));
+    html.push_str("<h2>Summary</h2>\n");
+    html.push_str(&table(&["statistic", "value"], &pairs(&report.overall)));
+    html.push_str("<h2>Runs</h2>\n");
+    html.push_str(&table(&report.run_headers, &report.run_rows.iter().map(|row| row.to_vec()).collect::<Vec<_>>()));
+    html.push_str("<h2>Polarization over time</h2>\n");
+    html.push_str(&report.polarization_chart);
+    html.push_str("<h2>Clusters over time</h2>\n");
+    html.push_str(&report.cluster_chart);
+    html.push_str("</body>\n</html>\n");
+    return html;
+}
+
+fn markdown(report: &Report, polarization_path: &Path, cluster_path: &Path) -> String {
+    let table = |headers: &[&str], rows: &[Vec<String>]| {
+        let mut table = format!("| {} |\n|{}\n", headers.join(" | "), " --- |".repeat(headers.len()));
+        for row in rows {
+            let _ = writeln!(table, "| {} |", row.join(" | "));
+        }
+        return table;
+    };
+    let pairs = |pairs: &[(&str, String)]| pairs.iter().map(|(name, value)| vec![name.to_string(), value.clone()]).collect::<Vec<_>>();
+    // the plots sit next to the report, so are linked by their file names alone
+    let file_name = |path: &Path| path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let mut markdown = String::from("# Sweep report\n\n## Configuration\n\n");
+    let _ = writeln!(markdown, "```\n{}\n```\n", report.command);
+    markdown.push_str(&table(&["setting", "value"], &pairs(&report.settings)));
+    markdown.push_str("\n## Summary\n\n");
+    markdown.push_str(&table(&["statistic", "value"], &pairs(&report.overall)));
+    markdown.push_str("\n## Runs\n\n");
+    markdown.push_str(&table(&report.run_headers, &report.run_rows.iter().map(|row| row.to_vec()).collect::<Vec<_>>()));
+    let _ = writeln!(markdown, "\n## Polarization over time\n\n![Polarization over time]({})", file_name(polarization_path));
+    let _ = writeln!(markdown, "\n## Clusters over time\n\n![Clusters over time]({})", file_name(cluster_path));
+    return markdown;
+}
+
+fn escape_html(text: &str) -> String {
+    return text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sweep::run_sweep;
+    use std::env;
+
+    fn sweep() -> (SweepConfig, Vec<RunSummary>) {
+        let args: Vec<String> = "--repulsion 0:0.5:2 --flock-size 10 --ticks 5".split_whitespace().map(str::to_string).collect();
+        let config = SweepConfig::from_args(&args).unwrap();
+        let summaries = run_sweep(&config);
+        return (config, summaries);
+    }
+
+    #[test]
+    fn test_html_report() {
+        let (config, summaries) = sweep();
+        let html = html(&build_report("boids sweep --repulsion 0:0.5:2 <flags>", &config, &summaries));
+        assert!(html.contains("<pre>boids sweep --repulsion 0:0.5:2 &lt;flags&gt;</pre>"));
+        assert!(html.contains("<td>ticks per run</td><td>5</td>"));
+        // one line per run on each plot
+        assert_eq!(html.matches("<svg").count(), 2);
+        assert_eq!(html.matches(&format!(r#"fill="none" stroke="{}""#, hex(LIGHT.cluster_color(1)))).count(), 2);
+    }
+
+    #[test]
+    fn test_markdown_report_written_with_plots() {
+        let (config, summaries) = sweep();
+        let dir = env::temp_dir().join(format!("boids-report-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        write_report("boids sweep", &config, &summaries, &dir.join("report.md")).unwrap();
+
+        let markdown = fs::read_to_string(dir.join("report.md")).unwrap();
+        assert!(markdown.contains("| ticks per run | 5 |"));
+        assert!(markdown.contains("| run | repulsion |"));
+        assert!(markdown.contains("![Polarization over time](report-polarization.svg)"));
+        assert!(fs::read_to_string(dir.join("report-clusters.svg")).unwrap().starts_with("<svg"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_chart_scaled_to_fit() {
+        let chart = line_chart(&[vec![0.0, 2.0]], 2.0);
+        // the first tick at the bottom left of the plot, the last at the top right
+        let plot_right = CHART_WIDTH - CHART_MARGIN - LEGEND_WIDTH;
+        assert!(chart.contains(&format!(r#"points="{},{} {},{}""#, CHART_MARGIN, CHART_HEIGHT - CHART_MARGIN, plot_right, CHART_MARGIN)));
+    }
+}
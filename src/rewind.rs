@@ -0,0 +1,144 @@
+//! Going back in time. A [`RewindBuffer`] keeps the flock's state from before each of its most
+//! recent steps, as many as fit in a memory budget, so a run can be wound back step by step to
+//! just before something interesting happened. Stepping again from there carries on from that
+//! point, and the steps that were rewound over are forgotten.
+
+use std::collections::VecDeque;
+use std::mem::size_of;
+use crate::boid_state::BoidCondition;
+use crate::food::FoodSource;
+use crate::{Boid, Flock};
+
+/// everything that changes as the flock steps, as taken by `Flock::save_state`
+#[derive(Clone, Debug)]
+pub struct FlockState {
+    pub(crate) boids: Vec<Boid>,
+    pub(crate) cluster_labels: Vec<usize>,
+    pub(crate) food_sources: Vec<FoodSource>, // how long eaten food has left to regrow
+    pub(crate) next_waypoints: Vec<usize>, // of each leader
+    pub(crate) boid_conditions: Vec<BoidCondition>, // each boid's state and energy
+}
+
+impl FlockState {
+    /// roughly how much memory the state takes up
+    pub fn size_in_bytes(&self) -> usize {
+        return size_of::<FlockState>()
+            + self.boids.len() * size_of::<Boid>()
+            + self.cluster_labels.len() * size_of::<usize>()
+            + self.food_sources.len() * size_of::<FoodSource>()
+            + self.next_waypoints.len() * size_of::<usize>()
+            + self.boid_conditions.len() * size_of::<BoidCondition>();
+    }
+}
+
+#[derive(Debug)]
+pub struct RewindBuffer {
+    states: VecDeque<FlockState>, // oldest first
+    memory_budget: usize, // in bytes; the oldest states are dropped to stay within it
+    memory_used: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(memory_budget: usize) -> RewindBuffer {
+        return RewindBuffer { states: VecDeque::new(), memory_budget, memory_used: 0 };
+    }
+
+    /// saves the flock as it is now; call before each step to be able to rewind over it
+    pub fn record(&mut self, flock: &Flock) {
+        let state = flock.save_state();
+        self.memory_used += state.size_in_bytes();
+        self.states.push_back(state);
+        while self.memory_used > self.memory_budget {
+            let Some(oldest) = self.states.pop_front() else {
+                break;
+            };
+            self.memory_used -= oldest.size_in_bytes();
+        }
+    }
+
+    /// puts the flock back as it was before its latest recorded step. False if there's nothing
+    /// left to rewind to, in which case the flock is left as it is
+    pub fn rewind(&mut self, flock: &mut Flock) -> bool {
+        let Some(state) = self.states.pop_back() else {
+            return false;
+        };
+        self.memory_used -= state.size_in_bytes();
+        flock.restore_state(&state);
+        return true;
+    }
+
+    /// how many steps can be rewound
+    pub fn len(&self) -> usize {
+        return self.states.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.states.is_empty();
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.memory_used = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::Foraging;
+
+    fn positions(flock: &Flock) -> Vec<(f32, f32)> {
+        return flock.boids().iter().map(|boid| (boid.x_pos, boid.y_pos)).collect();
+    }
+
+    #[test]
+    fn test_rewind_then_resume() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.boids = vec![Boid::new(-2.0, 0.0, 1.0, 0.0), Boid::new(2.0, 0.0, -1.0, 0.0)];
+        let start = positions(&flock);
+        let mut rewind_buffer = RewindBuffer::new(usize::MAX);
+        let mut after_each_step = Vec::new();
+        for _ in 0..3 {
+            rewind_buffer.record(&flock);
+            flock.step();
+            after_each_step.push(positions(&flock));
+        }
+
+        assert!(rewind_buffer.rewind(&mut flock));
+        assert_eq!(positions(&flock), after_each_step[1]);
+        assert!(rewind_buffer.rewind(&mut flock) && rewind_buffer.rewind(&mut flock));
+        assert_eq!(positions(&flock), start);
+        assert!(!rewind_buffer.rewind(&mut flock));
+
+        // stepping on from the start goes the same way as before
+        rewind_buffer.record(&flock);
+        flock.step();
+        assert_eq!(positions(&flock), after_each_step[0]);
+        assert_eq!(rewind_buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_oldest_states_dropped_to_fit_budget() {
+        let flock = Flock::new(10, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        let state_size = flock.save_state().size_in_bytes();
+        let mut rewind_buffer = RewindBuffer::new(3 * state_size);
+        for _ in 0..5 {
+            rewind_buffer.record(&flock);
+        }
+        assert_eq!(rewind_buffer.len(), 3);
+    }
+
+    #[test]
+    fn test_eaten_food_restored() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0)];
+        flock.set_foraging(Foraging::new(vec![FoodSource::new(0.5, 0.0)], 0.1, 10.0, 1.0, 100).unwrap());
+        let mut rewind_buffer = RewindBuffer::new(usize::MAX);
+        rewind_buffer.record(&flock);
+        flock.step();
+        assert!(!flock.foraging().unwrap().food_sources[0].is_available());
+
+        rewind_buffer.rewind(&mut flock);
+        assert!(flock.foraging().unwrap().food_sources[0].is_available());
+    }
+}
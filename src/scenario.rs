@@ -0,0 +1,259 @@
+//! Scenarios: scripted changes to a flock over a run, so a demonstration or an experiment can play
+//! out unattended. A scenario file has one keyframe or event per line, each starting with the tick
+//! it happens at, counted in steps from the start of the run. Blank lines and anything after a `#`
+//! are ignored.
+//!
+//! ```text
+//! # cohesion ramps from 0.1 to 0.9 over 5000 ticks
+//! 0 cohesion 0.1
+//! 5000 cohesion 0.9
+//! # a predator appears at the origin at tick 2000, heading right
+//! 2000 predator 0,0 1,0
+//! 3000 disable alignment
+//! 4000 enable alignment
+//! ```
+//!
+//! A parameter's keyframes (`repulsion`, `adhesion`, `cohesion` or `separation-falloff`) are
+//! blended between, and it holds at its last keyframe's value from then on. Before its first
+//! keyframe it's left as the flock was set up. `predator x,y x_vel,y_vel` replaces the flock's
+//! predator with one that flies straight on at that velocity, and `enable` and `disable` switch a
+//! rule, by its `Rule::name`, on and off.
+
+use std::fs;
+use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use crate::predator::Predator;
+use crate::{check_finite, check_float_between_zero_and_one, Boid, CreationError, Factor, Flock, InvalidFlockConfig, Rule};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Parameter {
+    Factor(Factor),
+    SeparationFalloff, // the flock's separation falloff exponent
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 4] = [Parameter::Factor(Factor::Repulsion), Parameter::Factor(Factor::Adhesion), Parameter::Factor(Factor::Cohesion), Parameter::SeparationFalloff];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Parameter::Factor(factor) => factor.name(),
+            Parameter::SeparationFalloff => "separation-falloff",
+        };
+    }
+
+    fn validate(&self, value: f32) -> Option<CreationError> {
+        return match self {
+            Parameter::Factor(factor) => check_float_between_zero_and_one(value, factor.name().to_string()),
+            Parameter::SeparationFalloff => check_finite(value, "separation falloff exponent")
+                .or_else(|| (value < 0.0).then_some(CreationError::FalloffExponentIsNegative)),
+        };
+    }
+
+    fn set(&self, flock: &mut Flock, value: f32) {
+        // every value between two valid keyframes is valid too, so neither of these can fail
+        let _ = match self {
+            Parameter::Factor(factor) => flock.set_factor(*factor, value),
+            Parameter::SeparationFalloff => flock.set_separation_falloff_exponent(value),
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScenarioEvent {
+    SpawnPredator { position: (f32, f32), velocity: (f32, f32) },
+    SetRuleEnabled(Rule, bool),
+}
+
+impl ScenarioEvent {
+    fn validate(&self) -> Vec<CreationError> {
+        return match self {
+            ScenarioEvent::SpawnPredator { position, velocity } => {
+                let errors: Vec<CreationError> = [
+                    check_finite(position.0, "predator position"),
+                    check_finite(position.1, "predator position"),
+                    check_finite(velocity.0, "predator velocity"),
+                    check_finite(velocity.1, "predator velocity"),
+                ].into_iter().flatten().collect();
+                if !errors.is_empty() {
+                    return errors;
+                }
+                // finite numbers can still make a predator too fast to be valid
+                spawned_predator(*position, *velocity).err().map_or_else(Vec::new, |invalid| invalid.errors)
+            }
+            ScenarioEvent::SetRuleEnabled(..) => Vec::new(),
+        };
+    }
+
+    fn happen(&self, flock: &mut Flock) {
+        match *self {
+            ScenarioEvent::SpawnPredator { position, velocity } => {
+                // always valid, as the predator was made once already when the scenario was
+                if let Ok(predator) = spawned_predator(position, velocity) {
+                    flock.set_predator(predator);
+                }
+            }
+            ScenarioEvent::SetRuleEnabled(rule, enabled) => flock.set_rule_enabled(rule, enabled),
+        }
+    }
+}
+
+fn spawned_predator(position: (f32, f32), velocity: (f32, f32)) -> Result<Predator, InvalidFlockConfig> {
+    let boid = Boid::new(position.0, position.1, velocity.0, velocity.1);
+    let top_speed = boid.speed().max(2.0);
+    return Predator::new(boid, 0.5, 0.1, top_speed, 1.0, 10.0, 0.5);
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scenario {
+    tracks: Vec<(Parameter, Vec<(u32, f32)>)>, // each parameter's keyframes, in order of their ticks
+    events: Vec<(u32, ScenarioEvent)>, // in order of their ticks
+}
+
+impl Scenario {
+    pub fn new(keyframes: Vec<(u32, Parameter, f32)>, mut events: Vec<(u32, ScenarioEvent)>) -> Result<Scenario, InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = keyframes.iter().filter_map(|(_, parameter, value)| parameter.validate(*value)).collect();
+        errors.extend(events.iter().flat_map(|(_, event)| event.validate()));
+        let mut tracks: Vec<(Parameter, Vec<(u32, f32)>)> = Vec::new();
+        for (tick, parameter, value) in keyframes {
+            match tracks.iter_mut().find(|(track_parameter, _)| *track_parameter == parameter) {
+                Some((_, track)) => track.push((tick, value)),
+                None => tracks.push((parameter, vec![(tick, value)])),
+            }
+        }
+        for (parameter, track) in &mut tracks {
+            track.sort_by_key(|(tick, _)| *tick);
+            if track.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+                errors.push(CreationError::ScenarioHasTwoKeyframesAtOnce(parameter.name().to_string()));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        // stable, so events at the same tick happen in the order they were given
+        events.sort_by_key(|(tick, _)| *tick);
+        return Ok(Scenario { tracks, events });
+    }
+
+    /// the lines of a scenario file, as described at the top of this module
+    pub fn parse(text: &str) -> Result<Scenario> {
+        let mut keyframes = Vec::new();
+        let mut events = Vec::new();
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some((tick, instruction)) = words.split_first() else {
+                continue;
+            };
+            let line_number = line_idx + 1;
+            let tick = tick.parse::<u32>().with_context(|| format!("line {}: '{}' is not a tick", line_number, tick))?;
+            let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("line {}: '{}' is not a number", line_number, part));
+            let parse_pair = |part: &str| -> Result<(f32, f32)> {
+                let (x, y) = part.split_once(',').ok_or_else(|| anyhow!("line {}: '{}' should be x,y", line_number, part))?;
+                return Ok((parse_float(x)?, parse_float(y)?));
+            };
+            match instruction {
+                ["predator", position, velocity] => events.push((tick, ScenarioEvent::SpawnPredator { position: parse_pair(position)?, velocity: parse_pair(velocity)? })),
+                ["enable", rule] => events.push((tick, ScenarioEvent::SetRuleEnabled(Rule::parse(rule)?, true))),
+                ["disable", rule] => events.push((tick, ScenarioEvent::SetRuleEnabled(Rule::parse(rule)?, false))),
+                [name, value] => {
+                    let parameter = Parameter::ALL
+                        .into_iter()
+                        .find(|parameter| parameter.name() == *name)
+                        .ok_or_else(|| anyhow!("line {}: unknown parameter '{}'; expected one of {}", line_number, name, Parameter::ALL.map(|parameter| parameter.name()).join(", ")))?;
+                    keyframes.push((tick, parameter, parse_float(value)?));
+                }
+                _ => bail!("line {}: '{}' should be a tick followed by a parameter and value, predator x,y x_vel,y_vel, or enable or disable and a rule", line_number, line.trim()),
+            }
+        }
+        return Ok(Scenario::new(keyframes, events)?);
+    }
+
+    pub fn load(path: &Path) -> Result<Scenario> {
+        let text = fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+        return Scenario::parse(&text).with_context(|| format!("in {}", path.display()));
+    }
+
+    /// the value of `parameter` at `tick`, or None if it hasn't reached a keyframe yet
+    pub fn value_at(&self, parameter: Parameter, tick: u32) -> Option<f32> {
+        let (_, track) = self.tracks.iter().find(|(track_parameter, _)| *track_parameter == parameter)?;
+        let next_keyframe = track.partition_point(|(keyframe_tick, _)| *keyframe_tick <= tick);
+        let (from_tick, from_value) = *track.get(next_keyframe.checked_sub(1)?)?;
+        let Some((to_tick, to_value)) = track.get(next_keyframe) else {
+            return Some(from_value);
+        };
+        return Some(from_value + (to_value - from_value) * (tick - from_tick) as f32 / (to_tick - from_tick) as f32);
+    }
+
+    /// sets the flock up for its next step, given that it has taken `tick` steps so far; call before every step
+    pub fn apply(&self, tick: u32, flock: &mut Flock) {
+        for (parameter, _) in &self.tracks {
+            if let Some(value) = self.value_at(*parameter, tick) {
+                parameter.set(flock, value);
+            }
+        }
+        let first_event = self.events.partition_point(|(event_tick, _)| *event_tick < tick);
+        for (_, event) in self.events[first_event..].iter().take_while(|(event_tick, _)| *event_tick == tick) {
+            event.happen(flock);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAMP: &str = "
+        # cohesion ramps up while a predator appears
+        100 cohesion 0.25
+        200 cohesion 0.75
+        150 predator 5,5 1,0  # heading right
+        180 disable alignment
+    ";
+
+    #[test]
+    fn test_parameters_blend_between_keyframes() {
+        let scenario = Scenario::parse(RAMP).unwrap();
+        let cohesion = Parameter::Factor(Factor::Cohesion);
+        assert_eq!(scenario.value_at(cohesion, 50), None);
+        assert_eq!(scenario.value_at(cohesion, 150), Some(0.5));
+        assert_eq!(scenario.value_at(cohesion, 1000), Some(0.75));
+        assert_eq!(scenario.value_at(Parameter::Factor(Factor::Repulsion), 150), None);
+    }
+
+    #[test]
+    fn test_scenario_applied_to_flock() {
+        let scenario = Scenario::parse(RAMP).unwrap();
+        let mut flock = Flock::new(10, 5.0, 20.0, 0.1, 0.1, 0.3).unwrap();
+        for tick in 0..150 {
+            scenario.apply(tick, &mut flock);
+            flock.step();
+        }
+        assert!(flock.predator().is_none());
+        assert_eq!(Some(flock.factor(Factor::Cohesion)), scenario.value_at(Parameter::Factor(Factor::Cohesion), 149));
+        assert!(flock.factor(Factor::Cohesion) > 0.4);
+
+        scenario.apply(150, &mut flock);
+        assert_eq!(flock.predator().unwrap().boid.x_pos, 5.0);
+        for tick in 151..=180 {
+            scenario.apply(tick, &mut flock);
+        }
+        assert!(!flock.is_rule_enabled(Rule::Alignment));
+    }
+
+    #[test]
+    fn test_invalid_scenarios_rejected() {
+        let keyframes = vec![(0, Parameter::Factor(Factor::Cohesion), 2.0), (5, Parameter::SeparationFalloff, 1.0), (5, Parameter::SeparationFalloff, 2.0)];
+        let errors = Scenario::new(keyframes, Vec::new()).unwrap_err().errors;
+        assert_eq!(errors, vec![
+            CreationError::FactorShouldBeLessThanOne("cohesion".to_string()),
+            CreationError::ScenarioHasTwoKeyframesAtOnce("separation-falloff".to_string()),
+        ]);
+        assert!(Scenario::parse("10 gravity 1").is_err());
+        assert!(Scenario::parse("10 disable gravity").is_err());
+        assert!(Scenario::parse("ten cohesion 0.5").is_err());
+        assert!(Scenario::parse("10 predator 0,0 inf,0").is_err());
+        // finite, but too fast for its speed to be a number
+        let too_fast = vec![(0, ScenarioEvent::SpawnPredator { position: (0.0, 0.0), velocity: (3e38, 3e38) })];
+        assert_eq!(Scenario::new(Vec::new(), too_fast).unwrap_err().errors, vec![CreationError::PredatorParameterIsNotPositive("max speed".to_string())]);
+    }
+}
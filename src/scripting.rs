@@ -0,0 +1,188 @@
+//! An extra steering rule defined in a rhai script.
+//!
+//! The script must define `fn steer(boid, neighbours)`, where `boid` is a map with
+//! `x_pos`, `y_pos`, `x_vel` and `y_vel`, and `neighbours` is an array of such maps
+//! for every other boid within the local radius. It returns `[x_force, y_force]`,
+//! which is added to the boid's velocity. e.g.
+//!
+//! ```rhai
+//! fn steer(boid, neighbours) {
+//!     [0.0, -0.1 * neighbours.len()]
+//! }
+//! ```
+//!
+//! The script is reloaded whenever the file changes on disk. Switching off `Rule::Scripted` on
+//! the flock switches the script off too, without unloading it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::{anyhow, Context, Result};
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use crate::{Boid, Flock, Rule};
+
+pub struct ScriptedRule {
+    engine: Engine,
+    ast: AST,
+    path: PathBuf,
+    last_modified: SystemTime,
+}
+
+impl ScriptedRule {
+    pub fn load(path: &Path) -> Result<ScriptedRule> {
+        let engine = Engine::new();
+        let last_modified = modified_time(path)?;
+        let ast = compile(&engine, path)?;
+        return Ok(ScriptedRule {
+            engine,
+            ast,
+            path: path.to_path_buf(),
+            last_modified,
+        });
+    }
+
+    /// recompiles the script if the file has changed since it was last loaded.
+    /// If the new script doesn't compile, the error is returned and the previous script is kept.
+    pub fn reload_if_changed(&mut self) -> Result<bool> {
+        let modified = modified_time(&self.path)?;
+        if modified == self.last_modified {
+            return Ok(false);
+        }
+        // only retry a broken script once it has been edited again
+        self.last_modified = modified;
+        self.ast = compile(&self.engine, &self.path)?;
+        return Ok(true);
+    }
+
+    /// adds the script's force to every boid's velocity, unless the flock has the scripted rule switched off.
+    /// All forces are computed before any boid is changed, so the result doesn't depend on boid order.
+    pub fn apply(&mut self, flock: &mut Flock) -> Result<()> {
+        self.reload_if_changed()?;
+        if !flock.is_rule_enabled(Rule::Scripted) {
+            return Ok(());
+        }
+
+        let mut forces = Vec::with_capacity(flock.boids.len());
+        for (boid_idx, boid) in flock.boids.iter().enumerate() {
+            let neighbours: Array = flock.boids
+                .iter()
+                .enumerate()
+                .filter(|(other_idx, other_boid)| *other_idx != boid_idx && boid.is_within_sight_of_local_boid(other_boid, flock.max_dist_of_local_boid))
+                .map(|(_, other_boid)| boid_to_dynamic(other_boid))
+                .collect();
+            let force: Array = self.engine
+                .call_fn(&mut Scope::new(), &self.ast, "steer", (boid_to_dynamic(boid), neighbours))
+                .map_err(|err| anyhow!("{} failed in steer(): {}", self.path.display(), err))?;
+            forces.push(force_from_array(&force)?);
+        }
+
+        for (boid, (x_force, y_force)) in flock.boids.iter_mut().zip(forces) {
+            boid.x_vel += x_force;
+            boid.y_vel += y_force;
+        }
+        return Ok(());
+    }
+}
+
+fn compile(engine: &Engine, path: &Path) -> Result<AST> {
+    let script = fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    return engine.compile(script).map_err(|err| anyhow!("{} doesn't compile: {}", path.display(), err));
+}
+
+fn modified_time(path: &Path) -> Result<SystemTime> {
+    return fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .with_context(|| format!("couldn't read modification time of {}", path.display()));
+}
+
+fn boid_to_dynamic(boid: &Boid) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("x_pos".into(), Dynamic::from_float(boid.x_pos as f64));
+    map.insert("y_pos".into(), Dynamic::from_float(boid.y_pos as f64));
+    map.insert("x_vel".into(), Dynamic::from_float(boid.x_vel as f64));
+    map.insert("y_vel".into(), Dynamic::from_float(boid.y_vel as f64));
+    return Dynamic::from_map(map);
+}
+
+fn force_from_array(force: &Array) -> Result<(f32, f32)> {
+    if force.len() != 2 {
+        return Err(anyhow!("steer() should return [x_force, y_force], got {} values", force.len()));
+    }
+    return Ok((number_to_f32(&force[0])?, number_to_f32(&force[1])?));
+}
+
+fn number_to_f32(value: &Dynamic) -> Result<f32> {
+    // scripts will often write `0` rather than `0.0`, so accept integers as well
+    if let Ok(float) = value.as_float() {
+        return Ok(float as f32);
+    }
+    if let Ok(int) = value.as_int() {
+        return Ok(int as f32);
+    }
+    return Err(anyhow!("steer() returned a {} rather than a number", value.type_name()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    fn write_script(name: &str, script: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("boids_{}_{}.rhai", name, std::process::id()));
+        fs::write(&path, script).unwrap();
+        return path;
+    }
+
+    #[test]
+    fn test_scripted_force_added_to_velocity() {
+        let path = write_script("force", "fn steer(boid, neighbours) { [1.0, neighbours.len()] }");
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 1.0, 1.0), Boid::new(10.0, 10.0, 0.0, 0.0), Boid::new(100.0, 100.0, 0.0, 0.0)];
+
+        let mut rule = ScriptedRule::load(&path).unwrap();
+        rule.apply(&mut flock).unwrap();
+        assert_eq!((flock.boids[0].x_vel, flock.boids[0].y_vel), (2.0, 2.0));
+        assert_eq!((flock.boids[1].x_vel, flock.boids[1].y_vel), (1.0, 1.0));
+        // too far from the others to see any neighbours
+        assert_eq!((flock.boids[2].x_vel, flock.boids[2].y_vel), (1.0, 0.0));
+
+        flock.toggle_rule(Rule::Scripted);
+        rule.apply(&mut flock).unwrap();
+        assert_eq!((flock.boids[0].x_vel, flock.boids[0].y_vel), (2.0, 2.0));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_script_reloaded_when_changed() {
+        let path = write_script("reload", "fn steer(boid, neighbours) { [0, 0] }");
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0)];
+
+        let mut rule = ScriptedRule::load(&path).unwrap();
+        assert!(!rule.reload_if_changed().unwrap());
+
+        fs::write(&path, "fn steer(boid, neighbours) { [0, -3] }").unwrap();
+        // don't rely on the filesystem's timestamp resolution to see the change
+        File::options().write(true).open(&path).unwrap().set_modified(rule.last_modified + Duration::from_secs(1)).unwrap();
+        rule.apply(&mut flock).unwrap();
+        assert_eq!(flock.boids[0].y_vel, -3.0);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_broken_script_keeps_previous_rule() {
+        let path = write_script("broken", "fn steer(boid, neighbours) { [0, 1] }");
+        let mut rule = ScriptedRule::load(&path).unwrap();
+
+        fs::write(&path, "fn steer(boid, neighbours) { [0, ").unwrap();
+        File::options().write(true).open(&path).unwrap().set_modified(rule.last_modified + Duration::from_secs(1)).unwrap();
+        assert!(rule.reload_if_changed().is_err());
+
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0)];
+        rule.apply(&mut flock).unwrap();
+        assert_eq!(flock.boids[0].y_vel, 1.0);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+//! Sharing one world between two processes, each simulating the boids on its side of a vertical
+//! seam. After every step the two exchange the boids that have crossed the seam, which join the
+//! other side's flock, and ghosts of the boids near the seam, which the other side's boids flock
+//! with but don't move. The processes can be on different machines, talking over TCP.
+//!
+//! `boids shard --side left --seam 0 --listen 0.0.0.0:7000` on one machine and
+//! `boids shard --side right --seam 0 --connect host:7000` on the other, with the same `--seed`,
+//! `--flock-size` and `--ticks`, start from the two halves of the same flock.
+//!
+//! Each message is the number of boids crossing, then the number of ghosts, each a little-endian
+//! u32, followed by those boids as four little-endian f32s each: position, then velocity. The
+//! left side sends first each tick, so the two never both wait to be read from. A message with
+//! more than `MAX_BOIDS_PER_MESSAGE` boids, or with a number that isn't finite, ends the exchange
+//! with an error rather than being trusted.
+//!
+//! Only a boid's position and velocity go across, so a boid that crosses the seam joins the other
+//! side as a new boid: it's given a new id there, and anything kept about it by id, such as its
+//! state and energy with `Flock::set_boid_states`, starts afresh.
+
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use anyhow::{anyhow, bail, Context, Result};
+use crate::distribution::InitialDistribution;
+use crate::{Boid, Flock};
+
+// far more than ever cross at once, but few enough that a bad message can't ask for all the memory there is
+pub const MAX_BOIDS_PER_MESSAGE: usize = 1 << 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    Left, // owns everything left of the seam
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Seam {
+    pub x: f32,
+    pub side: Side, // of this process
+    pub ghost_width: f32, // boids this close to the seam are shown to the other side
+}
+
+impl Seam {
+    fn owns(&self, boid: &Boid) -> bool {
+        return match self.side {
+            Side::Left => boid.x_pos < self.x,
+            Side::Right => boid.x_pos >= self.x,
+        };
+    }
+
+    fn is_near(&self, boid: &Boid) -> bool {
+        return (boid.x_pos - self.x).abs() < self.ghost_width;
+    }
+}
+
+/// one end of the connection between the processes either side of a seam
+pub struct BoundaryLink {
+    seam: Seam,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl BoundaryLink {
+    /// waits for the process on the other side to connect
+    pub fn accept(listener: &TcpListener, seam: Seam) -> io::Result<BoundaryLink> {
+        let (stream, _) = listener.accept()?;
+        return BoundaryLink::new(stream, seam);
+    }
+
+    pub fn connect(address: impl ToSocketAddrs, seam: Seam) -> io::Result<BoundaryLink> {
+        return BoundaryLink::new(TcpStream::connect(address)?, seam);
+    }
+
+    fn new(stream: TcpStream, seam: Seam) -> io::Result<BoundaryLink> {
+        // messages are small and sent once a tick, so shouldn't wait around to be batched up
+        stream.set_nodelay(true)?;
+        return Ok(BoundaryLink { seam, reader: BufReader::new(stream.try_clone()?), writer: BufWriter::new(stream) });
+    }
+
+    /// hands boids that have crossed the seam to the other side and takes in those that have come
+    /// the other way, then swaps ghosts of the boids near the seam. Call after every step
+    pub fn exchange(&mut self, flock: &mut Flock) -> io::Result<()> {
+        let seam = self.seam;
+        let leaving = flock.remove_boids_where(|boid| !seam.owns(boid));
+        let ghosts: Vec<Boid> = flock.boids().iter().copied().filter(|boid| seam.is_near(boid)).collect();
+        let (arriving, their_ghosts) = match seam.side {
+            Side::Left => {
+                self.send(&leaving, &ghosts)?;
+                self.receive()?
+            }
+            Side::Right => {
+                let received = self.receive()?;
+                self.send(&leaving, &ghosts)?;
+                received
+            }
+        };
+        // ids aren't sent over, so boids that cross are new boids to this side, with new ids
+        flock.add_boids(arriving);
+        flock.set_ghost_boids(their_ghosts);
+        return Ok(());
+    }
+
+    fn send(&mut self, leaving: &[Boid], ghosts: &[Boid]) -> io::Result<()> {
+        self.writer.write_all(&(leaving.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&(ghosts.len() as u32).to_le_bytes())?;
+        for boid in leaving.iter().chain(ghosts) {
+            for value in [boid.x_pos, boid.y_pos, boid.x_vel, boid.y_vel] {
+                self.writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        return self.writer.flush();
+    }
+
+    fn receive(&mut self) -> io::Result<(Vec<Boid>, Vec<Boid>)> {
+        let num_arriving = self.read_u32()? as usize;
+        let num_ghosts = self.read_u32()? as usize;
+        if num_arriving + num_ghosts > MAX_BOIDS_PER_MESSAGE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} boids sent at once, more than the {} allowed", num_arriving + num_ghosts, MAX_BOIDS_PER_MESSAGE)));
+        }
+        // grown as the boids arrive, so a message cut short doesn't leave a large buffer behind
+        let mut boids = Vec::new();
+        for _ in 0..num_arriving + num_ghosts {
+            boids.push(Boid::new(self.read_f32()?, self.read_f32()?, self.read_f32()?, self.read_f32()?));
+        }
+        let ghosts = boids.split_off(num_arriving);
+        return Ok((boids, ghosts));
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0; 4];
+        self.reader.read_exact(&mut bytes)?;
+        return Ok(u32::from_le_bytes(bytes));
+    }
+
+    fn read_f32(&mut self) -> io::Result<f32> {
+        let mut bytes = [0; 4];
+        self.reader.read_exact(&mut bytes)?;
+        let value = f32::from_le_bytes(bytes);
+        if !value.is_finite() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("boid sent with {} in its position or velocity", value)));
+        }
+        return Ok(value);
+    }
+}
+
+/// the `shard` subcommand; prints how many boids are on this side after the last tick
+pub fn run(args: &[String]) -> Result<()> {
+    let mut side = None;
+    let mut seam_x = 0.0;
+    let mut listen_address = None;
+    let mut connect_address = None;
+    let (mut num_ticks, mut flock_size, mut seed) = (600, 100, 0);
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| anyhow!("{} needs a value", flag))?;
+        match flag.as_str() {
+            "--side" => side = Some(match value.as_str() {
+                "left" => Side::Left,
+                "right" => Side::Right,
+                _ => bail!("--side should be left or right, not '{}'", value),
+            }),
+            "--seam" => seam_x = value.parse().with_context(|| format!("bad --seam '{}'", value))?,
+            "--listen" => listen_address = Some(value.clone()),
+            "--connect" => connect_address = Some(value.clone()),
+            "--ticks" => num_ticks = value.parse().with_context(|| format!("bad --ticks '{}'", value))?,
+            "--flock-size" => flock_size = value.parse().with_context(|| format!("bad --flock-size '{}'", value))?,
+            "--seed" => seed = value.parse().with_context(|| format!("bad --seed '{}'", value))?,
+            _ => bail!("unknown shard option {}", flag),
+        }
+    }
+    let side = side.ok_or_else(|| anyhow!("--side is needed"))?;
+
+    let local_radius = 20.0;
+    let mut flock = Flock::new(flock_size, 5.0, local_radius, 0.1, 0.1, 0.1)?;
+    // both sides make the same flock and keep their own half of it
+    flock.randomise_boids(&InitialDistribution::default(), seed)?;
+    let seam = Seam { x: seam_x, side, ghost_width: local_radius };
+    flock.remove_boids_where(|boid| !seam.owns(boid));
+
+    let mut link = match (listen_address, connect_address) {
+        (Some(address), None) => {
+            let listener = TcpListener::bind(&address).with_context(|| format!("couldn't listen on {}", address))?;
+            BoundaryLink::accept(&listener, seam)?
+        }
+        (None, Some(address)) => BoundaryLink::connect(&address, seam).with_context(|| format!("couldn't connect to {}", address))?,
+        _ => bail!("one of --listen or --connect is needed"),
+    };
+    // ghosts go across before the first step, so both sides start out seeing each other
+    link.exchange(&mut flock)?;
+    for _ in 0..num_ticks {
+        flock.step();
+        link.exchange(&mut flock).context("lost the other side")?;
+    }
+    println!("{} boids on the {:?} side after {} ticks", flock.boids().len(), side, num_ticks);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn seam(side: Side) -> Seam {
+        return Seam { x: 0.0, side, ghost_width: 5.0 };
+    }
+
+    #[test]
+    fn test_boids_cross_and_ghosts_swapped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let right = thread::spawn(move || {
+            let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+            flock.boids = vec![Boid::new(3.0, 0.0, 0.0, 0.0), Boid::new(50.0, 0.0, 0.0, 0.0)];
+            let mut link = BoundaryLink::connect(address, seam(Side::Right)).unwrap();
+            link.exchange(&mut flock).unwrap();
+            return flock;
+        });
+        let mut left = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        // one boid has just crossed the seam, and another is near it
+        left.boids = vec![Boid::new(0.5, 1.0, 2.0, 0.0), Boid::new(-2.0, 0.0, 1.0, 0.0), Boid::new(-50.0, 0.0, 0.0, 0.0)];
+        let mut link = BoundaryLink::accept(&listener, seam(Side::Left)).unwrap();
+        link.exchange(&mut left).unwrap();
+        let right = right.join().unwrap();
+
+        assert_eq!(left.boids().len(), 2);
+        assert_eq!(right.boids().len(), 3);
+        assert_eq!((right.boids()[2].x_pos, right.boids()[2].x_vel), (0.5, 2.0));
+        assert_eq!(left.ghost_boids.iter().map(|boid| boid.x_pos).collect::<Vec<f32>>(), vec![3.0]);
+        assert_eq!(right.ghost_boids.iter().map(|boid| boid.x_pos).collect::<Vec<f32>>(), vec![-2.0]);
+    }
+
+    // a link to a peer that sends `message` and nothing else
+    fn link_receiving(message: Vec<u8>) -> BoundaryLink {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let peer = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(&message).unwrap();
+        });
+        let link = BoundaryLink::accept(&listener, seam(Side::Left)).unwrap();
+        peer.join().unwrap();
+        return link;
+    }
+
+    #[test]
+    fn test_bad_messages_rejected() {
+        let huge = [u32::MAX.to_le_bytes(), u32::MAX.to_le_bytes()].concat();
+        assert_eq!(link_receiving(huge).receive().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let not_a_number = [1u32.to_le_bytes(), 0u32.to_le_bytes(), 0f32.to_le_bytes(), f32::NAN.to_le_bytes(), 0f32.to_le_bytes(), 0f32.to_le_bytes()].concat();
+        assert_eq!(link_receiving(not_a_number).receive().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // claims a boid but is cut off before sending it
+        let cut_short = [1u32.to_le_bytes(), 0u32.to_le_bytes()].concat();
+        assert_eq!(link_receiving(cut_short).receive().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_boids_kept_while_stepping_together() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let make_flock = |side| {
+            let mut flock = Flock::new(40, 2.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+            flock.randomise_boids(&InitialDistribution::GaussianBlob { std_dev: 10.0 }, 7).unwrap();
+            flock.remove_boids_where(|boid| !seam(side).owns(boid));
+            return flock;
+        };
+        let run = |mut flock: Flock, mut link: BoundaryLink| {
+            for _ in 0..30 {
+                flock.step();
+                link.exchange(&mut flock).unwrap();
+            }
+            return flock.boids().len();
+        };
+        let right = thread::spawn(move || run(make_flock(Side::Right), BoundaryLink::connect(address, seam(Side::Right)).unwrap()));
+        let left = run(make_flock(Side::Left), BoundaryLink::accept(&listener, seam(Side::Left)).unwrap());
+        assert_eq!(left + right.join().unwrap(), 40);
+    }
+}
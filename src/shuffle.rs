@@ -0,0 +1,160 @@
+//! Shuffling the flock's parameters, for finding interesting behaviour quickly. Each shuffle picks a
+//! new seed and, from it, a random combination of the three factors, the crowding, local and
+//! alignment radii and the maximum speed, always one that makes a valid flock. The same seed always
+//! gives the same parameters, so a combination worth keeping can be noted down by its seed, which
+//! is shown alongside the values, and brought back later with `ParameterShuffle::reapply` or
+//! `boids record --shuffle-seed`.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::{Factor, Flock, InvalidFlockConfig};
+
+const LOCAL_RADIUS_RANGE: RangeInclusive<f32> = 10.0..=50.0;
+// the crowding radius as a share of the local radius, so it's always the smaller of the two
+const CROWDING_SHARE_RANGE: RangeInclusive<f32> = 0.1..=0.5;
+const MAX_SPEED_RANGE: RangeInclusive<f32> = 1.0..=5.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShuffledParameters {
+    pub seed: u64, // the seed the rest were drawn from
+    pub repulsion: f32,
+    pub adhesion: f32,
+    pub cohesion: f32,
+    pub crowding_radius: f32,
+    pub local_radius: f32,
+    pub alignment_radius: f32,
+    pub max_speed: f32,
+}
+
+impl ShuffledParameters {
+    pub fn from_seed(seed: u64) -> ShuffledParameters {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let repulsion = rng.gen_range(0.0..=1.0);
+        let adhesion = rng.gen_range(0.0..=1.0);
+        let cohesion = rng.gen_range(0.0..=1.0);
+        let local_radius = rng.gen_range(LOCAL_RADIUS_RANGE);
+        let crowding_radius = local_radius * rng.gen_range(CROWDING_SHARE_RANGE);
+        // above the crowding radius and no larger than the local radius
+        let alignment_radius = local_radius - (local_radius - crowding_radius) * rng.gen::<f32>();
+        let max_speed = rng.gen_range(MAX_SPEED_RANGE);
+        return ShuffledParameters { seed, repulsion, adhesion, cohesion, crowding_radius, local_radius, alignment_radius, max_speed };
+    }
+
+    /// gives the flock these parameters, keeping its minimum speed. Fails, changing nothing, if the
+    /// minimum speed isn't below the maximum speed
+    pub fn apply(&self, flock: &mut Flock) -> Result<(), InvalidFlockConfig> {
+        let (min_speed, _) = flock.speed_limits();
+        flock.set_speed_limits(min_speed, self.max_speed)?;
+        // the rest are valid however they were drawn
+        flock.set_radii(self.crowding_radius, self.local_radius, self.alignment_radius)?;
+        flock.set_factor(Factor::Repulsion, self.repulsion)?;
+        flock.set_factor(Factor::Adhesion, self.adhesion)?;
+        flock.set_factor(Factor::Cohesion, self.cohesion)?;
+        return Ok(());
+    }
+}
+
+/// one line, for showing on screen
+impl fmt::Display for ShuffledParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "seed {}: repulsion {:.2}, adhesion {:.2}, cohesion {:.2}, crowding radius {:.1}, local radius {:.1}, alignment radius {:.1}, max speed {:.2}",
+                      self.seed, self.repulsion, self.adhesion, self.cohesion, self.crowding_radius, self.local_radius, self.alignment_radius, self.max_speed);
+    }
+}
+
+/// the shuffles so far, so the front end can step back through them
+#[derive(Clone, Debug)]
+pub struct ParameterShuffle {
+    rng: StdRng, // where each shuffle's seed comes from
+    shown: Vec<ShuffledParameters>, // oldest first
+}
+
+impl ParameterShuffle {
+    /// the same seed here gives the same run of shuffles
+    pub fn new(seed: u64) -> ParameterShuffle {
+        return ParameterShuffle { rng: StdRng::seed_from_u64(seed), shown: Vec::new() };
+    }
+
+    /// gives the flock a new random set of parameters, e.g. on a key press
+    pub fn shuffle(&mut self, flock: &mut Flock) -> Result<&ShuffledParameters, InvalidFlockConfig> {
+        let seed = self.rng.gen();
+        return self.reapply(seed, flock);
+    }
+
+    /// gives the flock the parameters from a seed shown before, in this run or another
+    pub fn reapply(&mut self, seed: u64, flock: &mut Flock) -> Result<&ShuffledParameters, InvalidFlockConfig> {
+        let parameters = ShuffledParameters::from_seed(seed);
+        parameters.apply(flock)?;
+        self.shown.push(parameters);
+        return Ok(&self.shown[self.shown.len() - 1]);
+    }
+
+    /// goes back to the parameters shown before the current ones, and returns them. None, changing
+    /// nothing, if there haven't been two shuffles yet
+    pub fn back(&mut self, flock: &mut Flock) -> Option<&ShuffledParameters> {
+        if self.shown.len() < 2 {
+            return None;
+        }
+        self.shown.pop();
+        let previous = self.shown.last()?;
+        // they were applied to this flock before, so can be again unless its minimum speed has since risen
+        previous.apply(flock).ok()?;
+        return Some(previous);
+    }
+
+    /// the parameters the flock was last given
+    pub fn current(&self) -> Option<&ShuffledParameters> {
+        return self.shown.last();
+    }
+
+    pub fn shown(&self) -> &[ShuffledParameters] {
+        return &self.shown;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shuffled_parameters_valid_and_reproducible() {
+        for seed in 0..200 {
+            let parameters = ShuffledParameters::from_seed(seed);
+            assert_eq!(parameters, ShuffledParameters::from_seed(seed));
+            let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+            parameters.apply(&mut flock).unwrap();
+            assert_eq!(flock.radii(), (parameters.crowding_radius, parameters.local_radius, parameters.alignment_radius));
+            assert_eq!(flock.speed_limits(), (0.0, parameters.max_speed));
+            // and would have made a valid flock from the start
+            assert!(Flock::new(0, parameters.crowding_radius, parameters.local_radius, parameters.repulsion, parameters.adhesion, parameters.cohesion).is_ok());
+        }
+        assert_ne!(ShuffledParameters::from_seed(1), ShuffledParameters::from_seed(2));
+        assert!(ShuffledParameters::from_seed(7).to_string().starts_with("seed 7: repulsion "));
+    }
+
+    #[test]
+    fn test_shuffles_reapplied_and_stepped_back_through() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        let mut shuffle = ParameterShuffle::new(3);
+        let first = *shuffle.shuffle(&mut flock).unwrap();
+        let second = *shuffle.shuffle(&mut flock).unwrap();
+        assert_eq!(flock.factor(Factor::Cohesion), second.cohesion);
+        assert_eq!(shuffle.back(&mut flock), Some(&first));
+        assert_eq!(flock.factor(Factor::Cohesion), first.cohesion);
+        assert_eq!(shuffle.back(&mut flock), None);
+
+        let mut other_flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        let reapplied = *ParameterShuffle::new(0).reapply(second.seed, &mut other_flock).unwrap();
+        assert_eq!(reapplied, second);
+        assert_eq!(other_flock.radii(), (second.crowding_radius, second.local_radius, second.alignment_radius));
+
+        // a flock that has to go faster than the shuffled maximum speed is left alone
+        other_flock.set_speed_limits(10.0, f32::INFINITY).unwrap();
+        assert!(shuffle.shuffle(&mut other_flock).is_err());
+        assert_eq!(other_flock.radii(), (second.crowding_radius, second.local_radius, second.alignment_radius));
+        assert_eq!(shuffle.current(), Some(&first));
+        assert_eq!(shuffle.shown().len(), 1);
+    }
+}
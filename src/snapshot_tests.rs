@@ -0,0 +1,66 @@
+//! Golden-file tests. Each scenario runs a small flock for a few steps and compares where every
+//! boid is, and how it's moving, with the state stored in `src/snapshots`, catching changes in
+//! behaviour too small for the unit tests to notice. After a change that's meant to alter the
+//! flock's behaviour, run the tests with `UPDATE_SNAPSHOTS=1` to rewrite the stored states, and
+//! check the diff before committing it.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use crate::arena::Arena;
+use crate::{Boid, Flock};
+
+/// the state of every boid at the start and then after every `ticks_between` steps, `num_snapshots` times
+fn run_and_snapshot(flock: &mut Flock, num_snapshots: usize, ticks_between: usize) -> String {
+    let mut snapshot = String::new();
+    for snapshot_idx in 0..=num_snapshots {
+        if snapshot_idx > 0 {
+            for _ in 0..ticks_between {
+                flock.step();
+            }
+        }
+        let _ = writeln!(snapshot, "tick {}", snapshot_idx * ticks_between);
+        for boid in flock.boids() {
+            // rounded, so that differences in the last bits of floating point maths between platforms don't matter
+            let _ = writeln!(snapshot, "{:.4} {:.4} {:.4} {:.4}", boid.x_pos, boid.y_pos, boid.x_vel, boid.y_vel);
+        }
+    }
+    return snapshot;
+}
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "src", "snapshots", &format!("{}.snap", name)].iter().collect();
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("no snapshot at {}; run with UPDATE_SNAPSHOTS=1 to create it", path.display()));
+    assert!(actual == expected, "{} doesn't match {}:\n{}\nrun with UPDATE_SNAPSHOTS=1 if the change is intended", name, path.display(), actual);
+}
+
+#[test]
+fn test_two_boids_converging() {
+    let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+    flock.boids = vec![Boid::new(-4.0, 0.0, 0.5, 0.0), Boid::new(4.0, 1.0, -0.5, 0.25)];
+    assert_snapshot("two_boids_converging", &run_and_snapshot(&mut flock, 4, 5));
+}
+
+#[test]
+fn test_boids_near_wall() {
+    let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+    flock.set_arena(Arena::circle((0.0, 0.0), 10.0).unwrap());
+    flock.boids = vec![Boid::new(7.0, 0.0, 1.5, 0.5), Boid::new(6.0, -2.0, 1.0, 0.0)];
+    assert_snapshot("boids_near_wall", &run_and_snapshot(&mut flock, 4, 5));
+}
+
+#[test]
+fn test_crowded_cluster() {
+    let mut flock = Flock::new(0, 2.0, 10.0, 0.1, 0.5, 0.1).unwrap();
+    // a tight three by three grid, all heading the same way
+    flock.boids = (0..9)
+        .map(|boid_idx| Boid::new((boid_idx % 3) as f32 * 0.5, (boid_idx / 3) as f32 * 0.5, 1.0, 0.0))
+        .collect();
+    assert_snapshot("crowded_cluster", &run_and_snapshot(&mut flock, 4, 5));
+}
@@ -0,0 +1,325 @@
+//! Summary measures of the state of a flock.
+
+use std::cmp::Reverse;
+use crate::Boid;
+use crate::events::FlockEvent;
+
+/// how aligned the flock's headings are: 1 when every moving boid heads the same way,
+/// near 0 when they head in random directions. Stationary boids have no heading, so are left out
+pub fn polarization(boids: &[Boid]) -> f32 {
+    let mut total_x_heading = 0.0;
+    let mut total_y_heading = 0.0;
+    let mut num_moving_boids = 0;
+    for boid in boids {
+        let speed = boid.speed();
+        if speed > 0.0 {
+            total_x_heading += boid.x_vel / speed;
+            total_y_heading += boid.y_vel / speed;
+            num_moving_boids += 1;
+        }
+    }
+    if num_moving_boids == 0 {
+        return 0.0;
+    }
+    return (total_x_heading * total_x_heading + total_y_heading * total_y_heading).sqrt() / num_moving_boids as f32;
+}
+
+/// the boids' average speed, or 0 if there are none
+pub fn mean_speed(boids: &[Boid]) -> f32 {
+    if boids.is_empty() {
+        return 0.0;
+    }
+    return boids.iter().map(|boid| boid.speed()).sum::<f32>() / boids.len() as f32;
+}
+
+/// the number of pairs of boids closer together than `collision_dist`
+pub fn count_collisions(boids: &[Boid], collision_dist: f32) -> usize {
+    let mut num_collisions = 0;
+    for (boid_idx, boid) in boids.iter().enumerate() {
+        for other_boid in &boids[boid_idx + 1..] {
+            if boid.dist_to_boid(other_boid) < collision_dist {
+                num_collisions += 1;
+            }
+        }
+    }
+    return num_collisions;
+}
+
+/// how many other boids each boid has within `local_radius` of it; with a density response, the
+/// crowd pressure each boid feels depends on this
+pub fn local_densities(boids: &[Boid], local_radius: f32) -> Vec<usize> {
+    return boids
+        .iter()
+        .enumerate()
+        .map(|(boid_idx, boid)| {
+            boids.iter().enumerate().filter(|(other_idx, other_boid)| *other_idx != boid_idx && boid.dist_to_boid(other_boid) < local_radius).count()
+        })
+        .collect();
+}
+
+/// the cluster each boid is in, where boids closer together than `link_dist` are in the same
+/// cluster, as is anything linked to them through a chain of such boids.
+/// Clusters are numbered from 0 in order of their lowest-indexed boid
+pub fn label_clusters(boids: &[Boid], link_dist: f32) -> Vec<usize> {
+    let mut labels = Vec::new();
+    label_clusters_into(boids, link_dist, &mut labels, &mut Vec::new());
+    return labels;
+}
+
+/// `label_clusters`, reusing the buffers passed in so that labelling every step needn't allocate.
+/// `cluster_of` is only used for working
+pub(crate) fn label_clusters_into(boids: &[Boid], link_dist: f32, labels: &mut Vec<usize>, cluster_of: &mut Vec<usize>) {
+    // union-find, where each boid points towards another boid in its cluster
+    cluster_of.clear();
+    cluster_of.extend(0..boids.len());
+    fn find_root(cluster_of: &mut [usize], mut boid_idx: usize) -> usize {
+        while cluster_of[boid_idx] != boid_idx {
+            cluster_of[boid_idx] = cluster_of[cluster_of[boid_idx]];
+            boid_idx = cluster_of[boid_idx];
+        }
+        return boid_idx;
+    }
+
+    for (boid_idx, boid) in boids.iter().enumerate() {
+        for (other_idx, other_boid) in boids.iter().enumerate().skip(boid_idx + 1) {
+            if boid.dist_to_boid(other_boid) < link_dist {
+                let root = find_root(cluster_of, boid_idx);
+                let other_root = find_root(cluster_of, other_idx);
+                // keep the lower index as the root, so the first boid of each cluster is its root
+                cluster_of[root.max(other_root)] = root.min(other_root);
+            }
+        }
+    }
+
+    labels.clear();
+    labels.resize(boids.len(), 0);
+    let mut num_clusters = 0;
+    for boid_idx in 0..boids.len() {
+        let root = find_root(cluster_of, boid_idx);
+        if root == boid_idx {
+            labels[boid_idx] = num_clusters;
+            num_clusters += 1;
+        } else {
+            // the root has a lower index, so it's already been labelled
+            labels[boid_idx] = labels[root];
+        }
+    }
+}
+
+/// Working space for `find_cluster_changes`, kept between steps so that finding changes needn't allocate
+#[derive(Debug, Default)]
+pub(crate) struct ClusterChangeScratch {
+    label_pairs: Vec<(usize, usize)>, // (before, after) for each boid, then (after, before)
+    sizes_before: Vec<usize>,
+    sizes_after: Vec<usize>,
+}
+
+/// events for the clusters that split or merged between two labellings of the same boids, e.g.
+/// before and after a step. A cluster that breaks into pieces gives one split for each piece but
+/// the largest, and each cluster formed from several gives one merge for each but the largest
+/// of the clusters it was formed from, with ties going to the lowest numbered. Boids joining or leaving the flock in between can't be
+/// followed, so nothing is found unless both labellings are of the same number of boids
+pub(crate) fn find_cluster_changes(labels_before: &[usize], labels_after: &[usize], scratch: &mut ClusterChangeScratch, events: &mut Vec<FlockEvent>) {
+    if labels_before.len() != labels_after.len() {
+        return;
+    }
+    fn count_sizes(labels: &[usize], sizes: &mut Vec<usize>) {
+        sizes.clear();
+        for label in labels {
+            if *label >= sizes.len() {
+                sizes.resize(label + 1, 0);
+            }
+            sizes[*label] += 1;
+        }
+    }
+    count_sizes(labels_before, &mut scratch.sizes_before);
+    count_sizes(labels_after, &mut scratch.sizes_after);
+
+    // each distinct (before, after) pair is a piece of a cluster that went from one to the other
+    let pairs = &mut scratch.label_pairs;
+    pairs.clear();
+    pairs.extend(labels_before.iter().copied().zip(labels_after.iter().copied()));
+    pairs.sort_unstable();
+    pairs.dedup();
+    for pieces in pairs.chunk_by(|a, b| a.0 == b.0).filter(|pieces| pieces.len() > 1) {
+        let main = pieces.iter().map(|(_, after)| *after).min_by_key(|after| (Reverse(scratch.sizes_after[*after]), *after)).expect("chunks aren't empty");
+        for (from, part) in pieces.iter().copied().filter(|(_, after)| *after != main) {
+            events.push(FlockEvent::ClusterSplit {
+                from, main, main_size: scratch.sizes_after[main], part, part_size: scratch.sizes_after[part],
+            });
+        }
+    }
+
+    for pair in pairs.iter_mut() {
+        *pair = (pair.1, pair.0);
+    }
+    pairs.sort_unstable();
+    for sources in pairs.chunk_by(|a, b| a.0 == b.0).filter(|sources| sources.len() > 1) {
+        let main = sources.iter().map(|(_, before)| *before).min_by_key(|before| (Reverse(scratch.sizes_before[*before]), *before)).expect("chunks aren't empty");
+        for (into, part) in sources.iter().copied().filter(|(_, before)| *before != main) {
+            events.push(FlockEvent::ClusterMerge {
+                into, main, main_size: scratch.sizes_before[main], part, part_size: scratch.sizes_before[part],
+            });
+        }
+    }
+}
+
+/// the number of separate clusters, as found by `label_clusters`
+pub fn count_groups(boids: &[Boid], link_dist: f32) -> usize {
+    return label_clusters(boids, link_dist).iter().max().map_or(0, |max_label| max_label + 1);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusterStats {
+    pub size: usize,
+    pub centroid: (f32, f32),
+    // the direction, in radians anticlockwise from the x axis, of the cluster's average heading;
+    // None if none of its boids are moving, or their headings cancel out exactly
+    pub mean_heading: Option<f32>,
+    pub polarization: f32,
+}
+
+/// stats for each cluster, indexed by cluster number, given the labels from `label_clusters`
+pub fn cluster_stats(boids: &[Boid], cluster_labels: &[usize]) -> Vec<ClusterStats> {
+    let num_clusters = cluster_labels.iter().max().map_or(0, |max_label| max_label + 1);
+    let mut boids_by_cluster: Vec<Vec<Boid>> = vec![Vec::new(); num_clusters];
+    for (boid, cluster) in boids.iter().zip(cluster_labels) {
+        boids_by_cluster[*cluster].push(*boid);
+    }
+
+    return boids_by_cluster
+        .iter()
+        .map(|cluster_boids| {
+            let size = cluster_boids.len();
+            let centroid = (
+                cluster_boids.iter().map(|boid| boid.x_pos).sum::<f32>() / size as f32,
+                cluster_boids.iter().map(|boid| boid.y_pos).sum::<f32>() / size as f32,
+            );
+            let (total_x_heading, total_y_heading) = cluster_boids
+                .iter()
+                .filter(|boid| boid.speed() > 0.0)
+                .fold((0.0, 0.0), |(x, y), boid| (x + boid.x_vel / boid.speed(), y + boid.y_vel / boid.speed()));
+            let mean_heading = if total_x_heading != 0.0 || total_y_heading != 0.0 {
+                Some(total_y_heading.atan2(total_x_heading))
+            } else {
+                None
+            };
+            ClusterStats {
+                size,
+                centroid,
+                mean_heading,
+                polarization: polarization(cluster_boids),
+            }
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Flock;
+
+    #[test]
+    fn test_polarization() {
+        let aligned = [Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(5.0, 5.0, 3.0, 0.0), Boid::new(1.0, 1.0, 0.0, 0.0)];
+        assert_eq!(polarization(&aligned), 1.0);
+
+        let opposed = [Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(5.0, 5.0, -3.0, 0.0)];
+        assert_eq!(polarization(&opposed), 0.0);
+        assert_eq!(polarization(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_mean_speed() {
+        let boids = [Boid::new(0.0, 0.0, 3.0, 4.0), Boid::new(5.0, 5.0, -1.0, 0.0)];
+        assert_eq!(mean_speed(&boids), 3.0);
+        assert_eq!(mean_speed(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_count_collisions() {
+        let boids = [Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(0.5, 0.0, 0.0, 0.0), Boid::new(0.0, 0.5, 0.0, 0.0), Boid::new(10.0, 0.0, 0.0, 0.0)];
+        assert_eq!(count_collisions(&boids, 0.6), 2);
+        assert_eq!(count_collisions(&boids, 1.0), 3);
+    }
+
+    #[test]
+    fn test_local_densities() {
+        let boids = [Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(0.5, 0.0, 0.0, 0.0), Boid::new(0.0, 0.5, 0.0, 0.0), Boid::new(10.0, 0.0, 0.0, 0.0)];
+        assert_eq!(local_densities(&boids, 0.6), vec![2, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_cluster_changes_found() {
+        let mut events = Vec::new();
+        // cluster 0 breaks into 0 and 2, while clusters 1 and 2 join up as 1
+        find_cluster_changes(&[0, 0, 0, 1, 2, 2], &[0, 0, 2, 1, 1, 1], &mut ClusterChangeScratch::default(), &mut events);
+        assert_eq!(events, vec![
+            FlockEvent::ClusterSplit { from: 0, main: 0, main_size: 2, part: 2, part_size: 1 },
+            FlockEvent::ClusterMerge { into: 1, main: 2, main_size: 2, part: 1, part_size: 1 },
+        ]);
+
+        events.clear();
+        find_cluster_changes(&[0, 1], &[0, 1], &mut ClusterChangeScratch::default(), &mut events);
+        find_cluster_changes(&[0, 1], &[0, 0, 1], &mut ClusterChangeScratch::default(), &mut events);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_flock_reports_clusters_merging() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        // two boids too far apart to see each other, then brought within sight of each other
+        flock.boids = vec![Boid::new(-6.0, 0.0, 0.0, 0.0), Boid::new(6.0, 0.0, 0.0, 0.0)];
+        flock.step();
+        assert!(flock.events().is_empty());
+        flock.boids[0].x_pos = 0.0;
+        flock.step();
+        assert_eq!(flock.events(), [FlockEvent::ClusterMerge { into: 0, main: 0, main_size: 1, part: 1, part_size: 1 }]);
+    }
+
+    #[test]
+    fn test_label_clusters() {
+        let boids = [
+            Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(50.0, 0.0, 0.0, 0.0), Boid::new(8.0, 0.0, 0.0, 0.0),
+            Boid::new(4.0, 0.0, 0.0, 0.0), Boid::new(52.0, 0.0, 0.0, 0.0), Boid::new(-50.0, 0.0, 0.0, 0.0),
+        ];
+        assert_eq!(label_clusters(&boids, 5.0), vec![0, 1, 0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_cluster_stats() {
+        let boids = [
+            Boid::new(0.0, 0.0, 0.0, 1.0), Boid::new(100.0, 0.0, 1.0, 0.0), Boid::new(2.0, 4.0, 0.0, 2.0),
+            Boid::new(102.0, 0.0, -1.0, 0.0), Boid::new(-100.0, 0.0, 0.0, 0.0),
+        ];
+        let labels = label_clusters(&boids, 5.0);
+        let stats = cluster_stats(&boids, &labels);
+        assert_eq!(stats.len(), 3);
+
+        assert_eq!(stats[0].size, 2);
+        assert_eq!(stats[0].centroid, (1.0, 2.0));
+        assert_eq!(stats[0].mean_heading, Some(std::f32::consts::FRAC_PI_2));
+        assert_eq!(stats[0].polarization, 1.0);
+
+        // heading opposite ways, so no overall heading
+        assert_eq!(stats[1].centroid, (101.0, 0.0));
+        assert_eq!(stats[1].mean_heading, None);
+        assert_eq!(stats[1].polarization, 0.0);
+
+        assert_eq!(stats[2].size, 1);
+        assert_eq!(stats[2].mean_heading, None);
+    }
+
+    #[test]
+    fn test_count_groups() {
+        // a chain of boids each close to the next is one group, even though its ends are far apart
+        let boids = [
+            Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(4.0, 0.0, 0.0, 0.0), Boid::new(8.0, 0.0, 0.0, 0.0),
+            Boid::new(100.0, 0.0, 0.0, 0.0),
+            Boid::new(0.0, 100.0, 0.0, 0.0), Boid::new(0.0, 102.0, 0.0, 0.0),
+        ];
+        assert_eq!(count_groups(&boids, 5.0), 3);
+        assert_eq!(count_groups(&boids, 1.0), 6);
+        assert_eq!(count_groups(&[], 1.0), 0);
+    }
+}
@@ -0,0 +1,159 @@
+//! Steering rules between a boid and a single other boid, e.g. a predator chasing its prey.
+//! Each rule returns the change in velocity for this frame, as `(x, y)`, rather than
+//! updating the boid, so callers can combine it with the flocking rules.
+
+use crate::Boid;
+
+/// where `boid` will be after `time_ahead` frames if it keeps its current velocity
+pub fn predicted_position(boid: &Boid, time_ahead: f32) -> (f32, f32) {
+    return (boid.x_pos + boid.x_vel * time_ahead, boid.y_pos + boid.y_vel * time_ahead);
+}
+
+/// how far ahead to predict the target: roughly how long the chaser takes to close the gap at its
+/// current speed, capped so that distant or slow chasers don't aim at a point the target will never reach
+fn time_to_close_gap(chaser: &Boid, target: &Boid, max_prediction_time: f32) -> f32 {
+    let dist = ((target.x_pos - chaser.x_pos).powi(2) + (target.y_pos - chaser.y_pos).powi(2)).sqrt();
+    let speed = chaser.speed();
+    if speed <= dist / max_prediction_time {
+        return max_prediction_time;
+    }
+    return dist / speed;
+}
+
+/// steer towards where the target is going to be rather than where it is now,
+/// so the pursuer cuts the target off instead of trailing behind it
+pub fn pursuit(pursuer: &Boid, target: &Boid, pursuit_factor: f32, max_prediction_time: f32) -> (f32, f32) {
+    let time_ahead = time_to_close_gap(pursuer, target, max_prediction_time);
+    let (predicted_x, predicted_y) = predicted_position(target, time_ahead);
+    return (
+        (predicted_x - pursuer.x_pos) * pursuit_factor,
+        (predicted_y - pursuer.y_pos) * pursuit_factor,
+    );
+}
+
+/// steer towards a spot `dist_behind_leader` behind the leader, along its heading,
+/// so followers trail the leader instead of piling on top of it
+pub fn follow_leader(follower: &Boid, leader: &Boid, dist_behind_leader: f32, follow_factor: f32) -> (f32, f32) {
+    let leader_speed = leader.speed();
+    // a stationary leader has no "behind", so follow it directly
+    let (behind_x, behind_y) = if leader_speed > 0.0 {
+        (leader.x_pos - leader.x_vel / leader_speed * dist_behind_leader, leader.y_pos - leader.y_vel / leader_speed * dist_behind_leader)
+    } else {
+        (leader.x_pos, leader.y_pos)
+    };
+    return (
+        (behind_x - follower.x_pos) * follow_factor,
+        (behind_y - follower.y_pos) * follow_factor,
+    );
+}
+
+/// steer directly away from where the threat is going to be, and towards the densest part of the
+/// nearby flock so that fleeing boids regroup rather than scattering.
+/// `neighbours` are the other boids the evader can see; the densest part is the neighbour
+/// with the most other neighbours within `group_radius` of it.
+pub fn evasion(evader: &Boid, threat: &Boid, neighbours: &[Boid],
+               evasion_factor: f32, grouping_factor: f32,
+               group_radius: f32, max_prediction_time: f32) -> (f32, f32) {
+    let time_ahead = time_to_close_gap(threat, evader, max_prediction_time);
+    let (predicted_x, predicted_y) = predicted_position(threat, time_ahead);
+    let away_x = evader.x_pos - predicted_x;
+    let away_y = evader.y_pos - predicted_y;
+    let dist_from_threat = (away_x * away_x + away_y * away_y).sqrt();
+
+    // flee at the same strength however close the threat is; a threat predicted to be exactly
+    // on top of the evader gives no direction to flee in, so only the regrouping applies
+    let (mut x_force, mut y_force) = if dist_from_threat > 0.0 {
+        (away_x / dist_from_threat * evasion_factor, away_y / dist_from_threat * evasion_factor)
+    } else {
+        (0.0, 0.0)
+    };
+
+    if let Some(densest) = densest_neighbour(neighbours, group_radius) {
+        x_force += (densest.x_pos - evader.x_pos) * grouping_factor;
+        y_force += (densest.y_pos - evader.y_pos) * grouping_factor;
+    }
+    return (x_force, y_force);
+}
+
+fn densest_neighbour(neighbours: &[Boid], group_radius: f32) -> Option<&Boid> {
+    let mut densest: Option<(&Boid, usize)> = None;
+    for neighbour in neighbours {
+        let num_close = neighbours
+            .iter()
+            .filter(|other| ((other.x_pos - neighbour.x_pos).powi(2) + (other.y_pos - neighbour.y_pos).powi(2)) < group_radius.powi(2))
+            .count();
+        // ties go to the first neighbour found, so the result doesn't flicker between equal groups
+        if densest.is_none_or(|(_, most_close)| num_close > most_close) {
+            densest = Some((neighbour, num_close));
+        }
+    }
+    return densest.map(|(neighbour, _)| neighbour);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pursuit_aims_ahead_of_moving_target() {
+        let pursuer = Boid::new(0.0, 0.0, 5.0, 0.0);
+        let target = Boid::new(10.0, 0.0, 0.0, 5.0);
+
+        // the pursuer closes the 10 unit gap in 2 frames, by which time the target has moved 10 units in y
+        let (x_force, y_force) = pursuit(&pursuer, &target, 0.5, 100.0);
+        assert_eq!(x_force, 5.0);
+        assert_eq!(y_force, 5.0);
+    }
+
+    #[test]
+    fn test_pursuit_of_stationary_target_aims_at_target() {
+        let pursuer = Boid::new(0.0, 0.0, 1.0, 1.0);
+        let target = Boid::new(-4.0, 3.0, 0.0, 0.0);
+        assert_eq!(pursuit(&pursuer, &target, 1.0, 10.0), (-4.0, 3.0));
+    }
+
+    #[test]
+    fn test_prediction_capped_for_stationary_pursuer() {
+        let pursuer = Boid::new(0.0, 0.0, 0.0, 0.0);
+        let target = Boid::new(10.0, 0.0, 1.0, 0.0);
+        assert_eq!(pursuit(&pursuer, &target, 1.0, 3.0), (13.0, 0.0));
+    }
+
+    #[test]
+    fn test_follow_leader_from_behind() {
+        let follower = Boid::new(0.0, 0.0, 0.0, 0.0);
+        let leader = Boid::new(10.0, 10.0, 0.0, 3.0);
+        assert_eq!(follow_leader(&follower, &leader, 4.0, 0.5), (5.0, 3.0));
+
+        let stationary_leader = Boid::new(10.0, 10.0, 0.0, 0.0);
+        assert_eq!(follow_leader(&follower, &stationary_leader, 4.0, 0.5), (5.0, 5.0));
+    }
+
+    #[test]
+    fn test_evasion_flees_predicted_threat_position() {
+        let evader = Boid::new(0.0, 0.0, 0.0, 0.0);
+        // the threat is heading to pass just above the evader rather than straight at it
+        let threat = Boid::new(-10.0, 10.0, 10.0, 0.0);
+
+        // one frame from now the threat will be at (0, 10), so the evader should flee straight down
+        let (x_force, y_force) = evasion(&evader, &threat, &[], 2.0, 0.0, 1.0, 1.0);
+        assert_eq!(x_force, 0.0);
+        assert_eq!(y_force, -2.0);
+    }
+
+    #[test]
+    fn test_evasion_biased_towards_densest_group() {
+        let evader = Boid::new(0.0, 0.0, 0.0, 0.0);
+        let threat = Boid::new(0.0, 100.0, 0.0, 0.0);
+        let neighbours = [
+            Boid::new(-10.0, 0.0, 0.0, 0.0),
+            Boid::new(10.0, 0.0, 0.0, 0.0),
+            Boid::new(10.5, 0.5, 0.0, 0.0),
+            Boid::new(10.0, 1.0, 0.0, 0.0),
+        ];
+
+        let (x_force, y_force) = evasion(&evader, &threat, &neighbours, 1.0, 0.1, 2.0, 1.0);
+        assert_eq!(x_force, 1.0);
+        assert_eq!(y_force, -1.0);
+    }
+}
@@ -0,0 +1,267 @@
+//! Export of a single frame as an SVG image, for figures of flock configurations.
+//! Each boid is drawn as a triangle pointing along its velocity, any uneaten food as a dot,
+//! the path the flock is following as a line, the walls of any arena, obstacles as filled circles,
+//! and the reach of any force sources as dashed circles. Any predator is drawn as a larger
+//! triangle, coloured by what it's doing. Boids can be
+//! coloured by the cluster they're in, and the colours come from a [`Theme`].
+//!
+//! The drawing is done by [`SvgRenderer`], which can be handed to `render::draw_flock` like any other renderer.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::arena::ArenaShape;
+use crate::food::FoodSource;
+use crate::force_field::ForceSource;
+use crate::predator::Predator;
+use crate::render::{draw_flock, BoidColor, Bounds, Renderer};
+use crate::theme::{hex, Theme};
+use crate::{Boid, Flock};
+
+pub use crate::render::ColorMode;
+
+// in world units; the tip of the triangle is this far in front of the boid's position
+pub(crate) const BOID_LENGTH: f32 = 2.0;
+// space left around the outermost boids so their triangles aren't clipped
+const MARGIN: f32 = 2.0 * BOID_LENGTH;
+const FOOD_RADIUS: f32 = BOID_LENGTH / 2.0;
+pub(crate) const PREDATOR_LENGTH: f32 = 2.0 * BOID_LENGTH;
+
+/// builds up a frame as SVG markup
+pub struct SvgRenderer<'a> {
+    theme: &'a Theme,
+    svg: String,
+}
+
+impl<'a> SvgRenderer<'a> {
+    pub fn new(theme: &'a Theme) -> SvgRenderer<'a> {
+        return SvgRenderer { theme, svg: String::new() };
+    }
+
+    /// the markup drawn so far, which is a whole SVG image once the frame has ended
+    pub fn into_svg(self) -> String {
+        return self.svg;
+    }
+}
+
+impl Renderer for SvgRenderer<'_> {
+    fn begin_frame(&mut self, bounds: Option<Bounds>) {
+        let (min_x, min_y, width, height) = view_box(bounds);
+        let _ = writeln!(self.svg, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#, min_x, min_y, width, height);
+        let _ = writeln!(self.svg, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#, min_x, min_y, width, height, hex(self.theme.background));
+    }
+
+    fn draw_force_source(&mut self, source: &ForceSource) {
+        let _ = writeln!(self.svg, r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="{}" stroke-dasharray="1"/>"#,
+                         source.centre.0, source.centre.1, source.radius, hex(self.theme.path));
+    }
+
+    fn draw_walls(&mut self, shape: &ArenaShape) {
+        match shape {
+            ArenaShape::Polygon(vertices) => {
+                let _ = writeln!(self.svg, r#"<polygon points="{}" fill="none" stroke="{}"/>"#, points_attribute(vertices), hex(self.theme.boid));
+            }
+            ArenaShape::Circle { centre, radius } => {
+                let _ = writeln!(self.svg, r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="{}"/>"#, centre.0, centre.1, radius, hex(self.theme.boid));
+            }
+        }
+    }
+
+    fn draw_wall(&mut self, start: (f32, f32), end: (f32, f32)) {
+        let _ = writeln!(self.svg, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}"/>"#, start.0, start.1, end.0, end.1, hex(self.theme.boid));
+    }
+
+    fn draw_path(&mut self, points: &[(f32, f32)]) {
+        let _ = writeln!(self.svg, r#"<polyline points="{}" fill="none" stroke="{}"/>"#, points_attribute(points), hex(self.theme.path));
+    }
+
+    fn draw_obstacle(&mut self, centre: (f32, f32), radius: f32) {
+        let _ = writeln!(self.svg, r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#, centre.0, centre.1, radius, hex(self.theme.path));
+    }
+
+    fn draw_food(&mut self, food: &FoodSource) {
+        let _ = writeln!(self.svg, r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#, food.x_pos, food.y_pos, FOOD_RADIUS, hex(self.theme.food));
+    }
+
+    fn draw_boid(&mut self, boid: &Boid, color: BoidColor) {
+        let color = self.theme.boid_color(color);
+        // tagged with the boid's id, so the same boid can be picked out across frames
+        let _ = writeln!(self.svg, r#"<polygon points="{}" fill="{}" data-boid="{}"/>"#,
+                         points_attribute(&triangle_for_boid(boid, BOID_LENGTH)), hex(color), boid.id.0);
+    }
+
+    fn draw_predator(&mut self, predator: &Predator) {
+        let _ = writeln!(self.svg, r#"<polygon points="{}" fill="{}" data-predator-state="{}"/>"#,
+                         points_attribute(&triangle_for_boid(&predator.boid, PREDATOR_LENGTH)), hex(self.theme.predator_color(predator.hunt_state())),
+                         predator.hunt_state().map_or("player", |state| state.name()));
+    }
+
+    fn end_frame(&mut self) {
+        self.svg.push_str("</svg>\n");
+    }
+}
+
+pub fn frame_to_svg(flock: &Flock, color_mode: ColorMode, theme: &Theme) -> String {
+    profile_scope!("frame_to_svg");
+    let mut renderer = SvgRenderer::new(theme);
+    draw_flock(flock, color_mode, &mut renderer);
+    return renderer.into_svg();
+}
+
+pub fn write_svg(flock: &Flock, color_mode: ColorMode, theme: &Theme, path: &Path) -> io::Result<()> {
+    return fs::write(path, frame_to_svg(flock, color_mode, theme));
+}
+
+/// runs the flock forward by `num_ticks` frames without any display and writes the frame reached
+pub fn export_frame_after_ticks(flock: &mut Flock, num_ticks: usize, color_mode: ColorMode, theme: &Theme, path: &Path) -> io::Result<()> {
+    for _ in 0..num_ticks {
+        flock.step();
+    }
+    return write_svg(flock, color_mode, theme, path);
+}
+
+/// the tip, then the two back corners, of a triangle centred on the boid and facing along its velocity.
+/// The tip is `length` in front of the boid
+pub(crate) fn triangle_for_boid(boid: &Boid, length: f32) -> [(f32, f32); 3] {
+    let speed = boid.speed();
+    // a stationary boid has no heading, so just face it along the x axis
+    let (heading_x, heading_y) = if speed > 0.0 {
+        (boid.x_vel / speed, boid.y_vel / speed)
+    } else {
+        (1.0, 0.0)
+    };
+    let half_width = length / 3.0;
+    let back_x = boid.x_pos - heading_x * length / 2.0;
+    let back_y = boid.y_pos - heading_y * length / 2.0;
+    return [
+        (boid.x_pos + heading_x * length, boid.y_pos + heading_y * length),
+        (back_x - heading_y * half_width, back_y + heading_x * half_width),
+        (back_x + heading_y * half_width, back_y - heading_x * half_width),
+    ];
+}
+
+fn points_attribute(points: &[(f32, f32)]) -> String {
+    let points: Vec<String> = points.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+    return points.join(" ");
+}
+
+fn view_box(bounds: Option<Bounds>) -> (f32, f32, f32, f32) {
+    let Some(Bounds { min, max }) = bounds else {
+        return (-MARGIN, -MARGIN, 2.0 * MARGIN, 2.0 * MARGIN);
+    };
+    return (min.0 - MARGIN, min.1 - MARGIN, max.0 - min.0 + 2.0 * MARGIN, max.1 - min.1 + 2.0 * MARGIN);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Arena;
+    use crate::food::Foraging;
+    use crate::force_field::{ForceField, ForceKind, ForceSource};
+    use crate::hunting::{HuntState, Hunting, HuntingBehaviour};
+    use crate::path::{Path, PathFollowing};
+    use crate::theme::DARK;
+
+    #[test]
+    fn test_one_triangle_per_boid() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(1.0, 1.0, 1.0, 0.0), Boid::new(20.0, 5.0, 0.0, 0.0), Boid::new(-3.0, 7.0, 0.0, -2.0)]);
+
+        let svg = frame_to_svg(&flock, ColorMode::Plain, &Theme::default());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polygon").count(), 3);
+        assert!(svg.contains(r#"data-boid="3""#));
+        assert_eq!(svg.matches("<circle").count(), 0);
+        assert_eq!(svg.matches("<polyline").count(), 0);
+    }
+
+    #[test]
+    fn test_path_drawn() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.set_path_following(PathFollowing::new(Path::polyline(vec![(0.0, 0.0), (5.0, 5.0), (10.0, 0.0)]).unwrap(), 0.1, 1.0).unwrap());
+
+        let svg = frame_to_svg(&flock, ColorMode::Plain, &Theme::default());
+        assert!(svg.contains(r#"<polyline points="0,0 5,5 10,0""#));
+    }
+
+    #[test]
+    fn test_arena_walls_drawn() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.set_arena(Arena::polygon(vec![(0.0, 0.0), (40.0, 0.0), (0.0, 30.0)]).unwrap());
+
+        let svg = frame_to_svg(&flock, ColorMode::Plain, &Theme::default());
+        assert!(svg.contains(r#"<polygon points="0,0 40,0 0,30" fill="none""#));
+
+        flock.set_arena(Arena::circle((0.0, 0.0), 20.0).unwrap());
+        let svg = frame_to_svg(&flock, ColorMode::Plain, &Theme::default());
+        assert!(svg.contains(r#"<circle cx="0" cy="0" r="20" fill="none""#));
+    }
+
+    #[test]
+    fn test_force_sources_drawn() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        let source = ForceSource { kind: ForceKind::Attractor, centre: (5.0, 5.0), radius: 8.0, strength: 1.0 };
+        flock.set_force_field(ForceField::new(vec![source]).unwrap());
+
+        let svg = frame_to_svg(&flock, ColorMode::Plain, &Theme::default());
+        assert!(svg.contains(r#"<circle cx="5" cy="5" r="8" fill="none""#));
+    }
+
+    #[test]
+    fn test_only_uneaten_food_drawn() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0)];
+        flock.set_foraging(Foraging::new(vec![FoodSource::new(0.1, 0.0), FoodSource::new(30.0, 30.0)], 0.1, 10.0, 1.0, 100).unwrap());
+        flock.step();
+
+        let svg = frame_to_svg(&flock, ColorMode::Plain, &Theme::default());
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(svg.contains(r#"cx="30" cy="30""#));
+    }
+
+    #[test]
+    fn test_colours_from_theme() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 1.0, 0.0)];
+
+        let svg = frame_to_svg(&flock, ColorMode::Plain, &DARK);
+        assert!(svg.contains(&format!(r#"fill="{}""#, hex(DARK.background))));
+        assert!(svg.contains(&format!(r#"fill="{}""#, hex(DARK.boid))));
+    }
+
+    #[test]
+    fn test_boids_coloured_by_cluster() {
+        let mut flock = Flock::new(0, 1.0, 5.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(2.0, 0.0, 0.0, 0.0), Boid::new(100.0, 0.0, 0.0, 0.0)];
+
+        let theme = Theme::default();
+        let svg = frame_to_svg(&flock, ColorMode::ByCluster, &theme);
+        assert_eq!(svg.matches(&hex(theme.cluster_color(0))).count(), 2);
+        assert_eq!(svg.matches(&hex(theme.cluster_color(1))).count(), 1);
+    }
+
+    #[test]
+    fn test_predator_coloured_by_hunt_state() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 1.0, 0.0)];
+        let mut predator = Predator::new(Boid::new(30.0, 0.0, -1.0, 0.0), 0.5, 0.1, 2.0, 1.0, 10.0, 0.5).unwrap();
+        predator.set_hunting(Hunting::new(HuntingBehaviour::default()).unwrap());
+        flock.set_predator(predator);
+
+        let theme = Theme::default();
+        let svg = frame_to_svg(&flock, ColorMode::Plain, &theme);
+        assert_eq!(svg.matches("<polygon").count(), 2);
+        assert!(svg.contains(&format!(r#"fill="{}" data-predator-state="patrol""#, hex(theme.predator_color(Some(HuntState::Patrol))))));
+    }
+
+    #[test]
+    fn test_triangle_points_along_velocity() {
+        let boid = Boid::new(10.0, 10.0, 0.0, 5.0);
+        let [tip, left, right] = triangle_for_boid(&boid, BOID_LENGTH);
+        assert_eq!(tip, (10.0, 10.0 + BOID_LENGTH));
+        // both back corners are behind the boid
+        assert!(left.1 < boid.y_pos && right.1 < boid.y_pos);
+    }
+}
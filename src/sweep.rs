@@ -0,0 +1,632 @@
+//! Headless parameter sweeps: run the simulation once for every combination of the given
+//! factors and radii, all from the same seeded start, and write a CSV summarising each run.
+//!
+//! `boids sweep --repulsion 0:1:5 --cohesion 0.1 --ticks 500 --out sweep.csv`
+//!
+//! Each parameter is either a single value or `start:end:steps`, with `steps` values spread
+//! evenly from `start` to `end` inclusive. `--distribution` sets where the boids start, in the
+//! form taken by `InitialDistribution::parse`, or `--formation` starts them in one of the fixed
+//! formations taken by `Formation::parse`, heading along at `--formation-speed`. `--min-speed`
+//! and `--max-speed` keep every boid's speed between them, and `--disable` switches off a
+//! comma-separated list of rules by their `Rule::name`s, e.g. `--disable alignment,cohesion`.
+//! `--sub-steps` checks each step's movement for collisions in that many parts; see `Flock::set_sub_steps`.
+//! Combinations that don't make a valid flock (e.g. a crowding radius larger than the local
+//! radius) are skipped.
+//!
+//! Each `--obstacle` adds an obstacle that stands still, swings back and forth or follows a path, in
+//! the form taken by `Obstacle::parse`, and `--obstacle-avoidance` lets boids steer round them, in the
+//! form taken by `ObstacleAvoidance::parse`. `--path` gives the flock a route to follow, in the
+//! form taken by `PathFollowing::parse`.
+//!
+//! `--arena` keeps the flock inside walls, in the form taken by `Arena::parse`. Boids bounce off
+//! the walls or, with `--wall-response slide`, slide along them, and `--whiskers` lets them see
+//! walls coming, in the form taken by `Whiskers::parse`. `--margin` pushes boids back from the
+//! walls, in the form taken by `SoftMargin::parse`. Each `--force-source` adds an attractor,
+//! repeller or vortex, in the form taken by `ForceSource::parse`. `--density-response` scales each
+//! boid's repulsion and cohesion by how crowded it is, in the form taken by `DensityResponse::parse`;
+//! the mean and most crowded boids' final local densities are in the CSV to show its effect.
+//! `--weather` changes the rain, wind and temperature over each run, in the form taken by `Weather::parse`.
+//! `--food` puts food out for the flock to forage, in the form taken by `Foraging::parse`.
+//! `--boid-states` gives every boid a state, flocking, fleeing the predator, foraging or resting,
+//! that changes how it flies, in the form taken by `StateRules::parse`; see `boid_state.rs`. Boids
+//! only forage where there's `--food` to see, and only flee a predator a `--scenario` releases.
+//! `--scenario` plays out a scenario file over each run, ramping parameters and spawning predators;
+//! see `scenario.rs`. A parameter it has keyframes for overrides the swept value once it reaches the first.
+//!
+//! `--cluster-series-out` also writes the number of clusters after every tick of every run to a
+//! second CSV, for studying how the flock fragments. Its `run` column matches the main CSV's.
+//!
+//! `--cluster-events-out` writes every split and merge of clusters in every run to another CSV,
+//! with the tick each happened in and the clusters' numbers and sizes, as in `FlockEvent`.
+//!
+//! `--report report.html` (or `report.md`) writes a report of the sweep, with plots of every run
+//! over time; see `report.rs`.
+//!
+//! With the `metrics` feature, `--metrics-address 0.0.0.0:9100` serves the progress of the sweep
+//! on `/metrics` while it runs; see `metrics.rs`.
+//!
+//! With the `parquet` feature, `--trajectories-out trajectories.parquet` also writes every boid's
+//! position and velocity after every tick of every run; see `parquet_export.rs`.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::thread;
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+use anyhow::{anyhow, bail, Context, Result};
+use crate::arena::{Arena, SoftMargin, WallResponse, Whiskers};
+use crate::boid_state::{BoidStates, StateRules};
+use crate::density::DensityResponse;
+use crate::distribution::InitialDistribution;
+use crate::events::FlockEvent;
+use crate::force_field::{ForceField, ForceSource};
+use crate::food::Foraging;
+use crate::formation::Formation;
+use crate::obstacle::{Obstacle, ObstacleAvoidance, Obstacles};
+use crate::path::PathFollowing;
+#[cfg(feature = "metrics")]
+use crate::metrics::{self, Metrics};
+#[cfg(feature = "parquet")]
+use crate::parquet_export::{write_trajectories, Trajectory};
+use crate::report::write_report;
+use crate::scenario::Scenario;
+use crate::stats::{count_collisions, count_groups, local_densities, polarization};
+use crate::weather::Weather;
+use crate::{check_finite, validate_speed_limits, CreationError, Flock, InvalidFlockConfig, Rule};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ParamRange {
+    pub start: f32,
+    pub end: f32,
+    pub steps: usize,
+}
+
+impl ParamRange {
+    pub fn single(value: f32) -> ParamRange {
+        return ParamRange { start: value, end: value, steps: 1 };
+    }
+
+    pub fn parse(text: &str) -> Result<ParamRange> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        return match parts.as_slice() {
+            [value] => Ok(ParamRange::single(parse_float(value)?)),
+            [start, end, steps] => {
+                let steps = steps.parse::<usize>().with_context(|| format!("'{}' in '{}' is not a number of steps", steps, text))?;
+                if steps == 0 {
+                    bail!("'{}' has no steps", text);
+                }
+                Ok(ParamRange { start: parse_float(start)?, end: parse_float(end)?, steps })
+            }
+            _ => Err(anyhow!("'{}' should be a value or start:end:steps", text)),
+        };
+    }
+
+    pub fn values(&self) -> Vec<f32> {
+        if self.steps == 1 {
+            return vec![self.start];
+        }
+        return (0..self.steps)
+            .map(|step| self.start + (self.end - self.start) * step as f32 / (self.steps - 1) as f32)
+            .collect();
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SweepConfig {
+    pub repulsion: ParamRange,
+    pub adhesion: ParamRange,
+    pub cohesion: ParamRange,
+    pub crowding_radius: ParamRange,
+    pub local_radius: ParamRange,
+    pub flock_size: usize,
+    pub distribution: InitialDistribution,
+    pub formation: Option<Formation>, // used instead of the distribution when set
+    pub formation_speed: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub sub_steps: u32, // see `Flock::set_sub_steps`
+    pub disabled_rules: Vec<Rule>,
+    pub arena: Option<Arena>,
+    pub wall_response: WallResponse,
+    pub whiskers: Option<Whiskers>,
+    pub soft_margin: Option<SoftMargin>,
+    pub force_sources: Vec<ForceSource>,
+    pub obstacles: Vec<Obstacle>,
+    pub obstacle_avoidance: Option<ObstacleAvoidance>,
+    pub path_following: Option<PathFollowing>,
+    pub foraging: Option<Foraging>,
+    pub density_response: Option<DensityResponse>,
+    pub weather: Option<Weather>,
+    pub boid_states: Option<StateRules>,
+    pub scenario: Option<Scenario>,
+    pub num_ticks: usize,
+    pub seed: u64,
+    pub collision_dist: f32, // boids closer than this count as colliding
+    pub parallel: bool,
+    pub output: PathBuf,
+    pub cluster_series_output: Option<PathBuf>,
+    pub cluster_events_output: Option<PathBuf>,
+    pub report_output: Option<PathBuf>, // HTML if it ends in .html, otherwise Markdown
+    #[cfg(feature = "metrics")]
+    pub metrics_address: Option<String>, // where `run` serves metrics from, e.g. 0.0.0.0:9100
+    #[cfg(feature = "metrics")]
+    pub metrics: Option<Arc<Metrics>>, // every run records each of its steps here when set
+    #[cfg(feature = "parquet")]
+    pub trajectories_output: Option<PathBuf>,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        return SweepConfig {
+            repulsion: ParamRange::single(0.1),
+            adhesion: ParamRange::single(0.1),
+            cohesion: ParamRange::single(0.1),
+            crowding_radius: ParamRange::single(5.0),
+            local_radius: ParamRange::single(20.0),
+            flock_size: 100,
+            distribution: InitialDistribution::default(),
+            formation: None,
+            formation_speed: 1.0,
+            min_speed: 0.0,
+            max_speed: f32::INFINITY,
+            sub_steps: 1,
+            disabled_rules: Vec::new(),
+            arena: None,
+            wall_response: WallResponse::Reflect,
+            whiskers: None,
+            soft_margin: None,
+            force_sources: Vec::new(),
+            obstacles: Vec::new(),
+            obstacle_avoidance: None,
+            path_following: None,
+            foraging: None,
+            density_response: None,
+            weather: None,
+            boid_states: None,
+            scenario: None,
+            num_ticks: 1000,
+            seed: 0,
+            collision_dist: 1.0,
+            parallel: false,
+            output: PathBuf::from("sweep.csv"),
+            cluster_series_output: None,
+            cluster_events_output: None,
+            report_output: None,
+            #[cfg(feature = "metrics")]
+            metrics_address: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            #[cfg(feature = "parquet")]
+            trajectories_output: None,
+        };
+    }
+}
+
+impl SweepConfig {
+    /// `args` are the arguments after `sweep`
+    pub fn from_args(args: &[String]) -> Result<SweepConfig> {
+        let mut config = SweepConfig::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            if flag == "--parallel" {
+                config.parallel = true;
+                continue;
+            }
+            let value = args.next().ok_or_else(|| anyhow!("{} needs a value", flag))?;
+            match flag.as_str() {
+                "--repulsion" => config.repulsion = ParamRange::parse(value)?,
+                "--adhesion" => config.adhesion = ParamRange::parse(value)?,
+                "--cohesion" => config.cohesion = ParamRange::parse(value)?,
+                "--crowding-radius" => config.crowding_radius = ParamRange::parse(value)?,
+                "--local-radius" => config.local_radius = ParamRange::parse(value)?,
+                "--flock-size" => config.flock_size = value.parse().with_context(|| format!("bad --flock-size '{}'", value))?,
+                "--distribution" => config.distribution = InitialDistribution::parse(value)?,
+                "--formation" => config.formation = Some(Formation::parse(value)?),
+                "--formation-speed" => config.formation_speed = value.parse().with_context(|| format!("bad --formation-speed '{}'", value))?,
+                "--arena" => config.arena = Some(Arena::parse(value)?),
+                "--wall-response" => config.wall_response = WallResponse::parse(value)?,
+                "--whiskers" => config.whiskers = Some(Whiskers::parse(value)?),
+                "--margin" => config.soft_margin = Some(SoftMargin::parse(value)?),
+                "--force-source" => config.force_sources.push(ForceSource::parse(value)?),
+                "--obstacle" => config.obstacles.push(Obstacle::parse(value)?),
+                "--obstacle-avoidance" => config.obstacle_avoidance = Some(ObstacleAvoidance::parse(value)?),
+                "--path" => config.path_following = Some(PathFollowing::parse(value)?),
+                "--food" => config.foraging = Some(Foraging::parse(value)?),
+                "--density-response" => config.density_response = Some(DensityResponse::parse(value)?),
+                "--weather" => config.weather = Some(Weather::parse(value)?),
+                "--boid-states" => config.boid_states = Some(StateRules::parse(value)?),
+                "--scenario" => config.scenario = Some(Scenario::load(&PathBuf::from(value))?),
+                "--min-speed" => config.min_speed = value.parse().with_context(|| format!("bad --min-speed '{}'", value))?,
+                "--max-speed" => config.max_speed = value.parse().with_context(|| format!("bad --max-speed '{}'", value))?,
+                "--sub-steps" => config.sub_steps = value.parse().with_context(|| format!("bad --sub-steps '{}'", value))?,
+                "--disable" => config.disabled_rules = value.split(',').map(Rule::parse).collect::<Result<Vec<Rule>>>()?,
+                "--ticks" => config.num_ticks = value.parse().with_context(|| format!("bad --ticks '{}'", value))?,
+                "--seed" => config.seed = value.parse().with_context(|| format!("bad --seed '{}'", value))?,
+                "--collision-dist" => config.collision_dist = value.parse().with_context(|| format!("bad --collision-dist '{}'", value))?,
+                "--out" => config.output = PathBuf::from(value),
+                "--cluster-series-out" => config.cluster_series_output = Some(PathBuf::from(value)),
+                "--cluster-events-out" => config.cluster_events_output = Some(PathBuf::from(value)),
+                "--report" => config.report_output = Some(PathBuf::from(value)),
+                #[cfg(feature = "metrics")]
+                "--metrics-address" => config.metrics_address = Some(value.clone()),
+                #[cfg(feature = "parquet")]
+                "--trajectories-out" => config.trajectories_output = Some(PathBuf::from(value)),
+                _ => bail!("unknown sweep option {}", flag),
+            }
+        }
+        // checked here rather than in every run
+        ForceField::new(config.force_sources.clone())?;
+        let mut errors = validate_speed_limits(config.min_speed, config.max_speed);
+        errors.extend(check_finite(config.formation_speed, "formation speed"));
+        errors.extend(check_finite(config.collision_dist, "collision distance"));
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors }.into());
+        }
+        if config.sub_steps == 0 {
+            return Err(InvalidFlockConfig { errors: vec![CreationError::NoSubSteps] }.into());
+        }
+        return Ok(config);
+    }
+
+    /// every combination of the swept parameters, valid or not
+    pub fn runs(&self) -> Vec<RunParams> {
+        let mut runs = Vec::new();
+        for repulsion in self.repulsion.values() {
+            for adhesion in self.adhesion.values() {
+                for cohesion in self.cohesion.values() {
+                    for crowding_radius in self.crowding_radius.values() {
+                        for local_radius in self.local_radius.values() {
+                            runs.push(RunParams { repulsion, adhesion, cohesion, crowding_radius, local_radius });
+                        }
+                    }
+                }
+            }
+        }
+        return runs;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RunParams {
+    pub repulsion: f32,
+    pub adhesion: f32,
+    pub cohesion: f32,
+    pub crowding_radius: f32,
+    pub local_radius: f32,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RunSummary {
+    pub params: RunParams,
+    pub final_polarization: f32,
+    pub total_collisions: usize, // summed over every tick
+    pub final_group_count: usize,
+    pub final_mean_density: f32, // the average number of boids in each boid's local radius
+    pub final_max_density: usize, // the most boids in any one boid's local radius
+    pub cluster_counts: Vec<usize>, // after each tick
+    pub polarization_series: Vec<f32>, // after each tick
+    pub cluster_events: Vec<(usize, FlockEvent)>, // the clusters splitting and merging, with the tick, from 1, each happened in
+    #[cfg(feature = "parquet")]
+    pub trajectory: Trajectory, // empty unless the config has a trajectories output
+}
+
+/// None if the parameters don't make a valid flock
+pub fn run_once(params: RunParams, config: &SweepConfig) -> Option<RunSummary> {
+    let mut flock = Flock::new(config.flock_size, params.crowding_radius, params.local_radius,
+                               params.repulsion, params.adhesion, params.cohesion).ok()?;
+    flock.set_speed_limits(config.min_speed, config.max_speed).ok()?;
+    flock.set_sub_steps(config.sub_steps).ok()?;
+    flock.set_density_response(config.density_response);
+    if let Some(weather) = &config.weather {
+        flock.set_weather(weather.clone());
+    }
+    if let Some(rules) = &config.boid_states {
+        flock.set_boid_states(BoidStates::new(rules.clone()).ok()?);
+    }
+    for rule in &config.disabled_rules {
+        flock.set_rule_enabled(*rule, false);
+    }
+    // set first, so randomly placed boids start inside it
+    if let Some(arena) = &config.arena {
+        let mut arena = arena.clone();
+        arena.wall_response = config.wall_response;
+        arena.whiskers = config.whiskers;
+        arena.soft_margin = config.soft_margin;
+        flock.set_arena(arena);
+    }
+    if !config.force_sources.is_empty() {
+        flock.set_force_field(ForceField::new(config.force_sources.clone()).ok()?);
+    }
+    if !config.obstacles.is_empty() {
+        let mut obstacles = Obstacles::new(config.obstacles.clone());
+        obstacles.avoidance = config.obstacle_avoidance;
+        flock.set_obstacles(obstacles);
+    }
+    if let Some(path_following) = &config.path_following {
+        flock.set_path_following(path_following.clone());
+    }
+    if let Some(foraging) = &config.foraging {
+        flock.set_foraging(foraging.clone());
+    }
+    // the distribution and formation were validated when the config was made
+    match &config.formation {
+        Some(formation) => flock.arrange_in_formation(formation, config.formation_speed).ok()?,
+        None => flock.randomise_boids(&config.distribution, config.seed).ok()?,
+    }
+    let mut total_collisions = 0;
+    let mut cluster_counts = Vec::with_capacity(config.num_ticks);
+    let mut polarization_series = Vec::with_capacity(config.num_ticks);
+    let mut cluster_events = Vec::new();
+    #[cfg(feature = "parquet")]
+    let mut trajectory = Trajectory::new();
+    for tick in 1..=config.num_ticks {
+        if let Some(scenario) = &config.scenario {
+            scenario.apply(tick as u32 - 1, &mut flock);
+        }
+        flock.step();
+        cluster_events.extend(flock
+            .events()
+            .iter()
+            .filter(|event| matches!(event, FlockEvent::ClusterSplit { .. } | FlockEvent::ClusterMerge { .. }))
+            .map(|event| (tick, *event)));
+        let collisions = count_collisions(flock.boids(), config.collision_dist);
+        total_collisions += collisions;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &config.metrics {
+            metrics.record_tick(flock.boids(), collisions);
+        }
+        cluster_counts.push(flock.cluster_labels().iter().max().map_or(0, |max_label| max_label + 1));
+        polarization_series.push(polarization(flock.boids()));
+        #[cfg(feature = "parquet")]
+        if config.trajectories_output.is_some() {
+            trajectory.record_tick(tick, flock.boids());
+        }
+    }
+    let final_densities = local_densities(flock.boids(), params.local_radius);
+    return Some(RunSummary {
+        params,
+        final_polarization: polarization(flock.boids()),
+        total_collisions,
+        final_group_count: count_groups(flock.boids(), params.local_radius),
+        final_mean_density: final_densities.iter().sum::<usize>() as f32 / final_densities.len().max(1) as f32,
+        final_max_density: final_densities.iter().copied().max().unwrap_or(0),
+        cluster_counts,
+        polarization_series,
+        cluster_events,
+        #[cfg(feature = "parquet")]
+        trajectory,
+    });
+}
+
+/// summaries are in the same order as `config.runs()`, whether or not the runs are done in parallel
+pub fn run_sweep(config: &SweepConfig) -> Vec<RunSummary> {
+    let runs = config.runs();
+    if !config.parallel {
+        return runs.into_iter().filter_map(|params| run_once(params, config)).collect();
+    }
+
+    let num_threads = thread::available_parallelism().map_or(1, |num| num.get());
+    let runs_per_thread = runs.len().div_ceil(num_threads).max(1);
+    return thread::scope(|scope| {
+        let handles: Vec<_> = runs
+            .chunks(runs_per_thread)
+            .map(|chunk| scope.spawn(move || chunk.iter().filter_map(|params| run_once(*params, config)).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().expect("sweep thread panicked")).collect()
+    });
+}
+
+pub fn write_csv(summaries: &[RunSummary], writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "run,repulsion,adhesion,cohesion,crowding_radius,local_radius,final_polarization,total_collisions,final_group_count,final_mean_density,final_max_density")?;
+    for (run, summary) in summaries.iter().enumerate() {
+        let params = summary.params;
+        writeln!(writer, "{},{},{},{},{},{},{},{},{},{},{}",
+                 run, params.repulsion, params.adhesion, params.cohesion, params.crowding_radius, params.local_radius,
+                 summary.final_polarization, summary.total_collisions, summary.final_group_count,
+                 summary.final_mean_density, summary.final_max_density)?;
+    }
+    return Ok(());
+}
+
+pub fn write_cluster_series_csv(summaries: &[RunSummary], writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "run,tick,cluster_count")?;
+    for (run, summary) in summaries.iter().enumerate() {
+        for (tick, cluster_count) in summary.cluster_counts.iter().enumerate() {
+            writeln!(writer, "{},{},{}", run, tick + 1, cluster_count)?;
+        }
+    }
+    return Ok(());
+}
+
+pub fn write_cluster_events_csv(summaries: &[RunSummary], writer: &mut impl Write) -> io::Result<()> {
+    // `cluster` is the one that split, as numbered before the tick, or that was merged into, as numbered after it
+    writeln!(writer, "run,tick,event,cluster,main,main_size,part,part_size")?;
+    for (run, summary) in summaries.iter().enumerate() {
+        for (tick, event) in &summary.cluster_events {
+            let (name, cluster, main, main_size, part, part_size) = match *event {
+                FlockEvent::ClusterSplit { from, main, main_size, part, part_size } => ("split", from, main, main_size, part, part_size),
+                FlockEvent::ClusterMerge { into, main, main_size, part, part_size } => ("merge", into, main, main_size, part, part_size),
+                _ => continue,
+            };
+            writeln!(writer, "{},{},{},{},{},{},{},{}", run, tick, name, cluster, main, main_size, part, part_size)?;
+        }
+    }
+    return Ok(());
+}
+
+/// starts the metrics server if the config asks for one, and has the runs report to it
+#[cfg(feature = "metrics")]
+fn start_serving_metrics(mut config: SweepConfig) -> Result<SweepConfig> {
+    if let Some(metrics_address) = &config.metrics_address {
+        let metrics = Arc::new(Metrics::new());
+        let address = metrics::serve(metrics_address, Arc::clone(&metrics)).with_context(|| format!("couldn't serve metrics on {}", metrics_address))?;
+        eprintln!("serving metrics on http://{}/metrics", address);
+        config.metrics = Some(metrics);
+    }
+    return Ok(config);
+}
+
+/// the `sweep` subcommand
+pub fn run(args: &[String]) -> Result<()> {
+    let config = SweepConfig::from_args(args)?;
+    #[cfg(feature = "metrics")]
+    let config = start_serving_metrics(config)?;
+    let summaries = run_sweep(&config);
+    let skipped = config.runs().len() - summaries.len();
+    if skipped > 0 {
+        eprintln!("skipped {} invalid parameter combinations", skipped);
+    }
+    let file = File::create(&config.output).with_context(|| format!("couldn't create {}", config.output.display()))?;
+    let mut writer = BufWriter::new(file);
+    write_csv(&summaries, &mut writer)?;
+    writer.flush()?;
+
+    if let Some(cluster_series_output) = &config.cluster_series_output {
+        let file = File::create(cluster_series_output).with_context(|| format!("couldn't create {}", cluster_series_output.display()))?;
+        let mut writer = BufWriter::new(file);
+        write_cluster_series_csv(&summaries, &mut writer)?;
+        writer.flush()?;
+    }
+    if let Some(cluster_events_output) = &config.cluster_events_output {
+        let file = File::create(cluster_events_output).with_context(|| format!("couldn't create {}", cluster_events_output.display()))?;
+        let mut writer = BufWriter::new(file);
+        write_cluster_events_csv(&summaries, &mut writer)?;
+        writer.flush()?;
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(trajectories_output) = &config.trajectories_output {
+        let file = File::create(trajectories_output).with_context(|| format!("couldn't create {}", trajectories_output.display()))?;
+        write_trajectories(summaries.iter().map(|summary| &summary.trajectory), BufWriter::new(file))
+            .with_context(|| format!("couldn't write {}", trajectories_output.display()))?;
+    }
+    if let Some(report_output) = &config.report_output {
+        let command = format!("boids sweep {}", args.join(" "));
+        write_report(&command, &config, &summaries, report_output).with_context(|| format!("couldn't write {}", report_output.display()))?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::ArenaShape;
+
+    fn args(text: &str) -> Vec<String> {
+        return text.split_whitespace().map(str::to_string).collect();
+    }
+
+    #[test]
+    fn test_param_range_parsing() {
+        assert_eq!(ParamRange::parse("0.5").unwrap().values(), vec![0.5]);
+        assert_eq!(ParamRange::parse("0:1:5").unwrap().values(), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert!(ParamRange::parse("0:1").is_err());
+        assert!(ParamRange::parse("0:1:0").is_err());
+        assert!(ParamRange::parse("a:1:2").is_err());
+    }
+
+    #[test]
+    fn test_config_from_args() {
+        let config = SweepConfig::from_args(&args("--repulsion 0:1:3 --ticks 20 --parallel --distribution ring:50:5 --out runs.csv")).unwrap();
+        assert_eq!(config.repulsion, ParamRange { start: 0.0, end: 1.0, steps: 3 });
+        assert_eq!(config.distribution, InitialDistribution::Ring { radius: 50.0, thickness: 5.0 });
+        assert_eq!(config.num_ticks, 20);
+        assert!(config.parallel);
+        assert_eq!(config.output, PathBuf::from("runs.csv"));
+
+        let config = SweepConfig::from_args(&args("--formation v:3:30 --formation-speed 2")).unwrap();
+        assert_eq!(config.formation, Some(Formation::V { spacing: 3.0, half_angle_degrees: 30.0 }));
+        assert_eq!(config.formation_speed, 2.0);
+
+        let config = SweepConfig::from_args(&args("--wall-response slide --arena polygon:0,0;50,0;0,50")).unwrap();
+        assert_eq!(config.wall_response, WallResponse::Slide);
+        assert_eq!(SweepConfig::from_args(&args("--margin 10:0.2")).unwrap().soft_margin, Some(SoftMargin { width: 10.0, push_factor: 0.2 }));
+        assert_eq!(SweepConfig::from_args(&args("--whiskers 10:20:0.1")).unwrap().whiskers, Some(Whiskers { length: 10.0, spread_degrees: 20.0, steering_factor: 0.1 }));
+        assert_eq!(config.arena.unwrap().shape(), &ArenaShape::Polygon(vec![(0.0, 0.0), (50.0, 0.0), (0.0, 50.0)]));
+
+        let config = SweepConfig::from_args(&args("--force-source attractor:0,0:10:1 --force-source repeller:5,5:10:1")).unwrap();
+        assert_eq!(config.force_sources.len(), 2);
+        assert!(SweepConfig::from_args(&args("--force-source attractor:0,0:10:0")).is_err());
+
+        let config = SweepConfig::from_args(&args("--obstacle still:0,0:5 --obstacle path:2:1:0,0;10,0 --obstacle-avoidance 20:2:0.3")).unwrap();
+        assert_eq!(config.obstacles.len(), 2);
+        assert_eq!(config.obstacle_avoidance, Some(ObstacleAvoidance { look_ahead: 20.0, clearance: 2.0, steering_factor: 0.3 }));
+
+        let path_following = SweepConfig::from_args(&args("--path polyline:0.1:5:0,0;100,0")).unwrap().path_following.unwrap();
+        assert_eq!(path_following.path.points(), &[(0.0, 0.0), (100.0, 0.0)]);
+        assert_eq!(SweepConfig::from_args(&args("--food 0.05:40:2:300:100,0")).unwrap().foraging.unwrap().food_sources.len(), 1);
+
+        let config = SweepConfig::from_args(&args("--min-speed 0.5 --max-speed 4")).unwrap();
+        assert_eq!((config.min_speed, config.max_speed), (0.5, 4.0));
+        assert!(SweepConfig::from_args(&args("--min-speed 5 --max-speed 4")).is_err());
+        assert_eq!(SweepConfig::from_args(&args("--sub-steps 4")).unwrap().sub_steps, 4);
+        assert!(SweepConfig::from_args(&args("--sub-steps 0")).is_err());
+        for bad_args in ["--formation-speed NaN", "--formation-speed inf", "--collision-dist -inf"] {
+            let error = SweepConfig::from_args(&args(bad_args)).unwrap_err();
+            assert!(error.to_string().contains("ValueIsNotFinite"), "{}: {}", bad_args, error);
+        }
+
+        assert_eq!(SweepConfig::from_args(&args("--disable separation,force-field")).unwrap().disabled_rules, vec![Rule::Separation, Rule::ForceField]);
+        assert!(SweepConfig::from_args(&args("--disable separation,gravity")).is_err());
+
+        assert_eq!(SweepConfig::from_args(&args("--density-response 8:1:4")).unwrap().density_response, Some(DensityResponse::new(8.0, 1.0, 4.0).unwrap()));
+        assert!(SweepConfig::from_args(&args("--density-response 8:1:0.5")).is_err());
+        assert!(SweepConfig::from_args(&args("--weather 0:0:0,0:1;100:1:1,0:0.5")).unwrap().weather.is_some());
+
+        assert!(SweepConfig::from_args(&args("--ticks")).is_err());
+        assert!(SweepConfig::from_args(&args("--speed 3")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_combinations_skipped() {
+        let config = SweepConfig::from_args(&args("--crowding-radius 5:15:3 --local-radius 12 --flock-size 10 --ticks 3")).unwrap();
+        let summaries = run_sweep(&config);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[1].params.crowding_radius, 10.0);
+    }
+
+    #[test]
+    fn test_scenario_overrides_swept_parameter() {
+        let mut config = SweepConfig::from_args(&args("--repulsion 0:0.5:2 --flock-size 15 --ticks 10 --seed 3")).unwrap();
+        config.scenario = Some(Scenario::parse("0 repulsion 0.2").unwrap());
+        let summaries = run_sweep(&config);
+        assert_eq!(summaries[0].polarization_series, summaries[1].polarization_series);
+        assert!(SweepConfig::from_args(&args("--scenario no-such-scenario.txt")).is_err());
+    }
+
+    #[test]
+    fn test_parallel_sweep_matches_serial() {
+        let mut config = SweepConfig::from_args(&args("--repulsion 0:0.5:3 --cohesion 0:0.2:2 --flock-size 15 --ticks 10 --seed 3")).unwrap();
+        let serial = run_sweep(&config);
+        config.parallel = true;
+        assert_eq!(run_sweep(&config), serial);
+
+        let mut csv = Vec::new();
+        write_csv(&serial, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 7);
+
+        let mut cluster_series_csv = Vec::new();
+        write_cluster_series_csv(&serial, &mut cluster_series_csv).unwrap();
+        let cluster_series_csv = String::from_utf8(cluster_series_csv).unwrap();
+        assert_eq!(cluster_series_csv.lines().count(), 1 + 6 * 10);
+        assert!(cluster_series_csv.lines().nth(1).unwrap().starts_with("0,1,"));
+        assert_eq!(*serial[0].cluster_counts.last().unwrap(), serial[0].final_group_count);
+
+        let mut cluster_events_csv = Vec::new();
+        write_cluster_events_csv(&serial, &mut cluster_events_csv).unwrap();
+        let num_cluster_events: usize = serial.iter().map(|summary| summary.cluster_events.len()).sum();
+        assert_eq!(String::from_utf8(cluster_events_csv).unwrap().lines().count(), 1 + num_cluster_events);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_trajectories_recorded_when_asked_for() {
+        let config = SweepConfig::from_args(&args("--repulsion 0:0.5:2 --flock-size 12 --ticks 5")).unwrap();
+        assert!(run_sweep(&config).iter().all(|summary| summary.trajectory.is_empty()));
+
+        let config = SweepConfig::from_args(&args("--repulsion 0:0.5:2 --flock-size 12 --ticks 5 --trajectories-out runs.parquet")).unwrap();
+        assert_eq!(config.trajectories_output, Some(PathBuf::from("runs.parquet")));
+        assert!(run_sweep(&config).iter().all(|summary| summary.trajectory.len() == 12 * 5));
+    }
+}
@@ -0,0 +1,362 @@
+//! Drawing the flock in a terminal with ratatui, for machines without a GPU or a display. Boids
+//! are dots on a grid of Braille (or block) characters, scaled so the whole world drawn fits, and
+//! the bottom line shows the tick and whether the flock is paused.
+//!
+//! `boids terminal --flock-size 200 --fps 30 --marker braille --theme dark`
+//!
+//! Space pauses and carries on, . steps once while paused, H shows the controls and Q or Esc
+//! quits. Pausing and stepping are on the same keys as in the Bevy adapter.
+
+use std::time::Duration;
+use anyhow::{anyhow, bail, Context, Result};
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::Color;
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::{Canvas, Circle, Line, Points};
+use ratatui::widgets::{Paragraph, Widget, Wrap};
+use ratatui::DefaultTerminal;
+use crate::arena::ArenaShape;
+use crate::distribution::InitialDistribution;
+use crate::food::FoodSource;
+use crate::force_field::ForceSource;
+use crate::predator::Predator;
+use crate::render::{draw_flock, BoidColor, Bounds, ColorMode, Renderer};
+use crate::theme::{self, Rgb, Theme};
+use crate::{Boid, Flock};
+
+// the share of the world's width and height left empty on each side of it
+const MARGIN: f64 = 0.05;
+// the least width and height of world shown, so a lone boid isn't zoomed in on without end
+const MIN_SPAN: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerminalConfig {
+    pub flock_size: usize,
+    pub seed: u64,
+    pub fps: u32,
+    pub marker: Marker,
+    pub theme: Theme,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        // most terminals are dark
+        return TerminalConfig { flock_size: 100, seed: 0, fps: 30, marker: Marker::Braille, theme: theme::DARK };
+    }
+}
+
+impl TerminalConfig {
+    /// `args` are the arguments after `terminal`
+    pub fn from_args(args: &[String]) -> Result<TerminalConfig> {
+        let mut config = TerminalConfig::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| anyhow!("{} needs a value", flag))?;
+            match flag.as_str() {
+                "--flock-size" => config.flock_size = value.parse().with_context(|| format!("bad --flock-size '{}'", value))?,
+                "--seed" => config.seed = value.parse().with_context(|| format!("bad --seed '{}'", value))?,
+                "--fps" => config.fps = value.parse().with_context(|| format!("bad --fps '{}'", value))?,
+                "--marker" => config.marker = match value.as_str() {
+                    "braille" => Marker::Braille,
+                    "block" => Marker::Block,
+                    "dot" => Marker::Dot,
+                    _ => bail!("--marker should be braille, block or dot, not '{}'", value),
+                },
+                "--theme" => config.theme = Theme::by_name(value).ok_or_else(|| anyhow!("unknown --theme '{}'", value))?,
+                _ => bail!("unknown terminal option {}", flag),
+            }
+        }
+        if config.fps == 0 {
+            bail!("--fps should be positive");
+        }
+        return Ok(config);
+    }
+}
+
+// from one point to another, in a colour
+type Segment = ((f32, f32), (f32, f32), Rgb);
+
+/// gathers up a frame's shapes, then draws them as a ratatui widget sized to wherever it's put
+pub struct TerminalRenderer {
+    theme: Theme,
+    marker: Marker,
+    bounds: Option<Bounds>,
+    lines: Vec<Segment>,
+    circles: Vec<((f32, f32), f32, Rgb)>,
+    points: Vec<((f32, f32), Rgb)>,
+}
+
+impl TerminalRenderer {
+    pub fn new(theme: Theme, marker: Marker) -> TerminalRenderer {
+        return TerminalRenderer { theme, marker, bounds: None, lines: Vec::new(), circles: Vec::new(), points: Vec::new() };
+    }
+
+    fn draw_polyline(&mut self, points: impl Iterator<Item = (f32, f32)> + Clone, color: Rgb) {
+        for (start, end) in points.clone().zip(points.skip(1)) {
+            self.lines.push((start, end, color));
+        }
+    }
+}
+
+fn terminal_color(color: Rgb) -> Color {
+    return Color::Rgb(color[0], color[1], color[2]);
+}
+
+/// the world's x and y ranges to show in `area`, around `bounds` and in proportion to the area. A
+/// cell is about twice as tall as it's wide, so a row covers twice as much of the world as a column
+pub fn view(bounds: Option<Bounds>, area: Rect) -> ([f64; 2], [f64; 2]) {
+    let bounds = bounds.unwrap_or(Bounds { min: (0.0, 0.0), max: (0.0, 0.0) });
+    let centre = ((bounds.min.0 + bounds.max.0) as f64 / 2.0, (bounds.min.1 + bounds.max.1) as f64 / 2.0);
+    let width = ((bounds.max.0 - bounds.min.0) as f64 * (1.0 + 2.0 * MARGIN)).max(MIN_SPAN);
+    let height = ((bounds.max.1 - bounds.min.1) as f64 * (1.0 + 2.0 * MARGIN)).max(MIN_SPAN);
+    let (columns, rows) = (area.width.max(1) as f64, area.height.max(1) as f64);
+    let units_per_column = (width / columns).max(height / (2.0 * rows));
+    let (half_width, half_height) = (units_per_column * columns / 2.0, units_per_column * rows);
+    return ([centre.0 - half_width, centre.0 + half_width], [centre.1 - half_height, centre.1 + half_height]);
+}
+
+impl Renderer for TerminalRenderer {
+    fn begin_frame(&mut self, bounds: Option<Bounds>) {
+        self.bounds = bounds;
+        self.lines.clear();
+        self.circles.clear();
+        self.points.clear();
+    }
+
+    fn draw_force_source(&mut self, source: &ForceSource) {
+        self.circles.push((source.centre, source.radius, self.theme.path));
+    }
+
+    fn draw_walls(&mut self, shape: &ArenaShape) {
+        match shape {
+            ArenaShape::Polygon(vertices) => {
+                // round to the first vertex again, to close the polygon
+                self.draw_polyline(vertices.iter().chain(vertices.first()).copied(), self.theme.boid);
+            }
+            ArenaShape::Circle { centre, radius } => self.circles.push((*centre, *radius, self.theme.boid)),
+        }
+    }
+
+    fn draw_wall(&mut self, start: (f32, f32), end: (f32, f32)) {
+        self.lines.push((start, end, self.theme.boid));
+    }
+
+    fn draw_path(&mut self, points: &[(f32, f32)]) {
+        self.draw_polyline(points.iter().copied(), self.theme.path);
+    }
+
+    fn draw_obstacle(&mut self, centre: (f32, f32), radius: f32) {
+        self.circles.push((centre, radius, self.theme.path));
+    }
+
+    fn draw_food(&mut self, food: &FoodSource) {
+        self.points.push(((food.x_pos, food.y_pos), self.theme.food));
+    }
+
+    // boids are far smaller than a character, so each is a dot
+    fn draw_boid(&mut self, boid: &Boid, color: BoidColor) {
+        self.points.push(((boid.x_pos, boid.y_pos), self.theme.boid_color(color)));
+    }
+
+    fn draw_predator(&mut self, predator: &Predator) {
+        self.points.push(((predator.boid.x_pos, predator.boid.y_pos), self.theme.predator_color(predator.hunt_state())));
+    }
+
+    fn end_frame(&mut self) {}
+}
+
+impl Widget for &TerminalRenderer {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (x_bounds, y_bounds) = view(self.bounds, area);
+        Canvas::default()
+            .marker(self.marker)
+            .background_color(terminal_color(self.theme.background))
+            .x_bounds(x_bounds)
+            .y_bounds(y_bounds)
+            .paint(|ctx| {
+                for (centre, radius, color) in &self.circles {
+                    ctx.draw(&Circle { x: centre.0 as f64, y: centre.1 as f64, radius: *radius as f64, color: terminal_color(*color) });
+                }
+                for (start, end, color) in &self.lines {
+                    ctx.draw(&Line::new(start.0 as f64, start.1 as f64, end.0 as f64, end.1 as f64, terminal_color(*color)));
+                }
+                for (point, color) in &self.points {
+                    ctx.draw(&Points { coords: &[(point.0 as f64, point.1 as f64)], color: terminal_color(*color) });
+                }
+            })
+            .render(area, buf);
+    }
+}
+
+/// what a key does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    TogglePause,
+    StepOnce, // while paused
+    ToggleHelp,
+    Quit,
+}
+
+impl Action {
+    pub fn description(&self) -> &'static str {
+        return match self {
+            Action::TogglePause => "pause or carry on",
+            Action::StepOnce => "step once while paused",
+            Action::ToggleHelp => "show or hide the controls",
+            Action::Quit => "quit",
+        };
+    }
+}
+
+/// every control, in the order the help lists them, with the name the help gives its keys
+pub const BINDINGS: [(&[KeyCode], &str, Action); 4] = [
+    (&[KeyCode::Char(' ')], "Space", Action::TogglePause),
+    (&[KeyCode::Char('.')], ".", Action::StepOnce),
+    (&[KeyCode::Char('h'), KeyCode::F(1)], "H/F1", Action::ToggleHelp),
+    (&[KeyCode::Char('q'), KeyCode::Esc], "Q/Esc", Action::Quit),
+];
+
+pub fn action_for(key: KeyCode) -> Option<Action> {
+    return BINDINGS.iter().find(|(keys, _, _)| keys.contains(&key)).map(|(_, _, action)| *action);
+}
+
+/// where the flock is up to, and whether it's paused
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Playback {
+    pub paused: bool,
+    pub help_visible: bool,
+    pub ticks: u64,
+    pub quit: bool,
+}
+
+impl Playback {
+    pub fn apply(&mut self, action: Action, flock: &mut Flock) {
+        match action {
+            Action::TogglePause => self.paused = !self.paused,
+            Action::StepOnce => if self.paused {
+                flock.step();
+                self.ticks += 1;
+            },
+            Action::ToggleHelp => self.help_visible = !self.help_visible,
+            Action::Quit => self.quit = true,
+        }
+    }
+
+    /// steps the flock, unless it's paused
+    pub fn tick(&mut self, flock: &mut Flock) {
+        if !self.paused {
+            flock.step();
+            self.ticks += 1;
+        }
+    }
+
+    /// the line under the flock: the controls while the help is open, or else e.g. "tick 120 · 100 boids · paused"
+    pub fn status(&self, flock: &Flock) -> String {
+        if self.help_visible {
+            return BINDINGS.iter().map(|(_, name, action)| format!("{}: {}", name, action.description())).collect::<Vec<String>>().join(" · ");
+        }
+        let state = if self.paused { "paused, . to step" } else { "H for help" };
+        return format!("tick {} · {} boids · {}", self.ticks, flock.boids().len(), state);
+    }
+}
+
+fn play(terminal: &mut DefaultTerminal, flock: &mut Flock, config: &TerminalConfig) -> Result<()> {
+    let frame_time = Duration::from_secs_f32(1.0 / config.fps as f32);
+    let mut playback = Playback::default();
+    let mut renderer = TerminalRenderer::new(config.theme, config.marker);
+    while !playback.quit {
+        draw_flock(flock, ColorMode::Plain, &mut renderer);
+        let status = playback.status(flock);
+        terminal.draw(|frame| {
+            // the help may need more than one line in a narrow terminal
+            let status_height = (status.chars().count() as u16).div_ceil(frame.area().width.max(1)).max(1);
+            let [view_area, status_area] = Layout::vertical([Constraint::Fill(1), Constraint::Length(status_height)]).areas(frame.area());
+            frame.render_widget(&renderer, view_area);
+            frame.render_widget(Paragraph::new(status).wrap(Wrap { trim: true }), status_area);
+        })?;
+        // a key press cuts the wait for the next frame short
+        if event::poll(frame_time)? {
+            if let Event::Key(key) = event::read()? {
+                if let Some(action) = action_for(key.code).filter(|_| key.kind == KeyEventKind::Press) {
+                    playback.apply(action, flock);
+                }
+            }
+            continue;
+        }
+        playback.tick(flock);
+    }
+    return Ok(());
+}
+
+/// the `terminal` subcommand
+pub fn run(args: &[String]) -> Result<()> {
+    let config = TerminalConfig::from_args(args)?;
+    let mut flock = Flock::new(config.flock_size, 5.0, 20.0, 0.1, 0.1, 0.1)?;
+    flock.randomise_boids(&InitialDistribution::default(), config.seed)?;
+    let mut terminal = ratatui::init();
+    let played = play(&mut terminal, &mut flock, &config);
+    // hand the terminal back however the run ended
+    ratatui::restore();
+    return played;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &str) -> Vec<String> {
+        return text.split_whitespace().map(String::from).collect();
+    }
+
+    #[test]
+    fn test_config_from_args() {
+        let config = TerminalConfig::from_args(&args("--flock-size 20 --marker block --theme light")).unwrap();
+        assert_eq!((config.flock_size, config.marker, config.theme), (20, Marker::Block, theme::LIGHT));
+        assert!(TerminalConfig::from_args(&args("--marker ascii")).is_err());
+        assert!(TerminalConfig::from_args(&args("--fps 0")).is_err());
+    }
+
+    #[test]
+    fn test_view_keeps_world_in_proportion() {
+        let bounds = Bounds { min: (0.0, 0.0), max: (10.0, 0.0) };
+        assert_eq!(view(Some(bounds), Rect::new(0, 0, 20, 10)), ([-0.5, 10.5], [-5.5, 5.5]));
+        assert_eq!(view(None, Rect::new(0, 0, 2, 1)), ([-0.5, 0.5], [-0.5, 0.5]));
+    }
+
+    #[test]
+    fn test_boids_drawn_as_dots() {
+        let mut flock = Flock::new(0, 1.0, 5.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(10.0, 0.0, 0.0, 0.0)];
+        let mut renderer = TerminalRenderer::new(theme::DARK, Marker::Braille);
+        draw_flock(&flock, ColorMode::Plain, &mut renderer);
+
+        let area = Rect::new(0, 0, 20, 10);
+        let mut buf = Buffer::empty(area);
+        (&renderer).render(area, &mut buf);
+        let drawn: Vec<(u16, u16)> = area.positions().filter(|position| buf[*position].symbol() != " ").map(|position| (position.x, position.y)).collect();
+        assert_eq!(drawn.len(), 2);
+        assert!(drawn[0].0 < 2 && drawn[1].0 > 17);
+        assert_eq!(buf[drawn[0]].fg, terminal_color(theme::DARK.boid));
+    }
+
+    #[test]
+    fn test_paused_flock_only_stepped_on_request() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(3.0, 0.0, 0.0, 1.0)]);
+        let mut playback = Playback::default();
+        playback.tick(&mut flock);
+        let x_pos = flock.boids()[0].x_pos;
+        playback.apply(action_for(KeyCode::Char(' ')).unwrap(), &mut flock);
+        playback.tick(&mut flock);
+        assert_eq!((playback.ticks, flock.boids()[0].x_pos), (1, x_pos));
+        assert_eq!(playback.status(&flock), "tick 1 · 2 boids · paused, . to step");
+
+        playback.apply(Action::StepOnce, &mut flock);
+        assert_eq!(playback.ticks, 2);
+        assert_ne!(flock.boids()[0].x_pos, x_pos);
+        playback.apply(action_for(KeyCode::Esc).unwrap(), &mut flock);
+        assert!(playback.quit);
+    }
+}
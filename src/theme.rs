@@ -0,0 +1,144 @@
+//! Colour themes shared by everything that draws the flock: the background, the boids, the
+//! palette boids are coloured from when coloured by cluster, the path and food markers, the predator, and boids coloured by what they're doing.
+
+use crate::boid_state::BoidState;
+use crate::hunting::HuntState;
+use crate::render::BoidColor;
+
+pub type Rgb = [u8; 3];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub background: Rgb,
+    pub boid: Rgb,
+    pub path: Rgb,
+    pub food: Rgb,
+    // by what a hunting predator is doing, in the order of `HuntState::ALL`; a player's predator is drawn as chasing
+    pub predator: [Rgb; 4],
+    // by what each boid is doing, in the order of `BoidState::ALL`
+    pub boid_state: [Rgb; 4],
+    // cycled through by cluster number; neighbouring entries are far apart so adjacent clusters stand out
+    pub cluster_palette: &'static [Rgb],
+}
+
+pub const LIGHT: Theme = Theme {
+    name: "light",
+    background: [255, 255, 255],
+    boid: [0, 0, 0],
+    path: [128, 128, 128],
+    food: [0, 128, 0],
+    predator: [[90, 90, 160], [200, 140, 0], [200, 0, 0], [150, 150, 150]],
+    boid_state: [[0, 0, 0], [220, 30, 30], [0, 150, 0], [120, 140, 200]],
+    cluster_palette: &[
+        [230, 25, 75], [60, 180, 75], [67, 99, 216], [245, 130, 49],
+        [145, 30, 180], [66, 212, 244], [240, 50, 230], [154, 99, 36],
+    ],
+};
+
+pub const DARK: Theme = Theme {
+    name: "dark",
+    background: [24, 24, 28],
+    boid: [235, 235, 235],
+    path: [110, 110, 120],
+    food: [120, 220, 120],
+    predator: [[140, 140, 230], [255, 190, 60], [255, 70, 70], [130, 130, 140]],
+    boid_state: [[235, 235, 235], [255, 90, 90], [120, 220, 120], [120, 150, 230]],
+    cluster_palette: &[
+        [255, 99, 132], [120, 230, 130], [110, 150, 255], [255, 170, 80],
+        [200, 120, 255], [100, 230, 255], [255, 120, 240], [220, 180, 120],
+    ],
+};
+
+// the Okabe-Ito palette, which stays distinguishable under the common forms of colour blindness
+pub const COLORBLIND_SAFE: Theme = Theme {
+    name: "colorblind",
+    background: [255, 255, 255],
+    boid: [0, 0, 0],
+    path: [153, 153, 153],
+    food: [0, 158, 115],
+    predator: [[0, 114, 178], [230, 159, 0], [213, 94, 0], [153, 153, 153]],
+    boid_state: [[0, 0, 0], [213, 94, 0], [0, 158, 115], [86, 180, 233]],
+    cluster_palette: &[
+        [230, 159, 0], [86, 180, 233], [0, 158, 115], [240, 228, 66],
+        [0, 114, 178], [213, 94, 0], [204, 121, 167], [0, 0, 0],
+    ],
+};
+
+/// in the order they're cycled through
+pub const THEMES: [Theme; 3] = [LIGHT, DARK, COLORBLIND_SAFE];
+
+impl Default for Theme {
+    fn default() -> Self {
+        return LIGHT;
+    }
+}
+
+impl Theme {
+    pub fn by_name(name: &str) -> Option<Theme> {
+        return THEMES.iter().find(|theme| theme.name == name).copied();
+    }
+
+    /// the theme after this one, wrapping back round to the first
+    pub fn next(&self) -> Theme {
+        let idx = THEMES.iter().position(|theme| theme.name == self.name).unwrap_or(0);
+        return THEMES[(idx + 1) % THEMES.len()];
+    }
+
+    pub fn boid_color(&self, color: BoidColor) -> Rgb {
+        return match color {
+            BoidColor::Plain => self.boid,
+            BoidColor::Cluster(cluster_label) => self.cluster_color(cluster_label),
+            BoidColor::State(state) => self.boid_state[BoidState::ALL.iter().position(|other| *other == state).unwrap_or(0)],
+        };
+    }
+
+    /// `hunt_state` is None for a predator steered by the player
+    pub fn predator_color(&self, hunt_state: Option<HuntState>) -> Rgb {
+        let state = hunt_state.unwrap_or(HuntState::Chase);
+        return self.predator[HuntState::ALL.iter().position(|other| *other == state).unwrap_or(0)];
+    }
+
+    pub fn cluster_color(&self, cluster_label: usize) -> Rgb {
+        return self.cluster_palette[cluster_label % self.cluster_palette.len()];
+    }
+}
+
+/// the colour as `#rrggbb`
+pub fn hex(color: Rgb) -> String {
+    return format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_themes_cycle() {
+        let mut theme = Theme::default();
+        for _ in 0..THEMES.len() {
+            theme = theme.next();
+        }
+        assert_eq!(theme, Theme::default());
+        assert_eq!(LIGHT.next(), DARK);
+    }
+
+    #[test]
+    fn test_theme_by_name() {
+        assert_eq!(Theme::by_name("colorblind"), Some(COLORBLIND_SAFE));
+        assert_eq!(Theme::by_name("neon"), None);
+    }
+
+    #[test]
+    fn test_cluster_colors_wrap() {
+        let palette_len = LIGHT.cluster_palette.len();
+        assert_eq!(LIGHT.cluster_color(palette_len + 1), LIGHT.cluster_color(1));
+        assert_eq!(hex(LIGHT.cluster_color(0)), "#e6194b");
+    }
+
+    #[test]
+    fn test_predator_colored_by_hunt_state() {
+        assert_eq!(LIGHT.predator_color(Some(HuntState::Rest)), [150, 150, 150]);
+        assert_eq!(LIGHT.predator_color(None), LIGHT.predator_color(Some(HuntState::Chase)));
+    }
+}
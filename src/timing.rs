@@ -0,0 +1,62 @@
+//! How long each part of `Flock::step` took, for an on-screen performance readout. Unlike the
+//! puffin scopes behind the `profiling` feature, these are always built in, but are only measured
+//! once turned on with `Flock::set_step_timing`, so a flock that isn't being watched pays nothing.
+
+use std::ops::AddAssign;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StepTimings {
+    pub neighbour_search: Duration, // finding each boid's crowding, aligning and local boids
+    pub rules: Duration, // steering by the flocking rules and the others, e.g. foraging and the predator
+    pub integration: Duration, // limiting speeds, the weather, and keeping boids out of walls, obstacles and portals
+    pub clusters: Duration, // labelling the clusters the boids ended up in
+}
+
+impl StepTimings {
+    pub fn total(&self) -> Duration {
+        return self.neighbour_search + self.rules + self.integration + self.clusters;
+    }
+
+    /// the parts in the order they happen in a step, with a short name for each
+    pub fn parts(&self) -> [(&'static str, Duration); 4] {
+        return [
+            ("neighbours", self.neighbour_search),
+            ("rules", self.rules),
+            ("moving", self.integration),
+            ("clusters", self.clusters),
+        ];
+    }
+}
+
+impl AddAssign for StepTimings {
+    fn add_assign(&mut self, other: StepTimings) {
+        self.neighbour_search += other.neighbour_search;
+        self.rules += other.rules;
+        self.integration += other.integration;
+        self.clusters += other.clusters;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Boid, Flock};
+
+    #[test]
+    fn test_step_only_timed_once_turned_on() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(2.0, 0.0, 1.0, 0.0)]);
+        flock.step();
+        assert_eq!(flock.step_timings(), None);
+
+        flock.set_step_timing(true);
+        flock.step();
+        let timings = flock.step_timings().unwrap();
+        assert_eq!(timings.total(), timings.parts().iter().map(|(_, duration)| *duration).sum());
+        assert!(timings.total() > Duration::ZERO);
+
+        flock.set_step_timing(false);
+        assert_eq!(flock.step_timings(), None);
+    }
+}
@@ -0,0 +1,481 @@
+//! Recording a headless run as a video, by drawing each frame into a pixel buffer and
+//! streaming the raw pixels to an `ffmpeg` child process. Frames are produced as fast as the
+//! simulation runs, and the video plays back at the chosen framerate regardless.
+//!
+//! `boids record --out flock.mp4 --ticks 600 --width 1280 --height 720 --fps 60`
+//!
+//! The view starts centred on the origin and `--view-width` world units across. With `--camera`
+//! it then follows the centre of the flock (`follow-centroid`) or zooms to keep the whole flock in
+//! view (`zoom-to-fit`), or `--follow` follows the boid with that id; see `camera.rs`. `--pov`
+//! adds an inset in the top right corner showing what the boid with that id can see, turned to
+//! its heading; see `point_of_view.rs`. `ffmpeg`
+//! must be on the PATH; the container is chosen by ffmpeg from the output's extension (e.g. .mp4, .webm).
+//!
+//! By default boids are drawn `--boid-size` world units long, so they shrink as the view widens,
+//! but never below `--min-boid-pixels` so that they stay visible. `--boid-sizing screen` instead
+//! draws them `--boid-size` pixels long however wide the view is. `--theme` picks the colours
+//! (`light`, `dark` or `colorblind`). `--scenario` plays out a scenario file as the video records;
+//! see `scenario.rs`. `--path` gives the flock a route to follow, drawn under the boids, in the
+//! form taken by `PathFollowing::parse`. `--shuffle-seed` gives the flock the shuffled parameters from that seed, as
+//! shown when shuffling them in a front end, and prints them; see `shuffle.rs`.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use anyhow::{anyhow, bail, Context, Result};
+use crate::camera::{Camera, CameraMode};
+use crate::distribution::InitialDistribution;
+use crate::point_of_view::{draw_point_of_view, PointOfView};
+use crate::render::{draw_flock, BoidColor, Bounds, ColorMode, Renderer};
+use crate::scenario::Scenario;
+use crate::shuffle::ShuffledParameters;
+use crate::path::PathFollowing;
+use crate::predator::Predator;
+use crate::svg::{triangle_for_boid, BOID_LENGTH, PREDATOR_LENGTH};
+use crate::theme::{Rgb, Theme};
+use crate::{is_positive_and_finite, Boid, BoidId, Flock};
+
+// the point of view inset's side, as a share of the video's height
+const INSET_SHARE_OF_HEIGHT: f32 = 1.0 / 3.0;
+// between the inset and the edges of the video, in pixels
+const INSET_MARGIN: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoidSizing {
+    World, // the boid size is in world units, so boids scale with the zoom
+    Screen, // the boid size is in pixels
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordingConfig {
+    pub width: usize,
+    pub height: usize,
+    pub fps: u32,
+    pub view_width: f32, // in world units, to start with; the view's height follows from the aspect ratio
+    pub camera: CameraMode,
+    pub follow: Option<BoidId>, // the boid to follow, when the camera follows a boid
+    pub point_of_view: Option<BoidId>, // the boid whose view is shown in an inset
+    pub boid_size: f32, // the length of each boid, in units set by `boid_sizing`
+    pub boid_sizing: BoidSizing,
+    pub min_boid_pixels: f32, // world-sized boids are never drawn shorter than this
+    pub theme: Theme,
+    pub num_ticks: usize,
+    pub flock_size: usize,
+    pub seed: u64,
+    pub scenario: Option<Scenario>,
+    pub path_following: Option<PathFollowing>,
+    pub shuffle_seed: Option<u64>, // to record with the parameters from a shuffle
+    pub output: PathBuf,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        return RecordingConfig {
+            width: 1280,
+            height: 720,
+            fps: 60,
+            view_width: 400.0,
+            camera: CameraMode::Free,
+            follow: None,
+            point_of_view: None,
+            boid_size: BOID_LENGTH,
+            boid_sizing: BoidSizing::World,
+            min_boid_pixels: 3.0,
+            theme: Theme::default(),
+            num_ticks: 600,
+            flock_size: 100,
+            seed: 0,
+            scenario: None,
+            path_following: None,
+            shuffle_seed: None,
+            output: PathBuf::from("flock.mp4"),
+        };
+    }
+}
+
+impl RecordingConfig {
+    /// `args` are the arguments after `record`
+    pub fn from_args(args: &[String]) -> Result<RecordingConfig> {
+        let mut config = RecordingConfig::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| anyhow!("{} needs a value", flag))?;
+            match flag.as_str() {
+                "--width" => config.width = value.parse().with_context(|| format!("bad --width '{}'", value))?,
+                "--height" => config.height = value.parse().with_context(|| format!("bad --height '{}'", value))?,
+                "--fps" => config.fps = value.parse().with_context(|| format!("bad --fps '{}'", value))?,
+                "--view-width" => config.view_width = value.parse().with_context(|| format!("bad --view-width '{}'", value))?,
+                "--camera" => config.camera = CameraMode::parse(value)?,
+                "--follow" => {
+                    config.follow = Some(BoidId(value.parse().with_context(|| format!("bad --follow '{}'", value))?));
+                    config.camera = CameraMode::FollowBoid;
+                }
+                "--pov" => config.point_of_view = Some(BoidId(value.parse().with_context(|| format!("bad --pov '{}'", value))?)),
+                "--boid-size" => config.boid_size = value.parse().with_context(|| format!("bad --boid-size '{}'", value))?,
+                "--boid-sizing" => config.boid_sizing = match value.as_str() {
+                    "world" => BoidSizing::World,
+                    "screen" => BoidSizing::Screen,
+                    _ => bail!("--boid-sizing should be world or screen, not '{}'", value),
+                },
+                "--min-boid-pixels" => config.min_boid_pixels = value.parse().with_context(|| format!("bad --min-boid-pixels '{}'", value))?,
+                "--theme" => config.theme = Theme::by_name(value).ok_or_else(|| anyhow!("unknown --theme '{}'", value))?,
+                "--ticks" => config.num_ticks = value.parse().with_context(|| format!("bad --ticks '{}'", value))?,
+                "--flock-size" => config.flock_size = value.parse().with_context(|| format!("bad --flock-size '{}'", value))?,
+                "--seed" => config.seed = value.parse().with_context(|| format!("bad --seed '{}'", value))?,
+                "--scenario" => config.scenario = Some(Scenario::load(&PathBuf::from(value))?),
+                "--path" => config.path_following = Some(PathFollowing::parse(value)?),
+                "--shuffle-seed" => config.shuffle_seed = Some(value.parse().with_context(|| format!("bad --shuffle-seed '{}'", value))?),
+                "--out" => config.output = PathBuf::from(value),
+                _ => bail!("unknown record option {}", flag),
+            }
+        }
+        // yuv420p output needs even dimensions
+        if config.width == 0 || config.height == 0 || config.width % 2 == 1 || config.height % 2 == 1 {
+            bail!("video size {}x{} should be non-zero and even", config.width, config.height);
+        }
+        if config.fps == 0 || !is_positive_and_finite(config.view_width) || !is_positive_and_finite(config.boid_size) {
+            bail!("--fps, --view-width and --boid-size should be positive");
+        }
+        if config.camera == CameraMode::FollowBoid && config.follow.is_none() {
+            bail!("--camera follow-boid needs --follow to say which boid");
+        }
+        return Ok(config);
+    }
+
+    /// the camera at the start of the recording
+    pub fn camera(&self) -> Camera {
+        return Camera {
+            mode: self.camera,
+            selected: self.follow,
+            ..Camera::new((0.0, 0.0), self.view_width, self.width as f32 / self.height as f32)
+        };
+    }
+
+    /// how long each boid is drawn, in pixels, when the camera is zoomed to `pixels_per_unit`
+    fn boid_length_in_pixels(&self, pixels_per_unit: f32) -> f32 {
+        return match self.boid_sizing {
+            BoidSizing::World => (self.boid_size * pixels_per_unit).max(self.min_boid_pixels),
+            BoidSizing::Screen => self.boid_size,
+        };
+    }
+}
+
+/// draws boids into a buffer of RGB pixels, top row first, as seen by the camera. The world beyond
+/// the boids isn't drawn
+pub struct PixelRenderer<'a> {
+    config: &'a RecordingConfig,
+    camera: &'a Camera,
+    width: usize, // the config's, except for insets
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl<'a> PixelRenderer<'a> {
+    pub fn new(config: &'a RecordingConfig, camera: &'a Camera) -> PixelRenderer<'a> {
+        return PixelRenderer::with_size(config, camera, config.width, config.height);
+    }
+
+    fn with_size(config: &'a RecordingConfig, camera: &'a Camera, width: usize, height: usize) -> PixelRenderer<'a> {
+        return PixelRenderer { config, camera, width, height, pixels: Vec::new() };
+    }
+
+    pub fn into_pixels(self) -> Vec<u8> {
+        return self.pixels;
+    }
+}
+
+impl PixelRenderer<'_> {
+    fn boid_length_in_pixels(&self) -> f32 {
+        return self.config.boid_length_in_pixels(self.width as f32 / self.camera.view_width);
+    }
+
+    fn fill_boid_triangle(&mut self, boid: &Boid, length_in_pixels: f32, color: Rgb) {
+        let (width, height) = (self.width, self.height);
+        // the triangle is sized in pixels, so work out where the boid is on screen first
+        let (x_pos, y_pos) = self.camera.world_to_screen((boid.x_pos, boid.y_pos), width, height);
+        let on_screen = Boid { x_pos, y_pos, ..*boid };
+        fill_triangle(&mut self.pixels, width, height, triangle_for_boid(&on_screen, length_in_pixels), color);
+    }
+}
+
+impl Renderer for PixelRenderer<'_> {
+    fn begin_frame(&mut self, _bounds: Option<Bounds>) {
+        self.pixels = self.config.theme.background.repeat(self.width * self.height);
+    }
+
+    fn draw_boid(&mut self, boid: &Boid, color: BoidColor) {
+        self.fill_boid_triangle(boid, self.boid_length_in_pixels(), self.config.theme.boid_color(color));
+    }
+
+    fn draw_predator(&mut self, predator: &Predator) {
+        let length = self.boid_length_in_pixels() * PREDATOR_LENGTH / BOID_LENGTH;
+        self.fill_boid_triangle(&predator.boid, length, self.config.theme.predator_color(predator.hunt_state()));
+    }
+
+    fn end_frame(&mut self) {}
+}
+
+/// the frame as rows of RGB pixels, top row first
+pub fn render_frame(flock: &Flock, config: &RecordingConfig, camera: &Camera) -> Vec<u8> {
+    profile_scope!("render_frame");
+    let mut renderer = PixelRenderer::new(config, camera);
+    draw_flock(flock, ColorMode::Plain, &mut renderer);
+    let mut pixels = renderer.into_pixels();
+    if let Some(id) = config.point_of_view {
+        draw_point_of_view_inset(&mut pixels, flock, config, id);
+    }
+    return pixels;
+}
+
+/// draws what the boid with this id can see in a round inset in the top right corner of the frame,
+/// as wide as its vision. Nothing is drawn once the boid has left the flock
+fn draw_point_of_view_inset(pixels: &mut [u8], flock: &Flock, config: &RecordingConfig, id: BoidId) {
+    let Some(point_of_view) = PointOfView::of(flock, id) else {
+        return;
+    };
+    let size = (config.height as f32 * INSET_SHARE_OF_HEIGHT) as usize;
+    let camera = Camera::new((0.0, 0.0), 2.0 * point_of_view.radius, 1.0);
+    let mut renderer = PixelRenderer::with_size(config, &camera, size, size);
+    draw_point_of_view(flock, id, ColorMode::Plain, &mut renderer);
+    let inset = renderer.into_pixels();
+
+    let left = config.width.saturating_sub(size + INSET_MARGIN);
+    let radius = size as f32 / 2.0;
+    for y in 0..size.min(config.height.saturating_sub(INSET_MARGIN)) {
+        for x in 0..size.min(config.width - left) {
+            let dist_from_centre = ((x as f32 + 0.5 - radius).powi(2) + (y as f32 + 0.5 - radius).powi(2)).sqrt();
+            // the corners outside the circle are left showing the main view, and the circle is ringed
+            let color = match dist_from_centre {
+                dist if dist > radius => continue,
+                dist if dist > radius - 1.0 => config.theme.path,
+                _ => {
+                    let inset_pixel = (y * size + x) * 3;
+                    [inset[inset_pixel], inset[inset_pixel + 1], inset[inset_pixel + 2]]
+                }
+            };
+            let pixel = ((y + INSET_MARGIN) * config.width + left + x) * 3;
+            pixels[pixel..pixel + 3].copy_from_slice(&color);
+        }
+    }
+}
+
+/// colours every pixel whose centre is inside the triangle
+fn fill_triangle(pixels: &mut [u8], width: usize, height: usize, corners: [(f32, f32); 3], color: Rgb) {
+    let [a, b, c] = corners;
+    // which side of the line from `start` to `end` the point is on; 0 on the line
+    let edge = |start: (f32, f32), end: (f32, f32), point: (f32, f32)| (end.0 - start.0) * (point.1 - start.1) - (end.1 - start.1) * (point.0 - start.0);
+    let area = edge(a, b, c);
+    if area == 0.0 {
+        return;
+    }
+
+    let min_x = a.0.min(b.0).min(c.0).floor().max(0.0) as usize;
+    let max_x = (a.0.max(b.0).max(c.0).ceil().max(0.0) as usize).min(width);
+    let min_y = a.1.min(b.1).min(c.1).floor().max(0.0) as usize;
+    let max_y = (a.1.max(b.1).max(c.1).ceil().max(0.0) as usize).min(height);
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let centre = (x as f32 + 0.5, y as f32 + 0.5);
+            // inside if on the same side of every edge as the triangle itself, whichever way round its corners are
+            let inside = [edge(a, b, centre), edge(b, c, centre), edge(c, a, centre)]
+                .iter()
+                .all(|side| side * area >= 0.0);
+            if inside {
+                let pixel = (y * width + x) * 3;
+                pixels[pixel..pixel + 3].copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+/// steps the flock `num_ticks` times, writing the frame after each step
+pub fn stream_frames(flock: &mut Flock, config: &RecordingConfig, writer: &mut impl Write) -> io::Result<()> {
+    let mut camera = config.camera();
+    // starts out already on target, rather than gliding over from the origin
+    camera.jump_to_target(flock);
+    for tick in 0..config.num_ticks {
+        if let Some(scenario) = &config.scenario {
+            scenario.apply(tick as u32, flock);
+        }
+        flock.step();
+        camera.update(flock);
+        writer.write_all(&render_frame(flock, config, &camera))?;
+        profile_frame_end!();
+    }
+    return writer.flush();
+}
+
+pub fn record(flock: &mut Flock, config: &RecordingConfig) -> Result<()> {
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-y", "-f", "rawvideo", "-pix_fmt", "rgb24"])
+        .args(["-s", &format!("{}x{}", config.width, config.height)])
+        .args(["-r", &config.fps.to_string()])
+        .args(["-i", "-", "-pix_fmt", "yuv420p"])
+        .arg(&config.output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("couldn't start ffmpeg; is it installed and on the PATH?")?;
+
+    let mut stdin = ffmpeg.stdin.take().expect("ffmpeg was started with a piped stdin");
+    let streamed = stream_frames(flock, config, &mut stdin);
+    // closing stdin tells ffmpeg there are no more frames
+    drop(stdin);
+    let status = ffmpeg.wait().context("ffmpeg didn't run")?;
+    if !status.success() {
+        bail!("ffmpeg failed with {}", status);
+    }
+    streamed.context("couldn't send frames to ffmpeg")?;
+    return Ok(());
+}
+
+/// the `record` subcommand
+pub fn run(args: &[String]) -> Result<()> {
+    let config = RecordingConfig::from_args(args)?;
+    let mut flock = Flock::new(config.flock_size, 5.0, 20.0, 0.1, 0.1, 0.1)?;
+    flock.randomise_boids(&InitialDistribution::default(), config.seed)?;
+    if let Some(path_following) = &config.path_following {
+        flock.set_path_following(path_following.clone());
+    }
+    if let Some(shuffle_seed) = config.shuffle_seed {
+        let parameters = ShuffledParameters::from_seed(shuffle_seed);
+        parameters.apply(&mut flock)?;
+        println!("{}", parameters);
+    }
+    return record(&mut flock, &config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Boid;
+
+    // the colours of the default theme
+    const BACKGROUND: Rgb = crate::theme::LIGHT.background;
+    const BOID_COLOR: Rgb = crate::theme::LIGHT.boid;
+
+    fn pixel(pixels: &[u8], width: usize, x: usize, y: usize) -> Rgb {
+        let idx = (y * width + x) * 3;
+        return [pixels[idx], pixels[idx + 1], pixels[idx + 2]];
+    }
+
+    #[test]
+    fn test_fill_triangle() {
+        let mut pixels = BACKGROUND.repeat(10 * 10);
+        fill_triangle(&mut pixels, 10, 10, [(0.0, 0.0), (0.0, 10.0), (10.0, 0.0)], BOID_COLOR);
+        assert_eq!(pixel(&pixels, 10, 1, 1), BOID_COLOR);
+        assert_eq!(pixel(&pixels, 10, 8, 8), BACKGROUND);
+        // the same triangle with its corners the other way round
+        let mut pixels = BACKGROUND.repeat(10 * 10);
+        fill_triangle(&mut pixels, 10, 10, [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)], BOID_COLOR);
+        assert_eq!(pixel(&pixels, 10, 1, 1), BOID_COLOR);
+    }
+
+    #[test]
+    fn test_boid_drawn_at_its_position_and_offscreen_boids_ignored() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 1.0, 0.0), Boid::new(1000.0, -1000.0, 1.0, 0.0)];
+
+        // 4 pixels per world unit
+        let config = RecordingConfig { width: 40, height: 20, view_width: 10.0, ..RecordingConfig::default() };
+        let pixels = render_frame(&flock, &config, &config.camera());
+        assert_eq!(pixels.len(), 40 * 20 * 3);
+        assert_eq!(pixel(&pixels, 40, 21, 10), BOID_COLOR);
+        assert_eq!(pixel(&pixels, 40, 2, 2), BACKGROUND);
+    }
+
+    #[test]
+    fn test_point_of_view_inset_in_corner() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.0).unwrap();
+        // far off the main view, heading along positive y, with a boid right behind it
+        flock.add_boids([Boid::new(1000.0, 0.0, 0.0, 1.0), Boid::new(1000.0, -5.0, 0.0, 1.0)]);
+        let config = RecordingConfig { width: 120, height: 120, view_width: 10.0, point_of_view: flock.boids().first().map(|boid| boid.id), ..RecordingConfig::default() };
+        let pixels = render_frame(&flock, &config, &config.camera());
+
+        // a 40 pixel inset, 8 pixels in from the top and right, with the viewer in the middle and
+        // the boid behind it halfway from the middle to the bottom of the circle
+        let (left, top) = (120 - 40 - 8, 8);
+        assert_eq!(pixel(&pixels, 120, left + 20, top + 19), BOID_COLOR);
+        assert_eq!(pixel(&pixels, 120, left + 20, top + 31), BOID_COLOR);
+        assert_eq!(pixel(&pixels, 120, left + 20, top), crate::theme::LIGHT.path);
+        assert_eq!(pixel(&pixels, 120, left, top), BACKGROUND);
+    }
+
+    #[test]
+    fn test_drawn_through_camera() {
+        let mut flock = Flock::new(0, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        flock.add_boids([Boid::new(1000.0, -1000.0, 1.0, 0.0)]);
+
+        let config = RecordingConfig { width: 40, height: 20, view_width: 10.0, follow: flock.boids().first().map(|boid| boid.id), camera: CameraMode::FollowBoid, ..RecordingConfig::default() };
+        let mut camera = config.camera();
+        camera.jump_to_target(&flock);
+        let pixels = render_frame(&flock, &config, &camera);
+        assert_eq!(pixel(&pixels, 40, 21, 10), BOID_COLOR);
+    }
+
+    #[test]
+    fn test_boid_size_follows_zoom_down_to_minimum() {
+        let config = RecordingConfig { boid_size: 2.0, min_boid_pixels: 3.0, ..RecordingConfig::default() };
+        assert_eq!(config.boid_length_in_pixels(10.0), 20.0);
+        assert_eq!(config.boid_length_in_pixels(0.1), 3.0);
+        let fixed = RecordingConfig { boid_sizing: BoidSizing::Screen, ..config };
+        assert_eq!(fixed.boid_length_in_pixels(10.0), 2.0);
+    }
+
+    #[test]
+    fn test_one_frame_streamed_per_tick() {
+        let mut flock = Flock::new(5, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        let config = RecordingConfig { width: 8, height: 6, num_ticks: 3, ..RecordingConfig::default() };
+        let mut sink = Vec::new();
+        stream_frames(&mut flock, &config, &mut sink).unwrap();
+        assert_eq!(sink.len(), 3 * 8 * 6 * 3);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn test_recording_profiled() {
+        let frame_view = puffin::GlobalFrameView::default();
+        puffin::set_scopes_on(true);
+        let mut flock = Flock::new(5, 1.0, 50.0, 0.0, 0.0, 0.0).unwrap();
+        let config = RecordingConfig { width: 8, height: 6, num_ticks: 2, ..RecordingConfig::default() };
+        stream_frames(&mut flock, &config, &mut Vec::new()).unwrap();
+
+        let frame_view = frame_view.lock();
+        assert!(frame_view.latest_frame().is_some());
+        assert!(frame_view.scope_collection().fetch_by_name("Flock::step").is_some());
+        assert!(frame_view.scope_collection().fetch_by_name("render_frame").is_some());
+    }
+
+    #[test]
+    fn test_config_from_args() {
+        let args: Vec<String> = "--width 640 --height 480 --fps 30 --out run.webm".split_whitespace().map(str::to_string).collect();
+        let config = RecordingConfig::from_args(&args).unwrap();
+        assert_eq!((config.width, config.height, config.fps), (640, 480, 30));
+        assert_eq!(config.output, PathBuf::from("run.webm"));
+
+        let themed: Vec<String> = "--theme dark".split_whitespace().map(str::to_string).collect();
+        assert_eq!(RecordingConfig::from_args(&themed).unwrap().theme, crate::theme::DARK);
+
+        let sizing: Vec<String> = "--boid-size 6 --boid-sizing screen".split_whitespace().map(str::to_string).collect();
+        let config = RecordingConfig::from_args(&sizing).unwrap();
+        assert_eq!((config.boid_size, config.boid_sizing), (6.0, BoidSizing::Screen));
+
+        let following: Vec<String> = "--follow 7 --pov 7".split_whitespace().map(str::to_string).collect();
+        let config = RecordingConfig::from_args(&following).unwrap();
+        assert_eq!((config.camera, config.follow, config.point_of_view), (CameraMode::FollowBoid, Some(BoidId(7)), Some(BoidId(7))));
+        let following_nothing: Vec<String> = "--camera follow-boid".split_whitespace().map(str::to_string).collect();
+        assert!(RecordingConfig::from_args(&following_nothing).is_err());
+
+        let shuffled: Vec<String> = "--shuffle-seed 42".split_whitespace().map(str::to_string).collect();
+        assert_eq!(RecordingConfig::from_args(&shuffled).unwrap().shuffle_seed, Some(42));
+
+        let path: Vec<String> = "--path spline:0.1:5:0,0;50,50;100,0".split_whitespace().map(str::to_string).collect();
+        assert!(RecordingConfig::from_args(&path).unwrap().path_following.is_some());
+
+        let odd_size: Vec<String> = "--width 641".split_whitespace().map(str::to_string).collect();
+        assert!(RecordingConfig::from_args(&odd_size).is_err());
+        for not_a_size in ["--view-width NaN", "--boid-size NaN", "--view-width inf"] {
+            let args: Vec<String> = not_a_size.split_whitespace().map(str::to_string).collect();
+            assert!(RecordingConfig::from_args(&args).is_err());
+        }
+    }
+}
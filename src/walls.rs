@@ -0,0 +1,148 @@
+//! Free-standing walls: straight lines anywhere in the world, e.g. drawn in the Bevy adapter's
+//! editor, as opposed to an arena's walls round the edge of the world. A boid that would cross one
+//! during a step bounces off it as if it were a mirror. The crossing is checked along the boid's
+//! whole movement, so it can't skip through however fast it's going, and a boid bounced into
+//! another wall bounces off that one too.
+
+use anyhow::{anyhow, Context, Result};
+use crate::arena::{fraction_of_movement_before_crossing, reflect, unit_normal, WALL_CLEARANCE};
+use crate::events::FlockEvent;
+use crate::{check_finite, Boid, CreationError, InvalidFlockConfig};
+
+// the most walls a boid can bounce off in one step, e.g. in a tight corner, before it's left where it was
+const MAX_BOUNCES: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wall {
+    pub start: (f32, f32),
+    pub end: (f32, f32),
+}
+
+impl Wall {
+    pub fn new(start: (f32, f32), end: (f32, f32)) -> Result<Wall, InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = [start.0, start.1, end.0, end.1].into_iter().filter_map(|value| check_finite(value, "wall end")).collect();
+        if errors.is_empty() && start == end {
+            errors.push(CreationError::WallHasNoLength);
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        return Ok(Wall { start, end });
+    }
+
+    /// `x,y;x,y`, from one end to the other, e.g. `0,0;100,0`
+    pub fn parse(text: &str) -> Result<Wall> {
+        let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, text));
+        let parse_pair = |part: &str| -> Result<(f32, f32)> {
+            let (x, y) = part.split_once(',').ok_or_else(|| anyhow!("'{}' in '{}' should be x,y", part, text))?;
+            return Ok((parse_float(x)?, parse_float(y)?));
+        };
+        let (start, end) = text.split_once(';').ok_or_else(|| anyhow!("wall '{}' should be x,y;x,y", text))?;
+        return Ok(Wall::new(parse_pair(start)?, parse_pair(end)?)?);
+    }
+
+    /// the distance from a point to the nearest point on the wall
+    pub fn dist_to(&self, (x, y): (f32, f32)) -> f32 {
+        let wall = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let fraction = (((x - self.start.0) * wall.0 + (y - self.start.1) * wall.1) / (wall.0 * wall.0 + wall.1 * wall.1)).clamp(0.0, 1.0);
+        return (x - (self.start.0 + wall.0 * fraction)).hypot(y - (self.start.1 + wall.1 * fraction));
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Walls {
+    pub walls: Vec<Wall>,
+}
+
+impl Walls {
+    pub fn new(walls: Vec<Wall>) -> Walls {
+        return Walls { walls };
+    }
+
+    /// how far through the movement it first crosses a wall, and that wall's unit normal
+    fn first_hit(&self, start: (f32, f32), movement: (f32, f32)) -> Option<(f32, (f32, f32))> {
+        return self.walls.iter()
+            .filter_map(|wall| fraction_of_movement_before_crossing(start, movement, (wall.start, wall.end)).map(|fraction| (fraction, unit_normal(wall.start, wall.end))))
+            .min_by(|(fraction, _), (other_fraction, _)| fraction.total_cmp(other_fraction));
+    }
+
+    /// bounces each boid that crossed a wall since it was at its previous position back off it
+    pub(crate) fn bounce(&self, boids: &mut [Boid], previous_positions: &[(f32, f32)], events: &mut Vec<FlockEvent>) {
+        for (boid, previous_position) in boids.iter_mut().zip(previous_positions) {
+            let mut start = *previous_position;
+            let mut movement = (boid.x_pos - start.0, boid.y_pos - start.1);
+            let mut num_bounces = 0;
+            while let Some((fraction, normal)) = self.first_hit(start, movement) {
+                if num_bounces == MAX_BOUNCES {
+                    // still heading through a wall, so it stays put for this step
+                    movement = (0.0, 0.0);
+                    break;
+                }
+                num_bounces += 1;
+                // stopped just short of the wall, on the side the boid came from
+                let towards_wall = if movement.0 * normal.0 + movement.1 * normal.1 > 0.0 { 1.0 } else { -1.0 };
+                let hit = (start.0 + movement.0 * fraction - normal.0 * towards_wall * WALL_CLEARANCE,
+                           start.1 + movement.1 * fraction - normal.1 * towards_wall * WALL_CLEARANCE);
+                movement = reflect((movement.0 * (1.0 - fraction), movement.1 * (1.0 - fraction)), normal);
+                (boid.x_vel, boid.y_vel) = reflect((boid.x_vel, boid.y_vel), normal);
+                start = hit;
+            }
+            if num_bounces > 0 {
+                (boid.x_pos, boid.y_pos) = (start.0 + movement.0, start.1 + movement.1);
+                events.push(FlockEvent::WallHit { boid: boid.id, position: (boid.x_pos, boid.y_pos) });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wall_parsed_and_validated() {
+        assert_eq!(Wall::parse("0,0;10,5").unwrap(), Wall { start: (0.0, 0.0), end: (10.0, 5.0) });
+        assert!(Wall::parse("0,0").is_err());
+        assert_eq!(Wall::new((1.0, 1.0), (1.0, 1.0)).unwrap_err().errors, vec![CreationError::WallHasNoLength]);
+        assert_eq!(Wall::new((0.0, f32::NAN), (1.0, 1.0)).unwrap_err().errors, vec![CreationError::ValueIsNotFinite("wall end".to_string())]);
+        assert_eq!(Wall::parse("0,0;10,0").unwrap().dist_to((5.0, 3.0)), 3.0);
+    }
+
+    #[test]
+    fn test_fast_boid_bounced_off_wall_it_would_skip_through() {
+        let walls = Walls::new(vec![Wall::new((5.0, -10.0), (5.0, 10.0)).unwrap()]);
+        // moved from 0 to 20 in one step, straight through the wall at x = 5
+        let mut boids = vec![Boid::new(20.0, 0.0, 20.0, 0.0)];
+        let mut events = Vec::new();
+        walls.bounce(&mut boids, &[(0.0, 0.0)], &mut events);
+        assert!((boids[0].x_pos - -10.0).abs() < 0.01);
+        assert_eq!(boids[0].x_vel, -20.0);
+        assert_eq!(events.len(), 1);
+
+        // one that didn't reach the wall is left alone
+        let mut boids = vec![Boid::new(4.0, 0.0, 4.0, 0.0)];
+        walls.bounce(&mut boids, &[(0.0, 0.0)], &mut events);
+        assert_eq!(boids[0].x_pos, 4.0);
+    }
+
+    #[test]
+    fn test_flock_kept_behind_wall() {
+        let mut flock = crate::Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.add_boids([Boid::new(0.0, 0.0, 5.0, 0.0), Boid::new(0.0, 2.0, 5.0, 0.0)]);
+        flock.set_walls(Walls::new(vec![Wall::parse("3,-10;3,10").unwrap()]));
+        flock.step();
+        assert!(flock.boids().iter().all(|boid| boid.x_pos < 3.0));
+        assert!(flock.boids()[0].x_vel < 0.0);
+        assert!(matches!(flock.events()[0], FlockEvent::WallHit { .. }));
+    }
+
+    #[test]
+    fn test_boid_bounced_between_walls() {
+        let walls = Walls::new(vec![Wall::new((5.0, -10.0), (5.0, 10.0)).unwrap(), Wall::new((-5.0, -10.0), (-5.0, 10.0)).unwrap()]);
+        let mut boids = vec![Boid::new(30.0, 0.0, 30.0, 0.0)];
+        walls.bounce(&mut boids, &[(0.0, 0.0)], &mut Vec::new());
+        // off the right wall at 5, then the left at -5 and the right again, ending up between them
+        assert!(boids[0].x_pos > -5.0 && boids[0].x_pos < 5.0);
+        assert_eq!(boids[0].x_vel, -30.0);
+    }
+}
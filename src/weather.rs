@@ -0,0 +1,186 @@
+//! Weather that changes as the flock flies. Rain shortens how far boids can see, wind carries
+//! every boid along with it, and the temperature scales the flock's maximum speed, so boids are
+//! sluggish in the cold. The weather is set at keyframes, given as the tick they apply from, and
+//! changes smoothly between them, holding at the last keyframe's weather from then on.
+
+use anyhow::{anyhow, Context, Result};
+use crate::{check_finite, check_float_between_zero_and_one, is_positive_and_finite, Boid, CreationError, InvalidFlockConfig};
+
+// in the heaviest rain, boids see this much less far
+const MAX_VISION_LOSS: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeatherState {
+    pub rain: f32, // from 0 when it's dry to 1 for a downpour
+    pub wind: (f32, f32), // how far the wind carries every boid each frame
+    pub temperature: f32, // 1 on a mild day; the maximum speed is multiplied by it
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        return WeatherState { rain: 0.0, wind: (0.0, 0.0), temperature: 1.0 };
+    }
+}
+
+impl WeatherState {
+    /// how far boids can see, as a fraction of how far they see when it's dry
+    pub fn visibility(&self) -> f32 {
+        return 1.0 - self.rain * MAX_VISION_LOSS;
+    }
+
+    /// `fraction` of the way from this weather to `other`
+    fn blend(&self, other: &WeatherState, fraction: f32) -> WeatherState {
+        let blend = |from: f32, to: f32| from + (to - from) * fraction;
+        return WeatherState {
+            rain: blend(self.rain, other.rain),
+            wind: (blend(self.wind.0, other.wind.0), blend(self.wind.1, other.wind.1)),
+            temperature: blend(self.temperature, other.temperature),
+        };
+    }
+
+    fn validate(&self) -> Vec<CreationError> {
+        let mut errors: Vec<CreationError> = [
+            check_float_between_zero_and_one(self.rain, "rain".to_string()),
+            check_finite(self.wind.0, "wind"),
+            check_finite(self.wind.1, "wind"),
+        ].into_iter().flatten().collect();
+        if !is_positive_and_finite(self.temperature) {
+            errors.push(CreationError::TemperatureIsNotPositive);
+        }
+        return errors;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Weather {
+    keyframes: Vec<(u32, WeatherState)>, // in order of the tick each applies from
+    ticks_elapsed: u32,
+    now: WeatherState,
+}
+
+impl Weather {
+    pub fn new(keyframes: Vec<(u32, WeatherState)>) -> Result<Weather, InvalidFlockConfig> {
+        let mut errors: Vec<CreationError> = keyframes.iter().flat_map(|(_, state)| state.validate()).collect();
+        if keyframes.is_empty() {
+            errors.push(CreationError::WeatherNeedsAKeyframe);
+        }
+        if keyframes.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            errors.push(CreationError::WeatherKeyframesAreOutOfOrder);
+        }
+        if !errors.is_empty() {
+            return Err(InvalidFlockConfig { errors });
+        }
+        let mut weather = Weather { keyframes, ticks_elapsed: 0, now: WeatherState::default() };
+        weather.now = weather.at_tick(0);
+        return Ok(weather);
+    }
+
+    /// the same weather throughout
+    pub fn constant(state: WeatherState) -> Result<Weather, InvalidFlockConfig> {
+        return Weather::new(vec![(0, state)]);
+    }
+
+    /// keyframes separated by `;`, each `tick:rain:wind_x,wind_y:temperature`,
+    /// e.g. `0:0:0,0:1;500:1:0.5,0:0.6` for a cold, wet westerly blowing in over 500 ticks
+    pub fn parse(text: &str) -> Result<Weather> {
+        let keyframes = text
+            .split(';')
+            .map(|keyframe| {
+                let parts: Vec<&str> = keyframe.split(':').collect();
+                let [tick, rain, wind, temperature] = parts.as_slice() else {
+                    return Err(anyhow!("weather keyframe '{}' should be tick:rain:wind_x,wind_y:temperature", keyframe));
+                };
+                let parse_float = |part: &str| part.parse::<f32>().with_context(|| format!("'{}' in '{}' is not a number", part, keyframe));
+                let (wind_x, wind_y) = wind.split_once(',').ok_or_else(|| anyhow!("wind '{}' in '{}' should be x,y", wind, keyframe))?;
+                let state = WeatherState { rain: parse_float(rain)?, wind: (parse_float(wind_x)?, parse_float(wind_y)?), temperature: parse_float(temperature)? };
+                return Ok((tick.parse::<u32>().with_context(|| format!("'{}' in '{}' is not a tick", tick, keyframe))?, state));
+            })
+            .collect::<Result<Vec<(u32, WeatherState)>>>()?;
+        return Ok(Weather::new(keyframes)?);
+    }
+
+    /// the weather for the flock's next step, e.g. to show on screen
+    pub fn current(&self) -> WeatherState {
+        return self.now;
+    }
+
+    fn at_tick(&self, tick: u32) -> WeatherState {
+        let next_keyframe = self.keyframes.partition_point(|(keyframe_tick, _)| *keyframe_tick <= tick);
+        if next_keyframe == 0 {
+            return self.keyframes[0].1;
+        }
+        let (from_tick, from_state) = self.keyframes[next_keyframe - 1];
+        let Some((to_tick, to_state)) = self.keyframes.get(next_keyframe) else {
+            return from_state;
+        };
+        return from_state.blend(to_state, (tick - from_tick) as f32 / (to_tick - from_tick) as f32);
+    }
+
+    /// moves on to the weather for the next step
+    pub(crate) fn advance(&mut self) {
+        self.ticks_elapsed = self.ticks_elapsed.saturating_add(1);
+        self.now = self.at_tick(self.ticks_elapsed);
+    }
+
+    pub(crate) fn blow(&self, boids: &mut [Boid], time_per_frame: f32) {
+        for boid in boids {
+            boid.x_pos += self.now.wind.0 * time_per_frame;
+            boid.y_pos += self.now.wind.1 * time_per_frame;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Flock;
+
+    #[test]
+    fn test_weather_blends_between_keyframes() {
+        let mut weather = Weather::parse("10:0:0,0:1;20:1:2,0:0.5").unwrap();
+        assert_eq!(weather.current(), WeatherState::default());
+        for _ in 0..15 {
+            weather.advance();
+        }
+        assert_eq!(weather.current(), WeatherState { rain: 0.5, wind: (1.0, 0.0), temperature: 0.75 });
+        assert_eq!(weather.current().visibility(), 0.75);
+        for _ in 0..100 {
+            weather.advance();
+        }
+        assert_eq!(weather.current(), WeatherState { rain: 1.0, wind: (2.0, 0.0), temperature: 0.5 });
+    }
+
+    #[test]
+    fn test_invalid_weather_rejected() {
+        let keyframes = vec![(5, WeatherState { rain: 2.0, temperature: 0.0, ..WeatherState::default() }), (0, WeatherState::default())];
+        let errors = Weather::new(keyframes).unwrap_err().errors;
+        assert_eq!(errors, vec![
+            CreationError::FactorShouldBeLessThanOne("rain".to_string()),
+            CreationError::TemperatureIsNotPositive,
+            CreationError::WeatherKeyframesAreOutOfOrder,
+        ]);
+        assert!(Weather::parse("0:0:0:1").is_err());
+    }
+
+    #[test]
+    fn test_wind_and_cold_slow_and_carry_the_flock() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.1, 0.1, 0.1).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 4.0, 0.0)];
+        flock.set_speed_limits(0.0, 2.0).unwrap();
+        flock.set_weather(Weather::constant(WeatherState { rain: 0.0, wind: (0.0, 1.0), temperature: 0.5 }).unwrap());
+        flock.step();
+        // a lone boid doesn't flock, so only the wind moves it, and the cold halves its top speed
+        assert_eq!((flock.boids()[0].x_pos, flock.boids()[0].y_pos), (0.0, 1.0));
+        assert_eq!(flock.boids()[0].x_vel, 1.0);
+    }
+
+    #[test]
+    fn test_rain_shortens_sight() {
+        let mut flock = Flock::new(0, 1.0, 10.0, 0.0, 0.0, 0.1).unwrap();
+        flock.boids = vec![Boid::new(0.0, 0.0, 0.0, 0.0), Boid::new(8.0, 0.0, 0.0, 0.0)];
+        flock.set_weather(Weather::constant(WeatherState { rain: 1.0, ..WeatherState::default() }).unwrap());
+        flock.step();
+        // 8 apart is beyond the 5 the boids can see in a downpour, so they don't cohere
+        assert_eq!(flock.boids()[0].x_vel, 0.0);
+    }
+}